@@ -1,26 +1,11 @@
 member(El, [El|_]).
 member(El, [_|T]):-member(El,T).
 
-%Branch Based on Condition form
-list_for_all(List,Cond):-
-    valid_functor(Cond),
-    list_for_all_1(List,Cond).
-list_for_all(List,Cond):-
-    Cond =.. CondList,
-    list_for_all_2(List,CondList).
-%Simple list for all
-list_for_all_1([],_).
-list_for_all_1([H|T],P):-
-    P(H),
-    list_for_all_1(T,P).
-%List for all with curried argument
-list_for_all_2([],_).
-list_for_all_2([H|T],[P,A]):-
-    P(A,H),
-    list_for_all_2(T,[P,A]).
-list_for_all_2([H|T],[P,A,B]):-
-    P(A,B,H),
-    list_for_all_2(T,[P,A,B]).
+%Cond may be a closure such as '<'(1), applied to each element
+list_for_all([],_).
+list_for_all([H|T],Cond):-
+    Cond(H),
+    list_for_all(T,Cond).
 
 
 map_list([],[],_).
@@ -31,15 +16,10 @@ map_list([H1|T1],[H2|T2],P):-
 %% Count True 
 % ---------------------------
 %initial call
-count_true([H|T],P,N):-
-    valid_functor(P),
-    count_true_1([H|T],P,0,N).
-count_true([H|T],Cond,N):-
-    Cond=..CondList,
-    count_true_2([H|T],CondList,0,N).
+count_true(List,P,N):-
+    count_true_1(List,P,0,N).
 %base case    
-count_true_1([],P,N,N).
-count_true_2([],P,N,N).
+count_true_1([],_,N,N).
 
 count_true_1([H|T],P,N1,N2):-
     P(H),
@@ -49,10 +29,108 @@ count_true_1([H|T],P,N1,N2):-
     not(P(H)),
     count_true_1(T,P,N1,N2).
 
-count_true_2([H|T],[P,A],N1,N2):-
-    P(A,H),
-    N3 is N1 + 1,
-    count_true_2(T,[P,A],N3,N2).
-count_true_2([H|T],[P,A],N1,N2):-
-    not(P(A,H)),
-    count_true_2(T,[P,A],N1,N2).
\ No newline at end of file
+%% Predsort
+% ---------------------------
+% predsort(P, List, Sorted): merge sort ordering elements with P(Order, A, B),
+% where Order is '<', '>' or '='. Elements P finds '=' to an earlier one are
+% dropped.
+predsort(P, List, Sorted):-
+    length(List, N),
+    predsort_1(P, N, List, _, Sorted1),
+    !,
+    Sorted = Sorted1.
+
+predsort_1(P, 2, [X1,X2|L], L, R):-
+    !,
+    P(Order, X1, X2),
+    predsort_pair(Order, X1, X2, R).
+predsort_1(_, 1, [X|L], L, [X]):- !.
+predsort_1(_, 0, L, L, []):- !.
+predsort_1(P, N, L1, L3, R):-
+    N1 is N / 2,
+    N2 is N - N1,
+    predsort_1(P, N1, L1, L2, R1),
+    predsort_1(P, N2, L2, L3, R2),
+    predmerge(P, R1, R2, R).
+
+predsort_pair('<', X1, X2, [X1,X2]).
+predsort_pair('=', X1, _, [X1]).
+predsort_pair('>', X1, X2, [X2,X1]).
+
+predmerge(_, [], R, R):- !.
+predmerge(_, R, [], R):- !.
+predmerge(P, [H1|T1], [H2|T2], Result):-
+    P(Order, H1, H2),
+    !,
+    predmerge_1(Order, P, H1, H2, T1, T2, Result).
+
+predmerge_1('<', P, H1, H2, T1, T2, [H1|R]):- predmerge(P, T1, [H2|T2], R).
+predmerge_1('=', P, H1, _, T1, T2, [H1|R]):- predmerge(P, T1, T2, R).
+predmerge_1('>', P, H1, H2, T1, T2, [H2|R]):- predmerge(P, [H1|T1], T2, R).
+
+%% List library
+% ---------------------------
+% append/3, reverse/2, nth0/3, nth1/3, last/2, select/3, delete/3, sum_list/2,
+% max_list/2, min_list/2, numlist/3 and list_to_ord_set/2 are native. These
+% clauses cover the modes the native predicates hand back to Prolog.
+append_1([], L, L).
+append_1([H|T], L, [H|R]):- append_1(T, L, R).
+
+last_1([X], X).
+last_1([_|T], X):- last_1(T, X).
+
+select_1(X, [X|T], T).
+select_1(X, [H|T], [H|R]):- select_1(X, T, R).
+
+% permutation(Xs, Ys): Ys is a permutation of Xs. One of them must be a
+% proper list.
+permutation(Xs, Ys):-
+    list(Xs) -> permutation_1(Xs, Ys) ; permutation_1(Ys, Xs).
+
+permutation_1([], []).
+permutation_1(L, [H|T]):-
+    select(H, L, R),
+    permutation_1(R, T).
+
+% include(P, List, Included): the elements of List for which P(El) succeeds
+include(_, [], []).
+include(P, [H|T], L):-
+    ( P(H) -> L = [H|L1] ; L = L1 ),
+    include(P, T, L1).
+
+% exclude(P, List, Excluded): the elements of List for which P(El) fails
+exclude(_, [], []).
+exclude(P, [H|T], L):-
+    ( P(H) -> L = L1 ; L = [H|L1] ),
+    exclude(P, T, L1).
+
+partition(_, [], [], []).
+partition(P, [H|T], I, E):-
+    ( P(H) -> I = [H|I1], E = E1 ; I = I1, E = [H|E1] ),
+    partition(P, T, I1, E1).
+
+maplist(_, []).
+maplist(P, [A|As]):- P(A), maplist(P, As).
+
+maplist(_, [], []).
+maplist(P, [A|As], [B|Bs]):- P(A, B), maplist(P, As, Bs).
+
+maplist(_, [], [], []).
+maplist(P, [A|As], [B|Bs], [C|Cs]):- P(A, B, C), maplist(P, As, Bs, Cs).
+
+maplist(_, [], [], [], []).
+maplist(P, [A|As], [B|Bs], [C|Cs], [D|Ds]):- P(A, B, C, D), maplist(P, As, Bs, Cs, Ds).
+
+% foldl(P, List, V0, V): V is P folded over List from the left, calling
+% P(El, Acc0, Acc) for each element
+foldl(P, As, V0, V):- foldl_1(As, P, V0, V).
+foldl_1([], _, V, V).
+foldl_1([A|As], P, V0, V):- P(A, V0, V1), foldl_1(As, P, V1, V).
+
+foldl(P, As, Bs, V0, V):- foldl_1(As, Bs, P, V0, V).
+foldl_1([], [], _, V, V).
+foldl_1([A|As], [B|Bs], P, V0, V):- P(A, B, V0, V1), foldl_1(As, Bs, P, V1, V).
+
+foldl(P, As, Bs, Cs, V0, V):- foldl_1(As, Bs, Cs, P, V0, V).
+foldl_1([], [], [], _, V, V).
+foldl_1([A|As], [B|Bs], [C|Cs], P, V0, V):- P(A, B, C, V0, V1), foldl_1(As, Bs, Cs, P, V1, V).
@@ -1,4 +1,4 @@
-use std::{fs, path::Path, process::ExitCode, sync::Arc};
+use std::{fs, io::BufRead, path::Path, process::ExitCode, sync::Arc};
 
 use rustyline::error::ReadlineError;
 
@@ -12,12 +12,17 @@ use crate::{
         symbol_db::SymbolDB,
     },
     parser::{
-        build_tree::TokenStream,
+        build_tree::{TokenStream, TreeClause},
         execute_tree::{build_clause, execute_tree},
+        operators::Operators,
+        term::Term as SyntaxTerm,
         tokeniser::tokenise,
     },
-    predicate_modules::{maths::set_approx_tolerance, PredicateModule, STANDARD_MODULES},
-    program::predicate_table::PredicateTable,
+    predicate_modules::{
+        maths::{set_approx_tolerance, FunctionModule},
+        PredicateModule, STANDARD_MODULES,
+    },
+    program::{output::OutputSink, predicate_table::PredicateTable},
     resolution::proof::Proof,
     Error, Result,
 };
@@ -34,6 +39,10 @@ pub struct Config {
     /// Enable debug trace output.
     #[serde(default)]
     pub debug: bool,
+    /// Number of cells a query heap may grow by between garbage
+    /// collections. `0` disables collection.
+    #[serde(default = "default_gc_threshold")]
+    pub gc_threshold: usize,
 }
 
 impl Default for Config {
@@ -43,10 +52,15 @@ impl Default for Config {
             max_clause: 4,
             max_pred: 2,
             debug: false,
+            gc_threshold: default_gc_threshold(),
         }
     }
 }
 
+fn default_gc_threshold() -> usize {
+    1 << 20
+}
+
 /// A body predicate declaration in the setup file.
 /// Deserialized from a string whith predicate and arity seperated by '/'
 /// e.g `"p/2"`.
@@ -278,6 +292,21 @@ impl App {
         self
     }
 
+    /// Sends the text written by Prolog (`write/1`, `format/2`, ...) to
+    /// `sink` instead of standard output. Pass an
+    /// [`OutputBuffer`](crate::program::output::OutputBuffer) to capture it.
+    pub fn output(self, sink: impl OutputSink + 'static) -> Self {
+        self.predicate_table.output.set_sink(Box::new(sink));
+        self
+    }
+
+    /// Reads the terms taken by `read_term/2` and `read/1` from `source`
+    /// instead of standard input.
+    pub fn input(self, source: impl BufRead + Send + 'static) -> Self {
+        self.predicate_table.input.set_source(Box::new(source));
+        self
+    }
+
     /// When `true`, solution search runs without pausing to ask the user
     /// whether to continue — equivalent to always pressing `;`.
     pub fn auto(self, auto: bool) -> Self {
@@ -350,23 +379,84 @@ impl App {
 
     /// Parses a Prolog source string and adds all clauses to the program.
     ///
+    /// Each directive `:- Goal.` is run once, as soon as the clauses before
+    /// it are loaded, and must succeed. For example `:- dynamic Name/Arity.`
+    /// declares a predicate whose clauses queries can change.
+    ///
     /// # Errors
     ///
-    /// Returns [`Error::Parser`] if the source contains a syntax error.
-    pub fn load_code(mut self, code: impl AsRef<str>) -> Result<Self> {
-        let syntax_tree = TokenStream::new(tokenise(code)?).parse_all()?;
-        execute_tree(syntax_tree, &mut self.prog_heap, &mut self.predicate_table);
-        Ok(self)
+    /// Returns [`Error::Parser`] if the source contains a syntax error, or
+    /// [`Error::Directive`] if a directive fails or raises an exception.
+    pub fn load_code(self, code: impl AsRef<str>) -> Result<Self> {
+        self.load_source(code.as_ref(), None)
     }
 
-    /// Reads a `.pl` file from disk and loads it with [`App::load_code`].
+    /// Reads a `.pl` file from disk and loads it like [`App::load_code`].
     ///
     /// # Errors
     ///
-    /// Returns [`Error::IO`] if the file cannot be read, or [`Error::Parser`]
-    /// if it contains a syntax error.
+    /// Returns [`Error::IO`] if the file cannot be read, [`Error::Parser`]
+    /// if it contains a syntax error, or [`Error::Directive`] if one of its
+    /// directives fails or raises an exception. Directive errors name the
+    /// file and line.
     pub fn load_file(self, file: &Path) -> Result<Self> {
-        self.load_code(fs::read_to_string(file)?)
+        let code = fs::read_to_string(file)?;
+        self.load_source(&code, Some(file))
+    }
+
+    /// Loads `code`, naming `file` as its source in directive errors.
+    ///
+    /// Clauses are parsed one at a time, so operators declared by an `op/3`
+    /// directive apply to the clauses after it.
+    fn load_source(mut self, code: &str, file: Option<&Path>) -> Result<Self> {
+        let _symbols = self.predicate_table.symbols.enter();
+        let mut token_stream = TokenStream::new(tokenise(code)?).with_operators(self.operators());
+        while let Some(clause) = token_stream.next_clause()? {
+            if let TreeClause::Directive(goals, line) = clause {
+                if let Err(message) = self.run_directive(goals) {
+                    let location = match file {
+                        Some(file) => format!("{}:{line}", file.display()),
+                        None => format!("line {line}"),
+                    };
+                    return Err(Error::Directive(format!("{location}: {message}")));
+                }
+                token_stream.set_operators(self.operators());
+            } else {
+                execute_tree(vec![clause], &mut self.prog_heap, &mut self.predicate_table);
+            }
+        }
+        Ok(self)
+    }
+
+    /// The program's current operator table.
+    fn operators(&self) -> Operators {
+        self.predicate_table.operators.read().unwrap().clone()
+    }
+
+    /// Proves the goals of a directive once, with learning disabled. On
+    /// failure returns a message describing the failure or the uncaught
+    /// exception.
+    fn run_directive(&self, literals: Vec<SyntaxTerm>) -> std::result::Result<(), String> {
+        let mut heap = QueryHeap::new(&self.prog_heap, None);
+        let goals = build_clause(literals, None, None, &mut heap, true);
+        let goal_text = goals
+            .iter()
+            .map(|&goal| heap.term_string(goal))
+            .collect::<Vec<_>>()
+            .join(",");
+        let mut config = self.config;
+        config.max_clause = 0;
+        let mut proof = Proof::new(&heap, &goals);
+        if proof.prove(&mut heap, &self.predicate_table, config) {
+            return Ok(());
+        }
+        match proof.take_exception(&mut heap) {
+            Some(ball) => Err(format!(
+                "directive `{goal_text}` raised {}",
+                heap.term_string(ball)
+            )),
+            None => Err(format!("directive `{goal_text}` failed")),
+        }
     }
 
     /// Recursively loads all `.pl` files found under `dir_path`.
@@ -401,6 +491,7 @@ impl App {
     /// Returns [`Error::Parser`] if the module's bundled source contains a
     /// syntax error.
     pub fn load_module(mut self, predicate_module: &PredicateModule) -> Result<Self> {
+        let _symbols = self.predicate_table.symbols.enter();
         for (symbol, arity, pred_fn) in predicate_module.0.iter() {
             self.predicate_table
                 .insert_predicate_function(
@@ -415,6 +506,20 @@ impl App {
         Ok(self)
     }
 
+    /// Adds arithmetic functions for `is/2` and the arithmetic comparisons,
+    /// replacing any built-in function with the same name and arity.
+    ///
+    /// ```
+    /// # use prolog2::{app::App, predicate_modules::maths::FunctionModule};
+    /// static DOUBLE: FunctionModule = &[("double", 1, |args| Ok(args[0].clone() + args[0].clone()))];
+    /// let app = App::default().load_functions(DOUBLE);
+    /// ```
+    pub fn load_functions(mut self, functions: FunctionModule) -> Self {
+        let _symbols = self.predicate_table.symbols.enter();
+        self.predicate_table.functions.load(functions);
+        self
+    }
+
     /// Marks a set of predicates as *body predicates* for Meta-Interpretive
     /// Learning.
     ///
@@ -442,6 +547,7 @@ impl App {
         mut self,
         body_preds: impl IntoIterator<Item = impl TryIntoBodyPred>,
     ) -> Result<Self> {
+        let _symbols = self.predicate_table.symbols.enter();
         for item in body_preds {
             let BodyPred { symbol, arity } = item.try_into_body_pred()?;
             let sym = SymbolDB::set_const(symbol.clone());
@@ -472,11 +578,12 @@ impl App {
     ///
     /// # Errors
     ///
-    /// Returns [`Error::Parser`] if `query` contains a syntax error.
+    /// Returns [`Error::Parser`] if `query` contains a syntax error, or
+    /// [`Error::Exception`] if the query raises an uncaught exception.
     pub fn start_query(&self, query: impl AsRef<str>) -> Result<()> {
         let mut session = self.query_session(query)?;
         loop {
-            if let Some(solution) = session.next() {
+            if let Some(solution) = session.next_solution()? {
                 println!("TRUE");
                 for (name, value) in &solution.bindings {
                     println!("{name} = {value}");
@@ -509,18 +616,23 @@ impl App {
     /// Returns [`Error::Parser`] if `query` contains a syntax error.
     pub fn query_session(&self, query: impl AsRef<str>) -> Result<QuerySession<'_>> {
         let query = query.as_ref();
-        let literals = TokenStream::new(tokenise(query)?).parse_goals()?;
+        let _symbols = self.predicate_table.symbols.enter();
+        let literals = TokenStream::new(tokenise(query)?)
+            .with_operators(self.operators())
+            .parse_goals()?;
 
         let mut query_heap = QueryHeap::new(&self.prog_heap, None);
         let goals = build_clause(literals, None, None, &mut query_heap, true);
-        let mut vars = Vec::new();
+        let mut vars: Vec<(Arc<str>, usize)> = Vec::new();
         for literal in goals.iter() {
-            vars.extend(query_heap.term_vars(*literal, false).iter().map(|addr| {
-                (
-                    SymbolDB::get_var(*addr, query_heap.get_id()).unwrap(),
-                    *addr,
-                )
-            }));
+            for addr in query_heap.term_vars(*literal, false) {
+                // Later occurrences of a variable are refs to its first cell.
+                if let Some(name) = SymbolDB::get_var(addr, query_heap.get_id()) {
+                    if !vars.iter().any(|(var, _)| *var == name) {
+                        vars.push((name, addr));
+                    }
+                }
+            }
         }
         let proof = Proof::new(&query_heap, &goals);
         Ok(QuerySession {
@@ -684,34 +796,132 @@ pub struct Solution {
     pub hypothesis: String,
 }
 
+impl QuerySession<'_> {
+    /// Searches for the next solution, resuming from the previous one.
+    ///
+    /// Returns `Ok(None)` once the search space is exhausted.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Exception`] if the query raises an exception that no
+    /// `catch/3` handles. The proof cannot be resumed after that, so later
+    /// calls return `Ok(None)`.
+    pub fn next_solution(&mut self) -> Result<Option<Solution>> {
+        let _symbols = self.predicate_table.symbols.enter();
+        if !self.proof.prove(&mut self.heap, self.predicate_table, self.config) {
+            return match self.proof.take_exception(&mut self.heap) {
+                Some(ball) => Err(Error::Exception(self.heap.term_string(ball))),
+                None => Ok(None),
+            };
+        }
+        let bindings = self
+            .vars
+            .iter()
+            .map(|(name, addr)| (name.clone(), self.heap.term_string(*addr)))
+            .collect();
+        let hypothesis = if self.proof.hypothesis.len() > 0 {
+            for clause in self.proof.hypothesis.iter() {
+                clause.normalise_clause_vars(&mut self.heap);
+            }
+            let clause_strings: Vec<String> = self.proof.hypothesis
+                .iter()
+                .map(|c| c.to_string(&self.heap))
+                .collect();
+            crate::normalise_hypothesis(&clause_strings).join("\n") + "\n"
+        } else {
+            String::new()
+        };
+        Ok(Some(Solution {
+            bindings,
+            hypothesis,
+        }))
+    }
+}
+
+/// Iterating ends at the first uncaught exception as if the search space were
+/// exhausted; use [`QuerySession::next_solution`] to tell the two apart.
 impl<'a> Iterator for QuerySession<'a> {
     type Item = Solution;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.proof.prove(&mut self.heap,self.predicate_table, self.config) {
-            let bindings = self
-                .vars
-                .iter()
-                .map(|(name, addr)| (name.clone(), self.heap.term_string(*addr)))
-                .collect();
-            let hypothesis = if self.proof.hypothesis.len() > 0 {
-                for clause in self.proof.hypothesis.iter() {
-                    clause.normalise_clause_vars(&mut self.heap);
-                }
-                let clause_strings: Vec<String> = self.proof.hypothesis
-                    .iter()
-                    .map(|c| c.to_string(&self.heap))
-                    .collect();
-                crate::normalise_hypothesis(&clause_strings).join("\n") + "\n"
-            } else {
-                String::new()
-            };
-            Some(Solution {
-                bindings,
-                hypothesis,
-            })
-        } else {
-            None
+        self.next_solution().ok().flatten()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use super::App;
+    use crate::Error;
+
+    fn directive_error(result: crate::Result<App>) -> String {
+        match result {
+            Err(Error::Directive(message)) => message,
+            Err(error) => panic!("expected a directive error, got {error}"),
+            Ok(_) => panic!("expected a directive error"),
         }
     }
+
+    #[test]
+    fn directives_run_in_order() {
+        let app = App::default()
+            .load_code(
+                "base(2).
+                :- base(X), Y is X * 10, assertz(scaled(Y)).
+                :- dynamic(count/1), assertz(count(0)).
+                count(1).",
+            )
+            .unwrap();
+        let solution = app.query_session("scaled(X).").unwrap().next().unwrap();
+        assert_eq!(solution.bindings[0].1, "20");
+        // Clauses after a dynamic declaration join the asserted ones
+        let counts: Vec<_> = app
+            .query_session("count(X).")
+            .unwrap()
+            .map(|solution| solution.bindings[0].1.clone())
+            .collect();
+        assert_eq!(counts, ["0", "1"]);
+    }
+
+    #[test]
+    fn failing_directive() {
+        let message = directive_error(App::default().load_code("p(a).\n\n:- p(b)."));
+        assert_eq!(message, "line 3: directive `p(b)` failed");
+    }
+
+    #[test]
+    fn directive_exception() {
+        let message = directive_error(App::default().load_code(":- X is foo + 1."));
+        assert!(message.starts_with(
+            "line 1: directive `is(X,+(foo,1))` raised error(type_error(evaluable,/(foo,0)),"
+        ));
+    }
+
+    #[test]
+    fn directive_error_names_file() {
+        let path = env::temp_dir().join("prolog2_directive_error_names_file.pl");
+        fs::write(&path, "p(a).\n:- dynamic(p/1).\n").unwrap();
+        let message = directive_error(App::default().load_file(&path));
+        fs::remove_file(&path).unwrap();
+        assert!(message.starts_with(&format!(
+            "{}:2: directive `dynamic(/(p,1))` raised error(permission_error(",
+            path.display()
+        )));
+    }
+
+    #[test]
+    fn engines_keep_their_own_symbols() {
+        let first = App::new().load_code("p(first_engine_atom, \"first\").").unwrap();
+        let second = App::new().load_code("p(second_engine_atom, \"second\").").unwrap();
+        let mut first_session = first.query_session("p(A, S).").unwrap();
+        let mut second_session = second.query_session("p(A, S).").unwrap();
+
+        let second_solution = second_session.next().unwrap();
+        let first_solution = first_session.next().unwrap();
+        assert_eq!(first_solution.bindings[0].1, "first_engine_atom");
+        assert_eq!(first_solution.bindings[1].1, "\"first\"");
+        assert_eq!(second_solution.bindings[0].1, "second_engine_atom");
+        assert_eq!(second_solution.bindings[1].1, "\"second\"");
+    }
 }
@@ -0,0 +1,181 @@
+//! Mark-and-compact garbage collection of query heaps.
+//!
+//! A proof only collects the cells it allocated, those at or above the heap
+//! length it started at (its floor). Cells below the floor belong to the
+//! query or to an enclosing proof and are never moved, but the proof may have
+//! bound them to its own cells, so they are scanned as roots.
+//!
+//! Live cells are slid down in order, so a heap length recorded before the
+//! collection still separates the cells allocated before it from those
+//! allocated after, and truncating to its relocated value on backtracking
+//! discards the same live cells it would have before.
+
+use super::{
+    heap::{Cell, Heap, Tag},
+    query_heap::QueryHeap,
+    symbol_db::SymbolDB,
+};
+
+/// Collects the addresses of the cells a proof still needs.
+pub(crate) struct Marker {
+    floor: usize,
+    pending: Vec<usize>,
+}
+
+impl Marker {
+    /// Keep the cell at `addr` and everything it references.
+    pub(crate) fn addr(&mut self, addr: usize) {
+        if addr >= self.floor {
+            self.pending.push(addr);
+        }
+    }
+
+    /// Keep everything `cell` references, for a cell held outside the heap.
+    pub(crate) fn cell(&mut self, cell: Cell) {
+        match cell {
+            (Tag::Ref | Tag::Str, pointer) => self.addr(pointer),
+            (Tag::Lis, pointer) => {
+                self.addr(pointer);
+                self.addr(pointer + 1);
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Where a collection moved the cells it kept.
+pub(crate) struct Relocation {
+    floor: usize,
+    /// New address of each cell from the floor up, and one past the end.
+    forward: Vec<usize>,
+}
+
+impl Relocation {
+    /// The new address of the live cell at `addr`. For a heap length
+    /// recorded before the collection, the length the cells below it now
+    /// take.
+    pub(crate) fn addr(&self, addr: usize) -> usize {
+        if addr < self.floor {
+            addr
+        } else {
+            self.forward[addr - self.floor]
+        }
+    }
+
+    /// `cell` with the address it holds relocated.
+    pub(crate) fn cell(&self, cell: Cell) -> Cell {
+        match cell {
+            (tag @ (Tag::Ref | Tag::Str | Tag::Lis), pointer) => (tag, self.addr(pointer)),
+            cell => cell,
+        }
+    }
+
+    fn live(&self, addr: usize) -> bool {
+        addr < self.floor || self.forward[addr - self.floor] < self.forward[addr - self.floor + 1]
+    }
+}
+
+impl QueryHeap<'_> {
+    /// Discard the cells at or above `floor` not reachable from the roots
+    /// `mark_roots` gives, compacting the rest down to the floor.
+    ///
+    /// The caller must relocate every address it holds at or above the floor
+    /// with the returned [`Relocation`].
+    pub(crate) fn collect_garbage(
+        &mut self,
+        floor: usize,
+        mark_roots: impl FnOnce(&mut Marker),
+    ) -> Relocation {
+        let len = self.heap_len();
+        let base = len - self.cells.len();
+        let mut marker = Marker {
+            floor,
+            pending: Vec::new(),
+        };
+        // Cells below the floor may have been bound to cells above it
+        for addr in base..floor {
+            marker.cell(self[addr]);
+        }
+        mark_roots(&mut marker);
+
+        let mut marked = vec![false; len - floor];
+        while let Some(addr) = marker.pending.pop() {
+            if marked[addr - floor] {
+                continue;
+            }
+            marked[addr - floor] = true;
+            match self[addr] {
+                (Tag::Comp | Tag::Tup | Tag::Set, arity) => {
+                    marker.pending.extend((addr + 1)..=(addr + arity))
+                }
+                cell => marker.cell(cell),
+            }
+        }
+
+        let mut forward = Vec::with_capacity(marked.len() + 1);
+        let mut next = floor;
+        for &live in &marked {
+            forward.push(next);
+            next += live as usize;
+        }
+        forward.push(next);
+        let relocation = Relocation { floor, forward };
+
+        for addr in base..floor {
+            let cell = self[addr];
+            self[addr] = relocation.cell(cell);
+        }
+        for addr in floor..len {
+            if marked[addr - floor] {
+                let cell = self[addr];
+                self[relocation.addr(addr)] = relocation.cell(cell);
+            }
+        }
+        self.truncate(next);
+        SymbolDB::relocate_vars(self.get_id(), |addr| {
+            relocation.live(addr).then(|| relocation.addr(addr))
+        });
+        relocation
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::heap::{
+        heap::{Cell, Heap, Tag},
+        query_heap::QueryHeap,
+        symbol_db::SymbolDB,
+    };
+
+    #[test]
+    fn compacts_live_cells() {
+        let prog: Vec<Cell> = Vec::new();
+        let mut heap = QueryHeap::new(&prog, None);
+        let f = SymbolDB::set_const("f");
+        let a = SymbolDB::set_const("a");
+        // X = f(a, Y) below the floor, bound to cells above it
+        heap.heap_push((Tag::Ref, 0));
+        let floor = heap.heap_len();
+        heap.heap_push((Tag::Con, a)); // garbage
+        heap.heap_push((Tag::Comp, 3));
+        heap.heap_push((Tag::Con, f));
+        heap.heap_push((Tag::Con, a));
+        heap.heap_push((Tag::Ref, 5));
+        heap.heap_push((Tag::Lis, 7)); // garbage list [a]
+        heap.heap_push((Tag::Con, a));
+        heap.heap_push((Tag::ELis, 0));
+        heap.heap_push((Tag::Str, 2)); // root
+        heap.bind(&[(0, 9)]);
+        SymbolDB::set_var("Y", 5, heap.get_id());
+
+        let relocation = heap.collect_garbage(floor, |marker| marker.addr(9));
+        assert_eq!(heap.heap_len(), 6);
+        assert_eq!(relocation.addr(9), 5);
+        assert_eq!(relocation.addr(2), 1);
+        assert_eq!(heap[0], (Tag::Ref, 5));
+        assert_eq!(heap.term_string(0), "f(a,Y)");
+        assert_eq!(SymbolDB::get_var(4, heap.get_id()).as_deref(), Some("Y"));
+        // A heap length before the garbage list still sits past f(a,Y)
+        assert_eq!(relocation.addr(6), 5);
+    }
+}
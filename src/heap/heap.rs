@@ -1,9 +1,12 @@
 use std::{
+    cmp::Ordering,
     collections::HashMap,
     mem,
     ops::{Index, IndexMut, Range, RangeInclusive},
+    sync::Arc,
 };
 use super::symbol_db::SymbolDB;
+use crate::predicate_modules::maths::Number;
 
 /// Tag discriminant for heap cells.
 ///
@@ -38,6 +41,12 @@ pub enum Tag {
     Stri,
     /// Anonymous variable: terms starting with '_' which unify with any term but don't bind
     AVar,
+    /// Integer too large for an `isize`: value is an index into
+    /// [`super::symbol_db::SymbolDB`] big integers.
+    BigInt,
+    /// Exact rational that is not an integer: value is an index into
+    /// [`super::symbol_db::SymbolDB`] rationals.
+    Rat,
 }
 
 impl std::fmt::Display for Tag {
@@ -362,7 +371,7 @@ pub trait Heap: IndexMut<usize, Output = Cell> + Index<Range<usize>, Output = [C
                     new_addr
                 }
             }
-            (Tag::Arg | Tag::Con | Tag::Int | Tag::Flt | Tag::Stri | Tag::ELis, _) => {
+            (Tag::Arg | Tag::AVar | Tag::Con | Tag::Int | Tag::BigInt | Tag::Rat | Tag::Flt | Tag::Stri | Tag::ELis, _) => {
                 self.heap_push(other[addr]);
                 self.heap_len() - 1
             }
@@ -404,14 +413,27 @@ pub trait Heap: IndexMut<usize, Output = Cell> + Index<Range<usize>, Output = [C
                 }
             }
             cell => {
+                // Strings and big numbers must outlive the heap they were
+                // made on
+                if self.get_id() != other.get_id() {
+                    match cell {
+                        (Tag::Stri, index) => SymbolDB::keep_string(index),
+                        (Tag::BigInt, index) => SymbolDB::keep_big_int(index),
+                        (Tag::Rat, index) => SymbolDB::keep_rational(index),
+                        _ => (),
+                    }
+                }
                 self.heap_push(cell);
             }
         }
     }
 
-    fn term_equal(&self, mut addr1: usize, mut addr2: usize) -> bool {
-        addr1 = self.deref_addr(addr1);
-        addr2 = self.deref_addr(addr2);
+    fn term_equal(&self, addr1: usize, addr2: usize) -> bool {
+        let addr1 = self.deref_addr(addr1);
+        let addr2 = self.deref_addr(addr2);
+        // A variable may be bound to a structure directly or through a Str cell
+        let addr1 = if let (Tag::Str, ptr) = self[addr1] { ptr } else { addr1 };
+        let addr2 = if let (Tag::Str, ptr) = self[addr2] { ptr } else { addr2 };
         match (self[addr1], self[addr2]) {
             (EMPTY_LIS, EMPTY_LIS) => true,
             (EMPTY_LIS, _) => false,
@@ -426,7 +448,6 @@ pub trait Heap: IndexMut<usize, Output = Cell> + Index<Range<usize>, Output = [C
             ((Tag::Lis, p1), (Tag::Lis, p2)) => {
                 self.term_equal(p1, p2) && self.term_equal(p1 + 1, p2 + 1)
             }
-            ((Tag::Str, p1), (Tag::Str, p2)) => self.term_equal(p1, p2),
             ((Tag::Set, len1), (Tag::Set, len2)) if len1 == len2 => {
                 // Set equality: every element in set1 must have a match in set2
                 // and vice-versa (lengths already equal, so one direction suffices
@@ -439,11 +460,144 @@ pub trait Heap: IndexMut<usize, Output = Cell> + Index<Range<usize>, Output = [C
             ((Tag::Stri, i1), (Tag::Stri, i2)) => {
                 SymbolDB::get_string(i1) == SymbolDB::get_string(i2)
             }
+            ((Tag::BigInt, i1), (Tag::BigInt, i2)) => {
+                SymbolDB::get_big_int(i1) == SymbolDB::get_big_int(i2)
+            }
+            ((Tag::Rat, i1), (Tag::Rat, i2)) => {
+                SymbolDB::get_rational(i1) == SymbolDB::get_rational(i2)
+            }
 
             _ => self[addr1] == self[addr2],
         }
     }
 
+    /// Check whether two terms are variants: structurally equal up to a
+    /// consistent one-to-one renaming of their variables. `pairs` records the
+    /// variable pairings made so far, so it should be shared across several
+    /// checks that must agree on one renaming.
+    fn term_variant(&self, addr1: usize, addr2: usize, pairs: &mut Vec<(usize, usize)>) -> bool {
+        let addr1 = self.deref_addr(addr1);
+        let addr2 = self.deref_addr(addr2);
+        let addr1 = if let (Tag::Str, ptr) = self[addr1] { ptr } else { addr1 };
+        let addr2 = if let (Tag::Str, ptr) = self[addr2] { ptr } else { addr2 };
+        match (self[addr1], self[addr2]) {
+            ((Tag::Ref, _), (Tag::Ref, _)) => {
+                match pairs.iter().find(|(v1, v2)| *v1 == addr1 || *v2 == addr2) {
+                    Some(&pair) => pair == (addr1, addr2),
+                    None => {
+                        pairs.push((addr1, addr2));
+                        true
+                    }
+                }
+            }
+            ((Tag::Lis, p1), (Tag::Lis, p2)) => {
+                self.term_variant(p1, p2, pairs) && self.term_variant(p1 + 1, p2 + 1, pairs)
+            }
+            (cell1 @ (Tag::Comp | Tag::Tup | Tag::Set, _), cell2) if cell1 == cell2 => self
+                .str_iterator(addr1)
+                .zip(self.str_iterator(addr2))
+                .all(|(addr1, addr2)| self.term_variant(addr1, addr2, pairs)),
+            ((Tag::Stri, i1), (Tag::Stri, i2)) => {
+                SymbolDB::get_string(i1) == SymbolDB::get_string(i2)
+            }
+            ((Tag::BigInt, i1), (Tag::BigInt, i2)) => {
+                SymbolDB::get_big_int(i1) == SymbolDB::get_big_int(i2)
+            }
+            ((Tag::Rat, i1), (Tag::Rat, i2)) => {
+                SymbolDB::get_rational(i1) == SymbolDB::get_rational(i2)
+            }
+            (cell1, cell2) => cell1 == cell2,
+        }
+    }
+
+    /// Compare two terms in the standard order of terms:
+    /// `Var < Number < Atom < String < Compound`.
+    ///
+    /// Variables compare by address. Numbers compare by value, with a float
+    /// before an equal integer or rational. Atoms (including `[]`) and strings compare
+    /// alphabetically. Compound terms compare by arity, then name, then
+    /// arguments left to right; lists compare as `'[|]'/2`, tuples as `'()'/N`
+    /// and sets as `'{}'/N` with their elements in standard order.
+    fn term_compare(&self, addr1: usize, addr2: usize) -> Ordering {
+        let addr1 = self.deref_addr(addr1);
+        let addr2 = self.deref_addr(addr2);
+        let addr1 = if let (Tag::Str, ptr) = self[addr1] { ptr } else { addr1 };
+        let addr2 = if let (Tag::Str, ptr) = self[addr2] { ptr } else { addr2 };
+        let (rank1, rank2) = (order_rank(self[addr1].0), order_rank(self[addr2].0));
+        if rank1 != rank2 {
+            return rank1.cmp(&rank2);
+        }
+        match (self[addr1], self[addr2]) {
+            ((Tag::Int, i1), (Tag::Int, i2)) => (i1 as isize).cmp(&(i2 as isize)),
+            (cell1, cell2) if order_rank(cell1.0) == 1 => {
+                let (n1, n2) = (Number::from_cell(cell1), Number::from_cell(cell2));
+                n1.partial_cmp(&n2)
+                    .unwrap_or(Ordering::Equal)
+                    // A float sorts before an exact number of equal value
+                    .then_with(|| (cell1.0 != Tag::Flt).cmp(&(cell2.0 != Tag::Flt)))
+            }
+            ((Tag::Stri, i1), (Tag::Stri, i2)) => {
+                SymbolDB::get_string(i1).cmp(&SymbolDB::get_string(i2))
+            }
+            ((Tag::Con | Tag::ELis, _), (Tag::Con | Tag::ELis, _)) => {
+                self.atom_name(addr1).cmp(&self.atom_name(addr2))
+            }
+            ((Tag::Ref | Tag::Arg | Tag::AVar, _), _) => addr1.cmp(&addr2),
+            _ => {
+                let (arity1, name1, args1) = self.compound_parts(addr1);
+                let (arity2, name2, args2) = self.compound_parts(addr2);
+                arity1
+                    .cmp(&arity2)
+                    .then_with(|| match (name1, name2) {
+                        (Some(n1), Some(n2)) => n1.cmp(&n2),
+                        (Some(_), None) => Ordering::Greater,
+                        (None, Some(_)) => Ordering::Less,
+                        (None, None) => self.term_compare(addr1 + 1, addr2 + 1),
+                    })
+                    .then_with(|| {
+                        args1
+                            .into_iter()
+                            .zip(args2)
+                            .map(|(a1, a2)| self.term_compare(a1, a2))
+                            .find(|ord| ord.is_ne())
+                            .unwrap_or(Ordering::Equal)
+                    })
+            }
+        }
+    }
+
+    /// Name of the atom (`Con` or `ELis`) at `addr`.
+    fn atom_name(&self, addr: usize) -> Arc<str> {
+        match self[addr] {
+            (Tag::Con, id) => SymbolDB::get_const(id),
+            _ => "[]".into(),
+        }
+    }
+
+    /// Arity, functor name and argument addresses of a compound-like term at
+    /// `addr`. The name is `None` when the functor is a variable.
+    fn compound_parts(&self, addr: usize) -> (usize, Option<Arc<str>>, Vec<usize>) {
+        match self[addr] {
+            (Tag::Lis, ptr) => (2, Some("[|]".into()), vec![ptr, ptr + 1]),
+            (Tag::Tup, len) => (len, Some("()".into()), self.str_iterator(addr).collect()),
+            (Tag::Set, len) => {
+                // Sets are unordered, so their elements compare in standard order
+                let mut elements: Vec<usize> = self.str_iterator(addr).collect();
+                elements.sort_by(|&a, &b| self.term_compare(a, b));
+                (len, Some("{}".into()), elements)
+            }
+            (Tag::Comp, len) => {
+                let functor = self.deref_addr(addr + 1);
+                let name = match self[functor] {
+                    (Tag::Con, id) => Some(SymbolDB::get_const(id)),
+                    _ => None,
+                };
+                (len - 1, name, self.str_iterator(addr).skip(1).collect())
+            }
+            cell => unreachable!("compound_parts: not a compound term {cell:?}"),
+        }
+    }
+
     /**Debug function for printing formatted string of current heap state */
     fn _print_heap(&self) {
         let w = 6;
@@ -471,6 +625,10 @@ pub trait Heap: IndexMut<usize, Output = Cell> + Index<Range<usize>, Output = [C
                     let value: fsize = unsafe { mem::transmute_copy(&value) };
                     println!("[{i:3}]|{tag:w$?}|{value:w$}|")
                 }
+                Tag::BigInt | Tag::Rat => {
+                    let number = Number::from_cell((tag, value));
+                    println!("[{i:3}]|{tag:w$?}|{value:w$}| {number}")
+                }
                 Tag::Tup => println!("[{i:3}]| Tup |{value:w$}| {}", self.tuple_string(i)),
                 Tag::Set => println!("[{i:3}]| Set |{value:w$}| {}", self.set_string(i)),
                 Tag::Stri => println!(
@@ -491,15 +649,17 @@ pub trait Heap: IndexMut<usize, Output = Cell> + Index<Range<usize>, Output = [C
         loop {
             buffer += &self.term_string(pointer);
 
-            match self[pointer + 1].0 {
+            // The tail may be a variable bound to the rest of the list
+            let tail = self.deref_addr(pointer + 1);
+            match self[tail].0 {
                 Tag::Lis => {
                     buffer += ",";
-                    pointer = self[pointer + 1].1
+                    pointer = self[tail].1
                 }
                 Tag::ELis => break,
                 _ => {
                     buffer += "|";
-                    buffer += &self.term_string(pointer + 1);
+                    buffer += &self.term_string(tail);
                     break;
                 }
             }
@@ -580,11 +740,23 @@ pub trait Heap: IndexMut<usize, Output = Cell> + Index<Range<usize>, Output = [C
             Tag::Set => self.set_string(addr),
             Tag::Str => self.term_string(self[addr].1),
             Tag::Stri => format!("\"{}\"", SymbolDB::get_string(self[addr].1)),
+            Tag::BigInt | Tag::Rat => Number::from_cell(self[addr]).to_string(),
             Tag::AVar => "_".into(),
         }
     }
 }
 
+/// Rank of a cell's tag in the standard order of terms.
+fn order_rank(tag: Tag) -> u8 {
+    match tag {
+        Tag::Ref | Tag::Arg | Tag::AVar => 0,
+        Tag::Int | Tag::BigInt | Tag::Rat | Tag::Flt => 1,
+        Tag::Con | Tag::ELis => 2,
+        Tag::Stri => 3,
+        Tag::Comp | Tag::Tup | Tag::Set | Tag::Lis | Tag::Str => 4,
+    }
+}
+
 impl Heap for Vec<Cell> {
     fn heap_push(&mut self, cell: Cell) -> usize {
         let i = self.len();
@@ -3,8 +3,10 @@
 //! The heap stores Prolog terms as flat arrays of [`Cell`](crate::heap::heap::Cell) values.
 //! [`SymbolDB`](crate::heap::symbol_db::SymbolDB) provides the global mapping between string
 //! symbols and numeric IDs. [`QueryHeap`](crate::heap::query_heap::QueryHeap) extends the static
-//! program heap with mutable storage for proof search.
+//! program heap with mutable storage for proof search, and is garbage
+//! collected during long proofs.
 
+pub(crate) mod gc;
 pub mod heap;
 pub mod query_heap;
 pub mod symbol_db;
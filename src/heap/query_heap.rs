@@ -1,10 +1,16 @@
-use crate::heap::{heap::Tag, symbol_db::SymbolDB};
+use crate::{
+    heap::{heap::Tag, symbol_db::SymbolDB},
+    predicate_modules::maths::Number,
+};
 use fsize::fsize;
 use std::{
     collections::HashMap,
     mem,
     ops::{Index, IndexMut, Range},
-    sync::atomic::{AtomicUsize, Ordering::Acquire},
+    sync::{
+        atomic::{AtomicUsize, Ordering::Acquire},
+        Arc,
+    },
 };
 
 use super::heap::{Cell, Heap};
@@ -18,6 +24,9 @@ static HEAP_ID_COUNTER: AtomicUsize = AtomicUsize::new(1);
 /// branching via an optional parent pointer for backtracking.
 pub struct QueryHeap<'a> {
     id: usize,
+    /// The table holding this heap's symbols, which are released from it
+    /// when the heap is dropped.
+    symbols: Arc<SymbolDB>,
     pub(crate) cells: Vec<Cell>,
     prog_cells: &'a [Cell],
     // TODO: handle branching query heap multi-threading
@@ -29,6 +38,7 @@ impl<'a> QueryHeap<'a> {
         let id = HEAP_ID_COUNTER.fetch_add(1, Acquire);
         QueryHeap {
             id,
+            symbols: SymbolDB::current(),
             cells: Vec::new(),
             prog_cells,
             root,
@@ -51,6 +61,13 @@ impl<'a> QueryHeap<'a> {
         }
     }
 
+    /// Whether the cell at `addr` is one of this heap's own cells, rather than
+    /// part of the shared program heap or a parent heap, and so may be
+    /// overwritten.
+    pub fn is_writable(&self, addr: usize) -> bool {
+        addr >= self.heap_len() - self.cells.len()
+    }
+
     /// Duplicate term from self, tracking variable identity
     /// via `ref_map`. Unbound Ref cells in `self` are mapped to fresh Ref
     /// cells in `self`; the same source Ref always maps to the same target Ref.
@@ -118,7 +135,7 @@ impl<'a> QueryHeap<'a> {
                     new_addr
                 }
             }
-            (Tag::Arg | Tag::Con | Tag::Int | Tag::Flt | Tag::Stri | Tag::ELis, _) => {
+            (Tag::Arg | Tag::AVar | Tag::Con | Tag::Int | Tag::BigInt | Tag::Rat | Tag::Flt | Tag::Stri | Tag::ELis, _) => {
                 self.heap_push(self[addr]);
                 self.heap_len() - 1
             }
@@ -239,6 +256,7 @@ impl Heap for QueryHeap<'_> {
             Tag::Set => self.set_string(addr),
             Tag::Str => self.term_string(self[addr].1),
             Tag::Stri => format!("\"{}\"", SymbolDB::get_string(self[addr].1)),
+            Tag::BigInt | Tag::Rat => Number::from_cell(self[addr]).to_string(),
             Tag::AVar => "_".into(),
         }
     }
@@ -254,6 +272,12 @@ impl Heap for QueryHeap<'_> {
     }
 }
 
+impl Drop for QueryHeap<'_> {
+    fn drop(&mut self) {
+        self.symbols.release_heap(self.id);
+    }
+}
+
 impl Index<usize> for QueryHeap<'_> {
     type Output = Cell;
 
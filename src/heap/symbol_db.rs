@@ -1,36 +1,40 @@
-//! Global symbol database.
+//! Symbol database.
 //!
 //! All constant and variable symbols are interned here. Heap cells store
 //! integer IDs rather than strings; this module provides the mapping in
 //! both directions.
+//!
+//! Each engine owns its own table, held by its
+//! [`PredicateTable`](crate::program::predicate_table::PredicateTable). The
+//! static functions of [`SymbolDB`] use the table the current thread has
+//! entered with [`SymbolDB::enter`], or a default table on threads that have
+//! entered none.
 
 use std::{
+    cell::RefCell,
     collections::HashMap,
-    sync::{Arc, RwLock},
+    fmt,
+    hash::{BuildHasher, RandomState},
+    marker::PhantomData,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex, OnceLock, RwLock,
+    },
 };
 
 use lazy_static::lazy_static;
+use num_bigint::BigInt;
+use num_rational::BigRational;
 
 const KNOWN_SYMBOLS: &[&str] = &[
     "false",
     "true", // indices 0, 1
-    "+",
-    "-",
-    "*",
-    "/",
-    "**", // indices 2-6
-    "cos",
-    "sin",
-    "tan",
-    "acos",
-    "asin",
-    "atan", // indices 7-12
-    "log",
-    "abs",
-    "round",
-    "sqrt",
-    "to_degrees",
-    "to_radians", // indices 13-18
+    "!",    // index 2
+    ";",
+    "->",
+    "*->",
+    "$softcut", // indices 3-6
+    "catch",    // index 7
 ];
 
 /// Compute the symbol ID for a known symbol at compile time
@@ -38,83 +42,380 @@ pub const fn known_symbol_id(index: usize) -> usize {
     isize::MAX as usize + index
 }
 
+/// Number of independently locked parts the hashed maps are split into, so
+/// threads interning different symbols rarely wait on each other.
+const SHARDS: usize = 16;
+
+/// Size of the first chunk of a [`Slots`]; each later chunk doubles.
+const FIRST_CHUNK: usize = 64;
+
+/// Heap id owning the cells of the program, whose symbols live as long as
+/// the table.
+const PROGRAM_HEAP: usize = 0;
+
 lazy_static! {
-    static ref SYMBOLS: RwLock<SymbolDB> = RwLock::new(SymbolDB {
-        const_symbols: KNOWN_SYMBOLS
-            .iter()
-            .map(|&symbol| symbol.to_string().into())
-            .collect(),
-        var_symbol_map: HashMap::new(),
-        strings: Vec::new(),
-    });
+    /// The table of threads that have not entered an engine's table.
+    static ref DEFAULT_SYMBOLS: Arc<SymbolDB> = Arc::new(SymbolDB::new());
+}
+
+thread_local! {
+    static CURRENT: RefCell<Arc<SymbolDB>> = RefCell::new(DEFAULT_SYMBOLS.clone());
+}
+
+/// Variable names by address, for each heap.
+type VarSymbols = HashMap<usize, HashMap<usize, Arc<str>>>;
+
+/// Append-only storage that can be read without locking.
+///
+/// Entries are kept in chunks of doubling size which never move once
+/// allocated, so a reference to an entry stays valid while others are
+/// pushed.
+struct Slots<T> {
+    chunks: [OnceLock<Box<[OnceLock<T>]>>; usize::BITS as usize],
+    len: AtomicUsize,
+}
+
+impl<T> Default for Slots<T> {
+    fn default() -> Self {
+        Slots {
+            chunks: [const { OnceLock::new() }; usize::BITS as usize],
+            len: AtomicUsize::new(0),
+        }
+    }
+}
+
+impl<T> Slots<T> {
+    /// The chunk holding `index` and the position of `index` within it.
+    fn locate(index: usize) -> (usize, usize) {
+        let chunk = (index / FIRST_CHUNK + 1).ilog2() as usize;
+        (chunk, index - FIRST_CHUNK * ((1 << chunk) - 1))
+    }
+
+    fn get(&self, index: usize) -> Option<&T> {
+        let (chunk, offset) = Self::locate(index);
+        self.chunks[chunk].get()?.get(offset)?.get()
+    }
+
+    /// Store `value` in a new slot and return its index.
+    fn push(&self, value: T) -> usize {
+        let index = self.len.fetch_add(1, Ordering::AcqRel);
+        let (chunk, offset) = Self::locate(index);
+        let slots = self.chunks[chunk]
+            .get_or_init(|| (0..FIRST_CHUNK << chunk).map(|_| OnceLock::new()).collect());
+        if slots[offset].set(value).is_err() {
+            unreachable!("symbol slot {index} set twice")
+        }
+        index
+    }
+}
+
+/// A value and the heap that owns it.
+struct Owned<T> {
+    value: RwLock<Option<T>>,
+    owner: AtomicUsize,
+}
+
+/// Values created on heaps, such as string literals. The values of a query
+/// heap are dropped when it is released and their slots reused.
+struct HeapValues<T> {
+    slots: Slots<Owned<T>>,
+    free: Mutex<Vec<usize>>,
+    by_heap: [Mutex<HashMap<usize, Vec<usize>>>; SHARDS],
+}
+
+impl<T> Default for HeapValues<T> {
+    fn default() -> Self {
+        HeapValues {
+            slots: Slots::default(),
+            free: Mutex::default(),
+            by_heap: Default::default(),
+        }
+    }
 }
 
-/// Global symbol table.
+impl<T: Clone> HeapValues<T> {
+    fn get(&self, index: usize) -> Option<T> {
+        self.slots.get(index)?.value.read().unwrap().clone()
+    }
+
+    fn set(&self, value: T, heap_id: usize) -> usize {
+        let free = self.free.lock().unwrap().pop();
+        let index = match free {
+            Some(index) => {
+                let slot = self.slots.get(index).unwrap();
+                *slot.value.write().unwrap() = Some(value);
+                slot.owner.store(heap_id, Ordering::Release);
+                index
+            }
+            None => self.slots.push(Owned {
+                value: RwLock::new(Some(value)),
+                owner: AtomicUsize::new(heap_id),
+            }),
+        };
+        if heap_id != PROGRAM_HEAP {
+            self.by_heap[heap_id % SHARDS]
+                .lock()
+                .unwrap()
+                .entry(heap_id)
+                .or_default()
+                .push(index);
+        }
+        index
+    }
+
+    /// Hand the value at `index` to the program heap.
+    fn keep(&self, index: usize) {
+        if let Some(slot) = self.slots.get(index) {
+            slot.owner.store(PROGRAM_HEAP, Ordering::Release);
+        }
+    }
+
+    /// Drop the values the heap `heap_id` still owns.
+    fn release(&self, heap_id: usize) {
+        let indices = self.by_heap[heap_id % SHARDS]
+            .lock()
+            .unwrap()
+            .remove(&heap_id)
+            .unwrap_or_default();
+        let mut free = Vec::new();
+        for index in indices {
+            let slot = self.slots.get(index).unwrap();
+            if slot.owner.load(Ordering::Acquire) == heap_id {
+                *slot.value.write().unwrap() = None;
+                free.push(index);
+            }
+        }
+        self.free.lock().unwrap().extend(free);
+    }
+}
+
+/// Symbol table of an engine.
 ///
 /// Maps between string representations and numeric IDs so the heap can
-/// work entirely with `usize` values. Constants, variables, and string
-/// literals are stored separately.
+/// work entirely with `usize` values. Constants, variables, string
+/// literals, and numbers too large for a cell are stored separately.
+///
+/// Constants are found by hash and read without locking. Variable names,
+/// string literals and big numbers belong to the heap that created them and
+/// are reclaimed by [`SymbolDB::release_heap`] when a query heap is dropped.
+/// A string or number copied into another heap, such as a dynamic clause or
+/// an answer table, is kept as long as the table.
 pub struct SymbolDB {
-    const_symbols: Vec<Arc<str>>,
-    var_symbol_map: HashMap<(usize, usize), Arc<str>>,
-    strings: Vec<Arc<str>>,
+    const_symbols: Slots<Arc<str>>,
+    const_ids: [RwLock<HashMap<Arc<str>, usize>>; SHARDS],
+    hasher: RandomState,
+    var_symbols: [RwLock<VarSymbols>; SHARDS],
+    strings: HeapValues<Arc<str>>,
+    big_ints: HeapValues<Arc<BigInt>>,
+    rationals: HeapValues<Arc<BigRational>>,
+}
+
+/// Keeps a table entered on the current thread until dropped, when the
+/// table entered before it is restored.
+pub struct SymbolScope {
+    previous: Arc<SymbolDB>,
+    // The scope belongs to the thread it was entered on
+    _thread: PhantomData<*const ()>,
+}
+
+impl Drop for SymbolScope {
+    fn drop(&mut self) {
+        CURRENT.with(|current| current.replace(self.previous.clone()));
+    }
+}
+
+impl fmt::Debug for SymbolDB {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SymbolDB").finish_non_exhaustive()
+    }
+}
+
+impl Default for SymbolDB {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl SymbolDB {
-    pub fn set_const(symbol: impl Into<Arc<str>>) -> usize {
-        let mut symbols = SYMBOLS.write().unwrap();
-        let symbol: Arc<str> = symbol.into();
-        match symbols.const_symbols.iter().position(|e| *e == symbol) {
-            Some(i) => i + isize::MAX as usize,
-            None => {
-                symbols.const_symbols.push(symbol);
-                symbols.const_symbols.len() - 1 + isize::MAX as usize
-            }
+    /// An empty table holding only the known symbols.
+    pub fn new() -> Self {
+        let symbols = SymbolDB {
+            const_symbols: Slots::default(),
+            const_ids: Default::default(),
+            hasher: RandomState::new(),
+            var_symbols: Default::default(),
+            strings: HeapValues::default(),
+            big_ints: HeapValues::default(),
+            rationals: HeapValues::default(),
+        };
+        for &symbol in KNOWN_SYMBOLS {
+            symbols.intern(symbol.into());
         }
+        symbols
+    }
+
+    /// Use this table for the static functions called on the current thread
+    /// until the returned scope is dropped.
+    pub fn enter(self: &Arc<Self>) -> SymbolScope {
+        SymbolScope {
+            previous: CURRENT.with(|current| current.replace(self.clone())),
+            _thread: PhantomData,
+        }
+    }
+
+    /// The table the current thread has entered.
+    pub fn current() -> Arc<SymbolDB> {
+        CURRENT.with(|current| current.borrow().clone())
+    }
+
+    fn with<R>(f: impl FnOnce(&SymbolDB) -> R) -> R {
+        CURRENT.with(|current| f(&current.borrow()))
+    }
+
+    fn intern(&self, symbol: Arc<str>) -> usize {
+        let shard = &self.const_ids[self.hasher.hash_one(&symbol) as usize % SHARDS];
+        if let Some(&id) = shard.read().unwrap().get(&symbol) {
+            return id;
+        }
+        let mut ids = shard.write().unwrap();
+        if let Some(&id) = ids.get(&symbol) {
+            return id;
+        }
+        let id = self.const_symbols.push(symbol.clone()) + isize::MAX as usize;
+        ids.insert(symbol, id);
+        id
+    }
+
+    pub fn set_const(symbol: impl Into<Arc<str>>) -> usize {
+        let symbol = symbol.into();
+        Self::with(|symbols| symbols.intern(symbol))
     }
 
     pub fn set_var(symbol: impl Into<Arc<str>>, addr: usize, heap_id: usize) {
-        SYMBOLS
-            .write()
-            .unwrap()
-            .var_symbol_map
-            .insert((addr, heap_id), symbol.into());
+        let symbol = symbol.into();
+        Self::with(|symbols| {
+            symbols.var_symbols[heap_id % SHARDS]
+                .write()
+                .unwrap()
+                .entry(heap_id)
+                .or_default()
+                .insert(addr, symbol);
+        })
     }
 
     pub fn get_const(id: usize) -> Arc<str> {
-        SYMBOLS.read().unwrap().const_symbols[id - isize::MAX as usize].clone()
+        Self::with(|symbols| {
+            symbols
+                .const_symbols
+                .get(id - isize::MAX as usize)
+                .unwrap_or_else(|| panic!("unknown constant symbol {id}"))
+                .clone()
+        })
     }
 
     pub fn get_var(addr: usize, heap_id: usize) -> Option<Arc<str>> {
-        let vars = &SYMBOLS.read().unwrap().var_symbol_map;
-        if let Some(symbol) = vars.get(&(addr, heap_id)) {
-            Some(symbol.clone())
-        } else {
-            None
-        }
+        Self::with(|symbols| {
+            symbols.var_symbols[heap_id % SHARDS]
+                .read()
+                .unwrap()
+                .get(&heap_id)?
+                .get(&addr)
+                .cloned()
+        })
+    }
+
+    /// Move the variable names of the heap `heap_id` to the addresses
+    /// `relocate` gives, forgetting those it gives none for.
+    pub(crate) fn relocate_vars(heap_id: usize, relocate: impl Fn(usize) -> Option<usize>) {
+        Self::with(|symbols| {
+            let mut shard = symbols.var_symbols[heap_id % SHARDS].write().unwrap();
+            if let Some(vars) = shard.get_mut(&heap_id) {
+                *vars = vars
+                    .drain()
+                    .filter_map(|(addr, symbol)| Some((relocate(addr)?, symbol)))
+                    .collect();
+            }
+        })
     }
 
     pub fn get_string(index: usize) -> Arc<str> {
-        // TODO: make this more efficient
-        SYMBOLS.read().unwrap().strings.get(index).unwrap().clone()
+        Self::with(|symbols| symbols.strings.get(index))
+            .unwrap_or_else(|| panic!("unknown string {index}"))
     }
 
-    pub fn set_string(value: String) -> usize {
-        let mut write_gaurd = SYMBOLS.write().unwrap();
-        write_gaurd.strings.push(value.into());
-        write_gaurd.strings.len() - 1
+    /// Store a string literal created on the heap `heap_id`.
+    pub fn set_string(value: String, heap_id: usize) -> usize {
+        Self::with(|symbols| symbols.strings.set(value.into(), heap_id))
+    }
+
+    /// Keep the string at `index` after its heap is released, because it
+    /// has been copied somewhere that outlives the heap.
+    pub fn keep_string(index: usize) {
+        Self::with(|symbols| symbols.strings.keep(index))
+    }
+
+    /// Forget the variable names, strings and big numbers of the heap
+    /// `heap_id`.
+    pub fn release_heap(&self, heap_id: usize) {
+        self.var_symbols[heap_id % SHARDS]
+            .write()
+            .unwrap()
+            .remove(&heap_id);
+        self.strings.release(heap_id);
+        self.big_ints.release(heap_id);
+        self.rationals.release(heap_id);
+    }
+
+    pub fn get_big_int(index: usize) -> Arc<BigInt> {
+        Self::with(|symbols| symbols.big_ints.get(index))
+            .unwrap_or_else(|| panic!("unknown big integer {index}"))
+    }
+
+    /// Store a big integer created on the heap `heap_id`.
+    pub fn set_big_int(value: BigInt, heap_id: usize) -> usize {
+        Self::with(|symbols| symbols.big_ints.set(value.into(), heap_id))
+    }
+
+    /// Keep the big integer at `index` after its heap is released.
+    pub fn keep_big_int(index: usize) {
+        Self::with(|symbols| symbols.big_ints.keep(index))
+    }
+
+    pub fn get_rational(index: usize) -> Arc<BigRational> {
+        Self::with(|symbols| symbols.rationals.get(index))
+            .unwrap_or_else(|| panic!("unknown rational {index}"))
+    }
+
+    /// Store a rational created on the heap `heap_id`.
+    pub fn set_rational(value: BigRational, heap_id: usize) -> usize {
+        Self::with(|symbols| symbols.rationals.set(value.into(), heap_id))
+    }
+
+    /// Keep the rational at `index` after its heap is released.
+    pub fn keep_rational(index: usize) {
+        Self::with(|symbols| symbols.rationals.keep(index))
     }
 
     pub fn _see_var_map() {
-        let symbols = SYMBOLS.read().unwrap();
-        for (k, v) in &symbols.var_symbol_map {
-            println!("{k:?}:\t{v}")
-        }
+        Self::with(|symbols| {
+            for shard in &symbols.var_symbols {
+                for (heap_id, vars) in shard.read().unwrap().iter() {
+                    for (addr, symbol) in vars {
+                        println!("{:?}:\t{symbol}", (addr, heap_id))
+                    }
+                }
+            }
+        })
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+
+    use num_bigint::BigInt;
+    use num_rational::BigRational;
+
     use super::super::{heap::_CON_PTR, symbol_db::SymbolDB};
 
     #[test]
@@ -145,7 +446,75 @@ mod tests {
 
     #[test]
     fn insert_string() {
-        let idx = SymbolDB::set_string("some string".into());
+        let idx = SymbolDB::set_string("some string".into(), 0);
         assert_eq!(*SymbolDB::get_string(idx), *"some string");
     }
+
+    #[test]
+    fn many_constants() {
+        let ids: Vec<usize> = (0..1000)
+            .map(|i| SymbolDB::set_const(format!("many_constants_{i}")))
+            .collect();
+        for (i, &id) in ids.iter().enumerate() {
+            assert_eq!(&*SymbolDB::get_const(id), format!("many_constants_{i}"));
+            assert_eq!(SymbolDB::set_const(format!("many_constants_{i}")), id);
+        }
+    }
+
+    #[test]
+    fn release_heap() {
+        let symbols = Arc::new(SymbolDB::new());
+        let _scope = symbols.enter();
+        let heap_id = 1;
+        SymbolDB::set_var("X", 10, heap_id);
+        SymbolDB::set_string("dropped".into(), heap_id);
+        let kept = SymbolDB::set_string("kept".into(), heap_id);
+        SymbolDB::keep_string(kept);
+        let big = BigInt::from(u64::MAX) * 5u8;
+        let dropped_big = SymbolDB::set_big_int(big.clone(), heap_id);
+        let kept_big = SymbolDB::set_big_int(big.clone(), heap_id);
+        SymbolDB::keep_big_int(kept_big);
+        let ratio = BigRational::new(2.into(), 7.into());
+        let kept_ratio = SymbolDB::set_rational(ratio.clone(), heap_id);
+        SymbolDB::keep_rational(kept_ratio);
+
+        symbols.release_heap(heap_id);
+        assert_eq!(SymbolDB::get_var(10, heap_id), None);
+        assert_eq!(*SymbolDB::get_string(kept), *"kept");
+        assert_eq!(*SymbolDB::get_big_int(kept_big), big);
+        assert_eq!(*SymbolDB::get_rational(kept_ratio), ratio);
+        assert_eq!(symbols.big_ints.get(dropped_big), None);
+    }
+
+    #[test]
+    fn separate_tables() {
+        let first = Arc::new(SymbolDB::new());
+        let second = Arc::new(SymbolDB::new());
+
+        let first_scope = first.enter();
+        let a = SymbolDB::set_const("only_in_first");
+        {
+            let _scope = second.enter();
+            let b = SymbolDB::set_const("only_in_second");
+            assert_eq!(b, a);
+            assert_eq!(&*SymbolDB::get_const(b), "only_in_second");
+            assert_eq!(&*SymbolDB::get_const(_CON_PTR + 1), "true");
+        }
+        assert!(Arc::ptr_eq(&SymbolDB::current(), &first));
+        assert_eq!(&*SymbolDB::get_const(a), "only_in_first");
+
+        drop(first_scope);
+        assert!(!Arc::ptr_eq(&SymbolDB::current(), &first));
+    }
+
+    #[test]
+    fn insert_big_number() {
+        let big = BigInt::from(u64::MAX) * 3u8;
+        let idx = SymbolDB::set_big_int(big.clone(), 0);
+        assert_eq!(*SymbolDB::get_big_int(idx), big);
+
+        let ratio = BigRational::new(1.into(), 3.into());
+        let idx = SymbolDB::set_rational(ratio.clone(), 0);
+        assert_eq!(*SymbolDB::get_rational(idx), ratio);
+    }
 }
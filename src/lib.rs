@@ -24,6 +24,7 @@
 //! - [`predicate_modules::PredReturn::False`] — deterministic failure; the engine backtracks.
 //! - [`predicate_modules::PredReturn::Success`] — success with a list of `(source, target)` variable
 //!   bindings to apply on the heap, and an optional list of new sub-goals to resolve.
+//! - [`predicate_modules::PredReturn::Throw`] — raise the term at a heap address as an exception.
 //!
 //! For the common case of a simple boolean check, `bool` converts directly via
 //! `Into<PredReturn>`.
@@ -61,6 +62,14 @@
 //! }
 //! ```
 //!
+//! ### Adding arithmetic functions
+//!
+//! Functions for `is/2` and the arithmetic comparisons are kept in a table
+//! of their own. A [`predicate_modules::maths::FunctionModule`] lists
+//! `(name, arity, function)` triples, each function computing a
+//! [`predicate_modules::maths::Number`] from its evaluated arguments, and is
+//! added with [`app::App::load_functions`].
+//!
 //! ## Error handling
 //!
 //! Fallible operations return [`Result<T>`], a type alias for
@@ -75,6 +84,10 @@
 //! - [`Error::Query`] — a query could not be executed (e.g. bad goal syntax).
 //! - [`Error::BodyPred`] — a body-predicate specification in the setup is invalid.
 //! - [`Error::Module`] — a predicate module could not be loaded (e.g. duplicate predicate).
+//! - [`Error::Exception`] — a query raised an exception that no `catch/3` handled;
+//!   carries the ball term as text.
+//! - [`Error::Directive`] — a `:- Goal.` directive in loaded source failed or
+//!   raised an exception; the message starts with the file and line.
 
 /// Application builder and configuration types.
 pub mod app;
@@ -105,6 +118,8 @@ pub enum Error {
     Query(String),
     BodyPred(String),
     Module(String),
+    Exception(String),
+    Directive(String),
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -118,6 +133,8 @@ impl fmt::Display for Error {
             Self::Query(msg) => write!(f, "query error: {msg}"),
             Self::BodyPred(msg) => write!(f, "body predicate error: {msg}"),
             Self::Module(msg) => write!(f, "module error: {msg}"),
+            Self::Exception(ball) => write!(f, "uncaught exception: {ball}"),
+            Self::Directive(msg) => write!(f, "directive error: {msg}"),
         }
     }
 }
@@ -1,18 +1,22 @@
 //! Syntax tree construction: parses a token stream into an AST of clauses and terms.
+//!
+//! Terms are read by operator precedence against the stream's [`Operators`]
+//! table. `,` is handled here rather than in the table: it builds a flat
+//! conjunction tuple at priority 1000 and separates arguments, list elements
+//! and set elements.
 
 // TODO: Handle sets
 
+use super::dcg;
+use super::operators::{Operators, MAX_PRIORITY, SYMBOL_CHARS};
 use super::term::{Term, Unit};
 use super::ParserError;
 
-const INFIX_ORDER: &[&[&str]] = &[
-    &["**"],
-    &["*", "/"],
-    &["+", "-"],
-    &[
-        "==", "=\\=", "\\=", "=:=", "=~=", "is", ">", ">=", "<", "=<", "=", "=..",
-    ],
-];
+/// Priority of the `,` operator.
+const COMMA_PRIORITY: usize = 1000;
+
+/// Tokens that end a term, so an operator before them is read as an atom.
+const TERM_END: &[&str] = &[")", ",", "|", "]", "}", "."];
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum TreeClause {
@@ -20,83 +24,153 @@ pub enum TreeClause {
     Rule(Vec<Term>),
     MetaRule(Vec<Term>),
     MetaFact(Term, Term), // head and set of existentially quantified variables
+    Directive(Vec<Term>, usize), // :- Goal. and the line it starts on
 }
 
 pub struct TokenStream {
     tokens: Vec<String>,
     index: usize,
     line: usize,
+    operators: Operators,
+}
+
+fn is_symbolic(token: &str) -> bool {
+    !token.is_empty() && token.chars().all(|c| SYMBOL_CHARS.contains(&c))
+}
+
+/// A single goal stays as it is; a conjunction tuple becomes its goals.
+fn conjunction_goals(term: Term) -> Vec<Term> {
+    match term {
+        Term::Tuple(goals) if !goals.is_empty() => goals,
+        term => vec![term],
+    }
+}
+
+fn unconstrained_vars(vars: &[Term]) -> bool {
+    vars.iter()
+        .all(|var| matches!(var, Term::Unit(Unit::Variable(_))))
 }
 
 impl TokenStream {
+    /// A stream read with the standard operator table.
     pub fn new(tokens: Vec<String>) -> Self {
         TokenStream {
             tokens,
             index: 0,
-            line: 0,
+            line: 1,
+            operators: Operators::default(),
+        }
+    }
+
+    /// Read the stream with `operators` instead of the standard table.
+    pub fn with_operators(mut self, operators: Operators) -> Self {
+        self.operators = operators;
+        self
+    }
+
+    /// Replace the operator table for the rest of the stream, as after an
+    /// `op/3` directive.
+    pub fn set_operators(&mut self, operators: Operators) {
+        self.operators = operators;
+    }
+
+    /// Length of the next token at the start of `token`. The tokeniser keeps
+    /// runs of symbol characters together, so `=>` can be read as one token
+    /// once it is declared an operator; until then a run is split into the
+    /// longest operators it starts with.
+    fn token_len(&self, token: &str) -> usize {
+        if token.len() > 1 && is_symbolic(token) {
+            self.operators.symbol_prefix(token)
+        } else {
+            token.len()
+        }
+    }
+
+    fn skip_newlines(&mut self) {
+        while self.tokens.get(self.index).is_some_and(|token| token == "\n") {
+            self.index += 1;
+            self.line += 1;
         }
     }
 
     pub fn next(&mut self) -> Option<&str> {
-        loop {
-            if self.index == self.tokens.len() {
-                return None;
-            }
-            match self.tokens[self.index].as_str() {
-                "\n" => {
-                    self.index += 1;
-                    self.line += 1
-                }
-                token => {
-                    self.index += 1;
-                    return Some(token);
-                }
-            }
+        self.skip_newlines();
+        let token = self.tokens.get(self.index)?;
+        let len = self.token_len(token);
+        if len < token.len() {
+            let rest = self.tokens[self.index].split_off(len);
+            self.tokens.insert(self.index + 1, rest);
         }
+        self.index += 1;
+        Some(&self.tokens[self.index - 1])
     }
 
     pub fn peek(&self) -> Option<&str> {
+        self.peek_nth(0)
+    }
+
+    /// The token `n` places ahead without consuming anything.
+    fn peek_nth(&self, mut n: usize) -> Option<&str> {
         let mut index = self.index;
+        let mut offset = 0;
         loop {
-            if index == self.tokens.len() {
-                return None;
+            let token = self.tokens.get(index)?;
+            if token == "\n" {
+                index += 1;
+                continue;
             }
-            match self.tokens[index].as_str() {
-                "\n" => {
-                    index += 1;
-                }
-                token => {
-                    return Some(token);
-                }
+            let rest = &token[offset..];
+            let len = self.token_len(rest);
+            if n == 0 {
+                return Some(&rest[..len]);
+            }
+            n -= 1;
+            if len < rest.len() {
+                offset += len;
+            } else {
+                index += 1;
+                offset = 0;
             }
         }
     }
-}
 
-fn is_operator(token: &str) -> bool {
-    INFIX_ORDER.iter().any(|group| group.contains(&token))
-}
-
-fn infix_order(operator: &str) -> usize {
-    INFIX_ORDER
-        .iter()
-        .position(|ops| ops.contains(&operator))
-        .unwrap()
-}
+    /// Whether the next token, `token`, starts a symbol character run that
+    /// isn't made of operators, so in operand position the whole run is one
+    /// atom: `=>` in `op(700, xfx, =>)` before `=>` is an operator. A `.`
+    /// ending the run is left to end the clause.
+    fn atom_run(&mut self, token: &str) -> bool {
+        self.skip_newlines();
+        let run = &self.tokens[self.index];
+        if run.len() == token.len() || !is_symbolic(run) {
+            return false;
+        }
+        if run.ends_with('.') {
+            self.tokens[self.index].pop();
+            self.tokens.insert(self.index + 1, ".".into());
+        }
+        true
+    }
 
-fn resolve_infix(term_stack: &mut Vec<Term>, op_stack: &mut Vec<String>, max_prescendence: usize) {
-    while let Some(p) = op_stack.last().map(|operator| infix_order(&operator)) {
-        if p > max_prescendence {
-            break;
+    /// Whether the token `n` places ahead can start an operand.
+    fn term_starts(&self, n: usize) -> bool {
+        match self.peek_nth(n) {
+            None => false,
+            Some(token) if TERM_END.contains(&token) => false,
+            Some(token) => {
+                self.operators.infix(token).is_none()
+                    || self.operators.prefix(token).is_some()
+            }
         }
-        let op = op_stack.pop().unwrap();
-        let right = term_stack.pop().unwrap();
-        let left = term_stack.pop().unwrap();
-        term_stack.push(Term::Atom(Unit::Constant(op), vec![left, right]));
     }
-}
 
-impl TokenStream {
+    /// Split a negative number like the `-1` of `3-1`, glued together by the
+    /// tokeniser, into a minus sign and a number.
+    fn split_sign(&mut self) {
+        self.skip_newlines();
+        let number = self.tokens[self.index].split_off(1);
+        self.tokens.insert(self.index + 1, number);
+    }
+
     fn expect(&mut self, value: &str) -> Result<(), ParserError> {
         match self.next() {
             Some(token) if token == value => Ok(()),
@@ -114,7 +188,7 @@ impl TokenStream {
     fn consume_args(&mut self) -> Result<Vec<Term>, ParserError> {
         let mut args = Vec::new();
         loop {
-            args.push(self.parse_expression()?);
+            args.push(self.parse(MAX_PRIORITY, false)?.0);
             match self.peek() {
                 Some(")" | "|" | "]" | "}") => return Ok(args), // TODO: end consuming args based on certain conditions, don't accept all end tokens
                 Some(",") => {
@@ -130,13 +204,92 @@ impl TokenStream {
         }
     }
 
-    pub(super) fn parse_term(&mut self) -> Result<Term, ParserError> {
+    /// Parse a single term without infix or postfix operators.
+    pub fn parse_term(&mut self) -> Result<Term, ParserError> {
+        Ok(self.parse(0, true)?.0)
+    }
+
+    /// Parse a term of any priority.
+    pub fn parse_expression(&mut self) -> Result<Term, ParserError> {
+        Ok(self.parse(MAX_PRIORITY, true)?.0)
+    }
+
+    /// Parse a term of priority at most `max`, returning it with its
+    /// priority. Without `commas` a `,` always ends the term, as it does
+    /// inside arguments, and `:-` or `;` may appear there unbracketed.
+    fn parse(&mut self, max: usize, commas: bool) -> Result<(Term, usize), ParserError> {
+        let (mut left, mut priority) = self.parse_primary(max, commas)?;
+        while let Some(token) = self.peek() {
+            if token == "," {
+                if !commas || max < COMMA_PRIORITY || priority >= COMMA_PRIORITY {
+                    break;
+                }
+                self.next();
+                let mut goals = match left {
+                    Term::Tuple(goals) if priority == COMMA_PRIORITY => goals,
+                    left => vec![left],
+                };
+                match self.parse(COMMA_PRIORITY, commas)? {
+                    (Term::Tuple(mut rest), COMMA_PRIORITY) => goals.append(&mut rest),
+                    (right, _) => goals.push(right),
+                }
+                left = Term::Tuple(goals);
+                priority = COMMA_PRIORITY;
+                continue;
+            }
+            let token = token.to_string();
+            let infix = match self.operators.infix(&token) {
+                None if token.len() > 1
+                    && token.starts_with('-')
+                    && matches!(
+                        Unit::parse_unit(&token),
+                        Some(Unit::Int(_) | Unit::BigInt(_) | Unit::Rational(_) | Unit::Float(_))
+                    ) =>
+                {
+                    self.operators.infix("-").map(|op| ("-".to_string(), op, true))
+                }
+                infix => infix.map(|op| (token.clone(), op, false)),
+            };
+            // An operator that is both infix and postfix is postfix when no
+            // operand follows it.
+            let postfix = self.operators.postfix(&token);
+            let infix = infix.filter(|_| postfix.is_none() || self.term_starts(1));
+            if let Some((name, (p, op_type), signed)) = infix {
+                let (left_max, right_max) = op_type.arg_priorities(p);
+                if p > max || priority > left_max {
+                    break;
+                }
+                if signed {
+                    self.split_sign();
+                }
+                self.next();
+                let (right, _) = self.parse(right_max, commas)?;
+                left = Term::Atom(Unit::Constant(name), vec![left, right]);
+                priority = p;
+            } else if let Some((p, op_type)) = postfix {
+                let (left_max, _) = op_type.arg_priorities(p);
+                if p > max || priority > left_max {
+                    break;
+                }
+                self.next();
+                left = Term::Atom(Unit::Constant(token), vec![left]);
+                priority = p;
+            } else {
+                break;
+            }
+        }
+        Ok((left, priority))
+    }
+
+    /// Parse an operand: a bracketed term, list, set, compound, atomic term
+    /// or prefix operator application.
+    fn parse_primary(&mut self, max: usize, commas: bool) -> Result<(Term, usize), ParserError> {
         match self.peek().ok_or(ParserError::UnexpectedEof)? {
             "{" => {
                 self.next();
                 let args = self.consume_args()?;
                 if self.next() == Some("}") {
-                    Ok(Term::Set(args))
+                    Ok((Term::Set(args), 0))
                 } else {
                     Err(ParserError::MalformedSet)
                 }
@@ -146,243 +299,202 @@ impl TokenStream {
                 let head = self.consume_args()?;
                 match self.next().ok_or(ParserError::UnexpectedEof)? {
                     "|" => {
-                        let tail = Box::new(self.parse_expression()?);
+                        let tail = Box::new(self.parse(MAX_PRIORITY, false)?.0);
                         self.expect("]")?;
-                        Ok(Term::List(head, tail))
-                    }
-                    "]" => Ok(Term::List(head, Box::new(Term::EmptyList))),
-                    token => {
-                        return Err(ParserError::UnexpectedToken {
-                            token: token.to_string(),
-                        })
+                        Ok((Term::List(head, tail), 0))
                     }
+                    "]" => Ok((Term::List(head, Box::new(Term::EmptyList)), 0)),
+                    token => Err(ParserError::UnexpectedToken {
+                        token: token.to_string(),
+                    }),
                 }
             }
             "[]" => {
                 self.next();
-                Ok(Term::EmptyList)
+                Ok((Term::EmptyList, 0))
             }
             "{}" => {
                 self.next();
-                Ok(Term::EmptySet)
+                Ok((Term::EmptySet, 0))
             }
             "()" => {
                 self.next();
-                Ok(Term::Tuple(vec![]))
+                Ok((Term::Tuple(vec![]), 0))
             }
             "(" => {
                 // Grouped expression or tuple
                 self.next();
-                let mut args = self.consume_args()?;
-                self.expect(")")?;
-                if args.len() == 1 {
-                    Ok(args.pop().unwrap())
-                } else {
-                    Ok(Term::Tuple(args))
+                let term = self.parse(MAX_PRIORITY, true)?.0;
+                match self.next() {
+                    Some(")") => Ok((term, 0)),
+                    Some(token) => Err(ParserError::UnexpectedToken {
+                        token: token.to_string(),
+                    }),
+                    None => Err(ParserError::UnexpectedEof),
                 }
             }
-            token if is_operator(token) => {
-                // Handle prefix operators (unary minus, unary plus)
-                let op = self.next().unwrap().to_string();
-                let operand = self.parse_term()?;
-                Ok(Term::Atom(Unit::Constant(op), vec![operand]))
-            }
             token => {
-                let token = token.to_string();
-                match Unit::parse_unit(self.next().unwrap()) {
-                    Some(unit @ (Unit::Constant(_) | Unit::Variable(_))) => {
-                        if self.peek() == Some("(") {
-                            self.next();
-                            let args = self.consume_args()?;
-                            self.expect(")")?;
-                            Ok(Term::Atom(unit, args))
-                        } else {
-                            Ok(Term::Unit(unit))
-                        }
+                let mut token = token.to_string();
+                if self.operators.prefix(&token).is_none() && self.atom_run(&token) {
+                    token = self.tokens[self.index].clone();
+                }
+                if let Some((p, op_type)) = self.operators.prefix(&token) {
+                    if self.peek_nth(1) != Some("(") && self.term_starts(1) {
+                        // Read leniently when the operator binds more loosely
+                        // than its context allows, as in `X = \+ a`.
+                        let p = p.min(max);
+                        let (_, right_max) = op_type.arg_priorities(p);
+                        self.next();
+                        let operand = self.parse(right_max, commas)?.0;
+                        return Ok((Term::Atom(Unit::Constant(token), vec![operand]), p));
                     }
-                    Some(unit) => Ok(Term::Unit(unit)),
-                    None => Err(ParserError::UnexpectedToken {
-                        token: token.to_string(),
-                    }),
                 }
-            }
-        }
-    }
-
-    pub(super) fn parse_expression(&mut self) -> Result<Term, ParserError> {
-        let mut op_stack = Vec::<String>::new();
-        let mut term_stack = Vec::<Term>::new();
-        loop {
-            //Consume a term
-            if self.peek() == Some("(") {
-                //Grouped Expression with brackets or tuple
-                self.next();
-                let mut args = self.consume_args()?;
-                self.expect(")")?;
-                if args.len() == 1 {
-                    term_stack.push(args.pop().unwrap());
+                if token.len() == self.tokens[self.index].len() {
+                    self.index += 1;
                 } else {
-                    term_stack.push(Term::Tuple(args));
-                }
-            } else {
-                term_stack.push(self.parse_term()?);
-            }
-
-            //Is next token an operator
-            match self.peek() {
-                Some(operator) if is_operator(operator) => {
-                    resolve_infix(&mut term_stack, &mut op_stack, infix_order(operator));
-                    op_stack.push(operator.into());
                     self.next();
                 }
-                // Some(token) => {println!("Token: {token}");break;}
-                _ => break,
+                let unit = match Unit::parse_unit(&token) {
+                    Some(unit) => unit,
+                    None if is_symbolic(&token) || token == ";" => Unit::Constant(token),
+                    None => return Err(ParserError::UnexpectedToken { token }),
+                };
+                match unit {
+                    Unit::Constant(_) | Unit::Variable(_) if self.peek() == Some("(") => {
+                        self.next();
+                        let args = self.consume_args()?;
+                        self.expect(")")?;
+                        Ok((Term::Atom(unit, args), 0))
+                    }
+                    unit => Ok((Term::Unit(unit), 0)),
+                }
             }
         }
-
-        resolve_infix(&mut term_stack, &mut op_stack, INFIX_ORDER.len());
-        term_stack.pop().ok_or(ParserError::UnexpectedEof)
     }
 
-    fn parse_body_literals(&mut self) -> Result<Vec<Term>, ParserError> {
-        let mut body = Vec::new();
-        loop {
-            body.push(self.parse_expression()?);
-            match self.next() {
-                Some(",") => continue,
-                Some(".") => break,
-                Some(token) => {
-                    return Err(ParserError::Expected {
-                        expected: "',' or '.'".into(),
-                        got: Some(token.to_string()),
-                    })
-                }
-                None => return Err(ParserError::UnexpectedEof),
-            }
+    /// Expect the `.` ending a clause or query.
+    fn expect_end(&mut self) -> Result<(), ParserError> {
+        match self.next() {
+            Some(".") => Ok(()),
+            Some(token) => Err(ParserError::Expected {
+                expected: "operator or '.'".into(),
+                got: Some(token.to_string()),
+            }),
+            None => Err(ParserError::UnexpectedEof),
         }
-        Ok(body)
     }
 
     pub fn parse_clause(&mut self) -> Result<Option<TreeClause>, ParserError> {
-        match self.peek() {
-            None => return Ok(None),
-            Some(_) => {
-                let mut literals = vec![self.parse_expression()?];
-                match self.next() {
-                    Some(":-") => {
-                        literals.append(&mut self.parse_body_literals()?);
-                        let len = literals.len();
-                        let meta_rule = match literals.last() {
-                            // Case 1: ...{P,Q,R}. — all constrained
-                            Some(Term::Set(eq_vars)) => {
-                                if eq_vars
-                                    .iter()
-                                    .any(|eq_var| !matches!(eq_var, Term::Unit(Unit::Variable(_))))
-                                {
+        if self.peek().is_none() {
+            return Ok(None);
+        }
+        self.skip_newlines();
+        let line = self.line;
+        let term = self.parse(MAX_PRIORITY, true)?.0;
+        self.expect_end()?;
+        match term {
+            Term::Atom(Unit::Constant(neck), mut args) if neck == ":-" && args.len() == 1 => {
+                Ok(Some(TreeClause::Directive(
+                    conjunction_goals(args.pop().unwrap()),
+                    line,
+                )))
+            }
+            Term::Atom(Unit::Constant(neck), mut args) if neck == ":-" && args.len() == 2 => {
+                let body = args.pop().unwrap();
+                let mut literals = args;
+                literals.append(&mut conjunction_goals(body));
+                let len = literals.len();
+                let meta_rule = match literals.last() {
+                    // Case 1: ...{P,Q,R}. — all constrained
+                    Some(Term::Set(eq_vars)) => {
+                        if !unconstrained_vars(eq_vars) {
+                            return Err(ParserError::MalformedMetaRule { detail: format!("incorrectly formatted existentially quantified variables: {:?}", eq_vars) });
+                        }
+                        true
+                    }
+                    // Case 2 or 3: last is [Q1,Q2] — unconstrained vars list
+                    Some(Term::List(vars, tail)) if matches!(tail.as_ref(), Term::EmptyList) => {
+                        if !unconstrained_vars(vars) {
+                            return Err(ParserError::MalformedMetaRule { detail: format!("unconstrained variable list should only contain variables, got {:?}", vars) });
+                        }
+                        // Case 2: ...{P},[Q1,Q2]. — check if second-to-last is a constrained set
+                        if len >= 2 {
+                            if let Term::Set(eq_vars) = &literals[len - 2] {
+                                if !unconstrained_vars(eq_vars) {
                                     return Err(ParserError::MalformedMetaRule { detail: format!("incorrectly formatted existentially quantified variables: {:?}", eq_vars) });
                                 }
-                                true
-                            }
-                            // Case 2 or 3: last is [Q1,Q2] — unconstrained vars list
-                            Some(Term::List(vars, tail))
-                                if matches!(tail.as_ref(), Term::EmptyList) =>
-                            {
-                                if vars
-                                    .iter()
-                                    .any(|v| !matches!(v, Term::Unit(Unit::Variable(_))))
-                                {
-                                    return Err(ParserError::MalformedMetaRule { detail: format!("unconstrained variable list should only contain variables, got {:?}", vars) });
-                                }
-                                // Case 2: ...{P},[Q1,Q2]. — check if second-to-last is a constrained set
-                                if len >= 2 {
-                                    if let Term::Set(eq_vars) = &literals[len - 2] {
-                                        if eq_vars.iter().any(|eq_var| {
-                                            !matches!(eq_var, Term::Unit(Unit::Variable(_)))
-                                        }) {
-                                            return Err(ParserError::MalformedMetaRule { detail: format!("incorrectly formatted existentially quantified variables: {:?}", eq_vars) });
-                                        }
-                                    }
-                                }
-                                // Case 3: ...[Q1,Q2]. — no constrained set, just unconstrained list
-                                true
                             }
-                            _ => false,
-                        };
-                        if meta_rule {
-                            Ok(Some(TreeClause::MetaRule(literals)))
-                        } else {
-                            Ok(Some(TreeClause::Rule(literals)))
                         }
+                        // Case 3: ...[Q1,Q2]. — no constrained set, just unconstrained list
+                        true
                     }
-                    Some(",") => {
-                        // Could be a MetaFact: Head, {EQVars}.
-                        let meta_data = self.parse_expression()?;
-                        if let Term::Set(eq_vars) = &meta_data {
-                            if eq_vars
-                                .iter()
-                                .any(|eq_var| !matches!(eq_var, Term::Unit(Unit::Variable(_))))
-                            {
-                                return Err(ParserError::MalformedMetaRule { detail: format!("incorrectly formatted existentially quantified variables: {:?}", eq_vars) });
-                            }
-                            self.expect(".")?;
-                            Ok(Some(TreeClause::MetaFact(
-                                literals.pop().unwrap(),
-                                meta_data,
-                            )))
-                        } else {
-                            Err(ParserError::MalformedMetaRule { detail: format!("expected set of existentially quantified variables after comma in meta-fact, got {:?}", meta_data) })
-                        }
+                    _ => false,
+                };
+                if meta_rule {
+                    Ok(Some(TreeClause::MetaRule(literals)))
+                } else {
+                    Ok(Some(TreeClause::Rule(literals)))
+                }
+            }
+            Term::Atom(Unit::Constant(arrow), mut args) if arrow == "-->" && args.len() == 2 => {
+                let body = args.pop().unwrap();
+                Ok(Some(dcg::translate(args.pop().unwrap(), body)?))
+            }
+            // Could be a MetaFact: Head, {EQVars}.
+            Term::Tuple(mut literals) if literals.len() == 2 => {
+                let meta_data = literals.pop().unwrap();
+                if let Term::Set(eq_vars) = &meta_data {
+                    if !unconstrained_vars(eq_vars) {
+                        return Err(ParserError::MalformedMetaRule { detail: format!("incorrectly formatted existentially quantified variables: {:?}", eq_vars) });
                     }
-                    Some(".") => Ok(Some(TreeClause::Fact(literals[0].clone()))),
-                    Some(token) => Err(ParserError::Expected {
-                        expected: "'.' or ',' or ':-'".into(),
-                        got: Some(token.to_string()),
-                    }),
-                    None => Err(ParserError::UnexpectedEof),
+                    Ok(Some(TreeClause::MetaFact(literals.pop().unwrap(), meta_data)))
+                } else {
+                    Err(ParserError::MalformedMetaRule { detail: format!("expected set of existentially quantified variables after comma in meta-fact, got {:?}", meta_data) })
                 }
             }
+            Term::Tuple(literals) => Err(ParserError::MalformedMetaRule {
+                detail: format!("expected a head and set of existentially quantified variables in meta-fact, got {:?}", literals),
+            }),
+            term => Ok(Some(TreeClause::Fact(term))),
         }
     }
 
     pub fn parse_goals(&mut self) -> Result<Vec<Term>, ParserError> {
-        let mut literals = vec![self.parse_expression()?];
-        loop {
-            match self.next() {
-                Some(",") => literals.push(self.parse_expression()?),
-                Some(".") => break,
-                Some(token) => {
-                    return Err(ParserError::UnexpectedToken {
-                        token: token.to_string(),
-                    })
-                }
-                None => return Err(ParserError::UnexpectedEof),
-            }
+        let goals = self.parse(MAX_PRIORITY, true)?.0;
+        match self.next() {
+            Some(".") => Ok(conjunction_goals(goals)),
+            Some(token) => Err(ParserError::UnexpectedToken {
+                token: token.to_string(),
+            }),
+            None => Err(ParserError::UnexpectedEof),
         }
+    }
 
-        Ok(literals)
+    /// Parse the next clause, reporting errors with the line they occur on.
+    pub fn next_clause(&mut self) -> Result<Option<TreeClause>, ParserError> {
+        self.parse_clause().map_err(|msg| ParserError::AtLine {
+            line: self.line,
+            cause: Box::new(msg),
+        })
     }
 
     pub fn parse_all(&mut self) -> Result<Vec<TreeClause>, ParserError> {
         let mut clauses = Vec::<TreeClause>::new();
-        loop {
-            match self.parse_clause() {
-                Ok(Some(clause)) => clauses.push(clause),
-                Ok(None) => return Ok(clauses),
-                Err(msg) => {
-                    return Err(ParserError::AtLine {
-                        line: self.line,
-                        cause: Box::new(msg),
-                    })
-                }
-            }
+        while let Some(clause) = self.next_clause()? {
+            clauses.push(clause);
         }
+        Ok(clauses)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::{
-        super::{tokeniser::tokenise, ParserError},
+        super::{
+            operators::{OpType, Operators},
+            tokeniser::tokenise,
+            ParserError,
+        },
         {Term, TokenStream, TreeClause, Unit},
     };
     #[test]
@@ -468,6 +580,16 @@ mod tests {
         assert_eq!(term, Term::Unit(Unit::Variable("VAR_Under2".into())));
         let term = TokenStream::new(text).parse_expression().unwrap();
         assert_eq!(term, Term::Unit(Unit::Variable("VAR_Under2".into())));
+
+        let text = tokenise("_G12").unwrap();
+        let term = TokenStream::new(text.clone()).parse_term().unwrap();
+        assert_eq!(term, Term::Unit(Unit::Variable("_G12".into())));
+        let term = TokenStream::new(text).parse_expression().unwrap();
+        assert_eq!(term, Term::Unit(Unit::Variable("_G12".into())));
+
+        let text = tokenise("_").unwrap();
+        let term = TokenStream::new(text).parse_term().unwrap();
+        assert_eq!(term, Term::Unit(Unit::AnonVar));
     }
 
     #[test]
@@ -937,6 +1059,88 @@ mod tests {
         );
     }
 
+    #[test]
+    fn control_operators() {
+        let a = Term::Unit(Unit::Constant("a".into()));
+        let b = Term::Unit(Unit::Constant("b".into()));
+        let c = Term::Unit(Unit::Constant("c".into()));
+        let d = Term::Unit(Unit::Constant("d".into()));
+        let e = Term::Unit(Unit::Constant("e".into()));
+        let or = Unit::Constant(";".into());
+        let if_then = Unit::Constant("->".into());
+        let soft_if_then = Unit::Constant("*->".into());
+
+        // Commas bind tighter than the control operators
+        let text = tokenise("(a, b -> c ; d)").unwrap();
+        let term = TokenStream::new(text).parse_expression().unwrap();
+        assert_eq!(
+            term,
+            Term::Atom(
+                or.clone(),
+                vec![
+                    Term::Atom(
+                        if_then.clone(),
+                        vec![Term::Tuple(vec![a.clone(), b.clone()]), c.clone()]
+                    ),
+                    d.clone()
+                ]
+            )
+        );
+
+        // Right associative
+        let text = tokenise("(a -> b ; c -> d ; e)").unwrap();
+        let term = TokenStream::new(text).parse_expression().unwrap();
+        assert_eq!(
+            term,
+            Term::Atom(
+                or.clone(),
+                vec![
+                    Term::Atom(if_then.clone(), vec![a.clone(), b.clone()]),
+                    Term::Atom(
+                        or.clone(),
+                        vec![Term::Atom(if_then, vec![c.clone(), d]), e]
+                    )
+                ]
+            )
+        );
+
+        let text = tokenise("(a *-> b, c)").unwrap();
+        let term = TokenStream::new(text).parse_expression().unwrap();
+        assert_eq!(
+            term,
+            Term::Atom(soft_if_then, vec![a.clone(), Term::Tuple(vec![b.clone(), c.clone()])])
+        );
+
+        // A body or query without brackets groups the same way
+        let text = tokenise("a, b ; c.").unwrap();
+        let goals = TokenStream::new(text).parse_goals().unwrap();
+        assert_eq!(
+            goals,
+            vec![Term::Atom(or, vec![Term::Tuple(vec![a.clone(), b.clone()]), c])]
+        );
+        let text = tokenise("a, (b).").unwrap();
+        let goals = TokenStream::new(text).parse_goals().unwrap();
+        assert_eq!(goals, vec![a, b]);
+    }
+
+    #[test]
+    fn existential_operator() {
+        let x = Term::Unit(Unit::Variable("X".into()));
+        let y = Term::Unit(Unit::Variable("Y".into()));
+        let caret = Unit::Constant("^".into());
+        let goal = Term::Atom(Unit::Constant("p".into()), vec![x.clone(), y.clone()]);
+
+        let text = tokenise("X^Y^p(X,Y)").unwrap();
+        let term = TokenStream::new(text).parse_expression().unwrap();
+        assert_eq!(
+            term,
+            Term::Atom(
+                caret.clone(),
+                vec![x, Term::Atom(caret, vec![y, goal])]
+            )
+        );
+    }
+
     #[test]
     fn parse_rule() {
         let mut token_stream = TokenStream::new(tokenise("gt1(X):-X>1.").unwrap());
@@ -1114,6 +1318,49 @@ mod tests {
         assert_eq!(token_stream.parse_clause().unwrap(), None);
     }
 
+    #[test]
+    fn parse_dynamic_directive() {
+        let mut token_stream = TokenStream::new(tokenise(":- dynamic p/1, q/2.").unwrap());
+        let indicator = |name: &str, arity| {
+            Term::Atom(
+                Unit::Constant("/".into()),
+                vec![
+                    Term::Unit(Unit::Constant(name.into())),
+                    Term::Unit(Unit::Int(arity)),
+                ],
+            )
+        };
+        assert_eq!(
+            token_stream.parse_clause().unwrap(),
+            Some(TreeClause::Directive(
+                vec![Term::Atom(
+                    Unit::Constant("dynamic".into()),
+                    vec![Term::Tuple(vec![indicator("p", 1), indicator("q", 2)])]
+                )],
+                1
+            ))
+        );
+    }
+
+    #[test]
+    fn parse_clause_term() {
+        let mut token_stream = TokenStream::new(tokenise("assertz((p :- q, r)).").unwrap());
+        let clause = Term::Atom(
+            Unit::Constant(":-".into()),
+            vec![
+                Term::Unit(Unit::Constant("p".into())),
+                Term::Tuple(vec![
+                    Term::Unit(Unit::Constant("q".into())),
+                    Term::Unit(Unit::Constant("r".into())),
+                ]),
+            ],
+        );
+        assert_eq!(
+            token_stream.parse_goals().unwrap(),
+            vec![Term::Atom(Unit::Constant("assertz".into()), vec![clause])]
+        );
+    }
+
     #[test]
     fn parse_all_clauses() {
         let text = "gt1(X):-X>1.\nman(plato).\nP(X,Y):-\n\tQ(X,Y),\n\t{P,Q}.".to_string();
@@ -1162,4 +1409,130 @@ mod tests {
             TreeClause::MetaRule(vec![head, body, meta_data])
         );
     }
+
+    #[test]
+    fn user_operators() {
+        let atom = |name: &str| Term::Unit(Unit::Constant(name.into()));
+        let op = |name: &str, args: Vec<Term>| Term::Atom(Unit::Constant(name.into()), args);
+        let mut operators = Operators::default();
+        operators.add(700, OpType::Xfx, "=>");
+        operators.add(200, OpType::Xfy, "::");
+
+        let text = tokenise("a::b::c => d").unwrap();
+        let term = TokenStream::new(text)
+            .with_operators(operators.clone())
+            .parse_expression()
+            .unwrap();
+        assert_eq!(
+            term,
+            op(
+                "=>",
+                vec![
+                    op("::", vec![atom("a"), op("::", vec![atom("b"), atom("c")])]),
+                    atom("d")
+                ]
+            )
+        );
+
+        // Without the declaration `=>` is read as one atom in operand position
+        let text = tokenise("op(700, xfx, =>).").unwrap();
+        let goals = TokenStream::new(text).parse_goals().unwrap();
+        assert_eq!(
+            goals,
+            vec![op(
+                "op",
+                vec![Term::Unit(Unit::Int(700)), atom("xfx"), atom("=>")]
+            )]
+        );
+
+        // Runs of symbol characters split into the operators they start with
+        let text = tokenise("p:-\\+q,X>=-Y.").unwrap();
+        let clause = TokenStream::new(text).parse_clause().unwrap().unwrap();
+        let x = Term::Unit(Unit::Variable("X".into()));
+        let y = Term::Unit(Unit::Variable("Y".into()));
+        assert_eq!(
+            clause,
+            TreeClause::Rule(vec![
+                atom("p"),
+                op("\\+", vec![atom("q")]),
+                op(">=", vec![x, op("-", vec![y])]),
+            ])
+        );
+    }
+
+    #[test]
+    fn operator_as_atom() {
+        let atom = |name: &str| Term::Unit(Unit::Constant(name.into()));
+        let text = tokenise("f(-, =, [+], mod)").unwrap();
+        let term = TokenStream::new(text).parse_expression().unwrap();
+        assert_eq!(
+            term,
+            Term::Atom(
+                Unit::Constant("f".into()),
+                vec![
+                    atom("-"),
+                    atom("="),
+                    Term::List(vec![atom("+")], Box::new(Term::EmptyList)),
+                    atom("mod")
+                ]
+            )
+        );
+    }
+
+    #[test]
+    fn glued_negative_number() {
+        let text = tokenise("3-1").unwrap();
+        let term = TokenStream::new(text).parse_expression().unwrap();
+        assert_eq!(
+            term,
+            Term::Atom(
+                Unit::Constant("-".into()),
+                vec![Term::Unit(Unit::Int(3)), Term::Unit(Unit::Int(1))]
+            )
+        );
+    }
+
+    #[test]
+    fn grammar_rules() {
+        let atom = |name: &str| Term::Unit(Unit::Constant(name.into()));
+        let var = |name: &str| Term::Unit(Unit::Variable(name.into()));
+        let op = |name: &str, args: Vec<Term>| Term::Atom(Unit::Constant(name.into()), args);
+        let pred = |name: &str, args: Vec<Term>| Term::Atom(Unit::Variable(name.into()), args);
+        let list = |items: Vec<Term>, tail: Term| Term::List(items, Box::new(tail));
+
+        let text = tokenise(
+            "greeting --> [hello], name.
+             digit(D) --> [D], {D >= 48}.
+             P --> Q, R, {P, Q, R}.",
+        )
+        .unwrap();
+        let clauses = TokenStream::new(text).parse_all().unwrap();
+
+        // Leading terminals are folded into the head
+        assert_eq!(
+            clauses[0],
+            TreeClause::Rule(vec![
+                op("greeting", vec![list(vec![atom("hello")], var("_S2")), var("_S1")]),
+                op("name", vec![var("_S2"), var("_S1")]),
+            ])
+        );
+        assert_eq!(
+            clauses[1],
+            TreeClause::Rule(vec![
+                op("digit", vec![var("D"), list(vec![var("D")], var("_S2")), var("_S1")]),
+                op(">=", vec![var("D"), Term::Unit(Unit::Int(48))]),
+                op("=", vec![var("_S2"), var("_S1")]),
+            ])
+        );
+        // A trailing set of variables makes a grammar meta-rule
+        assert_eq!(
+            clauses[2],
+            TreeClause::MetaRule(vec![
+                pred("P", vec![var("_S0"), var("_S1")]),
+                pred("Q", vec![var("_S0"), var("_S2")]),
+                pred("R", vec![var("_S2"), var("_S1")]),
+                Term::Set(vec![var("P"), var("Q"), var("R")]),
+            ])
+        );
+    }
 }
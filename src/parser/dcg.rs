@@ -0,0 +1,226 @@
+//! Definite clause grammar translation.
+//!
+//! A grammar rule `Head --> Body` is rewritten into an ordinary clause when
+//! it is parsed, threading a pair of difference-list arguments `S0`, `S`
+//! through every nonterminal: `greeting --> [hello], name.` becomes
+//! `greeting([hello|S1], S) :- name(S1, S).`
+//!
+//! A rule whose head or body uses a variable nonterminal and which ends in a
+//! set of variables is a meta-rule, so MIL can learn grammar rules:
+//! `P --> Q, R, {P,Q,R}.` becomes `P(S0,S) :- Q(S0,S1), R(S1,S), {P,Q,R}.`
+
+use super::{
+    build_tree::TreeClause,
+    term::{Term, Unit},
+    ParserError,
+};
+
+/// Translate the grammar rule `head --> body` into a clause.
+pub(super) fn translate(head: Term, body: Term) -> Result<TreeClause, ParserError> {
+    let mut body = match body {
+        Term::Tuple(goals) if !goals.is_empty() => goals,
+        body => vec![body],
+    };
+    let (head, pushback) = match head {
+        Term::Tuple(mut parts) if parts.len() == 2 => {
+            let pushback = parts.pop().unwrap();
+            (parts.pop().unwrap(), Some(pushback))
+        }
+        head => (head, None),
+    };
+
+    // A trailing set of variables marks a meta-rule, but only when there is
+    // a variable nonterminal for it to quantify; otherwise it is a `{}` goal.
+    let meta_data = match body.last() {
+        Some(Term::Set(vars))
+            if body.len() > 1
+                && vars
+                    .iter()
+                    .all(|var| matches!(var, Term::Unit(Unit::Variable(_))))
+                && (variable_nonterminal(&head)
+                    || body.iter().any(variable_nonterminal)) =>
+        {
+            body.pop()
+        }
+        _ => None,
+    };
+
+    let mut dcg = Dcg {
+        next_var: 0,
+        meta: meta_data.is_some(),
+    };
+    let s0 = dcg.fresh_var();
+    let s = dcg.fresh_var();
+    let mut head = dcg.nonterminal(head, s0.clone(), s.clone(), true)?;
+
+    let mut goals = Vec::new();
+    match pushback {
+        Some(pushback) => {
+            let s1 = dcg.fresh_var();
+            goals.append(&mut dcg.body(Term::Tuple(body), s0.clone(), s1.clone())?);
+            goals.append(&mut dcg.terminals(pushback, s, s1)?);
+        }
+        None => goals.append(&mut dcg.body(Term::Tuple(body), s0.clone(), s)?),
+    }
+
+    // Leading terminals go in the head: `a([x|S1], S)` rather than
+    // `a(S0, S) :- S0 = [x|S1]`.
+    if let Some(Term::Atom(Unit::Constant(eq), args)) = goals.first() {
+        if eq == "=" && args[0] == s0 {
+            let list = args[1].clone();
+            goals.remove(0);
+            if let Term::Atom(_, head_args) = &mut head {
+                let len = head_args.len();
+                head_args[len - 2] = list;
+            }
+        }
+    }
+
+    if let Some(meta_data) = meta_data {
+        let mut literals = vec![head];
+        literals.append(&mut goals);
+        literals.push(meta_data);
+        Ok(TreeClause::MetaRule(literals))
+    } else if goals.is_empty() {
+        Ok(TreeClause::Fact(head))
+    } else {
+        let mut literals = vec![head];
+        literals.append(&mut goals);
+        Ok(TreeClause::Rule(literals))
+    }
+}
+
+fn variable_nonterminal(term: &Term) -> bool {
+    matches!(
+        term,
+        Term::Unit(Unit::Variable(_)) | Term::Atom(Unit::Variable(_), _)
+    )
+}
+
+fn unify(left: Term, right: Term) -> Term {
+    Term::Atom(Unit::Constant("=".into()), vec![left, right])
+}
+
+/// A goal sequence as a single term.
+fn conjunction(mut goals: Vec<Term>) -> Term {
+    match goals.len() {
+        0 => Term::Unit(Unit::Constant("true".into())),
+        1 => goals.pop().unwrap(),
+        _ => Term::Tuple(goals),
+    }
+}
+
+fn malformed(detail: impl Into<String>) -> ParserError {
+    ParserError::MalformedDcg {
+        detail: detail.into(),
+    }
+}
+
+struct Dcg {
+    next_var: usize,
+    meta: bool,
+}
+
+impl Dcg {
+    /// A list variable no source variable can clash with: source variables
+    /// starting with `_` are anonymous.
+    fn fresh_var(&mut self) -> Term {
+        self.next_var += 1;
+        Term::Unit(Unit::Variable(format!("_S{}", self.next_var - 1)))
+    }
+
+    /// `term` with the list arguments `s0` and `s` appended.
+    fn nonterminal(&self, term: Term, s0: Term, s: Term, head: bool) -> Result<Term, ParserError> {
+        match term {
+            Term::Unit(unit @ Unit::Constant(_)) => Ok(Term::Atom(unit, vec![s0, s])),
+            Term::Unit(unit @ Unit::Variable(_)) if self.meta => Ok(Term::Atom(unit, vec![s0, s])),
+            Term::Unit(Unit::Variable(_)) if !head => Ok(Term::Atom(
+                Unit::Constant("phrase".into()),
+                vec![term, s0, s],
+            )),
+            Term::Atom(functor, mut args) => {
+                args.push(s0);
+                args.push(s);
+                Ok(Term::Atom(functor, args))
+            }
+            term => Err(malformed(format!("{term:?} is not a nonterminal"))),
+        }
+    }
+
+    /// The goals for a terminal list or string between `s0` and `s`.
+    fn terminals(&mut self, term: Term, s0: Term, s: Term) -> Result<Vec<Term>, ParserError> {
+        let items = match term {
+            Term::EmptyList => vec![],
+            Term::List(items, tail) if *tail == Term::EmptyList => items,
+            Term::Unit(Unit::String(text)) => text
+                .chars()
+                .map(|c| Term::Unit(Unit::Int(c as isize)))
+                .collect(),
+            term => return Err(malformed(format!("{term:?} is not a terminal list"))),
+        };
+        if items.is_empty() {
+            Ok(vec![unify(s0, s)])
+        } else {
+            Ok(vec![unify(s0, Term::List(items, Box::new(s)))])
+        }
+    }
+
+    /// The goals for the grammar body `term` between `s0` and `s`.
+    fn body(&mut self, term: Term, s0: Term, s: Term) -> Result<Vec<Term>, ParserError> {
+        match term {
+            Term::Tuple(items) => {
+                let mut goals = Vec::new();
+                let mut current = s0;
+                let len = items.len();
+                if len == 0 {
+                    return Ok(vec![unify(current, s)]);
+                }
+                for (i, item) in items.into_iter().enumerate() {
+                    let next = if i + 1 == len { s.clone() } else { self.fresh_var() };
+                    goals.append(&mut self.body(item, current, next.clone())?);
+                    current = next;
+                }
+                Ok(goals)
+            }
+            Term::Atom(Unit::Constant(op), mut args) if args.len() == 2 && op == ";" => {
+                let right = args.pop().unwrap();
+                let left = args.pop().unwrap();
+                let left = conjunction(self.body(left, s0.clone(), s.clone())?);
+                let right = conjunction(self.body(right, s0, s)?);
+                Ok(vec![Term::Atom(Unit::Constant(op), vec![left, right])])
+            }
+            Term::Atom(Unit::Constant(op), mut args)
+                if args.len() == 2 && (op == "->" || op == "*->") =>
+            {
+                let then = args.pop().unwrap();
+                let condition = args.pop().unwrap();
+                let s1 = self.fresh_var();
+                let condition = conjunction(self.body(condition, s0, s1.clone())?);
+                let then = conjunction(self.body(then, s1, s)?);
+                Ok(vec![Term::Atom(Unit::Constant(op), vec![condition, then])])
+            }
+            Term::Atom(Unit::Constant(op), mut args) if args.len() == 1 && op == "\\+" => {
+                let rest = self.fresh_var();
+                let goal = conjunction(self.body(args.pop().unwrap(), s0.clone(), rest)?);
+                Ok(vec![Term::Atom(Unit::Constant(op), vec![goal]), unify(s0, s)])
+            }
+            Term::Atom(Unit::Constant(call), mut args) if !args.is_empty() && call == "call" => {
+                args.push(s0);
+                args.push(s);
+                Ok(vec![Term::Atom(Unit::Constant(call), args)])
+            }
+            Term::Unit(Unit::Constant(cut)) if cut == "!" => {
+                Ok(vec![Term::Unit(Unit::Constant(cut)), unify(s0, s)])
+            }
+            Term::Set(mut goals) => {
+                goals.push(unify(s0, s));
+                Ok(goals)
+            }
+            Term::EmptySet => Ok(vec![unify(s0, s)]),
+            term @ (Term::EmptyList | Term::List(..) | Term::Unit(Unit::String(_))) => {
+                self.terminals(term, s0, s)
+            }
+            term => Ok(vec![self.nonterminal(term, s0, s, false)?]),
+        }
+    }
+}
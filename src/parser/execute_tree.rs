@@ -8,7 +8,7 @@ use super::{
 };
 use crate::{
     heap::heap::Heap,
-    program::{clause::Clause, predicate_table::PredicateTable},
+    program::{clause::Clause, dynamic::DynamicClause, predicate_table::PredicateTable},
 };
 
 pub fn build_clause(
@@ -112,6 +112,24 @@ fn extract_meta_rule_vars(terms: &mut Vec<Term>) -> (Vec<String>, Option<Vec<Str
     }
 }
 
+/// Add a clause loaded from source to its predicate, which keeps it with the
+/// dynamic clauses if the predicate was declared dynamic.
+fn add_clause(clause: Clause, heap: &impl Heap, pred_table: &mut PredicateTable) {
+    let symbol_arity = heap.str_symbol_arity(clause[0]);
+    if pred_table.dynamic.is_dynamic(symbol_arity) {
+        pred_table
+            .dynamic
+            .add_clause(symbol_arity, DynamicClause::new(heap, &clause), false);
+    } else {
+        pred_table
+            .add_clause_to_predicate(clause, symbol_arity)
+            .unwrap();
+    }
+}
+
+/// Encode the clauses of a syntax tree onto the heap and add them to the
+/// predicate table. Directives are run by [`App::load_code`](crate::app::App::load_code)
+/// and must not be passed here.
 pub(crate) fn execute_tree(
     syntax_tree: Vec<TreeClause>,
     heap: &mut impl Heap,
@@ -121,17 +139,11 @@ pub(crate) fn execute_tree(
         match clause {
             TreeClause::Fact(term) => {
                 let clause = build_clause(vec![term], None, None, heap, false);
-                let symbol_arity = heap.str_symbol_arity(clause[0]);
-                pred_table
-                    .add_clause_to_predicate(clause, symbol_arity)
-                    .unwrap();
+                add_clause(clause, heap, pred_table);
             }
             TreeClause::Rule(terms) => {
                 let clause = build_clause(terms, None, None, heap, false);
-                let symbol_arity = heap.str_symbol_arity(clause[0]);
-                pred_table
-                    .add_clause_to_predicate(clause, symbol_arity)
-                    .unwrap();
+                add_clause(clause, heap, pred_table);
             }
             TreeClause::MetaRule(mut terms) => {
                 let (meta_vars, constrained_vars) = extract_meta_rule_vars(&mut terms);
@@ -149,6 +161,7 @@ pub(crate) fn execute_tree(
                     .add_clause_to_predicate(clause, symbol_arity)
                     .unwrap();
             }
+            TreeClause::Directive(..) => unreachable!("directives are run by App::load_code"),
         }
     }
 }
@@ -6,8 +6,10 @@
 //! ([`execute_tree`](crate::parser::execute_tree)).
 
 pub mod build_tree;
+mod dcg;
 pub mod execute_tree;
-mod term;
+pub mod operators;
+pub(crate) mod term;
 pub mod tokeniser;
 
 use std::fmt;
@@ -27,6 +29,8 @@ pub enum ParserError {
     MalformedSet,
     /// Covers malformed existential-quantification syntax in meta-rules/meta-facts.
     MalformedMetaRule { detail: String },
+    /// A grammar rule (`-->`) whose head or body can't be translated.
+    MalformedDcg { detail: String },
     // --- Location wrapper ---
     /// Wraps any other variant with the source line number.
     AtLine { line: usize, cause: Box<ParserError> },
@@ -53,6 +57,8 @@ impl fmt::Display for ParserError {
                 write!(f, "incorrectly formatted set"),
             Self::MalformedMetaRule { detail } =>
                 write!(f, "malformed meta-rule: {detail}"),
+            Self::MalformedDcg { detail } =>
+                write!(f, "malformed grammar rule: {detail}"),
             Self::AtLine { line, cause } =>
                 write!(f, "line {line}: {cause}"),
         }
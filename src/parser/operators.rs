@@ -0,0 +1,160 @@
+//! Operator table: the prefix, infix and postfix operators the parser knows,
+//! with their priorities and associativity.
+//!
+//! Each [`App`](crate::app::App) starts with the standard table from
+//! [`Operators::default`] and changes it with `op/3`, either as a directive
+//! while loading source or as a goal in a query.
+
+use std::collections::HashMap;
+
+/// Highest operator priority. Clause and query terms are read at this level.
+pub const MAX_PRIORITY: usize = 1200;
+
+/// Priority of the argument of a compound term or list element, just below
+/// the `,` operator that separates them.
+pub const ARG_PRIORITY: usize = 999;
+
+/// Characters that form symbolic atoms like `=..` or `:-`.
+pub const SYMBOL_CHARS: &[char] = &[
+    '+', '-', '*', '/', '\\', '^', '<', '>', '=', '~', ':', '.', '?', '@', '#', '&',
+];
+
+/// Operator type. `f` marks the operator and `x`/`y` its arguments: an `x`
+/// argument must bind tighter than the operator, while a `y` argument may
+/// have the same priority, which makes `xfy` right and `yfx` left associative.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpType {
+    Xfx,
+    Xfy,
+    Yfx,
+    Fy,
+    Fx,
+    Xf,
+    Yf,
+}
+
+impl OpType {
+    pub fn from_name(name: &str) -> Option<OpType> {
+        match name {
+            "xfx" => Some(OpType::Xfx),
+            "xfy" => Some(OpType::Xfy),
+            "yfx" => Some(OpType::Yfx),
+            "fy" => Some(OpType::Fy),
+            "fx" => Some(OpType::Fx),
+            "xf" => Some(OpType::Xf),
+            "yf" => Some(OpType::Yf),
+            _ => None,
+        }
+    }
+
+    /// Highest priorities allowed for the left and right arguments of an
+    /// operator of this type with priority `priority`. Prefix operators only
+    /// have a right argument and postfix operators a left one.
+    pub fn arg_priorities(self, priority: usize) -> (usize, usize) {
+        let x = priority.saturating_sub(1);
+        match self {
+            OpType::Xfx => (x, x),
+            OpType::Xfy => (x, priority),
+            OpType::Yfx => (priority, x),
+            OpType::Fy => (0, priority),
+            OpType::Fx => (0, x),
+            OpType::Xf => (x, 0),
+            OpType::Yf => (priority, 0),
+        }
+    }
+}
+
+/// An operator definition: priority and type.
+pub type OpDef = (usize, OpType);
+
+/// The operators of a program, keyed by name within each class.
+#[derive(Debug, Clone)]
+pub struct Operators {
+    prefix: HashMap<String, OpDef>,
+    infix: HashMap<String, OpDef>,
+    postfix: HashMap<String, OpDef>,
+}
+
+const STANDARD_OPERATORS: &[(usize, OpType, &[&str])] = &[
+    (1200, OpType::Xfx, &[":-", "-->"]),
+    (1200, OpType::Fx, &[":-", "?-"]),
+    (1150, OpType::Fx, &["dynamic", "discontiguous", "initialization", "table"]),
+    (1100, OpType::Xfy, &[";"]),
+    (1050, OpType::Xfy, &["->", "*->"]),
+    (900, OpType::Fy, &["\\+"]),
+    (
+        700,
+        OpType::Xfx,
+        &[
+            "=", "\\=", "==", "\\==", "@<", "@>", "@=<", "@>=", "=..", "is", "=:=", "=\\=", "<",
+            ">", "=<", ">=", "=~=", "=@=", "\\=@=",
+        ],
+    ),
+    (600, OpType::Xfy, &[":"]),
+    (500, OpType::Yfx, &["+", "-", "/\\", "\\/", "xor"]),
+    (400, OpType::Yfx, &["*", "/", "//", "rem", "mod", "div", "rdiv", "<<", ">>"]),
+    (200, OpType::Xfx, &["**"]),
+    (200, OpType::Xfy, &["^"]),
+    (200, OpType::Fy, &["-", "+", "\\"]),
+];
+
+impl Default for Operators {
+    /// The standard operator table.
+    fn default() -> Self {
+        let mut operators = Operators {
+            prefix: HashMap::new(),
+            infix: HashMap::new(),
+            postfix: HashMap::new(),
+        };
+        for &(priority, op_type, names) in STANDARD_OPERATORS {
+            for name in names {
+                operators.add(priority, op_type, name);
+            }
+        }
+        operators
+    }
+}
+
+impl Operators {
+    /// Define `name` as an operator, replacing any operator of the same class
+    /// (prefix, infix or postfix). Priority 0 removes the definition.
+    pub fn add(&mut self, priority: usize, op_type: OpType, name: &str) {
+        let class = match op_type {
+            OpType::Xfx | OpType::Xfy | OpType::Yfx => &mut self.infix,
+            OpType::Fy | OpType::Fx => &mut self.prefix,
+            OpType::Xf | OpType::Yf => &mut self.postfix,
+        };
+        if priority == 0 {
+            class.remove(name);
+        } else {
+            class.insert(name.to_string(), (priority, op_type));
+        }
+    }
+
+    pub fn prefix(&self, name: &str) -> Option<OpDef> {
+        self.prefix.get(name).copied()
+    }
+
+    pub fn infix(&self, name: &str) -> Option<OpDef> {
+        self.infix.get(name).copied()
+    }
+
+    pub fn postfix(&self, name: &str) -> Option<OpDef> {
+        self.postfix.get(name).copied()
+    }
+
+    pub fn is_operator(&self, name: &str) -> bool {
+        self.prefix.contains_key(name)
+            || self.infix.contains_key(name)
+            || self.postfix.contains_key(name)
+    }
+
+    /// Length in bytes of the first token of the symbol character run `run`:
+    /// the longest operator name it starts with, or else its first character.
+    pub fn symbol_prefix(&self, run: &str) -> usize {
+        (2..=run.len())
+            .rev()
+            .find(|&len| run.is_char_boundary(len) && self.is_operator(&run[..len]))
+            .unwrap_or_else(|| run.chars().next().map_or(0, char::len_utf8))
+    }
+}
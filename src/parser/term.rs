@@ -1,10 +1,15 @@
 use std::{collections::HashMap, mem};
 
 use fsize::fsize;
+use num_bigint::BigInt;
+use num_rational::BigRational;
 
-use crate::heap::{
-    heap::{Cell, Heap, Tag, EMPTY_LIS},
-    symbol_db::SymbolDB,
+use crate::{
+    heap::{
+        heap::{Cell, Heap, Tag, EMPTY_LIS},
+        symbol_db::SymbolDB,
+    },
+    predicate_modules::maths::Number,
 };
 
 #[derive(Debug, PartialEq, Clone)]
@@ -12,6 +17,10 @@ pub enum Unit {
     Constant(String),
     Variable(String),
     Int(isize),
+    /// An integer too large for an `isize`.
+    BigInt(BigInt),
+    /// A rational such as `1r3`, which is never an integer.
+    Rational(BigRational),
     Float(fsize),
     String(String),
     AnonVar,
@@ -23,18 +32,17 @@ impl Unit {
         match c {
             '\'' => Some(Unit::Constant(token[1..token.len() - 1].into())),
             '"' => Some(Unit::String(token[1..token.len() - 1].into())),
-            '_' => Some(Unit::AnonVar),
+            '_' if token == "_" => Some(Unit::AnonVar),
+            '_' => Some(Unit::Variable(token.into())),
+            '!' => Some(Unit::Constant(token.into())),
             c if c.is_lowercase() => Some(Unit::Constant(token.into())),
             c if c.is_uppercase() => Some(Unit::Variable(token.into())),
-            c if c == '-' || c.is_numeric() => {
-                if let Ok(num) = token.parse::<isize>() {
-                    Some(Unit::Int(num))
-                } else if let Ok(num) = token.parse::<fsize>() {
-                    Some(Unit::Float(num))
-                } else {
-                    None
-                }
-            }
+            c if c == '-' || c.is_numeric() => match Number::parse(token)? {
+                Number::Int(num) => Some(Unit::Int(num)),
+                Number::Big(num) => Some(Unit::BigInt(num)),
+                Number::Rat(num) => Some(Unit::Rational(num)),
+                Number::Flt(num) => Some(Unit::Float(num)),
+            },
             _ => None,
         }
     }
@@ -95,8 +103,16 @@ impl Unit {
             Unit::Variable(symbol) => Self::encode_var(symbol, heap, var_values, query),
             Unit::Int(value) => heap.heap_push((Tag::Int, unsafe { mem::transmute_copy(value) })),
             Unit::Float(value) => heap.heap_push((Tag::Flt, unsafe { mem::transmute_copy(value) })),
+            Unit::BigInt(value) => {
+                let index = SymbolDB::set_big_int(value.clone(), heap.get_id());
+                heap.heap_push((Tag::BigInt, index))
+            }
+            Unit::Rational(value) => {
+                let index = SymbolDB::set_rational(value.clone(), heap.get_id());
+                heap.heap_push((Tag::Rat, index))
+            }
             Unit::String(text) => {
-                let str_id = SymbolDB::set_string(text.clone());
+                let str_id = SymbolDB::set_string(text.clone(), heap.get_id());
                 heap.heap_push((Tag::Stri, str_id))
             }
             Unit::AnonVar => heap.heap_push((Tag::AVar, 0)),
@@ -353,6 +369,31 @@ mod encode_tests {
         assert_eq!(heap.cells, [(Tag::Flt, value.to_bits() as usize)]);
     }
 
+    #[test]
+    fn encode_big_numbers() {
+        let big = "123456789012345678901234567890";
+        let Some(unit @ Unit::BigInt(_)) = Unit::parse_unit(big) else {
+            panic!("{big} should parse as a big integer");
+        };
+        let mut heap = QueryHeap::new(&[], None);
+        let addr = unit.encode(&mut heap, &mut HashMap::new(), false);
+        assert_eq!(heap[addr].0, Tag::BigInt);
+        assert_eq!(heap.term_string(addr), big);
+
+        let Some(unit @ Unit::Rational(_)) = Unit::parse_unit("-2r6") else {
+            panic!("-2r6 should parse as a rational");
+        };
+        let mut heap = QueryHeap::new(&[], None);
+        let addr = unit.encode(&mut heap, &mut HashMap::new(), false);
+        assert_eq!(heap[addr].0, Tag::Rat);
+        assert_eq!(heap.term_string(addr), "-1r3");
+
+        // Rationals that are whole numbers are integers, and zero
+        // denominators are not numbers
+        assert_eq!(Unit::parse_unit("6r3"), Some(Unit::Int(2)));
+        assert_eq!(Unit::parse_unit("1r0"), None);
+    }
+
     #[test]
     fn program_encode_functor() {
         let p_id = SymbolDB::set_const("p");
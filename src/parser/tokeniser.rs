@@ -1,12 +1,11 @@
 //! Lexer: converts Prolog source text into a token stream.
 
-use super::ParserError;
+use super::{operators::SYMBOL_CHARS, ParserError};
 
 const DELIMINATORS: &[char] = &[
     '(', ')', ',', '.', ' ', '\r', '\n', '\t', '\\', ':', '-', '+', '/', '*', '=', '[', ']', '|',
-    '>', '<', '{', '}',
+    '>', '<', '{', '}', '!', ';', '^', '~', '?', '@', '#', '&',
 ];
-const KNOWN_SYMBOLS: &[&str] = &[":-", "==", "=\\=", "\\=", "=:=", "=~=", "**", "=<", ">=", "/*", "*/", "=.."];
 
 // --------------------------------------------------------------------------------------
 // Tokenise File
@@ -181,12 +180,18 @@ fn join_decimal_nums(tokens: &mut Vec<String>) {
     }
 }
 
-/* If a substract sign is at the front of number unifiy into one token
+/* If a substract sign is at the front of number (or a rational like 1r3)
+unifiy into one token
 */
 fn form_negative_nums(tokens: &mut Vec<String>) {
+    let digits = |text: &str| !text.is_empty() && text.chars().all(|c| c.is_numeric());
+    let number = |token: &str| {
+        token.chars().all(|c| c.is_numeric() || c == '.')
+            || token.split_once('r').is_some_and(|(n, d)| digits(n) && digits(d))
+    };
     let mut i = 0;
     while tokens.len() > i + 1 {
-        if tokens[i] == "-" && tokens[i + 1].chars().all(|c| c.is_numeric() || c == '.') {
+        if tokens[i] == "-" && number(&tokens[i + 1]) {
             let combined_value = [tokens[i].as_str(), tokens[i + 1].as_str()].concat();
             tokens.remove(i + 1);
             tokens.remove(i);
@@ -196,20 +201,21 @@ fn form_negative_nums(tokens: &mut Vec<String>) {
     }
 }
 
-fn form_known_symbols(tokens: &mut Vec<String>) {
+/* Join adjacent symbol characters into one token, so `:-` or `=..` arrive as
+single tokens. Which operators a run like `:-\+` holds depends on the operator
+table, so the parser splits it up as it reads.
+*/
+fn form_symbol_runs(tokens: &mut Vec<String>) {
+    let is_symbol =
+        |token: &str| !token.is_empty() && token.chars().all(|c| SYMBOL_CHARS.contains(&c));
     let mut i = 0;
-    while i < tokens.len() - 1 {
-        if DELIMINATORS.contains(&tokens[i].chars().next().unwrap()) {
-            for &symbol in KNOWN_SYMBOLS {
-                if symbol.len() <= tokens.len() - i {
-                    if symbol == tokens[i..i + symbol.len()].concat() {
-                        tokens.drain(i..i + symbol.len());
-                        tokens.insert(i, symbol.into());
-                    }
-                }
-            }
+    while tokens.len() > i + 1 {
+        if is_symbol(&tokens[i]) && is_symbol(&tokens[i + 1]) {
+            let next = tokens.remove(i + 1);
+            tokens[i].push_str(&next);
+        } else {
+            i += 1;
         }
-        i += 1;
     }
 }
 
@@ -268,9 +274,9 @@ pub fn tokenise<'a>(text: impl AsRef<str>) -> Result<Vec<String>, ParserError> {
     }
     tokens.push(characters[last_i..].iter().collect());
     tokens.retain(|token| "" != *token);
-    form_known_symbols(&mut tokens);
     join_decimal_nums(&mut tokens);
     form_negative_nums(&mut tokens);
+    form_symbol_runs(&mut tokens);
     form_empty_list_token(&mut tokens);
     form_empty_set_token(&mut tokens);
     form_empty_tuple_token(&mut tokens);
@@ -447,6 +453,35 @@ mod tests {
         assert_eq!(tokenise("> =").unwrap(), [">", "=",]);
     }
 
+    #[test]
+    fn cut_tokenisation() {
+        assert_eq!(
+            tokenise("p:-q,!,r.").unwrap(),
+            ["p", ":-", "q", ",", "!", ",", "r", "."]
+        );
+        assert_eq!(tokenise("p:-!.").unwrap(), ["p", ":-", "!", "."]);
+    }
+
+    #[test]
+    fn control_operator_tokenisation() {
+        assert_eq!(
+            tokenise("(a->b;c)").unwrap(),
+            ["(", "a", "->", "b", ";", "c", ")"]
+        );
+        assert_eq!(tokenise("(a*->b)").unwrap(), ["(", "a", "*->", "b", ")"]);
+        assert_eq!(tokenise("X->-1").unwrap(), ["X", "->", "-1"]);
+        assert_eq!(tokenise("- >").unwrap(), ["-", ">"]);
+    }
+
+    #[test]
+    fn symbol_run_tokenisation() {
+        assert_eq!(tokenise("a=>b").unwrap(), ["a", "=>", "b"]);
+        assert_eq!(tokenise("X::Y").unwrap(), ["X", "::", "Y"]);
+        assert_eq!(tokenise("p:-\\+q").unwrap(), ["p", ":-\\+", "q"]);
+        assert_eq!(tokenise("X @< Y").unwrap(), ["X", "@<", "Y"]);
+        assert_eq!(tokenise("X=-1").unwrap(), ["X", "=", "-1"]);
+    }
+
     #[test]
     fn tokenise_multiple_clauses() {
         let text = " p(a,[b,c|[\t]]).
@@ -0,0 +1,466 @@
+//! Built-in predicates for the dynamic database.
+//!
+//! Clauses added with `assertz/1` and friends live in the predicate table's
+//! [`DynamicDatabase`](crate::program::dynamic::DynamicDatabase), so they
+//! outlive the query that added them. Changes follow the logical update
+//! view: a goal already running on a predicate keeps seeing the clauses it
+//! started with. Changing a predicate drops the answer tables that depend
+//! on it.
+
+use super::{helpers::*, PredReturn, PredicateModule};
+use crate::{
+    heap::{
+        heap::{Heap, Tag},
+        query_heap::QueryHeap,
+        symbol_db::{known_symbol_id, SymbolDB},
+    },
+    program::{
+        dynamic::{predicate_indicators, DynamicClause},
+        hypothesis::Hypothesis,
+        predicate_table::PredicateTable,
+    },
+    resolution::unification::unify,
+    Config,
+};
+
+const TRUE: usize = known_symbol_id(1);
+
+// ── Clause terms ──────────────────────────────────────────────────────────────
+
+/// Split the clause term at `addr` into its head and body: `Head :- Body`, or
+/// a fact `Head` whose body is `true`.
+fn split_clause(heap: &mut QueryHeap, addr: usize) -> (usize, usize) {
+    let base = resolve(heap, addr);
+    let neck = SymbolDB::set_const(":-");
+    if heap[base] == (Tag::Comp, 3) && heap[heap.deref_addr(base + 1)] == (Tag::Con, neck) {
+        (heap.deref_addr(base + 2), heap.deref_addr(base + 3))
+    } else {
+        (addr, heap.set_const(TRUE))
+    }
+}
+
+/// The predicate a clause head belongs to. Throws an instantiation error if
+/// the head or its functor is unbound and `type_error(callable, Head)` if it
+/// can't be a goal.
+fn head_predicate(heap: &mut QueryHeap, head: usize) -> Result<(usize, usize), PredReturn> {
+    if is_unbound(heap, head) {
+        return Err(instantiation_error(heap));
+    }
+    let base = resolve(heap, head);
+    match heap[base] {
+        (Tag::Con, symbol) => Ok((symbol, 0)),
+        (Tag::Comp, len) => match heap[heap.deref_addr(base + 1)] {
+            (Tag::Con, symbol) => Ok((symbol, len - 1)),
+            _ if is_unbound(heap, heap.deref_addr(base + 1)) => Err(instantiation_error(heap)),
+            _ => Err(type_error(heap, "callable", head)),
+        },
+        _ => Err(type_error(heap, "callable", head)),
+    }
+}
+
+/// The body literals of the clause body at `body`. A variable goal `G` is
+/// stored as `call(G)`, and a body of `true` has no literals.
+fn body_literals(heap: &mut QueryHeap, body: usize) -> Option<Vec<usize>> {
+    let base = resolve(heap, body);
+    let goals: Vec<usize> = match heap[base] {
+        (Tag::Tup, _) => heap
+            .str_iterator(base)
+            .map(|goal| heap.deref_addr(goal))
+            .collect(),
+        (Tag::Con, TRUE) => return Some(Vec::new()),
+        _ => vec![body],
+    };
+    let call = SymbolDB::set_const("call");
+    let mut literals = Vec::with_capacity(goals.len());
+    for goal in goals {
+        if is_unbound(heap, goal) {
+            let call = heap.set_const(call);
+            literals.push(build_compound_from_addrs(heap, &[call, goal]));
+        } else if matches!(heap[resolve(heap, goal)].0, Tag::Con | Tag::Comp | Tag::Tup) {
+            literals.push(goal);
+        } else {
+            return None;
+        }
+    }
+    Some(literals)
+}
+
+/// The term `Body` for the body literals at `literals`.
+fn body_term(heap: &mut QueryHeap, literals: &[usize]) -> usize {
+    match literals {
+        [] => heap.set_const(TRUE),
+        [literal] => *literal,
+        _ => build_tuple_from_addrs(heap, literals),
+    }
+}
+
+/// The predicate indicator `Name/Arity`.
+fn indicator(heap: &mut QueryHeap, (symbol, arity): (usize, usize)) -> usize {
+    let slash = heap.set_const(SymbolDB::set_const("/"));
+    let name = heap.set_const(symbol);
+    let arity = heap.heap_push((Tag::Int, arity));
+    build_compound_from_addrs(heap, &[slash, name, arity])
+}
+
+/// `permission_error(Action, Type, Name/Arity)` for a static predicate.
+fn static_error(
+    heap: &mut QueryHeap,
+    action: &str,
+    type_name: &str,
+    predicate: (usize, usize),
+) -> PredReturn {
+    let culprit = indicator(heap, predicate);
+    permission_error(heap, action, type_name, culprit)
+}
+
+/// One alternative per clause of `predicate` whose head and body unify with
+/// `head` and `body`, with `goals` scheduling any work that taking the
+/// alternative commits to.
+fn matching_clauses(
+    heap: &mut QueryHeap,
+    predicate_table: &PredicateTable,
+    predicate: (usize, usize),
+    head: usize,
+    body: usize,
+    goals: impl Fn(&mut QueryHeap, &DynamicClause) -> Vec<usize>,
+) -> PredReturn {
+    let clauses = predicate_table.dynamic.clauses(predicate).unwrap_or_default();
+    let target = build_tuple_from_addrs(heap, &[head, body]);
+    let mut choices = Vec::new();
+    for clause in clauses {
+        let literals = clause.instantiate(heap);
+        let body = body_term(heap, &literals[1..]);
+        let built = build_tuple_from_addrs(heap, &[literals[0], body]);
+        if let Some(sub) = unify(heap, built, target) {
+            let goals = goals(heap, &clause);
+            choices.push((sub.get_bindings().to_vec(), goals));
+        }
+    }
+    // Choices are popped from the back
+    choices.reverse();
+    PredReturn::Choices(choices)
+}
+
+// ── Predicates ────────────────────────────────────────────────────────────────
+
+/// Add the clause argument of `goal` to its predicate, at the start if
+/// `first` is set.
+fn add_clause(
+    heap: &mut QueryHeap,
+    goal: usize,
+    predicate_table: &PredicateTable,
+    first: bool,
+) -> PredReturn {
+    let clause = goal_arg(heap, goal, 0);
+    let (head, body) = split_clause(heap, clause);
+    let predicate = match head_predicate(heap, head) {
+        Ok(predicate) => predicate,
+        Err(error) => return error,
+    };
+    let Some(mut literals) = body_literals(heap, body) else {
+        return type_error(heap, "callable", clause);
+    };
+    if predicate_table.get_predicate(predicate).is_some() {
+        return static_error(heap, "modify", "static_procedure", predicate);
+    }
+    literals.insert(0, head);
+    predicate_table
+        .dynamic
+        .add_clause(predicate, DynamicClause::new(heap, &literals), first);
+    predicate_table.tables.invalidate(predicate);
+    PredReturn::True
+}
+
+/// `assertz(+Clause)` — add Clause as the last clause of its predicate,
+/// which becomes dynamic if it wasn't already. `assert/1` is the same.
+///
+/// Throws `permission_error(modify, static_procedure, Name/Arity)` for a
+/// predicate defined in loaded source without a `dynamic` declaration.
+pub fn assertz(
+    heap: &mut QueryHeap,
+    _: &mut Hypothesis,
+    goal: usize,
+    predicate_table: &PredicateTable,
+    _: Config,
+) -> PredReturn {
+    add_clause(heap, goal, predicate_table, false)
+}
+
+/// `asserta(+Clause)` — add Clause as the first clause of its predicate.
+pub fn asserta(
+    heap: &mut QueryHeap,
+    _: &mut Hypothesis,
+    goal: usize,
+    predicate_table: &PredicateTable,
+    _: Config,
+) -> PredReturn {
+    add_clause(heap, goal, predicate_table, true)
+}
+
+/// `retract(+Clause)` — remove the first clause unifying with Clause,
+/// binding it. On backtracking removes the next one.
+///
+/// A fact `Head` matches clauses with body `true` only. Fails for unknown
+/// predicates.
+pub fn retract(
+    heap: &mut QueryHeap,
+    _: &mut Hypothesis,
+    goal: usize,
+    predicate_table: &PredicateTable,
+    _: Config,
+) -> PredReturn {
+    let clause = goal_arg(heap, goal, 0);
+    let (head, body) = split_clause(heap, clause);
+    let predicate = match head_predicate(heap, head) {
+        Ok(predicate) => predicate,
+        Err(error) => return error,
+    };
+    if predicate_table.get_predicate(predicate).is_some() {
+        return static_error(heap, "modify", "static_procedure", predicate);
+    }
+    // The clause is only erased once its alternative is taken. If another
+    // goal erased it first, that alternative fails.
+    let erase = SymbolDB::set_const("$erase_clause");
+    matching_clauses(heap, predicate_table, predicate, head, body, |heap, clause| {
+        let erase = heap.set_const(erase);
+        let name = heap.set_const(predicate.0);
+        let arity = heap.heap_push((Tag::Int, predicate.1));
+        let id = heap.heap_push((Tag::Int, clause.id()));
+        vec![build_compound_from_addrs(heap, &[erase, name, arity, id])]
+    })
+}
+
+/// `'$erase_clause'(+Name, +Arity, +Id)` — erase the clause `retract/1`
+/// committed to, failing if it is already gone.
+fn erase_clause(
+    heap: &mut QueryHeap,
+    _: &mut Hypothesis,
+    goal: usize,
+    predicate_table: &PredicateTable,
+    _: Config,
+) -> PredReturn {
+    let [(Tag::Con, symbol), (Tag::Int, arity), (Tag::Int, id)] =
+        [0, 1, 2].map(|n| heap[goal_arg(heap, goal, n)])
+    else {
+        return PredReturn::False;
+    };
+    let erased = predicate_table.dynamic.erase((symbol, arity), id);
+    if erased {
+        predicate_table.tables.invalidate((symbol, arity));
+    }
+    erased.into()
+}
+
+/// `retractall(+Head)` — remove every clause whose head unifies with Head.
+/// Always succeeds, declaring the predicate dynamic if it is unknown.
+pub fn retractall(
+    heap: &mut QueryHeap,
+    _: &mut Hypothesis,
+    goal: usize,
+    predicate_table: &PredicateTable,
+    _: Config,
+) -> PredReturn {
+    let head = goal_arg(heap, goal, 0);
+    let predicate = match head_predicate(heap, head) {
+        Ok(predicate) => predicate,
+        Err(error) => return error,
+    };
+    if predicate_table.get_predicate(predicate).is_some() {
+        return static_error(heap, "modify", "static_procedure", predicate);
+    }
+    predicate_table.dynamic.declare(predicate);
+    let heap_len = heap.heap_len();
+    for clause in predicate_table.dynamic.clauses(predicate).unwrap() {
+        let literals = clause.instantiate(heap);
+        if unify(heap, literals[0], head).is_some() {
+            predicate_table.dynamic.erase(predicate, clause.id());
+        }
+        heap.truncate(heap_len);
+    }
+    predicate_table.tables.invalidate(predicate);
+    PredReturn::True
+}
+
+/// `clause(+Head, ?Body)` — Head and Body unify with a clause of a dynamic
+/// predicate. Enumerates the clauses on backtracking.
+///
+/// Throws `permission_error(access, private_procedure, Name/Arity)` for
+/// static predicates and `type_error(callable, Body)` if Body can't be a
+/// goal.
+pub fn clause(
+    heap: &mut QueryHeap,
+    _: &mut Hypothesis,
+    goal: usize,
+    predicate_table: &PredicateTable,
+    _: Config,
+) -> PredReturn {
+    let head = goal_arg(heap, goal, 0);
+    let body = goal_arg(heap, goal, 1);
+    let predicate = match head_predicate(heap, head) {
+        Ok(predicate) => predicate,
+        Err(error) => return error,
+    };
+    if !is_unbound(heap, body) && !matches!(heap[resolve(heap, body)].0, Tag::Con | Tag::Comp | Tag::Tup) {
+        return type_error(heap, "callable", body);
+    }
+    if predicate_table.get_predicate(predicate).is_some() {
+        return static_error(heap, "access", "private_procedure", predicate);
+    }
+    matching_clauses(heap, predicate_table, predicate, head, body, |_, _| Vec::new())
+}
+
+/// `dynamic(+Indicators)` — declare the predicates `Name/Arity` dynamic.
+/// Indicators may be a single indicator, a comma sequence or a list.
+///
+/// Throws `permission_error(modify, static_procedure, Name/Arity)` for a
+/// predicate that already has static clauses.
+pub fn dynamic(
+    heap: &mut QueryHeap,
+    _: &mut Hypothesis,
+    goal: usize,
+    predicate_table: &PredicateTable,
+    _: Config,
+) -> PredReturn {
+    let predicates = match predicate_indicators(&*heap, goal_arg(heap, goal, 0)) {
+        Ok(predicates) => predicates,
+        Err(culprit) => return must_be_error(heap, "predicate_indicator", culprit),
+    };
+    for predicate in predicates {
+        if predicate_table.get_predicate(predicate).is_some() {
+            return static_error(heap, "modify", "static_procedure", predicate);
+        }
+        predicate_table.dynamic.declare(predicate);
+    }
+    PredReturn::True
+}
+
+// ── Module registration ───────────────────────────────────────────────────────
+
+pub static DATABASE: PredicateModule = (
+    &[
+        ("assertz", 1, assertz),
+        ("assert", 1, assertz),
+        ("asserta", 1, asserta),
+        ("retract", 1, retract),
+        ("$erase_clause", 3, erase_clause),
+        ("retractall", 1, retractall),
+        ("clause", 2, clause),
+        ("dynamic", 1, dynamic),
+    ],
+    &[],
+);
+
+// ── Tests ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::{helpers::TestWrapper, DEFAULTS, MATHS, META_PREDICATES},
+        DATABASE,
+    };
+
+    fn tw(code: &str) -> TestWrapper {
+        let mut tw = TestWrapper::new(&[DEFAULTS, MATHS, META_PREDICATES, DATABASE]);
+        tw.app = tw.app.load_code(code).unwrap();
+        tw
+    }
+
+    #[test]
+    fn assert_and_call() {
+        let tw = tw("q(a).");
+        tw.assert_true("assertz(fact(1)), assertz(fact(2)), asserta(fact(0)).");
+        assert_eq!(tw.all_bindings("fact(X).", "X"), ["0", "1", "2"]);
+        tw.assert_true("assertz((double(X, Y) :- Y is X * 2)).");
+        tw.assert_binding("double(4, Y).", ("Y", "8"));
+    }
+
+    #[test]
+    fn asserted_big_numbers_outlive_query() {
+        let tw = tw("");
+        tw.assert_true("X is 2 ** 100, assertz(big(X)), Y is 1 rdiv 3, assertz(ratio(Y)).");
+        // Reuses the slots released with the first query's heap
+        tw.assert_true("X is 3 ** 100, Y is 2 rdiv 7.");
+        tw.assert_binding("big(X).", ("X", "1267650600228229401496703205376"));
+        tw.assert_binding("ratio(X).", ("X", "1r3"));
+    }
+
+    #[test]
+    fn declared_in_source() {
+        let tw = tw(":- dynamic counter/1, seen/1.\ncounter(0).\nbump :- retract(counter(N)), M is N + 1, assertz(counter(M)).");
+        tw.assert_false("seen(_).");
+        tw.assert_true("bump, bump.");
+        tw.assert_binding("counter(N).", ("N", "2"));
+        tw.assert_true("retractall(counter(_)).");
+        tw.assert_false("counter(_).");
+    }
+
+    #[test]
+    fn memoisation() {
+        let tw = tw(
+            ":- dynamic(memo/2).
+            fib(N, F) :- memo(N, F), !.
+            fib(N, N) :- N < 2, !.
+            fib(N, F) :- A is N - 1, B is N - 2, fib(A, FA), fib(B, FB), F is FA + FB, assertz(memo(N, F)).",
+        );
+        tw.assert_binding("fib(25, F).", ("F", "75025"));
+        tw.assert_true("memo(20, 6765).");
+    }
+
+    #[test]
+    fn logical_update_view() {
+        let tw = tw(":- dynamic p/1.\np(1).\np(2).");
+        // Clauses added while p/1 runs are not seen by that call
+        let results = tw.all_bindings("p(X), Y is X + 10, assertz(p(Y)).", "X");
+        assert_eq!(results, ["1", "2"]);
+        assert_eq!(tw.all_bindings("p(X).", "X"), ["1", "2", "11", "12"]);
+        // Nor are clauses removed while it runs
+        assert_eq!(tw.all_bindings("p(X), retractall(p(_)).", "X"), ["1", "2", "11", "12"]);
+        tw.assert_false("p(_).");
+    }
+
+    #[test]
+    fn retract_backtracks() {
+        let tw = tw(":- dynamic p/2.\np(a, 1).\np(b, 2).\np(a, 3).");
+        assert_eq!(tw.all_bindings("retract(p(a, X)).", "X"), ["1", "3"]);
+        assert_eq!(tw.all_bindings("p(K, _).", "K"), ["b"]);
+        tw.assert_false("retract(p(a, _)).");
+    }
+
+    #[test]
+    fn clause_and_retract_rules() {
+        let tw = tw(":- dynamic r/1.\nr(X) :- X > 1, X < 5.\nr(0).");
+        tw.assert_true("clause(r(X), B), B == (X > 1, X < 5).");
+        assert_eq!(tw.all_bindings("clause(r(X), true).", "X"), ["0"]);
+        // A fact only matches clauses with body true
+        tw.assert_true("retract(r(_)), not(r(0)), r(3).");
+        tw.assert_true("retract((r(X) :- X > 1, Y)), not(r(3)).");
+        tw.assert_false("r(_).");
+    }
+
+    #[test]
+    fn persists_between_queries() {
+        let tw = tw("q(a).");
+        tw.assert_false("stored(_).");
+        tw.assert_true("assertz(stored(result)).");
+        tw.assert_binding("stored(X).", ("X", "result"));
+        // Strings outlive the query heap they were written on
+        tw.assert_true("assertz(stored(\"text\")).");
+        tw.assert_true("X = \"other\".");
+        assert_eq!(tw.all_bindings("stored(X).", "X"), ["result", "\"text\""]);
+    }
+
+    #[test]
+    fn errors() {
+        let tw = tw("q(a).");
+        tw.assert_error("assertz(_).", "instantiation_error");
+        tw.assert_error("assertz(1).", "type_error(callable,1)");
+        tw.assert_error("assertz((p :- 1)).", "type_error(callable,:-(p,1))");
+        tw.assert_error("assertz(q(b)).", "permission_error(modify,static_procedure,/(q,1))");
+        tw.assert_error("retract(q(a)).", "permission_error(modify,static_procedure,/(q,1))");
+        tw.assert_error("clause(q(X), B).", "permission_error(access,private_procedure,/(q,1))");
+        tw.assert_error("dynamic(q/1).", "permission_error(modify,static_procedure,/(q,1))");
+        tw.assert_error("dynamic(foo).", "type_error(predicate_indicator,foo)");
+        tw.assert_false("retract(unknown(_)).");
+        tw.assert_false("clause(unknown(_), _).");
+    }
+}
@@ -0,0 +1,310 @@
+//! Built-in grammar predicates.
+//!
+//! Grammar rules (`-->`) are translated into ordinary clauses when they are
+//! loaded (see the parser's DCG translation). `phrase/2,3` runs a grammar
+//! body against a list, translating the body in the same way when called.
+
+use super::{helpers::*, PredReturn, PredicateModule};
+use crate::{
+    heap::{
+        heap::{Heap, Tag},
+        query_heap::QueryHeap,
+        symbol_db::SymbolDB,
+    },
+    program::{hypothesis::Hypothesis, predicate_table::PredicateTable},
+    Config,
+};
+
+fn constant(heap: &mut QueryHeap, name: &str) -> usize {
+    heap.set_const(SymbolDB::set_const(name))
+}
+
+fn unify_goal(heap: &mut QueryHeap, left: usize, right: usize) -> usize {
+    let eq = constant(heap, "=");
+    build_compound_from_addrs(heap, &[eq, left, right])
+}
+
+/// A goal sequence as a single goal.
+fn conjunction(heap: &mut QueryHeap, goals: &[usize]) -> usize {
+    match goals {
+        [] => constant(heap, "true"),
+        [goal] => *goal,
+        goals => build_tuple_from_addrs(heap, goals),
+    }
+}
+
+/// The list of character codes of a string.
+fn codes(heap: &mut QueryHeap, id: usize) -> usize {
+    let cells: Vec<_> = SymbolDB::get_string(id)
+        .chars()
+        .map(|c| (Tag::Int, c as usize))
+        .collect();
+    build_list(heap, &cells)
+}
+
+/// The goals for the grammar body at `body` between the lists `s0` and `s`.
+fn body_goals(
+    heap: &mut QueryHeap,
+    body: usize,
+    s0: usize,
+    s: usize,
+) -> Result<Vec<usize>, PredReturn> {
+    let body = heap.deref_addr(body);
+    if is_unbound(heap, body) {
+        return Err(instantiation_error(heap));
+    }
+    let base = resolve(heap, body);
+    match heap[base] {
+        (Tag::Tup, len) => {
+            let items: Vec<usize> = heap.str_iterator(base).collect();
+            let mut goals = Vec::new();
+            let mut current = s0;
+            for (i, item) in items.into_iter().enumerate() {
+                let next = if i + 1 == len { s } else { heap.set_ref(None) };
+                goals.append(&mut body_goals(heap, item, current, next)?);
+                current = next;
+            }
+            if len == 0 {
+                goals.push(unify_goal(heap, s0, s));
+            }
+            Ok(goals)
+        }
+        (Tag::Set, _) => {
+            let mut goals: Vec<usize> = heap.str_iterator(base).collect();
+            goals.push(unify_goal(heap, s0, s));
+            Ok(goals)
+        }
+        (Tag::ELis, _) => Ok(vec![unify_goal(heap, s0, s)]),
+        (Tag::Lis, _) => {
+            let (items, tail) = read_list_with_tail(heap, base);
+            match heap[tail] {
+                (Tag::ELis, _) => (),
+                _ if is_unbound(heap, tail) => return Err(instantiation_error(heap)),
+                _ => return Err(type_error(heap, "list", body)),
+            }
+            let list = build_list_with_tail(heap, &items, s);
+            Ok(vec![unify_goal(heap, s0, list)])
+        }
+        (Tag::Stri, id) => {
+            let list = codes(heap, id);
+            let (items, _) = read_list_with_tail(heap, list);
+            let list = build_list_with_tail(heap, &items, s);
+            Ok(vec![unify_goal(heap, s0, list)])
+        }
+        (Tag::Con, symbol) if &*SymbolDB::get_const(symbol) == "!" => {
+            Ok(vec![body, unify_goal(heap, s0, s)])
+        }
+        (Tag::Comp, 3 | 2) => {
+            let functor = match heap[heap.deref_addr(base + 1)] {
+                (Tag::Con, symbol) => SymbolDB::get_const(symbol),
+                _ => return nonterminal(heap, body, s0, s),
+            };
+            let args: Vec<usize> = (2..=heap[base].1)
+                .map(|i| heap.deref_addr(base + i))
+                .collect();
+            match (&*functor, args.as_slice()) {
+                (";", &[left, right]) => {
+                    let left = body_goals(heap, left, s0, s)?;
+                    let left = conjunction(heap, &left);
+                    let right = body_goals(heap, right, s0, s)?;
+                    let right = conjunction(heap, &right);
+                    let or = constant(heap, ";");
+                    Ok(vec![build_compound_from_addrs(heap, &[or, left, right])])
+                }
+                (op @ ("->" | "*->"), &[condition, then]) => {
+                    let s1 = heap.set_ref(None);
+                    let condition = body_goals(heap, condition, s0, s1)?;
+                    let condition = conjunction(heap, &condition);
+                    let then = body_goals(heap, then, s1, s)?;
+                    let then = conjunction(heap, &then);
+                    let op = constant(heap, op);
+                    Ok(vec![build_compound_from_addrs(heap, &[op, condition, then])])
+                }
+                ("\\+", &[goal]) => {
+                    let rest = heap.set_ref(None);
+                    let goal = body_goals(heap, goal, s0, rest)?;
+                    let goal = conjunction(heap, &goal);
+                    let not = constant(heap, "\\+");
+                    let not = build_compound_from_addrs(heap, &[not, goal]);
+                    Ok(vec![not, unify_goal(heap, s0, s)])
+                }
+                _ => nonterminal(heap, body, s0, s),
+            }
+        }
+        _ => nonterminal(heap, body, s0, s),
+    }
+}
+
+/// The call of the nonterminal at `body` with the lists appended. `call/N`
+/// bodies gain the lists as extra arguments like any other.
+fn nonterminal(
+    heap: &mut QueryHeap,
+    body: usize,
+    s0: usize,
+    s: usize,
+) -> Result<Vec<usize>, PredReturn> {
+    match heap[resolve(heap, body)].0 {
+        Tag::Con | Tag::Comp => Ok(vec![apply_closure(heap, body, &[s0, s]).unwrap()]),
+        _ => Err(type_error(heap, "callable", body)),
+    }
+}
+
+/// `phrase(+Body, ?List)` and `phrase(+Body, ?List, ?Rest)` — List, less its
+/// suffix Rest (`[]` for `phrase/2`), parses as the grammar body Body.
+///
+/// Body may be a nonterminal or any grammar body: terminal lists, strings,
+/// `{}` goals and control constructs. A string List is read as its list of
+/// character codes. Cuts in Body are local to the call. Throws an
+/// instantiation error for an unbound Body and `type_error(callable, Body)`
+/// for one that can't be a grammar body.
+pub fn phrase(
+    heap: &mut QueryHeap,
+    _: &mut Hypothesis,
+    goal: usize,
+    _: &PredicateTable,
+    _: Config,
+) -> PredReturn {
+    let arity = heap.str_symbol_arity(goal).1;
+    let body = goal_arg(heap, goal, 0);
+    let mut list = goal_arg(heap, goal, 1);
+    if let (Tag::Stri, id) = heap[list] {
+        list = codes(heap, id);
+    }
+    let rest = if arity == 3 {
+        goal_arg(heap, goal, 2)
+    } else {
+        heap.heap_push((Tag::ELis, 0))
+    };
+
+    match body_goals(heap, body, list, rest) {
+        Ok(goals) => {
+            let body = conjunction(heap, &goals);
+            let call = constant(heap, "call");
+            PredReturn::Success(vec![], vec![build_compound_from_addrs(heap, &[call, body])])
+        }
+        Err(error) => error,
+    }
+}
+
+// ── Module registration ───────────────────────────────────────────────────────
+
+pub static DCG: PredicateModule = (&[("phrase", 2, phrase), ("phrase", 3, phrase)], &[]);
+
+// ── Tests ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::{helpers::TestWrapper, DEFAULTS, LISTS, MATHS, META_PREDICATES},
+        DCG,
+    };
+    use crate::Config;
+
+    const GRAMMAR: &str = "
+        greeting --> [hello], name.
+        greeting --> [hi], name, [!].
+        name --> [world].
+        name --> [prolog].
+
+        digits([D|T]) --> digit(D), digits(T).
+        digits([D]) --> digit(D).
+        digit(D) --> [D], { D >= 48, D =< 57 }.
+
+        abc --> \"abc\".
+
+        look_ahead(X), [X] --> [X].
+
+        opt --> ([a] -> [b] ; [c]).
+        not_a --> \\+ [a], [_].
+        first --> [x], !, [y].
+        first --> [x].
+    ";
+
+    fn tw() -> TestWrapper {
+        let mut tw = TestWrapper::new(&[DEFAULTS, MATHS, META_PREDICATES, LISTS, DCG]);
+        tw.app = tw.app.load_code(GRAMMAR).unwrap();
+        tw
+    }
+
+    #[test]
+    fn nonterminals_and_terminals() {
+        let tw = tw();
+        tw.assert_true("phrase(greeting, [hello, world]).");
+        tw.assert_true("phrase(greeting, [hi, prolog, !]).");
+        tw.assert_false("phrase(greeting, [hello, there]).");
+        tw.assert_binding("phrase(greeting, [hello|R], []).", ("R", "[world]"));
+        tw.assert_binding("phrase(greeting, [hello, world, x], R).", ("R", "[x]"));
+        assert_eq!(tw.all_bindings("phrase(name, [N]).", "N"), vec!["world", "prolog"]);
+        // The translated clauses are ordinary predicates
+        tw.assert_true("name([world, x], [x]).");
+    }
+
+    #[test]
+    fn goals_and_strings() {
+        let tw = tw();
+        tw.assert_binding("phrase(digits(Ds), \"123\").", ("Ds", "[49,50,51]"));
+        tw.assert_false("phrase(digits(Ds), \"1a\").");
+        tw.assert_true("phrase(abc, \"abc\").");
+        tw.assert_true("phrase(abc, [97, 98, 99]).");
+        tw.assert_true("phrase(\"ab\", \"ab\").");
+    }
+
+    #[test]
+    fn pushback() {
+        let tw = tw();
+        tw.assert_bindings("phrase(look_ahead(X), [a, b], R).", &[("X", "a"), ("R", "[a,b]")]);
+    }
+
+    #[test]
+    fn control_constructs() {
+        let tw = tw();
+        tw.assert_true("phrase(opt, [a, b]).");
+        tw.assert_true("phrase(opt, [c]).");
+        tw.assert_false("phrase(opt, [a, c]).");
+        tw.assert_true("phrase(not_a, [b]).");
+        tw.assert_false("phrase(not_a, [a]).");
+        tw.assert_true("phrase(first, [x, y]).");
+        tw.assert_false("phrase(first, [x]).");
+        tw.assert_true("phrase(([a], {X = 1}, ([b] ; [c])), [a, c]), X == 1.");
+    }
+
+    #[test]
+    fn errors() {
+        let tw = tw();
+        tw.assert_error("phrase(G, [a]).", "instantiation_error");
+        tw.assert_error("phrase(1, [a]).", "type_error(callable,1)");
+    }
+
+    #[test]
+    fn meta_rule() {
+        let mut tw = TestWrapper::new(&[DEFAULTS, MATHS, META_PREDICATES, DCG]);
+        tw.app = tw
+            .app
+            .config(Config {
+                max_depth: 4,
+                max_clause: 1,
+                max_pred: 0,
+                debug: false,
+                ..Config::default()
+            })
+            .load_code(
+                "destroy_verb --> [destroy].
+                 target --> [target].
+                 creature --> [creature].
+                 P --> Q, R, {P, Q, R}.",
+            )
+            .unwrap();
+        tw.app = tw.app.add_body_predicates(["destroy_verb/2", "target/2"]).unwrap();
+        tw.assert_true("phrase(destroy_verb, [destroy]).");
+
+        // The grammar rule is learned like any other meta-rule
+        let solution = tw
+            .app
+            .query_session("phrase(destroy_target, [destroy, target]).")
+            .unwrap()
+            .next()
+            .unwrap();
+        assert!(solution.hypothesis.contains("destroy_target("));
+        assert!(solution.hypothesis.contains("destroy_verb("));
+    }
+}
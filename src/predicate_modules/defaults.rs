@@ -1,7 +1,10 @@
+use std::{cmp::Ordering, collections::HashMap};
+
 use crate::{
     heap::{
-        heap::{Heap, Tag},
+        heap::{Cell, Heap, Tag},
         query_heap::QueryHeap,
+        symbol_db::SymbolDB,
     },
     program::{hypothesis::Hypothesis, predicate_table::PredicateTable},
     resolution::unification::unify,
@@ -47,6 +50,81 @@ pub fn not_unify(
     unify(heap, goal + 2, goal + 3).is_none().into()
 }
 
+// ---------------------------------------------------------------------------
+// Standard order of terms
+// ---------------------------------------------------------------------------
+
+/// `compare/3`: `compare(O, A, B)` unifies O with `<`, `=` or `>` according
+/// to the standard order of A and B: `Var < Number < Atom < String <
+/// Compound`.
+pub fn compare(
+    heap: &mut QueryHeap,
+    _: &mut Hypothesis,
+    goal: usize,
+    _: &PredicateTable,
+    _: Config,
+) -> PredReturn {
+    let order = goal_arg(heap, goal, 0);
+    if !is_unbound(heap, order) {
+        match heap[order] {
+            (Tag::Con, id) if ["<", "=", ">"].contains(&&*SymbolDB::get_const(id)) => (),
+            (Tag::Con, _) => return domain_error(heap, "order", order),
+            _ => return type_error(heap, "atom", order),
+        }
+    }
+    let result = match heap.term_compare(goal_arg(heap, goal, 1), goal_arg(heap, goal, 2)) {
+        Ordering::Less => "<",
+        Ordering::Equal => "=",
+        Ordering::Greater => ">",
+    };
+    let result = heap.set_const(SymbolDB::set_const(result));
+    unify_result(heap, result, order)
+}
+
+/// `@</2`: the first term precedes the second in the standard order.
+pub fn term_less(
+    heap: &mut QueryHeap,
+    _: &mut Hypothesis,
+    goal: usize,
+    _: &PredicateTable,
+    _: Config,
+) -> PredReturn {
+    heap.term_compare(goal_arg(heap, goal, 0), goal_arg(heap, goal, 1)).is_lt().into()
+}
+
+/// `@>/2`: the first term follows the second in the standard order.
+pub fn term_greater(
+    heap: &mut QueryHeap,
+    _: &mut Hypothesis,
+    goal: usize,
+    _: &PredicateTable,
+    _: Config,
+) -> PredReturn {
+    heap.term_compare(goal_arg(heap, goal, 0), goal_arg(heap, goal, 1)).is_gt().into()
+}
+
+/// `@=</2`: the first term precedes or equals the second in the standard order.
+pub fn term_less_equal(
+    heap: &mut QueryHeap,
+    _: &mut Hypothesis,
+    goal: usize,
+    _: &PredicateTable,
+    _: Config,
+) -> PredReturn {
+    heap.term_compare(goal_arg(heap, goal, 0), goal_arg(heap, goal, 1)).is_le().into()
+}
+
+/// `@>=/2`: the first term follows or equals the second in the standard order.
+pub fn term_greater_equal(
+    heap: &mut QueryHeap,
+    _: &mut Hypothesis,
+    goal: usize,
+    _: &PredicateTable,
+    _: Config,
+) -> PredReturn {
+    heap.term_compare(goal_arg(heap, goal, 0), goal_arg(heap, goal, 1)).is_ge().into()
+}
+
 // ---------------------------------------------------------------------------
 // =.. (univ)
 // ---------------------------------------------------------------------------
@@ -89,6 +167,315 @@ pub fn univ(
     }
 }
 
+// ---------------------------------------------------------------------------
+// Term inspection and construction
+// ---------------------------------------------------------------------------
+
+/// The name `functor/3` and `arg/3` give a list cell, and the names that
+/// construct a tuple or a set.
+const LIST_FUNCTOR: &str = "[|]";
+const TUPLE_FUNCTOR: &str = "()";
+const SET_FUNCTOR: &str = "{}";
+
+/// True for terms with no arguments: atoms, numbers, strings, `[]`, `()`
+/// and `{}`.
+fn is_atomic_term(heap: &QueryHeap, addr: usize) -> bool {
+    match heap[resolve(heap, addr)] {
+        (Tag::Tup | Tag::Set, len) => len == 0,
+        (tag, _) => !matches!(tag, Tag::Comp | Tag::Lis | Tag::Ref | Tag::AVar),
+    }
+}
+
+/// Build a term named `name` with `arity` fresh variables as arguments.
+fn fresh_structure(heap: &mut QueryHeap, name: usize, arity: usize) -> usize {
+    let header = match &*SymbolDB::get_const(name) {
+        LIST_FUNCTOR if arity == 2 => heap.heap_push((Tag::Lis, heap.heap_len() + 1)),
+        TUPLE_FUNCTOR => heap.heap_push((Tag::Tup, arity)),
+        SET_FUNCTOR => heap.heap_push((Tag::Set, arity)),
+        _ => {
+            let header = heap.heap_push((Tag::Comp, arity + 1));
+            heap.heap_push((Tag::Con, name));
+            header
+        }
+    };
+    for _ in 0..arity {
+        heap.set_ref(None);
+    }
+    header
+}
+
+/// `functor/3`: `functor(T, N, A)` relates a term to its name and arity.
+///
+/// If T is bound, N and A are unified with its name and arity. Lists have
+/// the name `'[|]'`, tuples `'()'` and sets `'{}'`; atomic terms are their
+/// own name with arity 0. If T is unbound it is bound to a term named N with
+/// A fresh arguments, so `functor(T, '()', 2)` builds the tuple `(_, _)`.
+pub fn functor(
+    heap: &mut QueryHeap,
+    _: &mut Hypothesis,
+    goal: usize,
+    _: &PredicateTable,
+    _: Config,
+) -> PredReturn {
+    let term = resolve(heap, goal_arg(heap, goal, 0));
+    let name = goal_arg(heap, goal, 1);
+    let arity = goal_arg(heap, goal, 2);
+
+    if !is_unbound(heap, term) {
+        let (name_addr, len) = match heap[term] {
+            (Tag::Comp, len) => (term + 1, len - 1),
+            (Tag::Lis, _) => (heap.set_const(SymbolDB::set_const(LIST_FUNCTOR)), 2),
+            (Tag::Tup, len) if len > 0 => (heap.set_const(SymbolDB::set_const(TUPLE_FUNCTOR)), len),
+            (Tag::Set, len) if len > 0 => (heap.set_const(SymbolDB::set_const(SET_FUNCTOR)), len),
+            _ => (term, 0),
+        };
+        let len = heap.heap_push((Tag::Int, len));
+        let built = build_tuple_from_addrs(heap, &[name_addr, len]);
+        let target = build_tuple_from_addrs(heap, &[name, arity]);
+        return unify_result(heap, built, target);
+    }
+
+    if is_unbound(heap, name) {
+        return instantiation_error(heap);
+    }
+    let n = match heap[arity] {
+        (Tag::Int, n) => n as isize,
+        _ => return must_be_error(heap, "integer", arity),
+    };
+    let built = match heap[resolve(heap, name)] {
+        _ if n < 0 => return domain_error(heap, "not_less_than_zero", arity),
+        _ if n == 0 && is_atomic_term(heap, name) => name,
+        (Tag::Con, id) => fresh_structure(heap, id, n as usize),
+        _ if is_atomic_term(heap, name) => return type_error(heap, "atom", name),
+        _ => return type_error(heap, "atomic", name),
+    };
+    unify_result(heap, built, term)
+}
+
+/// `arg/3`: `arg(N, T, A)` unifies A with the Nth argument of T, counting
+/// from 1. The elements of tuples and sets are their arguments, and a list
+/// cell has its head and tail. With N unbound, enumerates the arguments on
+/// backtracking.
+pub fn arg(
+    heap: &mut QueryHeap,
+    _: &mut Hypothesis,
+    goal: usize,
+    _: &PredicateTable,
+    _: Config,
+) -> PredReturn {
+    let n = goal_arg(heap, goal, 0);
+    let term = goal_arg(heap, goal, 1);
+    let value = goal_arg(heap, goal, 2);
+    if !is_unbound(heap, n) && heap[n].0 != Tag::Int {
+        return type_error(heap, "integer", n);
+    }
+    let Some(slots) = arg_slots(heap, term) else {
+        return must_be_error(heap, "compound", term);
+    };
+
+    if let (Tag::Int, i) = heap[n] {
+        return match (i as isize).checked_sub(1).and_then(|i| slots.get(i as usize)) {
+            Some(&slot) if i as isize > 0 => unify_result(heap, slot, value),
+            _ => PredReturn::False,
+        };
+    }
+
+    let target = build_tuple_from_addrs(heap, &[n, value]);
+    let mut choices = Vec::with_capacity(slots.len());
+    for (i, slot) in slots.into_iter().enumerate() {
+        let index = heap.heap_push((Tag::Int, i + 1));
+        let built = build_tuple_from_addrs(heap, &[index, slot]);
+        if let Some(sub) = unify(heap, built, target) {
+            choices.push((sub.get_bindings().to_vec(), vec![]));
+        }
+    }
+    // Choices are popped from the back
+    choices.reverse();
+    PredReturn::Choices(choices)
+}
+
+/// `copy_term/2`: `copy_term(T, C)` unifies C with a copy of T in which
+/// every variable is replaced by a fresh one. Variables shared within T stay
+/// shared in the copy.
+pub fn copy_term(
+    heap: &mut QueryHeap,
+    _: &mut Hypothesis,
+    goal: usize,
+    _: &PredicateTable,
+    _: Config,
+) -> PredReturn {
+    let mut store = Vec::new();
+    let copy = copy_term_cell(&mut store, &*heap, goal_arg(heap, goal, 0), &mut HashMap::new());
+    let copy = copy_term_cell(heap, &store, copy, &mut HashMap::new());
+    unify_result(heap, copy, goal_arg(heap, goal, 1))
+}
+
+fn collect_variable(heap: &QueryHeap, addr: usize, vars: &mut Vec<usize>) -> bool {
+    // Each `_` is a distinct variable
+    if heap[addr].0 == Tag::AVar || (super::helpers::is_var(heap, addr) && !vars.contains(&addr)) {
+        vars.push(addr);
+    }
+    false
+}
+
+/// `term_variables/2`: `term_variables(T, Vs)` unifies Vs with the list of
+/// distinct variables of T, in depth-first, left-to-right order.
+pub fn term_variables(
+    heap: &mut QueryHeap,
+    _: &mut Hypothesis,
+    goal: usize,
+    _: &PredicateTable,
+    _: Config,
+) -> PredReturn {
+    let mut vars = Vec::new();
+    heap.walk_term(goal_arg(heap, goal, 0), &mut vars, collect_variable);
+    let list = build_list_from_addrs(heap, &vars);
+    unify_result(heap, list, goal_arg(heap, goal, 1))
+}
+
+/// The argument slot `setarg(N, T, _)` and `nb_setarg(N, T, _)` overwrite,
+/// `Ok(None)` if T has no Nth argument.
+fn setarg_slot(heap: &mut QueryHeap, goal: usize) -> Result<Option<usize>, PredReturn> {
+    let n = goal_arg(heap, goal, 0);
+    let term = goal_arg(heap, goal, 1);
+    let n = match heap[n] {
+        (Tag::Int, n) => n as isize,
+        _ => return Err(must_be_error(heap, "integer", n)),
+    };
+    let Some(slots) = arg_slots(heap, term) else {
+        return Err(must_be_error(heap, "compound", term));
+    };
+    let slot = match n.checked_sub(1) {
+        Some(i) if i >= 0 => slots.get(i as usize).copied(),
+        _ => None,
+    };
+    match slot {
+        Some(slot) if !heap.is_writable(slot) => {
+            Err(permission_error(heap, "modify", "compound", term))
+        }
+        slot => Ok(slot),
+    }
+}
+
+/// `setarg/3`: `setarg(N, T, A)` destructively replaces the Nth argument of
+/// T with A. The assignment is undone on backtracking.
+///
+/// Terms shared with the program, such as the arguments of facts, cannot be
+/// modified and raise a permission error; `copy_term/2` them first.
+pub fn setarg(
+    heap: &mut QueryHeap,
+    _: &mut Hypothesis,
+    goal: usize,
+    _: &PredicateTable,
+    _: Config,
+) -> PredReturn {
+    match setarg_slot(heap, goal) {
+        Ok(Some(slot)) => {
+            let value = cell_for_addr(heap, goal_arg(heap, goal, 2));
+            PredReturn::Assign(vec![(slot, value)])
+        }
+        Ok(None) => PredReturn::False,
+        Err(error) => error,
+    }
+}
+
+/// `nb_setarg/3`: like `setarg/3`, but the assignment survives
+/// backtracking.
+///
+/// The heap built after a choice point is reclaimed when backtracking to it,
+/// so the new argument must be atomic.
+pub fn nb_setarg(
+    heap: &mut QueryHeap,
+    _: &mut Hypothesis,
+    goal: usize,
+    _: &PredicateTable,
+    _: Config,
+) -> PredReturn {
+    let value = goal_arg(heap, goal, 2);
+    match setarg_slot(heap, goal) {
+        Err(error) => error,
+        Ok(_) if !matches!(heap[value].0, Tag::Con | Tag::Int | Tag::BigInt | Tag::Rat | Tag::Flt | Tag::Stri | Tag::ELis) => {
+            must_be_error(heap, "atomic", value)
+        }
+        Ok(Some(slot)) => {
+            heap[slot] = heap[value];
+            PredReturn::True
+        }
+        Ok(None) => PredReturn::False,
+    }
+}
+
+/// `subsumes_term/2`: `subsumes_term(G, S)` succeeds if S is an instance of
+/// G, i.e. G and S unify without binding any variable of S. Binds nothing.
+pub fn subsumes_term(
+    heap: &mut QueryHeap,
+    _: &mut Hypothesis,
+    goal: usize,
+    _: &PredicateTable,
+    _: Config,
+) -> PredReturn {
+    let heap_len = heap.heap_len();
+    let general = goal_arg(heap, goal, 0);
+    let specific = name_anonymous_vars(heap, goal_arg(heap, goal, 1));
+    let mut vars = Vec::new();
+    for var in heap.term_vars(specific, false) {
+        if !vars.contains(&var) {
+            vars.push(var);
+        }
+    }
+    let Some(sub) = unify(heap, general, specific) else {
+        heap.truncate(heap_len);
+        return PredReturn::False;
+    };
+    let bindings = sub.get_bindings();
+    heap.bind(&bindings);
+    // Each variable of S must still be unbound and distinct from the others
+    let mut seen = Vec::with_capacity(vars.len());
+    let subsumes = vars.into_iter().all(|var| {
+        let var = heap.deref_addr(var);
+        let fresh = super::helpers::is_var(heap, var) && !seen.contains(&var);
+        seen.push(var);
+        fresh
+    });
+    heap.unbind(&bindings);
+    heap.truncate(heap_len);
+    subsumes.into()
+}
+
+/// The term at `addr` with each `_` in it replaced by a fresh variable, so
+/// that binding it can be detected. Its other variables are kept.
+fn name_anonymous_vars(heap: &mut QueryHeap, addr: usize) -> usize {
+    if !has_anonymous_var(heap, addr) {
+        return addr;
+    }
+    let mut store: Vec<Cell> = Vec::new();
+    let mut ref_map = HashMap::new();
+    let copy = copy_term_cell(&mut store, &*heap, addr, &mut ref_map);
+    for (i, cell) in store.iter_mut().enumerate() {
+        if cell.0 == Tag::AVar {
+            *cell = (Tag::Ref, i);
+        }
+    }
+    let mut ref_map = ref_map.into_iter().map(|(var, copy)| (copy, var)).collect();
+    copy_term_cell(heap, &store, copy, &mut ref_map)
+}
+
+/// Whether the term at `addr` contains a `_`.
+fn has_anonymous_var(heap: &QueryHeap, addr: usize) -> bool {
+    let addr = heap.deref_addr(addr);
+    match heap[addr] {
+        (Tag::AVar, _) => true,
+        (Tag::Str, pointer) => has_anonymous_var(heap, pointer),
+        (Tag::Lis, pointer) => {
+            has_anonymous_var(heap, pointer) || has_anonymous_var(heap, pointer + 1)
+        }
+        (Tag::Comp | Tag::Tup | Tag::Set, _) => {
+            heap.str_iterator(addr).any(|arg| has_anonymous_var(heap, arg))
+        }
+        _ => false,
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Type checks
 // ---------------------------------------------------------------------------
@@ -147,7 +534,18 @@ pub fn is_int(
     _: &PredicateTable,
     _: Config,
 ) -> PredReturn {
-    (heap[goal_arg(heap, goal, 0)].0 == Tag::Int).into()
+    matches!(heap[goal_arg(heap, goal, 0)].0, Tag::Int | Tag::BigInt).into()
+}
+
+/// `is_rational/1`: succeeds if the argument is an integer or a rational.
+pub fn is_rational(
+    heap: &mut QueryHeap,
+    _: &mut Hypothesis,
+    goal: usize,
+    _: &PredicateTable,
+    _: Config,
+) -> PredReturn {
+    matches!(heap[goal_arg(heap, goal, 0)].0, Tag::Int | Tag::BigInt | Tag::Rat).into()
 }
 
 /// `is_float/1`: succeeds if the argument is a float.
@@ -161,7 +559,7 @@ pub fn is_float(
     (heap[goal_arg(heap, goal, 0)].0 == Tag::Flt).into()
 }
 
-/// `is_number/1`: succeeds if the argument is an integer or a float.
+/// `is_number/1`: succeeds if the argument is an integer, rational or float.
 pub fn is_number(
     heap: &mut QueryHeap,
     _: &mut Hypothesis,
@@ -169,7 +567,7 @@ pub fn is_number(
     _: &PredicateTable,
     _: Config,
 ) -> PredReturn {
-    matches!(heap[goal_arg(heap, goal, 0)], (Tag::Int, _) | (Tag::Flt, _)).into()
+    matches!(heap[goal_arg(heap, goal, 0)].0, Tag::Int | Tag::BigInt | Tag::Rat | Tag::Flt).into()
 }
 
 /// `is_string/1`: succeeds if the argument is a string literal.
@@ -254,6 +652,20 @@ pub static DEFAULTS: PredicateModule = (
         ("=\\=", 2, not_equal),
         ("\\=", 2, not_unify),
         ("=..", 2, univ),
+        // Standard order of terms
+        ("compare", 3, compare),
+        ("@<", 2, term_less),
+        ("@>", 2, term_greater),
+        ("@=<", 2, term_less_equal),
+        ("@>=", 2, term_greater_equal),
+        // Term inspection and construction
+        ("functor", 3, functor),
+        ("arg", 3, arg),
+        ("copy_term", 2, copy_term),
+        ("term_variables", 2, term_variables),
+        ("setarg", 3, setarg),
+        ("nb_setarg", 3, nb_setarg),
+        ("subsumes_term", 2, subsumes_term),
         // Type checks
         ("var", 1, is_var),
         ("nonvar", 1, non_var),
@@ -261,6 +673,7 @@ pub static DEFAULTS: PredicateModule = (
         ("valid_functor", 1, valid_functor),
         ("int", 1, is_int),
         ("float", 1, is_float),
+        ("rational", 1, is_rational),
         ("number", 1, is_number),
         ("string", 1, is_string),
         ("compound", 1, is_compound),
@@ -323,6 +736,32 @@ mod tests {
         tw.assert_false("1 = 2.");
     }
 
+    // ── standard order of terms ───────────────────────────────────────────
+
+    #[test]
+    fn compare() {
+        let tw = tw();
+        tw.assert_binding("compare(O, 1, a).", ("O", "<"));
+        tw.assert_binding("compare(O, p(a), p(a)).", ("O", "="));
+        tw.assert_binding("compare(O, p(b), p(a, a)).", ("O", "<"));
+        tw.assert_binding("compare(O, q(a), p(b)).", ("O", ">"));
+        tw.assert_binding("compare(O, {b, a}, {a, b}).", ("O", "="));
+        tw.assert_false("compare(<, b, a).");
+        tw.assert_error("compare(foo, a, b).", "domain_error(order,foo)");
+        tw.assert_error("compare(1, a, b).", "type_error(atom,1)");
+    }
+
+    #[test]
+    fn term_order() {
+        let tw = tw();
+        tw.assert_true("X @< 1, 1 @< a, a @< \"a\", \"a\" @< p(a).");
+        tw.assert_true("1.0 @< 1, 1 @< 1.5, [] @< a.");
+        tw.assert_true("p(b) @< p(a, a), p(a, b) @> p(a, a).");
+        tw.assert_true("(a, b, c) @> [a], {a} @=< {a}, b @>= a.");
+        tw.assert_false("a @> b.");
+        tw.assert_false("p(a) @< p(a).");
+    }
+
     // ── =.. (univ) ────────────────────────────────────────────────────────
 
     #[test]
@@ -350,6 +789,107 @@ mod tests {
         tw.assert_bindings("p(a,b,c,d)=..[p,a|T].", &[("T", "[b,c,d]")]);
     }
 
+    // ── term inspection and construction ──────────────────────────────────
+
+    #[test]
+    fn functor_decompose() {
+        let tw = tw();
+        tw.assert_bindings("functor(p(a,Y), N, A).", &[("N", "p"), ("A", "2")]);
+        tw.assert_bindings("functor([a,b], N, A).", &[("N", "[|]"), ("A", "2")]);
+        tw.assert_bindings("functor((a,b,c), N, A).", &[("N", "()"), ("A", "3")]);
+        tw.assert_bindings("functor({a,b}, N, A).", &[("N", "{}"), ("A", "2")]);
+        tw.assert_bindings("functor(\"abc\", N, A).", &[("N", "\"abc\""), ("A", "0")]);
+        tw.assert_bindings("functor(1.5, N, A).", &[("N", "1.5"), ("A", "0")]);
+        tw.assert_false("functor(p(a), p, 2).");
+    }
+
+    #[test]
+    fn functor_construct() {
+        let tw = tw();
+        tw.assert_true("functor(T, p, 2), T = p(a, b).");
+        tw.assert_true("functor(T, '[|]', 2), T = [a].");
+        tw.assert_true("functor(T, '()', 2), T = (a, b).");
+        tw.assert_true("functor(T, '{}', 2), set(T), arg(2, T, B), var(B).");
+        tw.assert_binding("functor(T, 42, 0).", ("T", "42"));
+        tw.assert_error("functor(T, N, 1).", "instantiation_error");
+        tw.assert_error("functor(T, p, -1).", "domain_error(not_less_than_zero,-1)");
+        tw.assert_error("functor(T, p(a), 1).", "type_error(atomic,p(a))");
+        tw.assert_error("functor(T, 1.5, 1).", "type_error(atom,1.5)");
+    }
+
+    #[test]
+    fn arg() {
+        let tw = tw();
+        tw.assert_binding("arg(2, p(a,b,c), X).", ("X", "b"));
+        tw.assert_binding("arg(2, [h|t], X).", ("X", "t"));
+        tw.assert_binding("arg(1, (x,y), X).", ("X", "x"));
+        tw.assert_true("arg(1, p(X), a), X == a.");
+        tw.assert_false("arg(0, p(a), _).");
+        tw.assert_false("arg(2, p(a), _).");
+        assert_eq!(tw.all_bindings("arg(N, p(a,b,c), X).", "X"), ["a", "b", "c"]);
+        assert_eq!(tw.all_bindings("arg(N, p(a,b,a), a).", "N"), ["1", "3"]);
+        tw.assert_error("arg(x, p(a), _).", "type_error(integer,x)");
+        tw.assert_error("arg(1, p, _).", "type_error(compound,p)");
+        tw.assert_error("arg(1, _, _).", "instantiation_error");
+    }
+
+    #[test]
+    fn copy_term() {
+        let tw = tw();
+        tw.assert_true("copy_term(p(X,Y,X), C), C = p(a,b,a), var(X), var(Y).");
+        tw.assert_false("copy_term(p(X,X), p(a,b)).");
+        tw.assert_true("copy_term([X|X], C), C = [a|a], var(X).");
+        tw.assert_binding("copy_term({a,\"s\"}, C).", ("C", "{a,\"s\"}"));
+    }
+
+    #[test]
+    fn term_variables() {
+        let tw = tw();
+        tw.assert_binding("term_variables(p(X, q(Y, X), [Z|W]), Vs).", ("Vs", "[X,Y,Z,W]"));
+        tw.assert_binding("term_variables((A, {B, A}), Vs).", ("Vs", "[A,B]"));
+        tw.assert_binding("term_variables(p(a, \"s\"), Vs).", ("Vs", "[]"));
+        tw.assert_binding("X = f(Y), term_variables(X, Vs).", ("Vs", "[Y]"));
+    }
+
+    #[test]
+    fn setarg() {
+        let tw = tw();
+        tw.assert_binding("T = p(a,b), setarg(1, T, c).", ("T", "p(c,b)"));
+        tw.assert_binding("T = [a,b], setarg(1, T, c).", ("T", "[c,b]"));
+        tw.assert_binding("T = (a,b), setarg(2, T, f(X)).", ("T", "(a,f(X))"));
+        tw.assert_binding("T = p(a), (setarg(1, T, b), fail ; true).", ("T", "p(a)"));
+        tw.assert_false("T = p(a), setarg(2, T, b).");
+        tw.assert_error("setarg(1, p, b).", "type_error(compound,p)");
+        tw.assert_error("setarg(_, p(a), b).", "instantiation_error");
+    }
+
+    #[test]
+    fn nb_setarg() {
+        let tw = tw();
+        tw.assert_binding("T = p(a), (nb_setarg(1, T, \"b\"), fail ; true).", ("T", "p(\"b\")"));
+        tw.assert_binding(
+            "T = p(0), (nb_setarg(1, T, 1), fail ; nb_setarg(1, T, 2), fail ; true).",
+            ("T", "p(2)"),
+        );
+        tw.assert_error("T = p(a), nb_setarg(1, T, f(x)).", "type_error(atomic,f(x))");
+    }
+
+    #[test]
+    fn subsumes_term() {
+        let tw = tw();
+        tw.assert_true("subsumes_term(p(X), p(a)).");
+        tw.assert_true("subsumes_term(p(X, Y), p(Z, Z)).");
+        tw.assert_false("subsumes_term(p(X, X), p(Y, Z)).");
+        tw.assert_false("subsumes_term(p(a), p(X)).");
+        tw.assert_false("subsumes_term(g(X), g(f(X))).");
+        tw.assert_true("subsumes_term(p(X), p(Y)), var(X), var(Y).");
+        tw.assert_false("subsumes_term(f(a), f(_)).");
+        tw.assert_false("subsumes_term(f(X, X), f(_, _)).");
+        tw.assert_true("subsumes_term(f(_), f(_)).");
+        tw.assert_true("subsumes_term(f(X, Y), f(Z, _)).");
+        tw.assert_false("subsumes_term(f(X, X), f(Y, g(_))).");
+    }
+
     // ── type checks ───────────────────────────────────────────────────────
 
     #[test]
@@ -401,6 +941,19 @@ mod tests {
         tw.assert_false("number(\"hi\").");
     }
 
+    #[test]
+    fn type_rational() {
+        let tw = tw();
+        tw.assert_true("rational(42).");
+        tw.assert_true("rational(1r3).");
+        tw.assert_true("rational(123456789012345678901234567890).");
+        tw.assert_true("int(123456789012345678901234567890).");
+        tw.assert_true("number(1r3).");
+        tw.assert_false("int(1r3).");
+        tw.assert_false("rational(0.5).");
+        tw.assert_false("rational(X).");
+    }
+
     #[test]
     fn type_string() {
         let tw = tw();
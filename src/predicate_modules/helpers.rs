@@ -1,7 +1,12 @@
+use std::collections::HashMap;
+
 use crate::{Config, app::Solution, heap::{
     heap::{Cell, Heap, Tag},
     query_heap::QueryHeap,
-}};
+    symbol_db::SymbolDB,
+}, resolution::unification::unify};
+
+use super::PredReturn;
 
 /// Dereferenced heap address of the nth argument (0-indexed) of `goal`.
 pub fn goal_arg(heap: &QueryHeap, goal: usize, n: usize) -> usize {
@@ -13,6 +18,11 @@ pub fn is_var(heap: &QueryHeap, addr: usize) -> bool {
     matches!(heap[addr], (Tag::Ref, r) if r == addr)
 }
 
+/// True if `addr` holds an unbound variable, including the anonymous `_`.
+pub fn is_unbound(heap: &QueryHeap, addr: usize) -> bool {
+    is_var(heap, addr) || heap[addr].0 == Tag::AVar
+}
+
 /// Resolve any `Str` indirection and return the structure's base address.
 pub fn resolve(heap: &QueryHeap, addr: usize) -> usize {
     match heap[addr] {
@@ -34,7 +44,7 @@ pub fn resolve(heap: &QueryHeap, addr: usize) -> usize {
 /// Return the cell to use when embedding the term at `addr` inside a new
 /// structure on the heap. Derefs, follows `Str`, and wraps compound-like
 /// terms in `Str` indirection.
-fn cell_for_addr(heap: &QueryHeap, addr: usize) -> Cell {
+pub fn cell_for_addr(heap: &QueryHeap, addr: usize) -> Cell {
     let addr = heap.deref_addr(addr);
     match heap[addr] {
         (Tag::Str, ptr) => (Tag::Str, ptr),
@@ -94,6 +104,20 @@ pub fn read_structure_addrs(heap: &QueryHeap, addr: usize) -> Option<Vec<usize>>
     }
 }
 
+/// Addresses of the argument cells of the compound-like term at `addr`: the
+/// arguments of a `Comp`, the elements of a `Tup` or `Set`, or the head and
+/// tail of a list cell. The addresses are the argument slots themselves, not
+/// dereferenced. `None` if the term is not compound.
+pub fn arg_slots(heap: &QueryHeap, addr: usize) -> Option<Vec<usize>> {
+    let addr = resolve(heap, addr);
+    match heap[addr] {
+        (Tag::Comp, _) => Some(heap.str_iterator(addr).skip(1).collect()),
+        (Tag::Tup | Tag::Set, len) if len > 0 => Some(heap.str_iterator(addr).collect()),
+        (Tag::Lis, ptr) => Some(vec![ptr, ptr + 1]),
+        _ => None,
+    }
+}
+
 // ---------------------------------------------------------------------------
 // List building
 // ---------------------------------------------------------------------------
@@ -112,6 +136,18 @@ pub fn build_list(heap: &mut QueryHeap, cells: &[Cell]) -> usize {
     list_start
 }
 
+/// Build a partial list `[a, b | Tail]` on the heap from element addresses
+/// and the address of its tail. Returns the tail itself for no elements.
+pub fn build_list_with_tail(heap: &mut QueryHeap, addrs: &[usize], tail: usize) -> usize {
+    if addrs.is_empty() {
+        return tail;
+    }
+    let tail_cell = cell_for_addr(heap, tail);
+    let list_addr = build_list_from_addrs(heap, addrs);
+    *heap.cells.last_mut().unwrap() = tail_cell;
+    list_addr
+}
+
 /// Build a proper list on the heap from element addresses.
 /// Addresses are dereferenced and complex terms are wrapped in `Str`
 /// indirection automatically.
@@ -146,15 +182,12 @@ pub fn build_tuple_from_addrs(heap: &mut QueryHeap, addrs: &[usize]) -> usize {
 }
 
 /// Build a `Set` on the heap from element addresses.
-/// Deduplicates using structural equality.
+/// Elements are kept in the standard order of terms, without duplicates.
 /// Returns the address of the `(Set, len)` header cell.
 pub fn build_set_from_addrs(heap: &mut QueryHeap, addrs: &[usize]) -> usize {
-    let mut unique_addrs: Vec<usize> = Vec::with_capacity(addrs.len());
-    for &a in addrs {
-        if !unique_addrs.iter().any(|&u| heap.term_equal(a, u)) {
-            unique_addrs.push(a);
-        }
-    }
+    let mut unique_addrs = addrs.to_vec();
+    unique_addrs.sort_by(|&a, &b| heap.term_compare(a, b));
+    unique_addrs.dedup_by(|a, b| heap.term_compare(*a, *b).is_eq());
     let header = heap.heap_push((Tag::Set, unique_addrs.len()));
     for &a in unique_addrs.iter() {
         heap.heap_push(cell_for_addr(heap, a));
@@ -171,7 +204,184 @@ pub fn build_set(heap: &mut QueryHeap, elements: &[Cell]) -> usize {
     addr
 }
 
-use crate::{app::App, predicate_modules::PredicateModule};
+/// Build the goal for calling `closure` with `extra` arguments appended, as
+/// in `call/N`: `foo(a)` with `[X, Y]` gives `foo(a, X, Y)`. An unbound
+/// closure gives a variable-predicate goal `P(X, Y)`. Returns the address of
+/// the goal, or `None` if the closure cannot be called.
+pub fn apply_closure(heap: &mut QueryHeap, closure: usize, extra: &[usize]) -> Option<usize> {
+    let closure = resolve(heap, heap.deref_addr(closure));
+    if extra.is_empty() {
+        return match heap[closure].0 {
+            Tag::Con | Tag::Comp | Tag::Tup => Some(closure),
+            _ => None,
+        };
+    }
+    let mut addrs: Vec<usize> = match heap[closure].0 {
+        Tag::Con | Tag::Ref => vec![closure],
+        Tag::Comp => heap.str_iterator(closure).collect(),
+        _ => return None,
+    };
+    addrs.extend_from_slice(extra);
+    Some(build_compound_from_addrs(heap, &addrs))
+}
+
+// ---------------------------------------------------------------------------
+// Copying and unification
+// ---------------------------------------------------------------------------
+
+/// Copy the term at `addr` in `from` onto `to` with fresh variables, and
+/// return the address of a cell holding the copy. Passing the same `ref_map`
+/// across several copies keeps their shared variables shared.
+pub fn copy_term_cell(
+    to: &mut impl Heap,
+    from: &impl Heap,
+    addr: usize,
+    ref_map: &mut HashMap<usize, usize>,
+) -> usize {
+    match to.copy_complex(from, addr, ref_map) {
+        Some(cell) => to.heap_push(cell),
+        None => {
+            to.copy_simple(from, addr, ref_map);
+            to.heap_len() - 1
+        }
+    }
+}
+
+/// Unify the terms at `built` and `target`, succeeding with the resulting
+/// bindings. Used to hand a freshly built result back to an output argument
+/// that may already be (partially) bound.
+pub fn unify_result(heap: &QueryHeap, built: usize, target: usize) -> PredReturn {
+    match unify(heap, built, target) {
+        Some(sub) => PredReturn::Success(sub.get_bindings().to_vec(), vec![]),
+        None => PredReturn::False,
+    }
+}
+
+/// The binding of the unbound output `addr` to the term at `value`. There is
+/// none when the output is `_`, which takes any value without being bound.
+pub fn bind_output(heap: &QueryHeap, addr: usize, value: usize) -> Vec<(usize, usize)> {
+    if heap[addr].0 == Tag::AVar {
+        vec![]
+    } else {
+        vec![(addr, value)]
+    }
+}
+
+/// One alternative of [`PredReturn::Choices`]: its bindings and sub-goals.
+pub type Alternative = (Vec<(usize, usize)>, Vec<usize>);
+
+/// The alternative that unifies each term in `targets` with the term at the
+/// same position in `values`, or `None` if they do not unify.
+pub fn alternative(
+    heap: &mut QueryHeap,
+    values: &[usize],
+    targets: &[usize],
+) -> Option<Alternative> {
+    let built = build_tuple_from_addrs(heap, values);
+    let target = build_tuple_from_addrs(heap, targets);
+    unify(heap, built, target).map(|sub| (sub.get_bindings().to_vec(), vec![]))
+}
+
+/// Offer `alternatives` on backtracking, first to last.
+pub fn choices(mut alternatives: Vec<Alternative>) -> PredReturn {
+    // Choices are popped from the back
+    alternatives.reverse();
+    PredReturn::Choices(alternatives)
+}
+
+// ---------------------------------------------------------------------------
+// ISO error terms
+// ---------------------------------------------------------------------------
+
+/// Throw `error(Formal, _)`, where `formal` is the address of the formal
+/// error term.
+pub fn throw_error(heap: &mut QueryHeap, formal: usize) -> PredReturn {
+    let error = heap.set_const(SymbolDB::set_const("error"));
+    let context = heap.set_ref(None);
+    PredReturn::Throw(build_compound_from_addrs(heap, &[error, formal, context]))
+}
+
+/// Throw `error(instantiation_error, _)`: an argument was unbound.
+pub fn instantiation_error(heap: &mut QueryHeap) -> PredReturn {
+    let formal = heap.set_const(SymbolDB::set_const("instantiation_error"));
+    throw_error(heap, formal)
+}
+
+/// Throw `error(type_error(Type, Culprit), _)`: the term at `culprit` is not
+/// of the expected type.
+pub fn type_error(heap: &mut QueryHeap, type_name: &str, culprit: usize) -> PredReturn {
+    let functor = heap.set_const(SymbolDB::set_const("type_error"));
+    let type_name = heap.set_const(SymbolDB::set_const(type_name));
+    let formal = build_compound_from_addrs(heap, &[functor, type_name, culprit]);
+    throw_error(heap, formal)
+}
+
+/// Throw `error(domain_error(Domain, Culprit), _)`: the term at `culprit`
+/// has the right type but is outside the expected domain.
+pub fn domain_error(heap: &mut QueryHeap, domain: &str, culprit: usize) -> PredReturn {
+    let functor = heap.set_const(SymbolDB::set_const("domain_error"));
+    let domain = heap.set_const(SymbolDB::set_const(domain));
+    let formal = build_compound_from_addrs(heap, &[functor, domain, culprit]);
+    throw_error(heap, formal)
+}
+
+/// Throw `error(evaluation_error(Error), _)`: an arithmetic function is
+/// undefined for its arguments, as for `zero_divisor`.
+pub fn evaluation_error(heap: &mut QueryHeap, error: &str) -> PredReturn {
+    let functor = heap.set_const(SymbolDB::set_const("evaluation_error"));
+    let error = heap.set_const(SymbolDB::set_const(error));
+    let formal = build_compound_from_addrs(heap, &[functor, error]);
+    throw_error(heap, formal)
+}
+
+/// Throw `error(permission_error(Action, Type, Culprit), _)`: `action` is
+/// not allowed on the term at `culprit`, which is of kind `type_name`.
+pub fn permission_error(
+    heap: &mut QueryHeap,
+    action: &str,
+    type_name: &str,
+    culprit: usize,
+) -> PredReturn {
+    let functor = heap.set_const(SymbolDB::set_const("permission_error"));
+    let action = heap.set_const(SymbolDB::set_const(action));
+    let type_name = heap.set_const(SymbolDB::set_const(type_name));
+    let formal = build_compound_from_addrs(heap, &[functor, action, type_name, culprit]);
+    throw_error(heap, formal)
+}
+
+/// Throw `error(syntax_error(Message), _)`: text could not be read as a
+/// term. `message` says why, as an atom.
+pub fn syntax_error(heap: &mut QueryHeap, message: &str) -> PredReturn {
+    let functor = heap.set_const(SymbolDB::set_const("syntax_error"));
+    let message = heap.set_const(SymbolDB::set_const(message));
+    let formal = build_compound_from_addrs(heap, &[functor, message]);
+    throw_error(heap, formal)
+}
+
+/// The error for an argument at `addr` that should have been of type
+/// `type_name`: an instantiation error if it is unbound, otherwise a type
+/// error.
+pub fn must_be_error(heap: &mut QueryHeap, type_name: &str, addr: usize) -> PredReturn {
+    if is_unbound(heap, heap.deref_addr(addr)) {
+        instantiation_error(heap)
+    } else {
+        type_error(heap, type_name, addr)
+    }
+}
+
+/// Read the proper list at `addr`, or produce the error for an argument that
+/// should have been one: an instantiation error for a partial list, otherwise
+/// `type_error(list, L)`.
+pub fn must_be_list(heap: &mut QueryHeap, addr: usize) -> Result<Vec<usize>, PredReturn> {
+    let (elements, tail) = read_list_with_tail(heap, addr);
+    match heap[tail] {
+        (Tag::ELis, _) => Ok(elements),
+        _ if is_unbound(heap, tail) => Err(instantiation_error(heap)),
+        _ => Err(type_error(heap, "list", addr)),
+    }
+}
+
+use crate::{app::App, predicate_modules::PredicateModule, Error};
 
 pub struct TestWrapper {
     pub app: App,
@@ -244,7 +454,20 @@ impl TestWrapper {
     }
 
     pub fn assert_false(&self, query: &str) {
-        assert!(self.app.query_session(query).unwrap().next().is_none())
+        let result = self.app.query_session(query).unwrap().next_solution();
+        assert!(matches!(result, Ok(None)), "expected {query} to fail, got {result:?}")
+    }
+
+    /// Assert that `query` raises `error(Formal, _)` where Formal prints as
+    /// `formal`.
+    pub fn assert_error(&self, query: &str, formal: &str) {
+        match self.app.query_session(query).unwrap().next_solution() {
+            Err(Error::Exception(ball)) => assert!(
+                ball.starts_with(&format!("error({formal},")),
+                "expected error({formal},_), got {ball}"
+            ),
+            result => panic!("expected error({formal},_), got {result:?}"),
+        }
     }
 
     pub fn assert_true(&self, query: &str) {
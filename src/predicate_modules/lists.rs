@@ -5,28 +5,33 @@
 //!   `(Lis, ptr) → head | (Lis, ptr) → head | … | (ELis, 0)`
 //! Partial lists like `[a | T]` have a `Ref` cell as the tail.
 
-use std::sync::Arc;
-
-use super::{helpers::*, maths::Number, PredReturn, PredicateModule};
+use super::{helpers::*, PredReturn, PredicateModule};
 use crate::{
     heap::{
-        heap::{Heap, Tag},
+        heap::{Heap, Tag, EMPTY_LIS},
         query_heap::QueryHeap,
         symbol_db::SymbolDB,
     },
+    predicate_modules::maths::Number,
     program::{hypothesis::Hypothesis, predicate_table::PredicateTable},
+    resolution::unification::unify,
     Config,
 };
 
 // ── Predicates ────────────────────────────────────────────────────────────────
 
-/// `length(+List, ?N)` — N is the number of elements in List.
+/// `length(?List, ?N)` — N is the number of elements in List.
 ///
 /// Modes:
 /// - `length(+List, -N)` — count elements and bind N.
 /// - `length(+List, +N)` — check that the list has exactly N elements.
+/// - `length(-List, +N)` — extend a partial list with fresh variables to
+///   N elements.
+/// - `length(-List, -N)` — enumerate ever longer partial lists on
+///   backtracking, starting from the elements already there.
 ///
-/// Fails for partial or improper lists.
+/// Throws `type_error(list, List)` if List is not a list and
+/// `type_error(integer, N)` if N is neither unbound nor an integer.
 pub fn length(
     heap: &mut QueryHeap,
     _: &mut Hypothesis,
@@ -37,29 +42,116 @@ pub fn length(
     let list_a = goal_arg(heap, goal, 0);
     let len_a = goal_arg(heap, goal, 1);
 
-    let Some(elements) = read_list_addrs(heap, list_a) else {
-        return false.into();
+    let (elements, tail) = read_list_with_tail(heap, list_a);
+    let partial = match heap[tail] {
+        (Tag::ELis, _) => false,
+        _ if is_unbound(heap, tail) => true,
+        _ => return type_error(heap, "list", list_a),
     };
     let n = elements.len();
 
+    if partial && heap[tail].0 == Tag::AVar && is_unbound(heap, len_a) {
+        // An anonymous tail cannot be extended, so enumerate lists ending in
+        // a fresh variable instead
+        let fresh = heap.set_ref(None);
+        let list = build_list_with_tail(heap, &elements, fresh);
+        let functor = heap.set_const(SymbolDB::set_const("length"));
+        let goal = build_compound_from_addrs(heap, &[functor, list, len_a]);
+        return PredReturn::Success(vec![], vec![goal]);
+    }
+
     match heap[len_a] {
-        (Tag::Ref, r) => {
+        _ if partial && is_unbound(heap, len_a) => {
+            // Either the list ends here, or it has another element and the
+            // goal is tried again on the longer list
+            let empty = heap.heap_push(EMPTY_LIS);
+            let int_addr = heap.heap_push((Tag::Int, n));
+            let element = heap.set_ref(None);
+            let rest = heap.set_ref(None);
+            let longer = build_list_with_tail(heap, &[element], rest);
+            let mut ended = vec![(tail, empty)];
+            ended.extend(bind_output(heap, len_a, int_addr));
+            choices(vec![(ended, vec![]), (vec![(tail, longer)], vec![goal])])
+        }
+        (Tag::Int, v) if partial => match (v as isize).checked_sub(n as isize) {
+            Some(extra) if extra >= 0 => {
+                let elements: Vec<usize> = (0..extra).map(|_| heap.set_ref(None)).collect();
+                let rest = build_list_from_addrs(heap, &elements);
+                PredReturn::Success(bind_output(heap, tail, rest), vec![])
+            }
+            _ => PredReturn::False,
+        },
+        _ if is_unbound(heap, len_a) => {
             let int_addr = heap.heap_push((Tag::Int, n));
-            PredReturn::Success(vec![(r, int_addr)], vec![])
+            PredReturn::Success(bind_output(heap, len_a, int_addr), vec![])
         }
         (Tag::Int, v) => (n == v).into(),
-        _ => false.into(),
+        // No list can be that long
+        (Tag::BigInt, _) => PredReturn::False,
+        _ => type_error(heap, "integer", len_a),
     }
 }
 
-/// `sort(+List, -Sorted)` — Sorted is a sorted copy of List.
-///
-/// All elements must be of a uniform type:
-/// - `Int` / `Flt` — sorted numerically.
-/// - `Con` / `Stri` — sorted lexicographically.
+/// Sort the elements of the proper list at `list` by the standard order of
+/// their keys: the whole element for `key` 0, otherwise its `key`th
+/// argument. The sort is stable; with `dedup` only the first of each run of
+/// equal keys is kept. Returns the error to throw if `list` is not a proper
+/// list or an element has no `key`th argument.
+fn sort_elements(
+    heap: &mut QueryHeap,
+    list: usize,
+    key: usize,
+    descending: bool,
+    dedup: bool,
+) -> Result<Vec<usize>, PredReturn> {
+    let elements = must_be_list(heap, list)?;
+    let mut keyed = Vec::with_capacity(elements.len());
+    for element in elements {
+        if key == 0 {
+            keyed.push((element, element));
+            continue;
+        }
+        match arg_slots(heap, element) {
+            Some(slots) if slots.len() >= key => keyed.push((slots[key - 1], element)),
+            _ => return Err(must_be_error(heap, "compound", element)),
+        }
+    }
+    keyed.sort_by(|&(a, _), &(b, _)| {
+        let order = heap.term_compare(a, b);
+        if descending {
+            order.reverse()
+        } else {
+            order
+        }
+    });
+    if dedup {
+        keyed.dedup_by(|(a, _), (b, _)| heap.term_compare(*a, *b).is_eq());
+    }
+    Ok(keyed.into_iter().map(|(_, element)| element).collect())
+}
+
+/// Unify the list of `elements` with the term at `target`.
+fn unify_list(
+    heap: &mut QueryHeap,
+    elements: Result<Vec<usize>, PredReturn>,
+    target: usize,
+) -> PredReturn {
+    match elements {
+        Ok(elements) => {
+            let list_addr = build_list_from_addrs(heap, &elements);
+            unify_result(heap, list_addr, target)
+        }
+        Err(error) => error,
+    }
+}
+
+/// `sort(+List, -Sorted)` — Sorted is List in the standard order of terms
+/// with duplicates removed.
 ///
-/// The output argument must be an unbound variable. Fails for mixed-type
-/// lists, partial lists, or a bound output argument.
+/// Numbers are compared by value, atoms and strings alphabetically, and
+/// compound terms by arity, name and then arguments. Throws an instantiation
+/// error for a partial list and `type_error(list, List)` if List is not a
+/// list.
 pub fn sort(
     heap: &mut QueryHeap,
     _: &mut Hypothesis,
@@ -67,74 +159,463 @@ pub fn sort(
     _: &PredicateTable,
     _: Config,
 ) -> PredReturn {
-    let list_a = goal_arg(heap, goal, 0);
-    let sorted_a = goal_arg(heap, goal, 1);
+    let sorted = sort_elements(heap, goal_arg(heap, goal, 0), 0, false, true);
+    unify_list(heap, sorted, goal_arg(heap, goal, 1))
+}
+
+/// `msort(+List, -Sorted)` — like `sort/2`, but duplicates are kept.
+pub fn msort(
+    heap: &mut QueryHeap,
+    _: &mut Hypothesis,
+    goal: usize,
+    _: &PredicateTable,
+    _: Config,
+) -> PredReturn {
+    let sorted = sort_elements(heap, goal_arg(heap, goal, 0), 0, false, false);
+    unify_list(heap, sorted, goal_arg(heap, goal, 1))
+}
+
+/// `sort(+Key, +Order, +List, -Sorted)` — sort List on the Keyth argument of
+/// each element, or on the whole element if Key is 0.
+///
+/// Order is one of `@<` and `@>`, which remove elements with equal keys
+/// after the first, or `@=<` and `@>=`, which keep them. The sort is stable.
+pub fn sort4(
+    heap: &mut QueryHeap,
+    _: &mut Hypothesis,
+    goal: usize,
+    _: &PredicateTable,
+    _: Config,
+) -> PredReturn {
+    let key = goal_arg(heap, goal, 0);
+    let order = goal_arg(heap, goal, 1);
+    let key = match heap[key] {
+        (Tag::Int, k) if (k as isize) < 0 => {
+            return domain_error(heap, "not_less_than_zero", key)
+        }
+        (Tag::Int, k) => k,
+        _ => return must_be_error(heap, "integer", key),
+    };
+    let (descending, dedup) = match heap[order] {
+        (Tag::Con, id) => match &*SymbolDB::get_const(id) {
+            "@<" => (false, true),
+            "@=<" => (false, false),
+            "@>" => (true, true),
+            "@>=" => (true, false),
+            _ => return domain_error(heap, "order", order),
+        },
+        _ if is_unbound(heap, order) => return instantiation_error(heap),
+        _ => return type_error(heap, "atom", order),
+    };
+    let sorted = sort_elements(heap, goal_arg(heap, goal, 2), key, descending, dedup);
+    unify_list(heap, sorted, goal_arg(heap, goal, 3))
+}
+
+/// `keysort(+Pairs, -Sorted)` — stable sort of a list of `Key-Value` pairs
+/// on their keys. Duplicates are kept.
+///
+/// Throws `type_error(pair, E)` for an element that is not a pair.
+pub fn keysort(
+    heap: &mut QueryHeap,
+    _: &mut Hypothesis,
+    goal: usize,
+    _: &PredicateTable,
+    _: Config,
+) -> PredReturn {
+    let pairs = goal_arg(heap, goal, 0);
+    let elements = match must_be_list(heap, pairs) {
+        Ok(elements) => elements,
+        Err(error) => return error,
+    };
+    let pair = SymbolDB::set_const("-");
+    for &element in &elements {
+        let element = resolve(heap, element);
+        let functor = heap.deref_addr(element + 1);
+        if heap[element] != (Tag::Comp, 3) || heap[functor] != (Tag::Con, pair) {
+            return must_be_error(heap, "pair", element);
+        }
+    }
+    let sorted = sort_elements(heap, pairs, 1, false, false);
+    unify_list(heap, sorted, goal_arg(heap, goal, 1))
+}
+
+// ── Relational helpers ────────────────────────────────────────────────────────
 
-    // Output must be an unbound variable.
-    let (Tag::Ref, r) = heap[sorted_a] else {
-        return false.into();
+/// Addresses of each suffix of the list at `addr`: the list itself, its tail,
+/// and so on down to the final tail, which is `[]` for a proper list and a
+/// variable for a partial one.
+fn list_suffixes(heap: &QueryHeap, addr: usize) -> Vec<usize> {
+    let mut suffixes = vec![heap.deref_addr(addr)];
+    while let (Tag::Lis, ptr) = heap[*suffixes.last().unwrap()] {
+        suffixes.push(heap.deref_addr(ptr + 1));
+    }
+    suffixes
+}
+
+/// Whether the list at `addr` is partial, i.e. ends in an unbound variable.
+fn is_partial_list(heap: &QueryHeap, addr: usize) -> bool {
+    let (_, tail) = read_list_with_tail(heap, addr);
+    is_unbound(heap, tail)
+}
+
+/// Resolve `goal` with the clauses of `name` in `lists.pl` instead, for the
+/// modes the native code does not cover.
+fn delegate(heap: &mut QueryHeap, goal: usize, name: &str) -> PredReturn {
+    let goal = resolve(heap, goal);
+    let mut addrs: Vec<usize> = heap.str_iterator(goal).collect();
+    addrs[0] = heap.set_const(SymbolDB::set_const(name));
+    let goal = build_compound_from_addrs(heap, &addrs);
+    PredReturn::Success(vec![], vec![goal])
+}
+
+/// `append(?List1, ?List2, ?List3)` — List3 is List1 followed by List2.
+///
+/// Deterministic when List1 is a proper list. When List1 is partial and
+/// List3 is a proper list, each split of List3 is an alternative on
+/// backtracking. Other modes enumerate through the Prolog definition.
+pub fn append(
+    heap: &mut QueryHeap,
+    _: &mut Hypothesis,
+    goal: usize,
+    _: &PredicateTable,
+    _: Config,
+) -> PredReturn {
+    let front = goal_arg(heap, goal, 0);
+    let back = goal_arg(heap, goal, 1);
+    let whole = goal_arg(heap, goal, 2);
+
+    let (elements, tail) = read_list_with_tail(heap, front);
+    if heap[tail].0 == Tag::ELis {
+        let joined = build_list_with_tail(heap, &elements, back);
+        return unify_result(heap, joined, whole);
+    }
+    if !is_unbound(heap, tail) {
+        return PredReturn::False;
+    }
+    let Some(whole_elements) = read_list_addrs(heap, whole) else {
+        return delegate(heap, goal, "append_1");
     };
+    let suffixes = list_suffixes(heap, whole);
+    let mut alternatives = Vec::with_capacity(suffixes.len());
+    for (i, &suffix) in suffixes.iter().enumerate() {
+        let prefix = build_list_from_addrs(heap, &whole_elements[..i]);
+        alternatives.extend(alternative(heap, &[prefix, suffix], &[front, back]));
+    }
+    choices(alternatives)
+}
 
-    let Some(element_addrs) = read_list_addrs(heap, list_a) else {
-        return false.into();
+/// `reverse(?List, ?Reversed)` — Reversed has the elements of List in
+/// reverse order. One of the two must be a proper list.
+pub fn reverse(
+    heap: &mut QueryHeap,
+    _: &mut Hypothesis,
+    goal: usize,
+    _: &PredicateTable,
+    _: Config,
+) -> PredReturn {
+    let mut list = goal_arg(heap, goal, 0);
+    let mut reversed = goal_arg(heap, goal, 1);
+    if is_partial_list(heap, list) && read_list_addrs(heap, reversed).is_some() {
+        (list, reversed) = (reversed, list);
+    }
+    let mut elements = match must_be_list(heap, list) {
+        Ok(elements) => elements,
+        Err(error) => return error,
     };
+    elements.reverse();
+    let built = build_list_from_addrs(heap, &elements);
+    unify_result(heap, built, reversed)
+}
 
-    if element_addrs.is_empty() {
-        let empty = heap.heap_push((Tag::ELis, 0));
-        return PredReturn::Success(vec![(r, empty)], vec![]);
+/// Shared body of `nth0/3` and `nth1/3`, counting positions from `base`.
+fn nth(heap: &mut QueryHeap, goal: usize, base: usize) -> PredReturn {
+    let index = goal_arg(heap, goal, 0);
+    let list = goal_arg(heap, goal, 1);
+    let element = goal_arg(heap, goal, 2);
+    let (elements, tail) = read_list_with_tail(heap, list);
+    if heap[tail].0 != Tag::ELis && !is_unbound(heap, tail) {
+        return type_error(heap, "list", list);
     }
 
-    let first_tag = heap[element_addrs[0]].0;
-    match first_tag {
-        Tag::Flt | Tag::Int => {
-            if !element_addrs
-                .iter()
-                .all(|&a| matches!(heap[a].0, Tag::Flt | Tag::Int))
-            {
-                return false.into();
+    match heap[index] {
+        (Tag::Int, i) => {
+            let Some(i) = (i as isize).checked_sub(base as isize).filter(|i| *i >= 0) else {
+                return PredReturn::False;
+            };
+            let i = i as usize;
+            if let Some(&found) = elements.get(i) {
+                return unify_result(heap, found, element);
             }
-            let mut indexed: Vec<(usize, Number)> = element_addrs
-                .into_iter()
-                .map(|a| (a, Number::from_cell(heap[a])))
-                .collect();
-            indexed.sort_by(|(_, n1), (_, n2)| {
-                n1.partial_cmp(n2).unwrap_or(std::cmp::Ordering::Equal)
-            });
-            let sorted_addrs: Vec<usize> = indexed.into_iter().map(|(a, _)| a).collect();
-            let list_addr = build_list_from_addrs(heap, &sorted_addrs);
-            PredReturn::Success(vec![(r, list_addr)], vec![])
+            if heap[tail].0 == Tag::ELis {
+                return PredReturn::False;
+            }
+            // Extend a partial list far enough to hold the element
+            let mut extension: Vec<usize> =
+                (elements.len()..i).map(|_| heap.set_ref(None)).collect();
+            extension.push(element);
+            let rest = heap.set_ref(None);
+            let extension = build_list_with_tail(heap, &extension, rest);
+            unify_result(heap, extension, tail)
         }
-        Tag::Stri | Tag::Con => {
-            if !element_addrs
-                .iter()
-                .all(|&a| matches!(heap[a].0, Tag::Stri | Tag::Con))
-            {
-                return false.into();
+        _ if is_unbound(heap, index) => {
+            let mut alternatives = Vec::with_capacity(elements.len());
+            for (i, found) in elements.into_iter().enumerate() {
+                let i = heap.heap_push((Tag::Int, i + base));
+                alternatives.extend(alternative(heap, &[i, found], &[index, element]));
             }
-            let mut indexed: Vec<(usize, Arc<str>)> = element_addrs
-                .into_iter()
-                .map(|a| {
-                    let s = match heap[a] {
-                        (Tag::Stri, idx) => SymbolDB::get_string(idx),
-                        (Tag::Con, id) => SymbolDB::get_const(id),
-                        _ => unreachable!("sort: tag changed unexpectedly"),
-                    };
-                    (a, s)
-                })
-                .collect();
-            indexed.sort_by(|(_, s1), (_, s2)| s1.cmp(s2));
-            let sorted_addrs: Vec<usize> = indexed.into_iter().map(|(a, _)| a).collect();
-            let list_addr = build_list_from_addrs(heap, &sorted_addrs);
-            PredReturn::Success(vec![(r, list_addr)], vec![])
+            choices(alternatives)
+        }
+        _ => type_error(heap, "integer", index),
+    }
+}
+
+/// `nth0(?Index, ?List, ?Elem)` — Elem is the element of List at the
+/// 0-based Index. With Index unbound, enumerates the elements of List on
+/// backtracking; a bound Index extends a partial List as needed.
+pub fn nth0(
+    heap: &mut QueryHeap,
+    _: &mut Hypothesis,
+    goal: usize,
+    _: &PredicateTable,
+    _: Config,
+) -> PredReturn {
+    nth(heap, goal, 0)
+}
+
+/// `nth1(?Index, ?List, ?Elem)` — like `nth0/3`, counting from 1.
+pub fn nth1(
+    heap: &mut QueryHeap,
+    _: &mut Hypothesis,
+    goal: usize,
+    _: &PredicateTable,
+    _: Config,
+) -> PredReturn {
+    nth(heap, goal, 1)
+}
+
+/// `last(?List, ?Last)` — Last is the last element of List. A partial List
+/// enumerates longer lists through the Prolog definition.
+pub fn last(
+    heap: &mut QueryHeap,
+    _: &mut Hypothesis,
+    goal: usize,
+    _: &PredicateTable,
+    _: Config,
+) -> PredReturn {
+    let list = goal_arg(heap, goal, 0);
+    let (elements, tail) = read_list_with_tail(heap, list);
+    match heap[tail] {
+        (Tag::ELis, _) => match elements.last() {
+            Some(&last) => unify_result(heap, last, goal_arg(heap, goal, 1)),
+            None => PredReturn::False,
+        },
+        _ if is_unbound(heap, tail) => delegate(heap, goal, "last_1"),
+        _ => type_error(heap, "list", list),
+    }
+}
+
+/// `select(?Elem, ?List, ?Rest)` — Rest is List with one occurrence of Elem
+/// removed.
+///
+/// With List proper, each element is selected in turn on backtracking. With
+/// List partial and Rest proper, Elem is inserted at each position of Rest
+/// instead. Other modes enumerate through the Prolog definition.
+pub fn select(
+    heap: &mut QueryHeap,
+    _: &mut Hypothesis,
+    goal: usize,
+    _: &PredicateTable,
+    _: Config,
+) -> PredReturn {
+    let element = goal_arg(heap, goal, 0);
+    let list = goal_arg(heap, goal, 1);
+    let rest = goal_arg(heap, goal, 2);
+
+    let (elements, tail) = read_list_with_tail(heap, list);
+    let mut alternatives = Vec::with_capacity(elements.len());
+    if heap[tail].0 == Tag::ELis {
+        for i in 0..elements.len() {
+            let mut others = elements.clone();
+            let selected = others.remove(i);
+            let others = build_list_from_addrs(heap, &others);
+            alternatives.extend(alternative(heap, &[selected, others], &[element, rest]));
+        }
+    } else if !is_unbound(heap, tail) {
+        return type_error(heap, "list", list);
+    } else if let Some(others) = read_list_addrs(heap, rest) {
+        for i in 0..=others.len() {
+            let mut inserted = others.clone();
+            inserted.insert(i, element);
+            let inserted = build_list_from_addrs(heap, &inserted);
+            alternatives.extend(alternative(heap, &[inserted], &[list]));
+        }
+    } else {
+        return delegate(heap, goal, "select_1");
+    }
+    choices(alternatives)
+}
+
+/// `delete(+List, @Elem, -Rest)` — Rest is List without the elements that
+/// unify with Elem. Elem is not bound.
+pub fn delete(
+    heap: &mut QueryHeap,
+    _: &mut Hypothesis,
+    goal: usize,
+    _: &PredicateTable,
+    _: Config,
+) -> PredReturn {
+    let element = goal_arg(heap, goal, 1);
+    let elements = match must_be_list(heap, goal_arg(heap, goal, 0)) {
+        Ok(elements) => elements,
+        Err(error) => return error,
+    };
+    let kept: Vec<usize> = elements
+        .into_iter()
+        .filter(|&e| unify(heap, e, element).is_none())
+        .collect();
+    let kept = build_list_from_addrs(heap, &kept);
+    unify_result(heap, kept, goal_arg(heap, goal, 2))
+}
+
+// ── Numeric lists ─────────────────────────────────────────────────────────────
+
+/// Read the proper list of numbers at `addr`, or produce the error for an
+/// argument that should have been one.
+fn number_list(heap: &mut QueryHeap, addr: usize) -> Result<Vec<Number>, PredReturn> {
+    let elements = must_be_list(heap, addr)?;
+    elements
+        .into_iter()
+        .map(|e| Number::try_from(heap[e]).map_err(|_| must_be_error(heap, "number", e)))
+        .collect()
+}
+
+/// Unify the number `n` with the term at `target`.
+fn unify_number(heap: &mut QueryHeap, n: Number, target: usize) -> PredReturn {
+    let n = heap.heap_push(n.to_cell(heap.get_id()));
+    unify_result(heap, n, target)
+}
+
+/// `sum_list(+List, ?Sum)` — Sum is the sum of the numbers in List.
+pub fn sum_list(
+    heap: &mut QueryHeap,
+    _: &mut Hypothesis,
+    goal: usize,
+    _: &PredicateTable,
+    _: Config,
+) -> PredReturn {
+    match number_list(heap, goal_arg(heap, goal, 0)) {
+        Ok(numbers) => {
+            let sum = numbers.into_iter().fold(Number::Int(0), |sum, n| sum + n);
+            unify_number(heap, sum, goal_arg(heap, goal, 1))
+        }
+        Err(error) => error,
+    }
+}
+
+/// Shared body of `max_list/2` and `min_list/2`: unify the second argument
+/// with the first number in the list that no later number is `better` than.
+fn extreme_list(
+    heap: &mut QueryHeap,
+    goal: usize,
+    better: fn(&Number, &Number) -> bool,
+) -> PredReturn {
+    let numbers = match number_list(heap, goal_arg(heap, goal, 0)) {
+        Ok(numbers) => numbers,
+        Err(error) => return error,
+    };
+    let extreme = numbers
+        .into_iter()
+        .reduce(|best, n| if better(&n, &best) { n } else { best });
+    match extreme {
+        Some(extreme) => unify_number(heap, extreme, goal_arg(heap, goal, 1)),
+        None => PredReturn::False,
+    }
+}
+
+/// `max_list(+List, ?Max)` — Max is the largest number in List. Fails for
+/// the empty list.
+pub fn max_list(
+    heap: &mut QueryHeap,
+    _: &mut Hypothesis,
+    goal: usize,
+    _: &PredicateTable,
+    _: Config,
+) -> PredReturn {
+    extreme_list(heap, goal, |n, best| n > best)
+}
+
+/// `min_list(+List, ?Min)` — Min is the smallest number in List. Fails for
+/// the empty list.
+pub fn min_list(
+    heap: &mut QueryHeap,
+    _: &mut Hypothesis,
+    goal: usize,
+    _: &PredicateTable,
+    _: Config,
+) -> PredReturn {
+    extreme_list(heap, goal, |n, best| n < best)
+}
+
+/// `numlist(+Low, +High, ?List)` — List is `[Low, Low+1, ..., High]`. Fails
+/// if High is less than Low.
+pub fn numlist(
+    heap: &mut QueryHeap,
+    _: &mut Hypothesis,
+    goal: usize,
+    _: &PredicateTable,
+    _: Config,
+) -> PredReturn {
+    let mut bounds = [0isize; 2];
+    for (i, bound) in bounds.iter_mut().enumerate() {
+        match heap[goal_arg(heap, goal, i)] {
+            (Tag::Int, n) => *bound = n as isize,
+            _ => return must_be_error(heap, "integer", goal_arg(heap, goal, i)),
         }
-        _ => false.into(),
     }
+    let [low, high] = bounds;
+    if high < low {
+        return PredReturn::False;
+    }
+    let cells: Vec<_> = (low..=high).map(|n| (Tag::Int, n as usize)).collect();
+    let list = build_list(heap, &cells);
+    unify_result(heap, list, goal_arg(heap, goal, 2))
+}
+
+/// `list_to_ord_set(+List, -OrdSet)` — OrdSet is List in the standard order
+/// of terms without duplicates, the representation of an ordered set.
+pub fn list_to_ord_set(
+    heap: &mut QueryHeap,
+    _: &mut Hypothesis,
+    goal: usize,
+    _: &PredicateTable,
+    _: Config,
+) -> PredReturn {
+    let sorted = sort_elements(heap, goal_arg(heap, goal, 0), 0, false, true);
+    unify_list(heap, sorted, goal_arg(heap, goal, 1))
 }
 
 // ── Module registration ───────────────────────────────────────────────────────
 
 pub static LISTS: PredicateModule = (
-    &[("length", 2, length), ("sort", 2, sort)],
+    &[
+        ("length", 2, length),
+        ("sort", 2, sort),
+        ("msort", 2, msort),
+        ("sort", 4, sort4),
+        ("keysort", 2, keysort),
+        ("append", 3, append),
+        ("reverse", 2, reverse),
+        ("nth0", 3, nth0),
+        ("nth1", 3, nth1),
+        ("last", 2, last),
+        ("select", 3, select),
+        ("delete", 3, delete),
+        ("sum_list", 2, sum_list),
+        ("max_list", 2, max_list),
+        ("min_list", 2, min_list),
+        ("numlist", 3, numlist),
+        ("list_to_ord_set", 2, list_to_ord_set),
+    ],
     &[include_str!("../../builtins/lists.pl")],
 );
 
@@ -171,16 +652,32 @@ mod tests {
     }
 
     #[test]
-    fn length_fails_non_list() {
+    fn length_non_list_errors() {
+        let tw = tw();
+        tw.assert_error("length(hello, N).", "type_error(list,hello)");
+        tw.assert_error("length(42, N).", "type_error(list,42)");
+        tw.assert_error("length([a], a).", "type_error(integer,a)");
+    }
+
+    #[test]
+    fn length_partial_list() {
         let tw = tw();
-        tw.assert_false("length(hello, N).");
-        tw.assert_false("length(42, N).");
+        tw.assert_binding("length([a|T], 3), length(T, N).", ("N", "2"));
+        tw.assert_false("length([a, b|_], 1).");
+        tw.assert_binding("length([a|T], N).", ("N", "1"));
+        tw.assert_binding("length(L, N), N >= 2, !, length(L, Len).", ("Len", "2"));
     }
 
     #[test]
-    fn length_fails_partial_list() {
+    fn length_anonymous() {
         let tw = tw();
-        tw.assert_false("length([a|_], N).");
+        tw.assert_true("length(_, 2).");
+        tw.assert_true("length([a|_], 2).");
+        tw.assert_false("length([a, b|_], 1).");
+        tw.assert_true("length([a], _).");
+        tw.assert_true("length([a|_], _), !.");
+        tw.assert_binding("length(_, N), N >= 2, !.", ("N", "2"));
+        tw.assert_binding("length(L, _), length(L, N), N >= 1, !.", ("N", "1"));
     }
 
     // ── sort/2 ────────────────────────────────────────────────────────────
@@ -218,15 +715,187 @@ mod tests {
     }
 
     #[test]
-    fn sort_fails_mixed() {
+    fn sort_mixed() {
+        let tw = tw();
+        tw.assert_binding("sort([1, a, 2], X).", ("X", "[1,2,a]"));
+        tw.assert_binding("sort([f(b), \"s\", b, 1.5], X).", ("X", "[1.5,b,\"s\",f(b)]"));
+        tw.assert_true("sort([2, 1], [1, 2]).");
+    }
+
+    #[test]
+    fn sort_errors() {
+        let tw = tw();
+        tw.assert_error("sort([a|_], X).", "instantiation_error");
+        tw.assert_error("sort(L, X).", "instantiation_error");
+        tw.assert_error("sort(hello, X).", "type_error(list,hello)");
+    }
+
+    #[test]
+    fn sort_removes_duplicates() {
+        let tw = tw();
+        tw.assert_binding("sort([b, a, b, 1, a], X).", ("X", "[1,a,b]"));
+        tw.assert_true("sort([1, 1.0, 1], [F, I]), float(F), int(I).");
+    }
+
+    #[test]
+    fn msort_keeps_duplicates() {
+        let tw = tw();
+        tw.assert_binding("msort([b, a, b, 1, a], X).", ("X", "[1,a,a,b,b]"));
+        tw.assert_binding("msort([p(b), \"s\", X, 2], L).", ("L", "[X,2,\"s\",p(b)]"));
+    }
+
+    #[test]
+    fn sort4() {
+        let tw = tw();
+        let pairs = "[p(2, b), p(1, a), p(2, c), p(1, a)]";
+        tw.assert_binding(&format!("sort(0, @<, {pairs}, L)."), ("L", "[p(1,a),p(2,b),p(2,c)]"));
+        tw.assert_binding(&format!("sort(0, @>=, {pairs}, L)."), ("L", "[p(2,c),p(2,b),p(1,a),p(1,a)]"));
+        tw.assert_binding(&format!("sort(1, @<, {pairs}, L)."), ("L", "[p(1,a),p(2,b)]"));
+        tw.assert_binding(&format!("sort(1, @=<, {pairs}, L)."), ("L", "[p(1,a),p(1,a),p(2,b),p(2,c)]"));
+        tw.assert_binding(&format!("sort(2, @>, {pairs}, L)."), ("L", "[p(2,c),p(2,b),p(1,a)]"));
+        tw.assert_error("sort(1, @<, [p(a), b], L).", "type_error(compound,b)");
+        tw.assert_error("sort(0, <, [], L).", "domain_error(order,<)");
+        tw.assert_error("sort(-1, @<, [], L).", "domain_error(not_less_than_zero,-1)");
+    }
+
+    #[test]
+    fn keysort() {
+        let tw = tw();
+        tw.assert_binding("keysort([b-1, a-2, b-0, a-1], L).", ("L", "[-(a,2),-(a,1),-(b,1),-(b,0)]"));
+        tw.assert_binding("keysort([], L).", ("L", "[]"));
+        tw.assert_error("keysort([a-1, b], L).", "type_error(pair,b)");
+        tw.assert_error("keysort([a-1, _], L).", "instantiation_error");
+    }
+
+    #[test]
+    fn predsort() {
+        let mut tw = tw();
+        tw.app = tw.app.load_code("
+            by_value(O, _-A, _-B) :- compare(O, A, B).
+            descending(O, A, B) :- compare(O, B, A).
+        ").unwrap();
+        tw.assert_binding("predsort(by_value, [a-3, b-1, c-2, d-1], L).", ("L", "[-(b,1),-(c,2),-(a,3)]"));
+        tw.assert_binding("predsort(descending, [1, 3, 2, 3], L).", ("L", "[3,2,1]"));
+        tw.assert_binding("predsort(descending, [], L).", ("L", "[]"));
+    }
+
+    // ── list library ──────────────────────────────────────────────────────
+
+    #[test]
+    fn append() {
+        let tw = tw();
+        tw.assert_binding("append([a, b], [c], L).", ("L", "[a,b,c]"));
+        tw.assert_true("append([a], T, L), T = [b], L == [a, b].");
+        tw.assert_binding("append(X, [c], [a, b, c]).", ("X", "[a,b]"));
+        tw.assert_false("append(X, [d], [a, b, c]).");
+        assert_eq!(tw.all_bindings("append(X, Y, [a, b]).", "X"), ["[]", "[a]", "[a,b]"]);
+        assert_eq!(tw.all_bindings("append([a|X], Y, [a, b]).", "Y"), ["[b]", "[]"]);
+        tw.assert_true("append(X, Y, [a|T]), X = [a, b], T == [b|Y].");
+    }
+
+    #[test]
+    fn reverse() {
+        let tw = tw();
+        tw.assert_binding("reverse([a, b, c], L).", ("L", "[c,b,a]"));
+        tw.assert_binding("reverse(L, [a, b, c]).", ("L", "[c,b,a]"));
+        tw.assert_binding("reverse([], L).", ("L", "[]"));
+        tw.assert_error("reverse(L, R).", "instantiation_error");
+    }
+
+    #[test]
+    fn nth0_nth1() {
+        let tw = tw();
+        tw.assert_binding("nth0(1, [a, b, c], X).", ("X", "b"));
+        tw.assert_binding("nth1(1, [a, b, c], X).", ("X", "a"));
+        tw.assert_false("nth1(0, [a, b, c], X).");
+        tw.assert_false("nth0(3, [a, b, c], X).");
+        assert_eq!(tw.all_bindings("nth1(I, [a, b, a], a).", "I"), ["1", "3"]);
+        assert_eq!(tw.all_bindings("nth0(I, [a, b], X).", "X"), ["a", "b"]);
+        tw.assert_true("nth0(2, L, x), L = [a, b, x|_].");
+        tw.assert_error("nth0(a, [a], X).", "type_error(integer,a)");
+    }
+
+    #[test]
+    fn last() {
+        let tw = tw();
+        tw.assert_binding("last([a, b, c], X).", ("X", "c"));
+        tw.assert_false("last([], X).");
+        tw.assert_true("last([a|T], c), T = [c].");
+    }
+
+    #[test]
+    fn select() {
+        let tw = tw();
+        tw.assert_binding("select(b, [a, b, c], R).", ("R", "[a,c]"));
+        assert_eq!(tw.all_bindings("select(X, [a, b, c], R).", "R"), ["[b,c]", "[a,c]", "[a,b]"]);
+        assert_eq!(tw.all_bindings("select(x, L, [a, b]).", "L"), ["[x,a,b]", "[a,x,b]", "[a,b,x]"]);
+        tw.assert_false("select(d, [a, b, c], R).");
+        tw.assert_binding("select(b, [a|T], [a, c]).", ("T", "[b,c]"));
+    }
+
+    #[test]
+    fn permutation() {
+        let tw = tw();
+        let perms = tw.all_bindings("permutation([a, b, c], P).", "P");
+        assert_eq!(perms, ["[a,b,c]", "[a,c,b]", "[b,a,c]", "[b,c,a]", "[c,a,b]", "[c,b,a]"]);
+        assert_eq!(tw.all_bindings("permutation(P, [a, b]).", "P").len(), 2);
+        tw.assert_false("permutation([a, b], [a, c]).");
+    }
+
+    #[test]
+    fn delete() {
+        let tw = tw();
+        tw.assert_binding("delete([a, b, a, c], a, R).", ("R", "[b,c]"));
+        tw.assert_binding("delete([f(1), g(2), f(3)], f(_), R).", ("R", "[g(2)]"));
+        tw.assert_true("delete([a, b], X, []), var(X).");
+    }
+
+    #[test]
+    fn numeric_lists() {
+        let tw = tw();
+        tw.assert_binding("sum_list([1, 2, 3], S).", ("S", "6"));
+        tw.assert_binding("sum_list([1, 2.5], S).", ("S", "3.5"));
+        tw.assert_binding("sum_list([], S).", ("S", "0"));
+        tw.assert_binding("max_list([3, 7, 2], M).", ("M", "7"));
+        tw.assert_binding("min_list([3, 7, 2], M).", ("M", "2"));
+        tw.assert_false("max_list([], M).");
+        tw.assert_error("sum_list([1, a], S).", "type_error(number,a)");
+        tw.assert_binding("numlist(1, 4, L).", ("L", "[1,2,3,4]"));
+        tw.assert_false("numlist(4, 1, L).");
+        tw.assert_error("numlist(1, a, L).", "type_error(integer,a)");
+    }
+
+    #[test]
+    fn list_to_ord_set() {
         let tw = tw();
-        tw.assert_false("sort([1, a, 2], X).");
+        tw.assert_binding("list_to_ord_set([c, a, b, a], S).", ("S", "[a,b,c]"));
     }
 
     #[test]
-    fn sort_fails_partial_list() {
+    fn include_exclude_partition() {
         let tw = tw();
-        tw.assert_false("sort([a|_], X).");
+        tw.assert_binding("include(int, [a, 1, b, 2], L).", ("L", "[1,2]"));
+        tw.assert_binding("exclude(int, [a, 1, b, 2], L).", ("L", "[a,b]"));
+        tw.assert_binding("include('<'(1), [0, 1, 2, 3], L).", ("L", "[2,3]"));
+        tw.assert_bindings("partition(int, [a, 1, b, 2], I, E).", &[("I", "[1,2]"), ("E", "[a,b]")]);
+    }
+
+    #[test]
+    fn maplist_foldl() {
+        let mut tw = tw();
+        tw.app = tw.app.load_code("
+            double(X, Y) :- Y is X * 2.
+            add(X, Y, Z) :- Z is X + Y.
+            add3(X, Y, Z, W) :- W is X + Y + Z.
+        ").unwrap();
+        tw.assert_true("maplist(int, [1, 2]).");
+        tw.assert_false("maplist(int, [1, a]).");
+        tw.assert_binding("maplist(double, [1, 2, 3], L).", ("L", "[2,4,6]"));
+        tw.assert_binding("maplist(add, [1, 2], [10, 20], L).", ("L", "[11,22]"));
+        tw.assert_binding("maplist(add3, [1], [2], [3], L).", ("L", "[6]"));
+        tw.assert_binding("foldl(add, [1, 2, 3], 0, S).", ("S", "6"));
+        tw.assert_binding("foldl(add3, [1, 2], [10, 20], 0, S).", ("S", "33"));
+        tw.assert_binding("foldl(plus4, [], [], [], 0, S).", ("S", "0"));
     }
 
     // ── member/2 (Prolog-defined) ─────────────────────────────────────────
@@ -253,6 +922,16 @@ mod tests {
     fn list_for_all(){
         let tw = tw();
         tw.assert_true("list_for_all([2,3,4],'<'(1)).");
+        tw.assert_false("list_for_all([2,3,4],'<'(2)).");
+        tw.assert_true("list_for_all([a,b],const).");
+    }
+
+    #[test]
+    fn map_list_closure(){
+        let mut tw = tw();
+        tw.app = tw.app.load_code("add(N, X, Y) :- Y is X + N.").unwrap();
+        tw.assert_binding("map_list([1,2,3],L,add(10)).", ("L", "[11,12,13]"));
+        tw.assert_binding("map_list(L,[1,2],'=').", ("L", "[1,2]"));
     }
 
     #[test]
@@ -261,5 +940,6 @@ mod tests {
         tw.assert_true("count_true([2,3,4],'<'(2),N).");
         let mut results = tw.all_bindings("count_true([X, b, c], nonvar, N).", "N");
         assert_eq!(results.pop().unwrap(),"2");
+        tw.assert_binding("count_true([],'<'(2),N).", ("N", "0"));
     }
 }
@@ -1,11 +1,20 @@
-use std::sync::atomic::{AtomicU8, Ordering};
+use std::{
+    collections::HashMap,
+    sync::atomic::{AtomicU8, Ordering},
+};
 
-use super::{PredReturn, PredicateModule};
+use super::{
+    helpers::{
+        bind_output, build_compound_from_addrs, choices, evaluation_error, goal_arg, instantiation_error,
+        is_unbound, resolve, type_error, unify_result,
+    },
+    PredReturn, PredicateModule,
+};
 use crate::{
     heap::{
         heap::{Cell, Heap, Tag},
         query_heap::QueryHeap,
-        symbol_db::known_symbol_id,
+        symbol_db::SymbolDB,
     },
     program::hypothesis::Hypothesis,
     program::predicate_table::PredicateTable,
@@ -13,6 +22,10 @@ use crate::{
 };
 
 use fsize::fsize;
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_rational::BigRational;
+use num_traits::{FromPrimitive, One, Pow, Signed, ToPrimitive, Zero};
 
 /// Tolerance for the `=~=` (approximately-equal) operator, stored as an
 /// integer percentage (e.g. `10` means within 10 %).
@@ -33,96 +46,243 @@ pub(crate) fn set_approx_tolerance(pct: u8) {
     APPROX_TOLERANCE_PCT.store(pct, Ordering::Relaxed);
 }
 
-/// Signature for functions in the [`FUNCTIONS`] table.
-/// Returns `None` if any sub-expression is not a valid arithmetic term.
-type MathFn = fn(usize, &QueryHeap) -> Option<Number>;
-
-// Minus symbol ID for distinguishing unary negation from binary subtraction
-const MINUS_SYMBOL: usize = known_symbol_id(3);
-
-// Math functions array using compile-time known symbol IDs
-// Indices match KNOWN_SYMBOLS in symbol_db.rs:
-// 0: false, 1: true, 2: +, 3: -, 4: *, 5: /, 6: **
-// 7: cos, 8: sin, 9: tan, 10: acos, 11: asin, 12: atan
-// 13: log, 14: abs, 15: round, 16: sqrt, 17: to_degrees, 18: to_radians
-const FUNCTIONS: [(usize, MathFn); 17] = [
-    (known_symbol_id(2), add),         // +
-    (known_symbol_id(3), sub),         // -
-    (known_symbol_id(4), mul),         // *
-    (known_symbol_id(5), div),         // /
-    (known_symbol_id(6), pow),         // **
-    (known_symbol_id(7), cos),         // cos
-    (known_symbol_id(8), sin),         // sin
-    (known_symbol_id(9), tan),         // tan
-    (known_symbol_id(10), acos),       // acos
-    (known_symbol_id(11), asin),       // asin
-    (known_symbol_id(12), atan),       // atan
-    (known_symbol_id(13), log),        // log
-    (known_symbol_id(14), abs),        // abs
-    (known_symbol_id(15), round),      // round
-    (known_symbol_id(16), sqrt),       // sqrt
-    (known_symbol_id(17), to_degrees), // to_degrees
-    (known_symbol_id(18), to_radians), // to_radians
-];
+/// An arithmetic function: computes its value from its evaluated arguments,
+/// which there are as many of as the arity it is registered with.
+pub type ArithmeticFunction = fn(&[Number]) -> Result<Number, EvalError>;
+
+/// A set of arithmetic functions as `(name, arity, function)` triples, for
+/// [`App::load_functions`](crate::app::App::load_functions). Constants such
+/// as `pi` are functions of arity 0.
+pub type FunctionModule = &'static [(&'static str, usize, ArithmeticFunction)];
+
+/// Why an arithmetic expression could not be evaluated.
+#[derive(Debug, Clone)]
+pub enum EvalError {
+    /// A sub-expression was an unbound variable.
+    Instantiation,
+    /// The term at this address is neither a number nor a known function.
+    NotEvaluable(usize),
+    /// An argument was not of the named type, as in `type_error(integer, 2.5)`.
+    Type(&'static str, Number),
+    /// The function is undefined for its arguments, as in
+    /// `evaluation_error(zero_divisor)`.
+    Evaluation(&'static str),
+}
 
-#[derive(Debug, Clone, Copy)]
+/// The arithmetic functions known to a program, by name and arity.
+///
+/// Starts with the standard [`FUNCTIONS`]; more can be added with
+/// [`App::load_functions`](crate::app::App::load_functions).
+#[derive(Debug)]
+pub struct ArithmeticFunctions {
+    functions: HashMap<(usize, usize), ArithmeticFunction>,
+}
+
+impl Default for ArithmeticFunctions {
+    fn default() -> Self {
+        let mut functions = ArithmeticFunctions { functions: HashMap::new() };
+        functions.load(FUNCTIONS);
+        functions
+    }
+}
+
+impl ArithmeticFunctions {
+    /// Add each function in `module`, replacing any with the same name and
+    /// arity.
+    pub fn load(&mut self, module: FunctionModule) {
+        for &(name, arity, function) in module {
+            self.functions.insert((SymbolDB::set_const(name), arity), function);
+        }
+    }
+
+    fn get(&self, symbol: usize, arity: usize) -> Option<ArithmeticFunction> {
+        self.functions.get(&(symbol, arity)).copied()
+    }
+}
+
+/// A number as arithmetic sees it.
+///
+/// Integers that fit an `isize` are always `Int`, and rationals with a
+/// denominator of one are always integers, so each exact value has a single
+/// representation.
+#[derive(Debug, Clone)]
 pub enum Number {
     Flt(fsize),
     Int(isize),
+    /// An integer outside the range of `isize`.
+    Big(BigInt),
+    /// An exact rational that is not an integer.
+    Rat(BigRational),
+}
+
+impl From<BigInt> for Number {
+    fn from(value: BigInt) -> Self {
+        match value.to_isize() {
+            Some(value) => Number::Int(value),
+            None => Number::Big(value),
+        }
+    }
+}
+
+impl From<BigRational> for Number {
+    fn from(value: BigRational) -> Self {
+        match value.is_integer() {
+            true => value.to_integer().into(),
+            false => Number::Rat(value),
+        }
+    }
+}
+
+impl std::fmt::Display for Number {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Number::Flt(value) => write!(f, "{value}"),
+            Number::Int(value) => write!(f, "{value}"),
+            Number::Big(value) => write!(f, "{value}"),
+            Number::Rat(value) => write!(f, "{}r{}", value.numer(), value.denom()),
+        }
+    }
 }
 
 impl Number {
-    fn float(&self) -> fsize {
+    /// The value as a float, rounded if it is exact.
+    pub fn float(&self) -> fsize {
         match self {
             Number::Flt(v) => *v,
             Number::Int(v) => *v as fsize,
+            Number::Big(v) => v.to_f64().unwrap_or(fsize::NAN),
+            Number::Rat(v) => v.to_f64().unwrap_or(fsize::NAN),
         }
     }
 
-    fn to_cell(&self) -> Cell {
+    /// The exact value of an integer or rational, or `None` for a float.
+    fn rational(&self) -> Option<BigRational> {
+        match self {
+            Number::Flt(_) => None,
+            Number::Int(v) => Some(BigRational::from_integer((*v).into())),
+            Number::Big(v) => Some(BigRational::from_integer(v.clone())),
+            Number::Rat(v) => Some(v.clone()),
+        }
+    }
+
+    /// Read `text` as a number: an integer of any size, a rational written
+    /// as `NrD` (`1r3`), or a float. Returns `None` if it is not a number.
+    pub fn parse(text: &str) -> Option<Number> {
+        if let Ok(value) = text.parse::<isize>() {
+            return Some(Number::Int(value));
+        }
+        let integer = |text: &str| {
+            let digits = text.strip_prefix('-').unwrap_or(text);
+            let valid = !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit());
+            valid.then(|| text.parse::<BigInt>().ok()).flatten()
+        };
+        if let Some(value) = integer(text) {
+            return Some(value.into());
+        }
+        if let Some((numer, denom)) = text.split_once('r') {
+            let (numer, denom) = (integer(numer)?, integer(denom)?);
+            return match denom.is_positive() {
+                true => Some(BigRational::new(numer, denom).into()),
+                false => None,
+            };
+        }
+        text.parse::<fsize>().ok().map(Number::Flt)
+    }
+
+    /// The heap cell for this number. Big integers and rationals are stored
+    /// in the [`SymbolDB`], owned by the heap `heap_id`, and the cell refers
+    /// to them.
+    pub fn to_cell(&self, heap_id: usize) -> Cell {
         match self {
             Number::Flt(value) => (Tag::Flt, f64::to_bits(*value) as usize),
             Number::Int(value) => (Tag::Int, isize::cast_unsigned(*value)),
+            Number::Big(value) => (Tag::BigInt, SymbolDB::set_big_int(value.clone(), heap_id)),
+            Number::Rat(value) => (Tag::Rat, SymbolDB::set_rational(value.clone(), heap_id)),
         }
     }
 
-    pub fn power(self, rhs: Self) -> Number {
+    /// Apply an arithmetic operator: `small` on two `isize`s, falling back
+    /// to `big` or `exact` when it overflows or either side is a big integer
+    /// or rational, and to `float` when either side is a float.
+    fn binary(
+        self,
+        rhs: Self,
+        small: fn(isize, isize) -> Option<isize>,
+        big: fn(BigInt, BigInt) -> BigInt,
+        exact: fn(BigRational, BigRational) -> BigRational,
+        float: fn(fsize, fsize) -> fsize,
+    ) -> Number {
         match (self, rhs) {
-            (Number::Int(v1), Number::Int(v2)) if v2 > 0 => {
-                // Saturate the exponent to u32::MAX; values that large will
-                // overflow to 0 or 1 anyway, which is the correct behaviour.
-                Number::Int(v1.pow(v2.min(u32::MAX as isize) as u32))
+            (Number::Int(v1), Number::Int(v2)) => match small(v1, v2) {
+                Some(result) => Number::Int(result),
+                None => big(v1.into(), v2.into()).into(),
+            },
+            (lhs @ Number::Flt(_), rhs) | (lhs, rhs @ Number::Flt(_)) => {
+                Number::Flt(float(lhs.float(), rhs.float()))
             }
-            (lhs, rhs) => Number::Flt(lhs.float().powf(rhs.float())),
+            (lhs, rhs) => match (lhs.big_int(), rhs.big_int()) {
+                (Some(v1), Some(v2)) => big(v1, v2).into(),
+                _ => exact(lhs.rational().unwrap(), rhs.rational().unwrap()).into(),
+            },
+        }
+    }
+
+    /// The value of an integer as a `BigInt`, or `None` for other numbers.
+    fn big_int(&self) -> Option<BigInt> {
+        match self {
+            Number::Int(v) => Some((*v).into()),
+            Number::Big(v) => Some(v.clone()),
+            _ => None,
+        }
+    }
+
+    pub fn power(self, rhs: Self) -> Number {
+        let exponent = match rhs {
+            Number::Int(v) => i32::try_from(v).ok(),
+            _ => None,
+        };
+        match (self, exponent) {
+            (Number::Int(v1), Some(v2)) if v2 >= 0 => match v1.checked_pow(v2 as u32) {
+                Some(result) => Number::Int(result),
+                None => BigInt::from(v1).pow(v2 as u32).into(),
+            },
+            (Number::Big(v1), Some(v2)) if v2 >= 0 => v1.pow(v2 as u32).into(),
+            (Number::Rat(v1), Some(v2)) => v1.pow(v2).into(),
+            (lhs, _) => Number::Flt(lhs.float().powf(rhs.float())),
         }
     }
 
     pub fn abs(self) -> Number {
         match self {
             Number::Flt(value) => Number::Flt(value.abs()),
-            Number::Int(value) => Number::Int(value.abs()),
+            Number::Int(value) => match value.checked_abs() {
+                Some(value) => Number::Int(value),
+                None => BigInt::from(value).abs().into(),
+            },
+            Number::Big(value) => Number::Big(value.abs()),
+            Number::Rat(value) => Number::Rat(value.abs()),
         }
     }
 
     pub fn round(self) -> Number {
         match self {
             Number::Flt(value) => Number::Int(value.round() as isize),
-            Number::Int(value) => Number::Int(value),
+            Number::Rat(value) => value.round().into(),
+            n => n,
         }
     }
 
-    /// Convert a heap cell known to be `Int` or `Flt` into a `Number`.
+    /// Convert a heap cell known to be numeric into a `Number`.
     ///
     /// # Panics
     ///
     /// This is an internal helper and should only ever be called with a cell
-    /// whose tag is `Int` or `Flt`. Calling it with any other tag is a
-    /// programmer error and will hit the `unreachable!` branch.
-    pub fn from_cell((tag, value): Cell) -> Self {
-        match tag {
-            Tag::Flt => Self::flt_from_value(value),
-            Tag::Int => Self::int_from_value(value),
-            _ => unreachable!("from_cell called with non-numeric tag {:?}", tag),
+    /// whose tag is `Int`, `BigInt`, `Rat` or `Flt`. Calling it with any
+    /// other tag is a programmer error and will hit the `unreachable!` branch.
+    pub fn from_cell(cell: Cell) -> Self {
+        match Self::try_from(cell) {
+            Ok(number) => number,
+            Err(tag) => unreachable!("from_cell called with non-numeric tag {:?}", tag),
         }
     }
 
@@ -148,6 +308,8 @@ impl TryFrom<Cell> for Number{
         match value.0 {
             Tag::Flt => Ok(Self::flt_from_value(value.1)),
             Tag::Int => Ok(Self::int_from_value(value.1)),
+            Tag::BigInt => Ok(Number::Big((*SymbolDB::get_big_int(value.1)).clone())),
+            Tag::Rat => Ok(Number::Rat((*SymbolDB::get_rational(value.1)).clone())),
             tag => Err(tag)
         }
     }
@@ -156,64 +318,39 @@ impl TryFrom<Cell> for Number{
 impl std::ops::Add for Number {
     type Output = Number;
     fn add(self, rhs: Self) -> Self::Output {
-        match (self, rhs) {
-            (Number::Int(v1), Number::Int(v2)) => match v1.checked_add(v2) {
-                Some(result) => Number::Int(result),
-                None => Number::Flt(v1 as f64 + v2 as f64),
-            },
-            (lhs, rhs) => Number::Flt(lhs.float() + rhs.float()),
-        }
+        self.binary(rhs, isize::checked_add, |a, b| a + b, |a, b| a + b, |a, b| a + b)
     }
 }
 
 impl std::ops::Sub for Number {
     type Output = Number;
     fn sub(self, rhs: Self) -> Self::Output {
-        match (self, rhs) {
-            (Number::Int(v1), Number::Int(v2)) => match v1.checked_sub(v2) {
-                Some(result) => Number::Int(result),
-                None => Number::Flt(v1 as f64 - v2 as f64),
-            },
-            (lhs, rhs) => Number::Flt(lhs.float() - rhs.float()),
-        }
+        self.binary(rhs, isize::checked_sub, |a, b| a - b, |a, b| a - b, |a, b| a - b)
     }
 }
 
 impl std::ops::Mul for Number {
     type Output = Number;
     fn mul(self, rhs: Self) -> Self::Output {
-        match (self, rhs) {
-            (Number::Int(v1), Number::Int(v2)) => match v1.checked_mul(v2) {
-                Some(result) => Number::Int(result),
-                None => Number::Flt(v1 as f64 * v2 as f64),
-            },
-            (lhs, rhs) => Number::Flt(lhs.float() * rhs.float()),
-        }
+        self.binary(rhs, isize::checked_mul, |a, b| a * b, |a, b| a * b, |a, b| a * b)
     }
 }
 
+/// Division truncates between integers and is exact when either side is a
+/// rational. An exact zero divisor gives `nan`.
 impl std::ops::Div for Number {
     type Output = Number;
     fn div(self, rhs: Self) -> Self::Output {
-        match (self, rhs) {
-            (Number::Int(v1), Number::Int(v2)) => {
-                if v2 == 0 {
-                    Number::Flt(f64::NAN)
-                } else {
-                    Number::Int(v1 / v2)
-                }
-            }
-            (lhs, rhs) => Number::Flt(lhs.float() / rhs.float()),
+        if rhs.rational().is_some_and(|rhs| rhs.is_zero()) {
+            return Number::Flt(f64::NAN);
         }
+        self.binary(rhs, isize::checked_div, |a, b| a / b, |a, b| a / b, |a, b| a / b)
     }
 }
 
 impl PartialEq for Number {
     fn eq(&self, other: &Self) -> bool {
-        match (self, other) {
-            (Number::Int(v1), Number::Int(v2)) => v1 == v2,
-            (lhs, rhs) => lhs.float() == rhs.float(),
-        }
+        self.partial_cmp(other) == Some(std::cmp::Ordering::Equal)
     }
 }
 
@@ -221,144 +358,371 @@ impl PartialOrd for Number {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
         match (self, other) {
             (Number::Int(v1), Number::Int(v2)) => Some(v1.cmp(v2)),
-            _ => self.float().partial_cmp(&other.float()),
+            (Number::Flt(_), _) | (_, Number::Flt(_)) => self.float().partial_cmp(&other.float()),
+            _ => self.rational().partial_cmp(&other.rational()),
         }
     }
 }
 
 // ---------------------------------------------------------------------------
-// Math operation functions — all return Option<Number> so that a bad
-// sub-expression propagates as None rather than panicking.
+// Arithmetic functions
 // ---------------------------------------------------------------------------
 
-fn add(addr: usize, heap: &QueryHeap) -> Option<Number> {
-    Some(evaluate_term(addr + 2, heap)? + evaluate_term(addr + 3, heap)?)
+/// The integer value of `n`, or a type error for a float or rational.
+fn int(n: &Number) -> Result<BigInt, EvalError> {
+    n.big_int().ok_or_else(|| EvalError::Type("integer", n.clone()))
 }
 
-fn sub(addr: usize, heap: &QueryHeap) -> Option<Number> {
-    Some(evaluate_term(addr + 2, heap)? - evaluate_term(addr + 3, heap)?)
+/// The exact value of `n`, or a type error for a float.
+fn exact(n: &Number) -> Result<BigRational, EvalError> {
+    n.rational().ok_or_else(|| EvalError::Type("rational", n.clone()))
 }
 
-fn mul(addr: usize, heap: &QueryHeap) -> Option<Number> {
-    Some(evaluate_term(addr + 2, heap)? * evaluate_term(addr + 3, heap)?)
+/// Apply `op` to two float arguments.
+fn float2(args: &[Number], op: fn(fsize, fsize) -> fsize) -> Result<Number, EvalError> {
+    Ok(Number::Flt(op(args[0].float(), args[1].float())))
 }
 
-fn div(addr: usize, heap: &QueryHeap) -> Option<Number> {
-    Some(evaluate_term(addr + 2, heap)? / evaluate_term(addr + 3, heap)?)
+/// Apply `op` to a float argument.
+fn float1(args: &[Number], op: fn(fsize) -> fsize) -> Result<Number, EvalError> {
+    Ok(Number::Flt(op(args[0].float())))
 }
 
-fn pow(addr: usize, heap: &QueryHeap) -> Option<Number> {
-    Some(evaluate_term(addr + 2, heap)?.power(evaluate_term(addr + 3, heap)?))
+/// Apply `small` to two integer arguments, or `big` if either is a big
+/// integer or `small` overflows. `big` gives `None` for a result too large to
+/// represent, which is an evaluation error.
+fn int2(
+    args: &[Number],
+    small: fn(isize, isize) -> Option<isize>,
+    big: fn(BigInt, BigInt) -> Option<BigInt>,
+) -> Result<Number, EvalError> {
+    if let (Number::Int(x), Number::Int(y)) = (&args[0], &args[1]) {
+        if let Some(result) = small(*x, *y) {
+            return Ok(Number::Int(result));
+        }
+    }
+    big(int(&args[0])?, int(&args[1])?)
+        .map(Number::from)
+        .ok_or(EvalError::Evaluation("int_overflow"))
 }
 
-fn cos(addr: usize, heap: &QueryHeap) -> Option<Number> {
-    Some(Number::Flt(evaluate_term(addr + 2, heap)?.float().cos()))
+/// An integer division of `args[0]` by `args[1]`, with a zero divisor as an
+/// evaluation error.
+fn int_division(
+    args: &[Number],
+    small: fn(isize, isize) -> Option<isize>,
+    big: fn(BigInt, BigInt) -> Option<BigInt>,
+) -> Result<Number, EvalError> {
+    match int(&args[1])?.is_zero() {
+        true => Err(EvalError::Evaluation("zero_divisor")),
+        false => int2(args, small, big),
+    }
 }
 
-fn sin(addr: usize, heap: &QueryHeap) -> Option<Number> {
-    Some(Number::Flt(evaluate_term(addr + 2, heap)?.float().sin()))
+/// `n` rounded to an integer: by `float_op` for a float or `exact_op` for a
+/// rational, or `n` itself if it is already an integer.
+fn to_integer(
+    n: &Number,
+    float_op: fn(fsize) -> fsize,
+    exact_op: fn(&BigRational) -> BigRational,
+) -> Result<Number, EvalError> {
+    match n {
+        Number::Int(_) | Number::Big(_) => Ok(n.clone()),
+        Number::Rat(value) => Ok(exact_op(value).into()),
+        Number::Flt(value) if value.is_nan() => Err(EvalError::Evaluation("undefined")),
+        Number::Flt(value) => BigInt::from_f64(float_op(*value))
+            .map(Number::from)
+            .ok_or(EvalError::Evaluation("int_overflow")),
+    }
 }
 
-fn tan(addr: usize, heap: &QueryHeap) -> Option<Number> {
-    Some(Number::Flt(evaluate_term(addr + 2, heap)?.float().tan()))
+/// Shift `value` left by `shift` bits, or right for a negative `shift`.
+fn shift_left(value: isize, shift: isize) -> Option<isize> {
+    if shift < 0 {
+        let shift = shift.unsigned_abs().min(isize::BITS as usize - 1);
+        return Some(value >> shift);
+    }
+    let shift = u32::try_from(shift).ok().filter(|&s| s < isize::BITS)?;
+    let shifted = value << shift;
+    // Bits shifted out must all be copies of the sign bit
+    (shifted >> shift == value).then_some(shifted)
 }
 
-fn acos(addr: usize, heap: &QueryHeap) -> Option<Number> {
-    Some(Number::Flt(evaluate_term(addr + 2, heap)?.float().acos()))
+/// [`shift_left`] for big integers. Gives `None` for a left shift too large
+/// to represent.
+fn big_shift_left(value: BigInt, shift: BigInt) -> Option<BigInt> {
+    match shift.to_i64()? {
+        shift if shift < 0 => Some(value >> shift.unsigned_abs()),
+        shift => Some(value << u32::try_from(shift).ok()?),
+    }
 }
 
-fn asin(addr: usize, heap: &QueryHeap) -> Option<Number> {
-    Some(Number::Flt(evaluate_term(addr + 2, heap)?.float().asin()))
+/// The remainder of dividing `x` by `y`, rounding the quotient down, so it
+/// has the sign of `y`.
+fn floor_mod(x: isize, y: isize) -> Option<isize> {
+    let r = x.checked_rem_euclid(y)?;
+    Some(if y < 0 && r != 0 { r + y } else { r })
 }
 
-fn atan(addr: usize, heap: &QueryHeap) -> Option<Number> {
-    Some(Number::Flt(evaluate_term(addr + 2, heap)?.float().atan()))
+fn gcd(a: isize, b: isize) -> Option<isize> {
+    let (mut a, mut b) = (a.unsigned_abs(), b.unsigned_abs());
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    isize::try_from(a).ok()
 }
 
-fn log(addr: usize, heap: &QueryHeap) -> Option<Number> {
-    Some(Number::Flt(
-        evaluate_term(addr + 2, heap)?
-            .float()
-            .log(evaluate_term(addr + 3, heap)?.float()),
-    ))
+fn sign(args: &[Number]) -> Result<Number, EvalError> {
+    Ok(match &args[0] {
+        Number::Int(value) => Number::Int(value.signum()),
+        Number::Big(value) => Number::Int(value.signum().to_isize().unwrap_or_default()),
+        Number::Rat(value) => Number::Int(value.numer().signum().to_isize().unwrap_or_default()),
+        Number::Flt(value) if *value == 0.0 || value.is_nan() => Number::Flt(*value),
+        Number::Flt(value) => Number::Flt(value.signum()),
+    })
 }
 
-fn abs(addr: usize, heap: &QueryHeap) -> Option<Number> {
-    Some(evaluate_term(addr + 2, heap)?.abs())
+fn min(args: &[Number]) -> Result<Number, EvalError> {
+    Ok(if args[1] < args[0] { args[1].clone() } else { args[0].clone() })
 }
 
-fn round(addr: usize, heap: &QueryHeap) -> Option<Number> {
-    Some(evaluate_term(addr + 2, heap)?.round())
+fn max(args: &[Number]) -> Result<Number, EvalError> {
+    Ok(if args[1] > args[0] { args[1].clone() } else { args[0].clone() })
 }
 
-fn to_radians(addr: usize, heap: &QueryHeap) -> Option<Number> {
-    Some(Number::Flt(evaluate_term(addr + 2, heap)?.float().to_radians()))
+fn msb(args: &[Number]) -> Result<Number, EvalError> {
+    match int(&args[0])? {
+        value if value.is_positive() => Ok(Number::Int(value.bits() as isize - 1)),
+        _ => Err(EvalError::Type("not_less_than_one", args[0].clone())),
+    }
 }
 
-fn to_degrees(addr: usize, heap: &QueryHeap) -> Option<Number> {
-    Some(Number::Flt(evaluate_term(addr + 2, heap)?.float().to_degrees()))
+/// `rdiv`: exact division of integers and rationals.
+fn rdiv(args: &[Number]) -> Result<Number, EvalError> {
+    let (x, y) = (exact(&args[0])?, exact(&args[1])?);
+    match y.is_zero() {
+        true => Err(EvalError::Evaluation("zero_divisor")),
+        false => Ok((x / y).into()),
+    }
 }
 
-fn neg(addr: usize, heap: &QueryHeap) -> Option<Number> {
-    Some(match evaluate_term(addr + 2, heap)? {
-        Number::Int(v) => Number::Int(-v),
-        Number::Flt(v) => Number::Flt(-v),
-    })
+/// `rational`: the exact value of a float, or an integer or rational itself.
+fn rational(args: &[Number]) -> Result<Number, EvalError> {
+    match &args[0] {
+        Number::Flt(value) => BigRational::from_float(*value)
+            .map(Number::from)
+            .ok_or(EvalError::Evaluation("undefined")),
+        n => Ok(n.clone()),
+    }
 }
 
-fn sqrt(addr: usize, heap: &QueryHeap) -> Option<Number> {
-    Some(Number::Flt(evaluate_term(addr + 2, heap)?.float().sqrt()))
+/// `rationalize`: the simplest rational that converts back to the same float,
+/// found from the continued fraction of its exact value.
+fn rationalize(args: &[Number]) -> Result<Number, EvalError> {
+    let Number::Flt(value) = args[0] else {
+        return Ok(args[0].clone());
+    };
+    let Some(target) = BigRational::from_float(value) else {
+        return Err(EvalError::Evaluation("undefined"));
+    };
+    // Successive convergents p/q, starting from 0/1 and 1/0
+    let (mut p0, mut q0, mut p1, mut q1) = (BigInt::zero(), BigInt::one(), BigInt::one(), BigInt::zero());
+    let mut rest = target.clone();
+    loop {
+        let whole = rest.floor();
+        let (p2, q2) = (whole.numer() * &p1 + &p0, whole.numer() * &q1 + &q0);
+        let approximation = BigRational::new(p2.clone(), q2.clone());
+        if approximation == target || approximation.to_f64() == Some(value) {
+            return Ok(approximation.into());
+        }
+        rest = (rest - whole).recip();
+        (p0, q0, p1, q1) = (p1, q1, p2, q2);
+    }
 }
 
-/// Evaluate a functor/structure term as an arithmetic expression.
-/// Returns `None` if the functor is not a known arithmetic operator.
-fn evaluate_str(addr: usize, heap: &QueryHeap) -> Option<Number> {
-    let symbol = heap[addr + 1].1;
-    let arity = heap[addr].1;
-
-    // Unary minus: -(X) has arity 2 (functor cell + 1 arg)
-    if symbol == MINUS_SYMBOL && arity == 2 {
-        return neg(addr, heap);
-    }
+/// The standard arithmetic functions.
+///
+/// Integer-only functions (`//`, `mod`, `gcd`, the bitwise operators, ...)
+/// throw `type_error(integer, X)` for a float argument. Float functions
+/// follow IEEE semantics, so `sqrt(-1)` is `nan` rather than an error.
+pub static FUNCTIONS: FunctionModule = &[
+    // Basic arithmetic
+    ("+", 2, |a| Ok(a[0].clone() + a[1].clone())),
+    ("-", 2, |a| Ok(a[0].clone() - a[1].clone())),
+    ("*", 2, |a| Ok(a[0].clone() * a[1].clone())),
+    ("/", 2, |a| Ok(a[0].clone() / a[1].clone())),
+    ("+", 1, |a| Ok(a[0].clone())),
+    ("-", 1, |a| Ok(Number::Int(0) - a[0].clone())),
+    ("**", 2, |a| Ok(a[0].clone().power(a[1].clone()))),
+    ("^", 2, |a| Ok(a[0].clone().power(a[1].clone()))),
+    ("abs", 1, |a| Ok(a[0].clone().abs())),
+    ("sign", 1, sign),
+    ("min", 2, min),
+    ("max", 2, max),
+    // Integer division
+    ("//", 2, |a| int_division(a, isize::checked_div, |x, y| Some(x / y))),
+    ("rem", 2, |a| int_division(a, isize::checked_rem, |x, y| Some(x % y))),
+    ("mod", 2, |a| int_division(a, floor_mod, |x, y| Some(x.mod_floor(&y)))),
+    (
+        "div",
+        2,
+        |a| int_division(a, |x, y| x.checked_sub(floor_mod(x, y)?)?.checked_div(y), |x, y| Some(x.div_floor(&y))),
+    ),
+    ("gcd", 2, |a| int2(a, gcd, |x, y| Some(x.gcd(&y)))),
+    // Bitwise
+    ("msb", 1, msb),
+    ("/\\", 2, |a| int2(a, |x, y| Some(x & y), |x, y| Some(x & y))),
+    ("\\/", 2, |a| int2(a, |x, y| Some(x | y), |x, y| Some(x | y))),
+    ("xor", 2, |a| int2(a, |x, y| Some(x ^ y), |x, y| Some(x ^ y))),
+    ("\\", 1, |a| Ok((!int(&a[0])?).into())),
+    ("<<", 2, |a| int2(a, shift_left, big_shift_left)),
+    (">>", 2, |a| int2(a, |x, y| shift_left(x, y.checked_neg()?), |x, y| big_shift_left(x, -y))),
+    // Rounding and conversion
+    ("round", 1, |a| to_integer(&a[0], fsize::round, BigRational::round)),
+    ("integer", 1, |a| to_integer(&a[0], fsize::round, BigRational::round)),
+    ("truncate", 1, |a| to_integer(&a[0], fsize::trunc, BigRational::trunc)),
+    ("floor", 1, |a| to_integer(&a[0], fsize::floor, BigRational::floor)),
+    ("ceiling", 1, |a| to_integer(&a[0], fsize::ceil, BigRational::ceil)),
+    ("float", 1, |a| Ok(Number::Flt(a[0].float()))),
+    ("float_integer_part", 1, |a| float1(a, fsize::trunc)),
+    ("float_fractional_part", 1, |a| float1(a, fsize::fract)),
+    // Rationals
+    ("rdiv", 2, rdiv),
+    ("rational", 1, rational),
+    ("rationalize", 1, rationalize),
+    ("numerator", 1, |a| Ok(exact(&a[0])?.numer().clone().into())),
+    ("denominator", 1, |a| Ok(exact(&a[0])?.denom().clone().into())),
+    // Powers and logarithms
+    ("sqrt", 1, |a| float1(a, fsize::sqrt)),
+    ("exp", 1, |a| float1(a, fsize::exp)),
+    ("log", 1, |a| float1(a, fsize::ln)),
+    ("log", 2, |a| float2(a, |base, x| x.log(base))),
+    ("log2", 1, |a| float1(a, fsize::log2)),
+    ("hypot", 2, |a| float2(a, fsize::hypot)),
+    // Trigonometry
+    ("sin", 1, |a| float1(a, fsize::sin)),
+    ("cos", 1, |a| float1(a, fsize::cos)),
+    ("tan", 1, |a| float1(a, fsize::tan)),
+    ("cot", 1, |a| float1(a, |x| 1.0 / x.tan())),
+    ("asin", 1, |a| float1(a, fsize::asin)),
+    ("acos", 1, |a| float1(a, fsize::acos)),
+    ("atan", 1, |a| float1(a, fsize::atan)),
+    ("atan", 2, |a| float2(a, fsize::atan2)),
+    ("atan2", 2, |a| float2(a, fsize::atan2)),
+    ("sinh", 1, |a| float1(a, fsize::sinh)),
+    ("cosh", 1, |a| float1(a, fsize::cosh)),
+    ("tanh", 1, |a| float1(a, fsize::tanh)),
+    ("asinh", 1, |a| float1(a, fsize::asinh)),
+    ("acosh", 1, |a| float1(a, fsize::acosh)),
+    ("atanh", 1, |a| float1(a, fsize::atanh)),
+    ("to_degrees", 1, |a| float1(a, fsize::to_degrees)),
+    ("to_radians", 1, |a| float1(a, fsize::to_radians)),
+    // Constants
+    ("pi", 0, |_| Ok(Number::Flt(std::f64::consts::PI))),
+    ("e", 0, |_| Ok(Number::Flt(std::f64::consts::E))),
+    ("inf", 0, |_| Ok(Number::Flt(fsize::INFINITY))),
+    ("infinite", 0, |_| Ok(Number::Flt(fsize::INFINITY))),
+    ("nan", 0, |_| Ok(Number::Flt(fsize::NAN))),
+    ("epsilon", 0, |_| Ok(Number::Flt(fsize::EPSILON))),
+    ("max_tagged_integer", 0, |_| Ok(Number::Int(isize::MAX))),
+    ("min_tagged_integer", 0, |_| Ok(Number::Int(isize::MIN))),
+];
 
-    for (id, funct) in FUNCTIONS.iter() {
-        if *id == symbol {
-            return funct(addr, heap);
+/// Evaluate a functor/structure term as an arithmetic expression.
+/// Fails with [`EvalError::NotEvaluable`] if the functor is not a known
+/// arithmetic function.
+fn evaluate_str(addr: usize, heap: &QueryHeap, functions: &ArithmeticFunctions) -> Result<Number, EvalError> {
+    let arity = heap[addr].1 - 1;
+    let (Tag::Con, symbol) = heap[heap.deref_addr(addr + 1)] else {
+        return Err(EvalError::NotEvaluable(addr));
+    };
+    let function = functions.get(symbol, arity).ok_or(EvalError::NotEvaluable(addr))?;
+    // Most functions have at most two arguments, so avoid allocating for them
+    let mut buffer = [Number::Int(0), Number::Int(0)];
+    let mut spilled = Vec::new();
+    let args = match arity <= buffer.len() {
+        true => &mut buffer[..arity],
+        false => {
+            spilled.resize(arity, Number::Int(0));
+            &mut spilled[..]
         }
+    };
+    for (i, arg) in args.iter_mut().enumerate() {
+        *arg = evaluate_term(addr + 2 + i, heap, functions)?;
     }
-    None
+    function(args)
 }
 
 /// Evaluate a heap term as an arithmetic expression.
-/// Returns `None` if the term is not a number or a known arithmetic expression.
-fn evaluate_term(addr: usize, heap: &QueryHeap) -> Option<Number> {
+/// Fails if the term is unbound, or is not a number or a known arithmetic
+/// expression.
+fn evaluate_term(addr: usize, heap: &QueryHeap, functions: &ArithmeticFunctions) -> Result<Number, EvalError> {
     let addr = heap.deref_addr(addr);
     match heap[addr] {
-        (Tag::Comp, _) => evaluate_str(addr, heap),
-        (Tag::Str, ptr) => evaluate_str(ptr, heap),
-        (tag @ (Tag::Int | Tag::Flt), value) => Some(Number::from_cell((tag, value))),
-        _ => None,
+        (Tag::Comp, _) => evaluate_str(addr, heap, functions),
+        (Tag::Str, ptr) => evaluate_str(ptr, heap, functions),
+        (Tag::Con, symbol) => match functions.get(symbol, 0) {
+            Some(function) => function(&[]),
+            None => Err(EvalError::NotEvaluable(addr)),
+        },
+        (tag @ (Tag::Int | Tag::BigInt | Tag::Rat | Tag::Flt), value) => Ok(Number::from_cell((tag, value))),
+        (Tag::Ref | Tag::AVar, _) => Err(EvalError::Instantiation),
+        _ => Err(EvalError::NotEvaluable(addr)),
     }
 }
 
+/// Throw the ISO error for an expression that could not be evaluated:
+/// `instantiation_error`, `type_error(evaluable, Name/Arity)` for an unknown
+/// function (`type_error(evaluable, Term)` for other non-numbers), or the
+/// type or evaluation error raised by a function.
+fn throw_eval_error(heap: &mut QueryHeap, error: EvalError) -> PredReturn {
+    let culprit = match error {
+        EvalError::Instantiation => return instantiation_error(heap),
+        EvalError::Type(type_name, culprit) => {
+            let culprit = heap.heap_push(culprit.to_cell(heap.get_id()));
+            return type_error(heap, type_name, culprit);
+        }
+        EvalError::Evaluation(error) => return evaluation_error(heap, error),
+        EvalError::NotEvaluable(addr) => match heap[addr] {
+            (Tag::Con, _) => (addr, 0),
+            (Tag::Comp, arity) => (heap.deref_addr(addr + 1), arity - 1),
+            _ => return type_error(heap, "evaluable", addr),
+        },
+    };
+    let (name, arity) = culprit;
+    let slash = heap.set_const(SymbolDB::set_const("/"));
+    let arity = heap.heap_push((Tag::Int, arity));
+    let indicator = build_compound_from_addrs(heap, &[slash, name, arity]);
+    type_error(heap, "evaluable", indicator)
+}
+
+/// Evaluate the arithmetic expression at `addr` for a predicate outside this
+/// module, producing the error `is/2` would throw if it is not evaluable.
+pub fn evaluate(heap: &mut QueryHeap, predicate_table: &PredicateTable, addr: usize) -> Result<Number, PredReturn> {
+    evaluate_term(addr, heap, &predicate_table.functions).map_err(|error| throw_eval_error(heap, error))
+}
+
 // ---------------------------------------------------------------------------
 // Shared helper for comparison predicates
 // ---------------------------------------------------------------------------
 
-/// Resolve the goal to its functor address and evaluate both arguments as numbers.
-/// Returns `None` if the goal is malformed or either argument is not a valid
-/// arithmetic expression — the caller should treat this as failure.
-fn eval_comparison(heap: &QueryHeap, goal: usize) -> Option<(Number, Number)> {
-    let goal_addr = heap.deref_addr(goal);
-    let func_addr = match heap[goal_addr] {
-        (Tag::Str, ptr) => ptr,
-        (Tag::Comp, _) => goal_addr,
-        _ => return None,
-    };
-    Some((
-        evaluate_term(func_addr + 2, heap)?,
-        evaluate_term(func_addr + 3, heap)?,
-    ))
+/// Resolve the goal to its functor address, evaluate both arguments as
+/// numbers and compare them with `compare`. Throws if either argument is not
+/// a valid arithmetic expression.
+fn eval_comparison(
+    heap: &mut QueryHeap,
+    goal: usize,
+    predicate_table: &PredicateTable,
+    compare: impl Fn(Number, Number) -> bool,
+) -> PredReturn {
+    let func_addr = resolve(heap, goal);
+    let functions = &predicate_table.functions;
+    let evaluated = evaluate_term(func_addr + 2, heap, functions)
+        .and_then(|lhs| Ok((lhs, evaluate_term(func_addr + 3, heap, functions)?)));
+    match evaluated {
+        Ok((lhs, rhs)) => compare(lhs, rhs).into(),
+        Err(error) => throw_eval_error(heap, error),
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -369,12 +733,13 @@ fn eval_comparison(heap: &QueryHeap, goal: usize) -> Option<(Number, Number)> {
 ///
 /// - If LHS is an unbound variable, it is bound to the result.
 /// - If LHS is already bound, the predicate succeeds only if LHS equals the result.
-/// - Fails (rather than panicking) if either side is not a valid arithmetic expression.
+/// - Throws if the RHS is not a valid arithmetic expression, and fails if a
+///   bound LHS is not.
 pub fn is_pred(
     heap: &mut QueryHeap,
     _hypothesis: &mut Hypothesis,
     goal: usize,
-    _pred_table: &PredicateTable,
+    pred_table: &PredicateTable,
     _config: Config,
 ) -> PredReturn {
     let goal_addr = heap.deref_addr(goal);
@@ -384,22 +749,23 @@ pub fn is_pred(
         _ => return false.into(),
     };
 
-    let Some(rhs) = evaluate_term(func_addr + 3, heap) else {
-        return false.into();
+    let rhs = match evaluate_term(func_addr + 3, heap, &pred_table.functions) {
+        Ok(rhs) => rhs,
+        Err(error) => return throw_eval_error(heap, error),
     };
     let lhs_addr = heap.deref_addr(func_addr + 2);
 
     match heap[lhs_addr] {
         (Tag::Ref, _) => {
             // LHS is unbound — bind it to the result
-            let result_addr = heap.heap_push(rhs.to_cell());
+            let result_addr = heap.heap_push(rhs.to_cell(heap.get_id()));
             PredReturn::Success(vec![(lhs_addr, result_addr)], vec![])
         }
         _ => {
             // LHS is already bound — check numeric equality
-            match evaluate_term(lhs_addr, heap) {
-                Some(lhs) => (lhs == rhs).into(),
-                None => PredReturn::False,
+            match evaluate_term(lhs_addr, heap, &pred_table.functions) {
+                Ok(lhs) => (lhs == rhs).into(),
+                Err(_) => PredReturn::False,
             }
         }
     }
@@ -410,13 +776,10 @@ pub fn lt_pred(
     heap: &mut QueryHeap,
     _: &mut Hypothesis,
     goal: usize,
-    _: &PredicateTable,
+    predicate_table: &PredicateTable,
     _: Config,
 ) -> PredReturn {
-    match eval_comparison(heap, goal) {
-        Some((lhs, rhs)) => (lhs < rhs).into(),
-        None => PredReturn::False,
-    }
+    eval_comparison(heap, goal, predicate_table, |lhs, rhs| lhs < rhs)
 }
 
 /// `>/2`: succeeds if LHS evaluates to a number strictly greater than RHS.
@@ -424,13 +787,10 @@ pub fn gt_pred(
     heap: &mut QueryHeap,
     _: &mut Hypothesis,
     goal: usize,
-    _: &PredicateTable,
+    predicate_table: &PredicateTable,
     _: Config,
 ) -> PredReturn {
-    match eval_comparison(heap, goal) {
-        Some((lhs, rhs)) => (lhs > rhs).into(),
-        None => PredReturn::False,
-    }
+    eval_comparison(heap, goal, predicate_table, |lhs, rhs| lhs > rhs)
 }
 
 /// `=</2`: succeeds if LHS evaluates to a number less than or equal to RHS.
@@ -438,13 +798,10 @@ pub fn le_pred(
     heap: &mut QueryHeap,
     _: &mut Hypothesis,
     goal: usize,
-    _: &PredicateTable,
+    predicate_table: &PredicateTable,
     _: Config,
 ) -> PredReturn {
-    match eval_comparison(heap, goal) {
-        Some((lhs, rhs)) => (lhs <= rhs).into(),
-        None => PredReturn::False,
-    }
+    eval_comparison(heap, goal, predicate_table, |lhs, rhs| lhs <= rhs)
 }
 
 /// `>=/2`: succeeds if LHS evaluates to a number greater than or equal to RHS.
@@ -452,13 +809,10 @@ pub fn ge_pred(
     heap: &mut QueryHeap,
     _: &mut Hypothesis,
     goal: usize,
-    _: &PredicateTable,
+    predicate_table: &PredicateTable,
     _: Config,
 ) -> PredReturn {
-    match eval_comparison(heap, goal) {
-        Some((lhs, rhs)) => (lhs >= rhs).into(),
-        None => PredReturn::False,
-    }
+    eval_comparison(heap, goal, predicate_table, |lhs, rhs| lhs >= rhs)
 }
 
 /// `=:=/2`: succeeds if both sides evaluate to numerically equal values.
@@ -469,13 +823,10 @@ pub fn arith_eq_pred(
     heap: &mut QueryHeap,
     _: &mut Hypothesis,
     goal: usize,
-    _: &PredicateTable,
+    predicate_table: &PredicateTable,
     _: Config,
 ) -> PredReturn {
-    match eval_comparison(heap, goal) {
-        Some((lhs, rhs)) => (lhs == rhs).into(),
-        None => PredReturn::False,
-    }
+    eval_comparison(heap, goal, predicate_table, |lhs, rhs| lhs == rhs)
 }
 
 /// `=~=/2`: succeeds if both sides evaluate to approximately equal values.
@@ -490,21 +841,122 @@ pub fn approx_eq_pred(
     heap: &mut QueryHeap,
     _: &mut Hypothesis,
     goal: usize,
-    _: &PredicateTable,
+    predicate_table: &PredicateTable,
     _: Config,
 ) -> PredReturn {
-    let Some((lhs, rhs)) = eval_comparison(heap, goal) else {
-        return false.into();
-    };
     let pct = APPROX_TOLERANCE_PCT.load(Ordering::Relaxed);
     let tolerance = pct as fsize / 100.0;
-    let a = lhs.float();
-    let b = rhs.float();
-    let diff = (a - b).abs();
-    let scale = a.abs().max(b.abs());
-    // Both zero → exact equality, always succeeds.
-    // Otherwise succeed when the relative difference is within tolerance.
-    (scale == 0.0 || diff <= tolerance * scale).into()
+    eval_comparison(heap, goal, predicate_table, |lhs, rhs| {
+        let a = lhs.float();
+        let b = rhs.float();
+        let diff = (a - b).abs();
+        let scale = a.abs().max(b.abs());
+        // Both zero → exact equality, always succeeds.
+        // Otherwise succeed when the relative difference is within tolerance.
+        scale == 0.0 || diff <= tolerance * scale
+    })
+}
+
+/// `between(+Low, +High, ?X)`: succeeds if `Low =< X =< High`.
+///
+/// With X bound this is a check, which works for floats too. Otherwise Low
+/// and High must be integers, or High `inf`, and X is bound to Low, then to
+/// each following integer on backtracking. Each solution leaves a choice
+/// point for the rest of the range rather than listing it, so infinite
+/// ranges are enumerated lazily.
+pub fn between(
+    heap: &mut QueryHeap,
+    _: &mut Hypothesis,
+    goal: usize,
+    predicate_table: &PredicateTable,
+    _: Config,
+) -> PredReturn {
+    let [low_a, high_a, x_a] = [0, 1, 2].map(|n| goal_arg(heap, goal, n));
+    let (low, high) = match evaluate(heap, predicate_table, low_a)
+        .and_then(|low| Ok((low, evaluate(heap, predicate_table, high_a)?)))
+    {
+        Ok(bounds) => bounds,
+        Err(error) => return error,
+    };
+    if !is_unbound(heap, x_a) {
+        return match evaluate(heap, predicate_table, x_a) {
+            Ok(x) => (low <= x && x <= high).into(),
+            Err(error) => error,
+        };
+    }
+    if !matches!(low, Number::Int(_) | Number::Big(_)) {
+        return type_error(heap, "integer", low_a);
+    }
+    if !matches!(high, Number::Int(_) | Number::Big(_) | Number::Flt(fsize::INFINITY)) {
+        return type_error(heap, "integer", high_a);
+    }
+    if low > high {
+        return PredReturn::False;
+    }
+    let low_addr = heap.heap_push(low.to_cell(heap.get_id()));
+    if low == high {
+        return PredReturn::Success(bind_output(heap, x_a, low_addr), vec![]);
+    }
+    let next = heap.heap_push((low + Number::Int(1)).to_cell(heap.get_id()));
+    let functor = heap.set_const(SymbolDB::set_const("between"));
+    let rest = build_compound_from_addrs(heap, &[functor, next, high_a, x_a]);
+    choices(vec![(bind_output(heap, x_a, low_addr), vec![]), (vec![], vec![rest])])
+}
+
+/// `succ(?X, ?Y)`: Y is X + 1, for integers X >= 0.
+///
+/// Either argument may be unbound. With both unbound, the pairs `X = 0,
+/// Y = 1`, `X = 1, Y = 2`, ... are enumerated on backtracking, as with
+/// `between(0, inf, X)`. Throws `type_error(integer, A)` for a bound
+/// non-integer and `type_error(not_less_than_zero, A)` for a negative one.
+pub fn succ(
+    heap: &mut QueryHeap,
+    _: &mut Hypothesis,
+    goal: usize,
+    _: &PredicateTable,
+    _: Config,
+) -> PredReturn {
+    let [x_a, y_a] = [0, 1].map(|n| goal_arg(heap, goal, n));
+    let mut values = [None, None];
+    for (value, addr) in values.iter_mut().zip([x_a, y_a]) {
+        if is_unbound(heap, addr) {
+            continue;
+        }
+        match Number::try_from(heap[addr]) {
+            Ok(n @ (Number::Int(_) | Number::Big(_))) if n < Number::Int(0) => {
+                return type_error(heap, "not_less_than_zero", addr)
+            }
+            Ok(n @ (Number::Int(_) | Number::Big(_))) => *value = Some(n),
+            _ => return type_error(heap, "integer", addr),
+        }
+    }
+    match values {
+        [Some(x), _] => {
+            let y = heap.heap_push((x + Number::Int(1)).to_cell(heap.get_id()));
+            unify_result(heap, y, y_a)
+        }
+        [None, Some(Number::Int(0))] => PredReturn::False,
+        [None, Some(y)] => {
+            let x = heap.heap_push((y - Number::Int(1)).to_cell(heap.get_id()));
+            PredReturn::Success(bind_output(heap, x_a, x), vec![])
+        }
+        [None, None] if heap[x_a].0 == Tag::AVar => {
+            // Only Y is named, so enumerate it directly
+            let functor = heap.set_const(SymbolDB::set_const("between"));
+            let one = heap.heap_push((Tag::Int, 1));
+            let inf = heap.set_const(SymbolDB::set_const("inf"));
+            let generator = build_compound_from_addrs(heap, &[functor, one, inf, y_a]);
+            PredReturn::Success(vec![], vec![generator])
+        }
+        [None, None] => {
+            // Enumerate X, then call this goal again with X bound
+            let functor = heap.set_const(SymbolDB::set_const("between"));
+            let zero = heap.heap_push((Tag::Int, 0));
+            let inf = heap.set_const(SymbolDB::set_const("inf"));
+            let generator = build_compound_from_addrs(heap, &[functor, zero, inf, x_a]);
+            PredReturn::Success(vec![], vec![generator, goal])
+        }
+    }
 }
 
 /// Built-in maths predicates.
@@ -517,6 +969,8 @@ pub static MATHS: PredicateModule = (
         (">=", 2, ge_pred),
         ("=:=", 2, arith_eq_pred),
         ("=~=", 2, approx_eq_pred),
+        ("between", 3, between),
+        ("succ", 2, succ),
     ],
     &[include_str!("../../builtins/maths.pl")],
 );
@@ -524,13 +978,13 @@ pub static MATHS: PredicateModule = (
 #[cfg(test)]
 mod tests {
     use crate::predicate_modules::helpers::TestWrapper;
-    use super::MATHS;
+    use super::{super::DEFAULTS, MATHS};
 
     // ── helpers ──────────────────────────────────────────────────────────────
 
-    /// Load a fresh App with only the MATHS module registered.
+    /// Load a fresh App with the MATHS module and the DEFAULTS it relies on.
     fn test_wrapper() -> TestWrapper {
-        TestWrapper::new(&[MATHS])
+        TestWrapper::new(&[DEFAULTS, MATHS])
     }
 
     /// Run a query and return the display string bound to `var` in the first
@@ -557,6 +1011,130 @@ mod tests {
         assert!(!succeeds("6 is 2 + 3."));
     }
 
+    #[test]
+    fn is_errors() {
+        let tw = test_wrapper();
+        tw.assert_error("X is Y + 1.", "instantiation_error");
+        tw.assert_error("X is foo + 1.", "type_error(evaluable,/(foo,0))");
+        tw.assert_error("X is bar(1).", "type_error(evaluable,/(bar,1))");
+        tw.assert_false("a is 1 + 1.");
+    }
+
+    // ── arithmetic functions ─────────────────────────────────────────────────
+
+    #[test]
+    fn integer_division() {
+        let tw = test_wrapper();
+        tw.assert_binding("X is -7 // 2.", ("X", "-3"));
+        tw.assert_binding("X is -7 div 2.", ("X", "-4"));
+        tw.assert_binding("X is -7 rem 2.", ("X", "-1"));
+        tw.assert_binding("X is -7 mod 2.", ("X", "1"));
+        tw.assert_binding("X is 7 mod -2.", ("X", "-1"));
+        tw.assert_binding("X is gcd(12, -18).", ("X", "6"));
+        tw.assert_error("X is 1 // 0.", "evaluation_error(zero_divisor)");
+        tw.assert_error("X is 1 mod 0.", "evaluation_error(zero_divisor)");
+        tw.assert_error("X is 2.5 mod 2.", "type_error(integer,2.5)");
+    }
+
+    #[test]
+    fn bitwise() {
+        let tw = test_wrapper();
+        tw.assert_binding("X is 5 /\\ 3.", ("X", "1"));
+        tw.assert_binding("X is 5 \\/ 3.", ("X", "7"));
+        tw.assert_binding("X is 5 xor 3.", ("X", "6"));
+        tw.assert_binding("X is \\ 5.", ("X", "-6"));
+        tw.assert_binding("X is 1 << 4.", ("X", "16"));
+        tw.assert_binding("X is -16 >> 2.", ("X", "-4"));
+        tw.assert_binding("X is msb(1000).", ("X", "9"));
+        tw.assert_binding("X is 1 << 64.", ("X", "18446744073709551616"));
+        tw.assert_error("X is msb(0).", "type_error(not_less_than_one,0)");
+    }
+
+    #[test]
+    fn rounding_and_casts() {
+        let tw = test_wrapper();
+        tw.assert_binding("X is truncate(-2.5).", ("X", "-2"));
+        tw.assert_binding("X is floor(-2.5).", ("X", "-3"));
+        tw.assert_binding("X is ceiling(2.1).", ("X", "3"));
+        tw.assert_binding("X is round(2.5).", ("X", "3"));
+        tw.assert_binding("X is integer(-2.5).", ("X", "-3"));
+        tw.assert_true("X is float(3), X =:= 3.0, float(X).");
+        tw.assert_binding("X is sign(-3).", ("X", "-1"));
+        tw.assert_binding("X is min(2, 3), Y is max(2, 3).", ("Y", "3"));
+        tw.assert_error("X is truncate(nan).", "evaluation_error(undefined)");
+    }
+
+    #[test]
+    fn float_functions() {
+        let tw = test_wrapper();
+        tw.assert_true("X is exp(1), X =:= e.");
+        tw.assert_true("X is log(e), X =:= 1.");
+        tw.assert_true("X is log(2, 8), abs(X - 3) < 1.0e-9.");
+        tw.assert_true("X is atan2(1, 1), abs(X - pi / 4) < 1.0e-9.");
+        tw.assert_true("X is atan(1, 1), abs(X - pi / 4) < 1.0e-9.");
+        tw.assert_true("X is hypot(3, 4), X =:= 5.");
+        tw.assert_true("X is cot(1), abs(X * tan(1) - 1) < 1.0e-9.");
+        tw.assert_true("X is sinh(0) + tanh(0), X =:= 0, Y is cosh(0), Y =:= 1.");
+        tw.assert_true("X is inf, X > 10 ** 300.");
+        tw.assert_false("X is nan, X =:= X.");
+        tw.assert_binding("X is 2 ** 10.", ("X", "1024"));
+        tw.assert_binding("X is 2 ^ 3 + -(1).", ("X", "7"));
+    }
+
+    #[test]
+    fn load_functions() {
+        use crate::predicate_modules::maths::{FunctionModule, Number};
+        static FUNCTIONS: FunctionModule = &[
+            ("double", 1, |args| Ok(args[0].clone() + args[0].clone())),
+            ("answer", 0, |_| Ok(Number::Int(42))),
+        ];
+        let tw = TestWrapper { app: test_wrapper().app.load_functions(FUNCTIONS) };
+        tw.assert_binding("X is double(answer) + 1.", ("X", "85"));
+        // Other apps are unaffected
+        test_wrapper().assert_error("X is answer.", "type_error(evaluable,/(answer,0))");
+    }
+
+    #[test]
+    fn big_integers() {
+        let tw = test_wrapper();
+        tw.assert_binding("X is 2 ** 100.", ("X", "1267650600228229401496703205376"));
+        tw.assert_binding("X is 9223372036854775807 + 1.", ("X", "9223372036854775808"));
+        tw.assert_binding("X is abs(-9223372036854775807 - 1).", ("X", "9223372036854775808"));
+        tw.assert_binding("X is 2 ** 100 - 2 ** 100.", ("X", "0"));
+        tw.assert_binding("X is -(2 ** 100) // 3.", ("X", "-422550200076076467165567735125"));
+        tw.assert_binding("X is 2 ** 100 mod 7.", ("X", "2"));
+        tw.assert_binding("X is msb(1 << 100).", ("X", "100"));
+        tw.assert_binding("X is (1 << 100) >> 98.", ("X", "4"));
+        tw.assert_binding("X is gcd(2 ** 100, 6 ** 50).", ("X", "1125899906842624"));
+        tw.assert_binding("X is truncate(10.0 ** 30).", ("X", "1000000000000000019884624838656"));
+        // Big integers unify and compare by value
+        tw.assert_true("X is 2 ** 100, int(X), X = 1267650600228229401496703205376.");
+        tw.assert_true("X is 2 ** 100 + 1, Y is 2 ** 100, X > Y, X =:= Y + 1, compare(>, X, Y).");
+        tw.assert_true("X is 2 ** 64, Y is 2 ** 64 * 1.0, X =:= Y.");
+    }
+
+    #[test]
+    fn rationals() {
+        let tw = test_wrapper();
+        tw.assert_binding("X is 1 rdiv 3 + 1 rdiv 6.", ("X", "1r2"));
+        tw.assert_binding("X is 1r3 * 3.", ("X", "1"));
+        tw.assert_binding("X is 7 rdiv 2 / 2.", ("X", "7r4"));
+        tw.assert_binding("X is (1 rdiv 2) ** -3.", ("X", "8"));
+        tw.assert_binding("X is -(1r3).", ("X", "-1r3"));
+        tw.assert_binding("X is round(7r2).", ("X", "4"));
+        tw.assert_binding("X is floor(-7r2).", ("X", "-4"));
+        tw.assert_binding("X is numerator(4 rdiv 6).", ("X", "2"));
+        tw.assert_binding("X is denominator(4 rdiv 6).", ("X", "3"));
+        tw.assert_binding("X is rational(0.5).", ("X", "1r2"));
+        tw.assert_binding("X is rationalize(0.1).", ("X", "1r10"));
+        tw.assert_binding("X is float(1r4).", ("X", "0.25"));
+        tw.assert_true("1r3 < 0.34, 1r3 > 0.33, 2 rdiv 6 =:= 1r3.");
+        tw.assert_true("X is 1 rdiv 3, X = 1r3.");
+        tw.assert_error("X is 1 rdiv 0.", "evaluation_error(zero_divisor)");
+        tw.assert_error("X is 0.5 rdiv 2.", "type_error(rational,0.5)");
+        tw.assert_error("X is 1r2 mod 2.", "type_error(integer,1r2)");
+    }
+
     // ── comparison predicates ─────────────────────────────────────────────────
 
     #[test]
@@ -593,6 +1171,14 @@ mod tests {
         assert!(!succeeds("3 =:= 4."));
     }
 
+    #[test]
+    fn comparison_errors() {
+        let tw = test_wrapper();
+        tw.assert_error("X < 3.", "instantiation_error");
+        tw.assert_error("a >= 3.", "type_error(evaluable,/(a,0))");
+        tw.assert_error("1 =:= \"one\".", "type_error(evaluable,\"one\")");
+    }
+
     // ── succ/2 ───────────────────────────────────────────────────────────────
 
     #[test]
@@ -605,6 +1191,26 @@ mod tests {
         assert_eq!(binding("succ(X, 5).", "X").as_deref(), Some("4"));
     }
 
+    #[test]
+    fn succ_enumerates() {
+        let tw = test_wrapper();
+        tw.assert_binding("succ(X, Y), X >= 3, !.", ("Y", "4"));
+        tw.assert_false("succ(X, 0).");
+        tw.assert_true("succ(3, 4).");
+        tw.assert_error("succ(X, -1).", "type_error(not_less_than_zero,-1)");
+        tw.assert_error("succ(a, X).", "type_error(integer,a)");
+    }
+
+    #[test]
+    fn succ_anonymous() {
+        let tw = test_wrapper();
+        tw.assert_true("succ(_, 3).");
+        tw.assert_false("succ(_, 0).");
+        tw.assert_true("succ(_, _), !.");
+        tw.assert_binding("succ(X, _), X >= 2, !.", ("X", "2"));
+        tw.assert_binding("succ(_, Y), Y >= 2, !.", ("Y", "2"));
+    }
+
     // ── plus/3 ───────────────────────────────────────────────────────────────
 
     #[test]
@@ -700,7 +1306,7 @@ mod tests {
     }
 
     // ── between/3 ────────────────────────────────────────────────────────────
-    // Check mode only: all arguments must be bound. Floats are supported.
+    // Check mode supports floats; generation needs integer bounds.
 
     #[test]
     fn between_check() {
@@ -714,6 +1320,34 @@ mod tests {
         assert!(!succeeds("between(1.0, 5.0, 5.1)."));
     }
 
+    #[test]
+    fn between_generate() {
+        let tw = test_wrapper();
+        tw.assert_binding("between(1, 3, X).", ("X", "1"));
+        tw.assert_binding("between(3, 3, X).", ("X", "3"));
+        tw.assert_false("between(3, 2, X).");
+        tw.assert_binding("between(1, 5, X), X > 3.", ("X", "4"));
+        tw.assert_false("between(1, 5, X), X > 5.");
+        tw.assert_error("between(1.5, 3, X).", "type_error(integer,1.5)");
+        tw.assert_error("between(1, X, Y).", "instantiation_error");
+    }
+
+    #[test]
+    fn between_infinite() {
+        let tw = test_wrapper();
+        tw.assert_binding("between(1, inf, X), X > 10, !.", ("X", "11"));
+        tw.assert_binding("between(1, infinite, X), X * X > 50, !.", ("X", "8"));
+    }
+
+    #[test]
+    fn between_anonymous() {
+        let tw = test_wrapper();
+        assert_eq!(tw.all_bindings("between(1, 3, _), X = x.", "X"), ["x", "x", "x"]);
+        tw.assert_true("between(3, 3, _).");
+        tw.assert_false("between(3, 2, _).");
+        tw.assert_true("between(1, inf, _), !.");
+    }
+
     // ── negate/2 ─────────────────────────────────────────────────────────────
 
     #[test]
@@ -1,13 +1,20 @@
+use std::collections::HashMap;
+
 use crate::{
     Config, heap::{
-        heap::Heap,
+        heap::{Cell, Heap, Tag},
         query_heap::QueryHeap,
-    }, predicate_modules::helpers::{goal_arg, resolve}, program::{hypothesis::Hypothesis, predicate_table::PredicateTable}, resolution::proof::Proof
+        symbol_db::SymbolDB,
+    }, predicate_modules::helpers::{
+        apply_closure, build_list_from_addrs, build_list_with_tail, build_tuple_from_addrs, copy_term_cell,
+        domain_error, goal_arg, instantiation_error, is_unbound, must_be_error, resolve, unify_result,
+    }, predicate_modules::maths::{evaluate, Number}, program::{hypothesis::Hypothesis, predicate_table::PredicateTable}, resolution::{proof::Proof, unification::unify}
 };
 
 use super::{PredReturn, PredicateModule};
 
-/// Negation as failure: succeeds if the inner goal cannot be proven
+/// Negation as failure: succeeds if the inner goal cannot be proven.
+/// Also available as the prefix operator `\+`.
 pub fn not(
     heap: &mut QueryHeap,
     hypothesis: &mut Hypothesis,
@@ -52,6 +59,10 @@ pub fn not(
     inner_proof.undo_all(heap);
     heap.truncate(snapshot_len);
 
+    if let Some(ball) = inner_proof.take_exception(heap) {
+        return PredReturn::Throw(ball);
+    }
+
     if proved {
         if config.debug {
             eprintln!(
@@ -71,22 +82,684 @@ pub fn not(
     }
 }
 
-///find_all(+Template,:Goal,-Bag)
+/// call(:Closure, ?Arg...): call Closure with the extra arguments appended,
+/// so `call('<'(1), X)` runs `1 < X`. Registered for arities 1 to 8. A cut
+/// inside the called goal is local to it.
+pub fn call(
+    heap: &mut QueryHeap,
+    _hypothesis: &mut Hypothesis,
+    goal: usize,
+    _predicate_table: &PredicateTable,
+    _config: Config,
+) -> PredReturn {
+    let arity = heap.str_symbol_arity(goal).1;
+    let extra: Vec<usize> = (1..arity).map(|n| goal_arg(heap, goal, n)).collect();
+    let closure = goal_arg(heap, goal, 0);
+    match apply_closure(heap, closure, &extra) {
+        Some(inner_goal) => PredReturn::Success(vec![], vec![inner_goal]),
+        None => must_be_error(heap, "callable", closure),
+    }
+}
+
+/// throw(+Ball): raise Ball as an exception, to be caught by the innermost
+/// enclosing catch/3 whose catcher unifies with it.
+pub fn throw(
+    heap: &mut QueryHeap,
+    _hypothesis: &mut Hypothesis,
+    goal: usize,
+    _predicate_table: &PredicateTable,
+    _config: Config,
+) -> PredReturn {
+    let ball = goal_arg(heap, goal, 0);
+    if is_unbound(heap, ball) {
+        instantiation_error(heap)
+    } else {
+        PredReturn::Throw(ball)
+    }
+}
+
+/// Prove `inner_goal` to exhaustion in a nested proof and return, for each
+/// solution in order, fresh copies of the terms at `templates`.
+///
+/// Like [`not`], the nested proof runs on the shared heap with learning
+/// disabled and leaves no bindings behind. Each solution is copied out to a
+/// scratch heap before backtracking destroys it, then copied back once the
+/// proof is finished. The templates of one solution share a single variable
+/// map, so variables common to them stay shared in the copies.
+///
+/// An exception raised by `inner_goal` is returned as `Err(ball)`.
+fn collect_solutions(
+    heap: &mut QueryHeap,
+    hypothesis: &Hypothesis,
+    inner_goal: usize,
+    templates: &[usize],
+    predicate_table: &PredicateTable,
+    config: Config,
+) -> Result<Vec<Vec<usize>>, usize> {
+    let limit = usize::MAX;
+    collect_solutions_up_to(heap, hypothesis, inner_goal, templates, predicate_table, config, limit)
+}
+
+/// As [`collect_solutions`], but stop after the first `limit` solutions.
+pub(super) fn collect_solutions_up_to(
+    heap: &mut QueryHeap,
+    hypothesis: &Hypothesis,
+    inner_goal: usize,
+    templates: &[usize],
+    predicate_table: &PredicateTable,
+    config: Config,
+    limit: usize,
+) -> Result<Vec<Vec<usize>>, usize> {
+    let mut inner_config = config;
+    inner_config.max_clause = 0;
+
+    let mut store: Vec<Cell> = Vec::new();
+    let mut solutions: Vec<Vec<usize>> = Vec::new();
+
+    let snapshot_len = heap.heap_len();
+    let mut inner_proof = Proof::with_hypothesis(heap, &[inner_goal], hypothesis.clone());
+
+    if config.debug {
+        eprintln!("[COLLECT] {}", heap.term_string(inner_goal));
+    }
+
+    while solutions.len() < limit && inner_proof.prove(heap, predicate_table, inner_config) {
+        let mut ref_map = HashMap::new();
+        solutions.push(
+            templates
+                .iter()
+                .map(|&template| copy_term_cell(&mut store, &*heap, template, &mut ref_map))
+                .collect(),
+        );
+    }
+
+    inner_proof.undo_all(heap);
+    heap.truncate(snapshot_len);
+
+    if let Some(ball) = inner_proof.take_exception(heap) {
+        return Err(ball);
+    }
+
+    Ok(solutions
+        .into_iter()
+        .map(|copies| {
+            let mut ref_map = HashMap::new();
+            copies
+                .into_iter()
+                .map(|copy| copy_term_cell(heap, &store, copy, &mut ref_map))
+                .collect()
+        })
+        .collect())
+}
+
+/// Instances of `template` for every solution of `inner_goal`, in order.
+fn findall_instances(
+    heap: &mut QueryHeap,
+    hypothesis: &Hypothesis,
+    goal: usize,
+    predicate_table: &PredicateTable,
+    config: Config,
+) -> Result<Vec<usize>, usize> {
+    let template = goal_arg(heap, goal, 0);
+    let inner_goal = resolve(heap, goal_arg(heap, goal, 1));
+    let solutions =
+        collect_solutions(heap, hypothesis, inner_goal, &[template], predicate_table, config)?;
+    Ok(solutions.into_iter().map(|solution| solution[0]).collect())
+}
+
+/// findall(+Template, :Goal, -Bag): Bag is the list of instances of Template
+/// for every solution of Goal. Succeeds with `[]` if Goal has no solutions.
 pub fn findall(
-    _heap: &mut QueryHeap,
+    heap: &mut QueryHeap,
+    hypothesis: &mut Hypothesis,
+    goal: usize,
+    predicate_table: &PredicateTable,
+    config: Config,
+) -> PredReturn {
+    let instances = match findall_instances(heap, hypothesis, goal, predicate_table, config) {
+        Ok(instances) => instances,
+        Err(ball) => return PredReturn::Throw(ball),
+    };
+    let bag = build_list_from_addrs(heap, &instances);
+    unify_result(heap, bag, goal_arg(heap, goal, 2))
+}
+
+/// findall(+Template, :Goal, -Bag, ?Tail): as findall/3, but Bag is the
+/// difference list ending in Tail.
+pub fn findall_tail(
+    heap: &mut QueryHeap,
+    hypothesis: &mut Hypothesis,
+    goal: usize,
+    predicate_table: &PredicateTable,
+    config: Config,
+) -> PredReturn {
+    let instances = match findall_instances(heap, hypothesis, goal, predicate_table, config) {
+        Ok(instances) => instances,
+        Err(ball) => return PredReturn::Throw(ball),
+    };
+    let bag = build_list_with_tail(heap, &instances, goal_arg(heap, goal, 3));
+    unify_result(heap, bag, goal_arg(heap, goal, 2))
+}
+
+/// Strip any `Var^Goal` prefixes from a bagof/setof goal, returning the inner
+/// goal and the existentially quantified terms.
+fn strip_existential(heap: &QueryHeap, goal: usize) -> (usize, Vec<usize>) {
+    let caret = SymbolDB::set_const("^");
+    let mut quantified = Vec::new();
+    let mut goal = resolve(heap, heap.deref_addr(goal));
+    while heap[goal] == (Tag::Comp, 3) && heap[heap.deref_addr(goal + 1)] == (Tag::Con, caret) {
+        quantified.push(goal + 2);
+        goal = resolve(heap, heap.deref_addr(goal + 3));
+    }
+    (goal, quantified)
+}
+
+/// Shared implementation of bagof/3 and setof/3.
+///
+/// Variables of Goal that occur neither in Template nor under `^` are free.
+/// Solutions are grouped by the bindings of the free variables (up to
+/// variable renaming), in order of first appearance, and each group is
+/// offered as a separate choice binding the free variables and Bag.
+fn bag_of(
+    heap: &mut QueryHeap,
+    hypothesis: &Hypothesis,
+    goal: usize,
+    predicate_table: &PredicateTable,
+    config: Config,
+    sorted: bool,
+) -> PredReturn {
+    let template = goal_arg(heap, goal, 0);
+    let (inner_goal, quantified) = strip_existential(heap, goal_arg(heap, goal, 1));
+
+    let mut bound = heap.term_vars(template, false);
+    for term in quantified {
+        bound.extend(heap.term_vars(term, false));
+    }
+    let mut free_vars: Vec<usize> = Vec::new();
+    for var in heap.term_vars(inner_goal, false) {
+        if !bound.contains(&var) && !free_vars.contains(&var) {
+            free_vars.push(var);
+        }
+    }
+
+    let mut templates = vec![template];
+    templates.extend(&free_vars);
+    let solutions =
+        match collect_solutions(heap, hypothesis, inner_goal, &templates, predicate_table, config) {
+            Ok(solutions) => solutions,
+            Err(ball) => return PredReturn::Throw(ball),
+        };
+
+    // Each group pairs the free variable bindings (witness) with its instances
+    let mut groups: Vec<(Vec<usize>, Vec<usize>)> = Vec::new();
+    for solution in solutions {
+        let (instance, witness) = (solution[0], &solution[1..]);
+        let group = groups.iter_mut().find(|(other, _)| {
+            let mut pairs = Vec::new();
+            other
+                .iter()
+                .zip(witness)
+                .all(|(&a, &b)| heap.term_variant(a, b, &mut pairs))
+        });
+        match group {
+            Some((_, instances)) => instances.push(instance),
+            None => groups.push((witness.to_vec(), vec![instance])),
+        }
+    }
+
+    // Bind the free variables and Bag together by unifying two tuples
+    let mut target = free_vars;
+    target.push(goal_arg(heap, goal, 2));
+    let target = build_tuple_from_addrs(heap, &target);
+
+    let mut choices = Vec::with_capacity(groups.len());
+    for (mut witness, mut instances) in groups {
+        if sorted {
+            instances.sort_by(|&a, &b| heap.term_compare(a, b));
+            instances.dedup_by(|a, b| heap.term_compare(*a, *b).is_eq());
+        }
+        witness.push(build_list_from_addrs(heap, &instances));
+        let built = build_tuple_from_addrs(heap, &witness);
+        if let Some(sub) = unify(heap, built, target) {
+            choices.push((sub.get_bindings().to_vec(), vec![]));
+        }
+    }
+
+    // Choices are popped from the back
+    choices.reverse();
+    PredReturn::Choices(choices)
+}
+
+/// bagof(+Template, :Goal, -Bag): Bag is the list of instances of Template for
+/// the solutions of Goal, grouped by its free variables. Fails if Goal has no
+/// solutions.
+pub fn bagof(
+    heap: &mut QueryHeap,
+    hypothesis: &mut Hypothesis,
+    goal: usize,
+    predicate_table: &PredicateTable,
+    config: Config,
+) -> PredReturn {
+    bag_of(heap, hypothesis, goal, predicate_table, config, false)
+}
+
+/// setof(+Template, :Goal, -Set): as bagof/3, but each Set is sorted in the
+/// standard order of terms with duplicates removed.
+pub fn setof(
+    heap: &mut QueryHeap,
+    hypothesis: &mut Hypothesis,
+    goal: usize,
+    predicate_table: &PredicateTable,
+    config: Config,
+) -> PredReturn {
+    bag_of(heap, hypothesis, goal, predicate_table, config, true)
+}
+
+/// Var^Goal: outside bagof/setof the quantifier is ignored and Goal is called.
+pub fn existential(
+    heap: &mut QueryHeap,
     _hypothesis: &mut Hypothesis,
-    _goal: usize,
+    goal: usize,
     _predicate_table: &PredicateTable,
     _config: Config,
 ) -> PredReturn {
-    todo!()
+    PredReturn::Success(vec![], vec![resolve(heap, goal_arg(heap, goal, 1))])
+}
+
+/// The aggregation named by an aggregate_all/3,4 specification.
+#[derive(Clone, Copy, PartialEq)]
+enum Aggregate {
+    Count,
+    Sum,
+    Max,
+    Min,
+    Bag,
+    Set,
+}
+
+/// Read an aggregate_all specification: `count`, or one of `sum(Expr)`,
+/// `max(Expr)`, `min(Expr)`, `bag(Template)` and `set(Template)` paired with
+/// the address of its argument.
+fn aggregate_spec(
+    heap: &mut QueryHeap,
+    spec: usize,
+) -> Result<(Aggregate, Option<usize>), PredReturn> {
+    let spec = resolve(heap, heap.deref_addr(spec));
+    let (name, arg) = match heap[spec] {
+        (Tag::Con, name) => (name, None),
+        (Tag::Comp, 2) => match heap[heap.deref_addr(spec + 1)] {
+            (Tag::Con, name) => (name, Some(spec + 2)),
+            _ => return Err(domain_error(heap, "aggregate_spec", spec)),
+        },
+        (Tag::Ref | Tag::AVar, _) => return Err(instantiation_error(heap)),
+        _ => return Err(domain_error(heap, "aggregate_spec", spec)),
+    };
+    let aggregate = match (SymbolDB::get_const(name).as_ref(), arg) {
+        ("count", None) => Aggregate::Count,
+        ("sum", Some(_)) => Aggregate::Sum,
+        ("max", Some(_)) => Aggregate::Max,
+        ("min", Some(_)) => Aggregate::Min,
+        ("bag", Some(_)) => Aggregate::Bag,
+        ("set", Some(_)) => Aggregate::Set,
+        _ => return Err(domain_error(heap, "aggregate_spec", spec)),
+    };
+    Ok((aggregate, arg))
+}
+
+/// Shared implementation of aggregate_all/3 and aggregate_all/4.
+///
+/// With a `discriminator`, solutions agreeing on both the discriminator and
+/// the aggregated term count once, as if the pairs had been collected with
+/// findall/3 and passed through sort/2.
+fn aggregate(
+    heap: &mut QueryHeap,
+    hypothesis: &Hypothesis,
+    goal: usize,
+    discriminator: Option<usize>,
+    predicate_table: &PredicateTable,
+    config: Config,
+) -> PredReturn {
+    let offset = discriminator.is_some() as usize;
+    let (aggregate, template) = match aggregate_spec(heap, goal_arg(heap, goal, 0)) {
+        Ok(spec) => spec,
+        Err(error) => return error,
+    };
+    let inner_goal = resolve(heap, goal_arg(heap, goal, 1 + offset));
+    let templates: Vec<usize> = discriminator.into_iter().chain(template).collect();
+    let mut solutions =
+        match collect_solutions(heap, hypothesis, inner_goal, &templates, predicate_table, config) {
+            Ok(solutions) => solutions,
+            Err(ball) => return PredReturn::Throw(ball),
+        };
+
+    let compare = |heap: &QueryHeap, a: &[usize], b: &[usize]| {
+        a.iter()
+            .zip(b)
+            .map(|(&a, &b)| heap.term_compare(a, b))
+            .find(|order| order.is_ne())
+            .unwrap_or(std::cmp::Ordering::Equal)
+    };
+    if discriminator.is_some() {
+        solutions.sort_by(|a, b| compare(heap, a, b));
+        solutions.dedup_by(|a, b| compare(heap, a, b).is_eq());
+    }
+    let count = solutions.len();
+    let mut values: Vec<usize> = solutions
+        .into_iter()
+        .filter_map(|solution| solution.get(offset).copied())
+        .collect();
+
+    let result = match aggregate {
+        Aggregate::Count => heap.heap_push((Tag::Int, count)),
+        Aggregate::Sum | Aggregate::Max | Aggregate::Min => {
+            let mut numbers = Vec::with_capacity(values.len());
+            for value in values {
+                match evaluate(heap, predicate_table, value) {
+                    Ok(n) => numbers.push(n),
+                    Err(error) => return error,
+                }
+            }
+            let result = match aggregate {
+                Aggregate::Sum => {
+                    Some(numbers.into_iter().fold(Number::Int(0), |sum, n| sum + n))
+                }
+                Aggregate::Max => {
+                    numbers.into_iter().reduce(|best, n| if n > best { n } else { best })
+                }
+                _ => numbers.into_iter().reduce(|best, n| if n < best { n } else { best }),
+            };
+            match result {
+                Some(n) => heap.heap_push(n.to_cell(heap.get_id())),
+                None => return PredReturn::False,
+            }
+        }
+        Aggregate::Bag => build_list_from_addrs(heap, &values),
+        Aggregate::Set => {
+            values.sort_by(|&a, &b| heap.term_compare(a, b));
+            values.dedup_by(|a, b| heap.term_compare(*a, *b).is_eq());
+            build_list_from_addrs(heap, &values)
+        }
+    };
+    unify_result(heap, result, goal_arg(heap, goal, 2 + offset))
 }
 
-/// Built-in meta-predicates: `not/1` (negation as failure).
+/// aggregate_all(+Spec, :Goal, -Result): aggregate the solutions of Goal
+/// according to Spec. `count` counts them, `sum(Expr)`, `max(Expr)` and
+/// `min(Expr)` evaluate Expr for each, and `bag(T)` and `set(T)` collect the
+/// instances of T as findall/3 and setof/3 would. Only max and min fail when
+/// Goal has no solutions.
+pub fn aggregate_all(
+    heap: &mut QueryHeap,
+    hypothesis: &mut Hypothesis,
+    goal: usize,
+    predicate_table: &PredicateTable,
+    config: Config,
+) -> PredReturn {
+    aggregate(heap, hypothesis, goal, None, predicate_table, config)
+}
+
+/// aggregate_all(+Spec, +Discriminator, :Goal, -Result): as aggregate_all/3,
+/// but solutions with the same Discriminator and aggregated term are only
+/// counted once, so `aggregate_all(count, X, member(X, [a, b, a]), N)` gives
+/// `N = 2`.
+pub fn aggregate_all_grouped(
+    heap: &mut QueryHeap,
+    hypothesis: &mut Hypothesis,
+    goal: usize,
+    predicate_table: &PredicateTable,
+    config: Config,
+) -> PredReturn {
+    let discriminator = goal_arg(heap, goal, 1);
+    aggregate(heap, hypothesis, goal, Some(discriminator), predicate_table, config)
+}
+
+/// Built-in meta-predicates: `not/1` (negation as failure), `call/1..8`,
+/// `throw/1`, the all-solutions predicates `findall/3,4`, `bagof/3` and
+/// `setof/3`, and `aggregate_all/3,4`. `catch/3` is a control construct handled by the resolution
+/// engine.
 pub static META_PREDICATES: PredicateModule = (
     &[
         ("not", 1, not),
-        // ("findall", 3, findall),
+        ("\\+", 1, not),
+        ("call", 1, call),
+        ("call", 2, call),
+        ("call", 3, call),
+        ("call", 4, call),
+        ("call", 5, call),
+        ("call", 6, call),
+        ("call", 7, call),
+        ("call", 8, call),
+        ("throw", 1, throw),
+        ("findall", 3, findall),
+        ("findall", 4, findall_tail),
+        ("bagof", 3, bagof),
+        ("setof", 3, setof),
+        ("^", 2, existential),
+        ("aggregate_all", 3, aggregate_all),
+        ("aggregate_all", 4, aggregate_all_grouped),
     ],
     &[include_str!("../../builtins/meta_predicates.pl")],
 );
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::{helpers::TestWrapper, DEFAULTS, MATHS},
+        META_PREDICATES,
+    };
+
+    const FACTS: &str = "
+        age(peter, 7).
+        age(ann, 11).
+        age(pat, 8).
+        age(tom, 5).
+        age(mike, 11).
+        class(a, peter). class(a, ann). class(b, pat). class(b, tom). class(b, mike).
+    ";
+
+    fn tw() -> TestWrapper {
+        let mut tw = TestWrapper::new(&[DEFAULTS, MATHS, META_PREDICATES]);
+        tw.app = tw.app.load_code(FACTS).unwrap();
+        tw
+    }
+
+    // ── call/N ────────────────────────────────────────────────────────────
+
+    #[test]
+    fn call_goal() {
+        let tw = tw();
+        tw.assert_binding("call(age(ann, A)).", ("A", "11"));
+        tw.assert_binding("call((age(N, A), A < 6)).", ("N", "tom"));
+        tw.assert_false("call(age(ann, 7)).");
+        tw.assert_error("call(P).", "instantiation_error");
+        tw.assert_error("call(1).", "type_error(callable,1)");
+    }
+
+    #[test]
+    fn call_closure() {
+        let tw = tw();
+        tw.assert_true("call('<'(1), 2).");
+        tw.assert_false("call('<'(1), 0).");
+        tw.assert_binding("call(age, ann, A).", ("A", "11"));
+        tw.assert_binding("call(age(ann), A).", ("A", "11"));
+        tw.assert_binding("call(class, C, peter, ignore) ; C = none.", ("C", "none"));
+        tw.assert_true("P = '<'(1), P(2).");
+        tw.assert_false("P = '<'(1), P(0).");
+    }
+
+    #[test]
+    fn call_cut_is_local() {
+        let mut tw = tw();
+        tw.app = tw
+            .app
+            .load_code("t :- call(!), fail.\nt.\n")
+            .unwrap();
+        tw.assert_true("t.");
+        tw.assert_binding("findall(N, call((class(b, N), !)), L).", ("L", "[pat]"));
+        assert_eq!(tw.all_bindings("call(class(b), N).", "N"), vec!["pat", "tom", "mike"]);
+    }
+
+    // ── findall/3,4 ───────────────────────────────────────────────────────
+
+    #[test]
+    fn findall_collects_in_order() {
+        let tw = tw();
+        tw.assert_binding(
+            "findall(X, age(X, _), L).",
+            ("L", "[peter,ann,pat,tom,mike]"),
+        );
+        tw.assert_binding(
+            "findall(X-A, (age(X, A), A > 7), L).",
+            ("L", "[-(ann,11),-(pat,8),-(mike,11)]"),
+        );
+    }
+
+    #[test]
+    fn findall_no_solutions() {
+        let tw = tw();
+        tw.assert_binding("findall(X, age(X, 100), L).", ("L", "[]"));
+        tw.assert_false("findall(X, age(X, _), []).");
+    }
+
+    #[test]
+    fn findall_leaves_goal_unbound() {
+        let tw = tw();
+        tw.assert_true("findall(A, age(X, A), L), var(A), var(X).");
+        tw.assert_binding("findall(A, age(X, A), L), X = ann.", ("X", "ann"));
+    }
+
+    #[test]
+    fn findall_nested() {
+        let tw = tw();
+        tw.assert_binding(
+            "findall(C-L, ((class(C, peter) ; class(C, pat)), findall(N, class(C, N), L)), R).",
+            ("R", "[-(a,[peter,ann]),-(b,[pat,tom,mike])]"),
+        );
+    }
+
+    #[test]
+    fn findall_with_tail() {
+        let tw = tw();
+        tw.assert_binding("findall(X, class(a, X), L, [end]).", ("L", "[peter,ann,end]"));
+        tw.assert_binding("findall(X, class(c, X), L, T), T = [].", ("L", "[]"));
+    }
+
+    // ── bagof/3 ───────────────────────────────────────────────────────────
+
+    #[test]
+    fn bagof_groups_free_variables() {
+        let tw = tw();
+        assert_eq!(
+            tw.all_bindings("bagof(N, class(C, N), L).", "L"),
+            vec!["[peter,ann]", "[pat,tom,mike]"]
+        );
+        assert_eq!(
+            tw.all_bindings("bagof(N, class(C, N), L).", "C"),
+            vec!["a", "b"]
+        );
+    }
+
+    #[test]
+    fn bagof_existential() {
+        let tw = tw();
+        tw.assert_binding(
+            "bagof(N, C^class(C, N), L).",
+            ("L", "[peter,ann,pat,tom,mike]"),
+        );
+        assert_eq!(
+            tw.all_bindings("bagof(N, A^age(N, A), L).", "L"),
+            vec!["[peter,ann,pat,tom,mike]"]
+        );
+    }
+
+    #[test]
+    fn bagof_fails_without_solutions() {
+        let tw = tw();
+        tw.assert_false("bagof(X, age(X, 100), L).");
+        tw.assert_true("bagof(X, class(a, X), [peter, ann]).");
+    }
+
+    // ── setof/3 ───────────────────────────────────────────────────────────
+
+    #[test]
+    fn setof_sorts_and_deduplicates() {
+        let tw = tw();
+        tw.assert_binding("setof(A, N^age(N, A), L).", ("L", "[5,7,8,11]"));
+        tw.assert_binding(
+            "setof(N, C^class(C, N), L).",
+            ("L", "[ann,mike,pat,peter,tom]"),
+        );
+    }
+
+    #[test]
+    fn setof_groups() {
+        let tw = tw();
+        assert_eq!(
+            tw.all_bindings("setof(N-A, (class(C, N), age(N, A)), L).", "L"),
+            vec!["[-(ann,11),-(peter,7)]", "[-(mike,11),-(pat,8),-(tom,5)]"]
+        );
+        tw.assert_false("setof(X, age(X, 100), L).");
+    }
+
+    #[test]
+    fn existential_outside_bagof() {
+        let tw = tw();
+        tw.assert_binding("A^age(N, A), N = ann.", ("A", "11"));
+    }
+
+    // ── aggregate_all/3,4 ─────────────────────────────────────────────────
+
+    #[test]
+    fn aggregate_all_count() {
+        let tw = tw();
+        tw.assert_binding("aggregate_all(count, age(_, _), N).", ("N", "5"));
+        tw.assert_binding("aggregate_all(count, age(_, 100), N).", ("N", "0"));
+        tw.assert_binding("aggregate_all(count, class(b, _), N).", ("N", "3"));
+    }
+
+    #[test]
+    fn aggregate_all_arithmetic() {
+        let tw = tw();
+        tw.assert_binding("aggregate_all(sum(A), age(_, A), S).", ("S", "42"));
+        tw.assert_binding("aggregate_all(sum(A / 2), age(_, A), S).", ("S", "19"));
+        tw.assert_binding("aggregate_all(sum(A), age(_, 100), S).", ("S", "0"));
+        tw.assert_binding("aggregate_all(max(A), age(_, A), M).", ("M", "11"));
+        tw.assert_binding("aggregate_all(min(A), age(_, A), M).", ("M", "5"));
+        tw.assert_false("aggregate_all(max(A), age(_, 100), M).");
+        tw.assert_error(
+            "aggregate_all(sum(N), age(N, _), S).",
+            "type_error(evaluable,/(peter,0))",
+        );
+    }
+
+    #[test]
+    fn aggregate_all_bag_and_set() {
+        let tw = tw();
+        tw.assert_binding("aggregate_all(bag(A), age(_, A), L).", ("L", "[7,11,8,5,11]"));
+        tw.assert_binding("aggregate_all(set(A), age(_, A), L).", ("L", "[5,7,8,11]"));
+        tw.assert_binding("aggregate_all(bag(A), age(_, 100), L).", ("L", "[]"));
+        tw.assert_true("aggregate_all(count, age(N, _), _), var(N).");
+    }
+
+    #[test]
+    fn aggregate_all_discriminator() {
+        let tw = tw();
+        tw.assert_binding("aggregate_all(count, C, class(C, _), N).", ("N", "2"));
+        tw.assert_binding("aggregate_all(sum(A), A, age(_, A), S).", ("S", "31"));
+        tw.assert_binding(
+            "aggregate_all(bag(N), A, age(N, A), L).",
+            ("L", "[tom,peter,pat,ann,mike]"),
+        );
+    }
+
+    #[test]
+    fn aggregate_all_bad_spec() {
+        let tw = tw();
+        tw.assert_error("aggregate_all(S, age(_, _), N).", "instantiation_error");
+        tw.assert_error(
+            "aggregate_all(total, age(_, _), N).",
+            "domain_error(aggregate_spec,total)",
+        );
+        tw.assert_error("aggregate_all(sum, age(_, _), N).", "domain_error(aggregate_spec,sum)");
+    }
+}
@@ -1,3 +1,7 @@
+/// Built-in dynamic database predicates (`assertz/1`, `retract/1`, ...).
+pub mod database;
+/// Built-in grammar predicates (`phrase/2,3`).
+pub mod dcg;
 /// Built-in defaults
 pub mod defaults;
 /// Helper functions for predicate modules
@@ -6,21 +10,32 @@ pub mod helpers;
 pub mod lists;
 /// Built-in maths predicates.
 pub mod maths;
-/// Built-in meta-predicates (`not/1`).
+/// Built-in output predicates (`write/1`, `format/2,3`, ...).
+pub mod output;
+/// Built-in meta-predicates (`not/1`, `call/N`, `findall/3`, `throw/1`, ...).
 pub mod meta_predicates;
 /// Built-in set predicates
 pub mod sets;
 /// Built-in string and atom predicates.
 pub mod strings;
+/// Built-in syntax predicates (`op/3`).
+pub mod syntax;
+/// Built-in tabling predicates (`table/1`, `abolish_all_tables/0`).
+pub mod tabling;
 
+pub use database::DATABASE;
+pub use dcg::DCG;
 pub use defaults::DEFAULTS;
 pub use lists::LISTS;
 pub use maths::MATHS;
 pub use meta_predicates::META_PREDICATES;
+pub use output::OUTPUT;
 pub use strings::STRINGS;
+pub use syntax::SYNTAX;
+pub use tabling::TABLING;
 
 use crate::{
-    heap::query_heap::QueryHeap,
+    heap::{heap::Cell, query_heap::QueryHeap},
     predicate_modules::sets::SETS,
     program::{hypothesis::Hypothesis, predicate_table::PredicateTable},
     Config,
@@ -29,7 +44,8 @@ use crate::{
 /// Return type for predicate functions.
 ///
 /// A predicate either succeeds ([`PredReturn::True`]), fails ([`PredReturn::False`]),
-/// or succeeds with heap mutations and/or new sub-goals ([`PredReturn::Success`]).
+/// succeeds with heap mutations and/or new sub-goals ([`PredReturn::Success`]),
+/// or raises an exception ([`PredReturn::Throw`]).
 ///
 /// # Variants
 ///
@@ -58,6 +74,19 @@ pub enum PredReturn {
     /// [`Success`](PredReturn::Success). The engine stores these alternatives and
     /// pops one per attempt, undoing bindings on backtrack just like clause choices.
     Choices(Vec<(Vec<(usize, usize)>, Vec<usize>)>),
+    /// Raise an exception: the field is the heap address of the ball term.
+    ///
+    /// The engine unwinds to the innermost `catch/3` whose catcher unifies
+    /// with a copy of the ball, or fails the query with [`crate::Error::Exception`]
+    /// if there is none. See [`helpers::instantiation_error`] and
+    /// [`helpers::type_error`] for building ISO error terms.
+    Throw(usize),
+    /// Success after overwriting heap cells in place, as in `setarg/3`.
+    ///
+    /// Each `(addr, cell)` pair writes `cell` to the heap at `addr`. The engine
+    /// keeps the cells they replace and restores them on backtracking, so the
+    /// addresses must belong to the query heap and predate the call.
+    Assign(Vec<(usize, Cell)>),
 }
 
 impl From<bool> for PredReturn {
@@ -114,4 +143,6 @@ pub type PredicateModule = (
 );
 
 pub static STANDARD_MODULES: &[PredicateModule] =
-    &[DEFAULTS, MATHS, META_PREDICATES, LISTS, STRINGS, SETS];
+    &[
+    DEFAULTS, MATHS, META_PREDICATES, LISTS, STRINGS, SETS, DATABASE, SYNTAX, DCG, TABLING, OUTPUT,
+];
@@ -0,0 +1,960 @@
+//! Built-in term output predicates.
+//!
+//! `write/1`, `print/1`, `writeq/1`, `nl/0`, `tab/1` and `format/1,2` write
+//! to the program's [`Output`](crate::program::output::Output), which is
+//! standard output unless the [`App`](crate::app::App) was given another
+//! sink. `format/3` and `with_output_to/2` capture the text instead and
+//! unify it with `atom(A)`, `string(S)`, `codes(Cs)` or `chars(Cs)`.
+//!
+//! Terms are written with the program's operators, so `1+2*3` is written
+//! as `1+2*3` rather than `+(1,*(2,3))`. `writeq/1` and `print/1` quote
+//! atoms and strings where needed to read them back.
+
+use super::{
+    helpers::{
+        build_compound_from_addrs, build_list, build_tuple_from_addrs, domain_error,
+        evaluation_error, goal_arg, instantiation_error, is_unbound, must_be_error, read_list_addrs, resolve, throw_error,
+        type_error, unify_result,
+    },
+    maths::{evaluate, Number},
+    meta_predicates::collect_solutions_up_to,
+    PredReturn, PredicateModule,
+};
+use crate::{
+    heap::{
+        heap::{Cell, Heap, Tag},
+        query_heap::QueryHeap,
+        symbol_db::SymbolDB,
+    },
+    parser::operators::{Operators, ARG_PRIORITY, MAX_PRIORITY, SYMBOL_CHARS},
+    program::{hypothesis::Hypothesis, predicate_table::PredicateTable},
+    Config,
+};
+
+use fsize::fsize;
+use num_bigint::BigInt;
+use num_traits::Signed;
+
+// ── Writing terms ─────────────────────────────────────────────────────────────
+
+/// Whether `name` must be quoted to read back as the same atom.
+fn atom_needs_quotes(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        None => true,
+        _ if matches!(name, "[]" | "!" | ";" | "{}") => false,
+        Some(first) if first.is_lowercase() => !chars.all(|c| c.is_alphanumeric() || c == '_'),
+        _ => !name.chars().all(|c| SYMBOL_CHARS.contains(&c)),
+    }
+}
+
+/// `text` between `quote` characters, with backslash escapes.
+fn quote_text(text: &str, quote: char) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push(quote);
+    for c in text.chars() {
+        match c {
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            c if c == quote => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push(quote);
+    quoted
+}
+
+/// A float written so that it reads back as a float, e.g. `1.0` not `1`.
+fn float_text(value: fsize) -> String {
+    let text = format!("{value:?}");
+    if text.contains(['.', 'e', 'i', 'N']) {
+        text
+    } else {
+        text + ".0"
+    }
+}
+
+fn is_symbol_char(c: Option<char>) -> bool {
+    c.is_some_and(|c| SYMBOL_CHARS.contains(&c))
+}
+
+/// Writes terms as text, using a program's operators.
+struct TermWriter<'a, 'h> {
+    heap: &'a QueryHeap<'h>,
+    operators: &'a Operators,
+    quoted: bool,
+}
+
+impl TermWriter<'_, '_> {
+    fn atom(&self, name: &str) -> String {
+        if self.quoted && atom_needs_quotes(name) {
+            quote_text(name, '\'')
+        } else {
+            name.to_string()
+        }
+    }
+
+    /// The term at `addr` as an operand of priority at most `max_priority`.
+    fn term(&self, addr: usize, max_priority: usize) -> String {
+        let addr = self.heap.deref_addr(addr);
+        match self.heap[addr] {
+            (Tag::Ref, _) => format!("_G{addr}"),
+            (Tag::Arg, n) => format!("_A{n}"),
+            (Tag::AVar, _) => "_".into(),
+            (Tag::Int, value) => (value as isize).to_string(),
+            (Tag::Flt, value) => float_text(fsize::from_bits(value as u64)),
+            (Tag::BigInt | Tag::Rat, _) => Number::from_cell(self.heap[addr]).to_string(),
+            (Tag::Con, id) => self.atom(&SymbolDB::get_const(id)),
+            (Tag::ELis, _) => "[]".into(),
+            (Tag::Stri, idx) if self.quoted => quote_text(&SymbolDB::get_string(idx), '"'),
+            (Tag::Stri, idx) => SymbolDB::get_string(idx).to_string(),
+            (Tag::Str, ptr) => self.term(ptr, max_priority),
+            (Tag::Lis, _) => self.list(addr),
+            (Tag::Tup, _) => format!("({})", self.arguments(self.heap.str_iterator(addr))),
+            (Tag::Set, _) => format!("{{{}}}", self.arguments(self.heap.str_iterator(addr))),
+            (Tag::Comp, _) => self.compound(addr, max_priority),
+        }
+    }
+
+    /// Terms separated by commas, each as an argument.
+    fn arguments(&self, addrs: impl Iterator<Item = usize>) -> String {
+        addrs
+            .map(|addr| self.term(addr, ARG_PRIORITY))
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    fn list(&self, addr: usize) -> String {
+        let mut text = String::from("[");
+        let mut cell = addr;
+        loop {
+            let ptr = self.heap[cell].1;
+            text += &self.term(ptr, ARG_PRIORITY);
+            cell = self.heap.deref_addr(ptr + 1);
+            match self.heap[cell].0 {
+                Tag::Lis => text.push(','),
+                Tag::ELis => break,
+                _ => {
+                    text.push('|');
+                    text += &self.term(cell, ARG_PRIORITY);
+                    break;
+                }
+            }
+        }
+        text.push(']');
+        text
+    }
+
+    fn compound(&self, addr: usize, max_priority: usize) -> String {
+        let arity = self.heap[addr].1 - 1;
+        let functor = self.heap.deref_addr(addr + 1);
+        let args: Vec<usize> = self.heap.str_iterator(addr).skip(1).collect();
+        let (Tag::Con, id) = self.heap[functor] else {
+            // A variable functor, as in a second-order term P(X, Y)
+            return format!(
+                "{}({})",
+                self.term(functor, 0),
+                self.arguments(args.into_iter())
+            );
+        };
+        let name = SymbolDB::get_const(id);
+
+        if &*name == "{}" && arity == 1 {
+            return format!("{{{}}}", self.term(args[0], MAX_PRIORITY));
+        }
+        let (priority, text) =
+            if let (2, Some((priority, op_type))) = (arity, self.operators.infix(&name)) {
+                let (left_max, right_max) = op_type.arg_priorities(priority);
+                let left = self.term(args[0], left_max);
+                let right = self.term(args[1], right_max);
+                let text = if &*name == "," {
+                    format!("{left},{right}")
+                } else if name.starts_with(char::is_alphabetic) {
+                    format!("{left} {} {right}", self.atom(&name))
+                } else {
+                    // Keep symbol characters of the operator and its operands apart
+                    let op = self.atom(&name);
+                    let left_gap = if is_symbol_char(left.chars().last()) {
+                        " "
+                    } else {
+                        ""
+                    };
+                    let right_gap = if is_symbol_char(right.chars().next()) {
+                        " "
+                    } else {
+                        ""
+                    };
+                    format!("{left}{left_gap}{op}{right_gap}{right}")
+                };
+                (priority, text)
+            } else if let (1, Some((priority, op_type))) = (arity, self.operators.prefix(&name)) {
+                let operand = self.term(args[0], op_type.arg_priorities(priority).1);
+                let numeric = matches!(
+                    self.heap[self.heap.deref_addr(args[0])].0,
+                    Tag::Int | Tag::BigInt | Tag::Rat | Tag::Flt
+                );
+                let gap = if name.starts_with(char::is_alphabetic)
+                    || is_symbol_char(operand.chars().next())
+                    || (numeric && matches!(&*name, "-" | "+"))
+                {
+                    " "
+                } else {
+                    ""
+                };
+                (priority, format!("{}{gap}{operand}", self.atom(&name)))
+            } else if let (1, Some((priority, op_type))) = (arity, self.operators.postfix(&name)) {
+                let operand = self.term(args[0], op_type.arg_priorities(priority).0);
+                (priority, format!("{operand}{}", self.atom(&name)))
+            } else {
+                return format!("{}({})", self.atom(&name), self.arguments(args.into_iter()));
+            };
+
+        if priority > max_priority {
+            format!("({text})")
+        } else {
+            text
+        }
+    }
+}
+
+/// The term at `addr` as text, written with the operators of
+/// `predicate_table`. With `quoted` set, atoms and strings are quoted where
+/// needed to read them back, as by `writeq/1`.
+pub fn term_text(
+    heap: &QueryHeap,
+    addr: usize,
+    predicate_table: &PredicateTable,
+    quoted: bool,
+) -> String {
+    let operators = predicate_table.operators.read().unwrap();
+    TermWriter {
+        heap,
+        operators: &operators,
+        quoted,
+    }
+    .term(addr, MAX_PRIORITY)
+}
+
+// ── Text arguments and output targets ─────────────────────────────────────────
+
+/// The text of an atom, string, number, or list of character codes or
+/// single-character atoms, or `None` for anything else.
+pub(super) fn text_of(heap: &QueryHeap, addr: usize) -> Option<String> {
+    let addr = heap.deref_addr(addr);
+    match heap[addr] {
+        (Tag::Con, id) => Some(SymbolDB::get_const(id).to_string()),
+        (Tag::Stri, idx) => Some(SymbolDB::get_string(idx).to_string()),
+        (Tag::Int, value) => Some((value as isize).to_string()),
+        (Tag::Flt, value) => Some(float_text(fsize::from_bits(value as u64))),
+        (Tag::BigInt | Tag::Rat, _) => Some(Number::from_cell(heap[addr]).to_string()),
+        (Tag::ELis, _) => Some(String::new()),
+        (Tag::Lis, _) => read_list_addrs(heap, addr)?
+            .into_iter()
+            .map(|element| match heap[heap.deref_addr(element)] {
+                (Tag::Int, code) => char::from_u32(code as u32),
+                (Tag::Con, id) => {
+                    let name = SymbolDB::get_const(id);
+                    let mut chars = name.chars();
+                    chars.next().filter(|_| chars.next().is_none())
+                }
+                _ => None,
+            })
+            .collect(),
+        _ => None,
+    }
+}
+
+/// Where `format/3` and `with_output_to/2` send their output.
+pub(super) enum Target {
+    /// Unify the text, converted by the named function, with an argument.
+    Capture(&'static str, usize),
+    /// Write to the program output.
+    Output,
+    /// Write to standard error.
+    Error,
+}
+
+/// Read an output target: `atom(A)`, `string(S)`, `codes(Cs)` or
+/// `chars(Cs)`, and with `streams` set, `user_output` or `user_error`.
+pub(super) fn output_target(heap: &mut QueryHeap, addr: usize, streams: bool) -> Result<Target, PredReturn> {
+    let addr = heap.deref_addr(addr);
+    if is_unbound(heap, addr) {
+        return Err(instantiation_error(heap));
+    }
+    let addr = resolve(heap, addr);
+    let target = match heap[addr] {
+        (Tag::Con, id) if streams => match &*SymbolDB::get_const(id) {
+            "user_output" => Some(Target::Output),
+            "user_error" => Some(Target::Error),
+            _ => None,
+        },
+        (Tag::Comp, 2) => match heap[heap.deref_addr(addr + 1)] {
+            (Tag::Con, id) => ["atom", "string", "codes", "chars"]
+                .into_iter()
+                .find(|&kind| *SymbolDB::get_const(id) == *kind)
+                .map(|kind| Target::Capture(kind, addr + 2)),
+            _ => None,
+        },
+        _ => None,
+    };
+    target.ok_or_else(|| domain_error(heap, "output_sink", addr))
+}
+
+/// Push `text` converted as `kind` names: an atom, a string, or a list of
+/// character codes or single-character atoms.
+pub(super) fn capture_value(heap: &mut QueryHeap, kind: &str, text: &str) -> usize {
+    match kind {
+        "atom" => heap.heap_push((Tag::Con, SymbolDB::set_const(text))),
+        "string" => heap.heap_push((Tag::Stri, SymbolDB::set_string(text.to_string(), heap.get_id()))),
+        "codes" => {
+            let codes: Vec<Cell> = text.chars().map(|c| (Tag::Int, c as usize)).collect();
+            build_list(heap, &codes)
+        }
+        _ => {
+            let chars: Vec<Cell> = text
+                .chars()
+                .map(|c| (Tag::Con, SymbolDB::set_const(c.to_string())))
+                .collect();
+            build_list(heap, &chars)
+        }
+    }
+}
+
+// ── format/2 ──────────────────────────────────────────────────────────────────
+
+/// Throw `error(format(Message), _)` for a malformed format call.
+fn format_error(heap: &mut QueryHeap, message: &str) -> PredReturn {
+    let functor = heap.set_const(SymbolDB::set_const("format"));
+    let message = heap.heap_push((Tag::Stri, SymbolDB::set_string(message.to_string(), heap.get_id())));
+    let formal = build_compound_from_addrs(heap, &[functor, message]);
+    throw_error(heap, formal)
+}
+
+/// Text produced by a format directive, with the column stops and fill
+/// points of `~t`, `~|` and `~+`.
+struct Formatter {
+    text: String,
+    /// Column the text starts at.
+    start_column: usize,
+    /// Column of the last column stop.
+    last_stop: usize,
+    /// Byte offsets of the fill points since the last column stop, with
+    /// their fill characters.
+    fill_points: Vec<(usize, char)>,
+}
+
+impl Formatter {
+    fn column(&self) -> usize {
+        match self.text.rfind('\n') {
+            Some(newline) => self.text[newline + 1..].chars().count(),
+            None => self.start_column + self.text.chars().count(),
+        }
+    }
+
+    fn newline(&mut self) {
+        self.text.push('\n');
+        self.last_stop = 0;
+        self.fill_points.clear();
+    }
+
+    /// Pad the text since the last column stop out to `target`, spreading
+    /// the padding over its fill points, or after it if it has none.
+    fn column_stop(&mut self, target: usize) {
+        let column = self.column();
+        if target > column {
+            let pad = target - column;
+            if self.fill_points.is_empty() {
+                self.text.extend(std::iter::repeat_n(' ', pad));
+            } else {
+                let (each, extra) = (pad / self.fill_points.len(), pad % self.fill_points.len());
+                for (i, &(offset, fill)) in self.fill_points.iter().enumerate().rev() {
+                    let count = each + usize::from(i < extra);
+                    self.text
+                        .insert_str(offset, &fill.to_string().repeat(count));
+                }
+            }
+        }
+        self.last_stop = target.max(column);
+        self.fill_points.clear();
+    }
+}
+
+/// Run the format directives of `format` over `args`, starting at column
+/// `start_column`.
+fn format_text(
+    heap: &mut QueryHeap,
+    predicate_table: &PredicateTable,
+    format: &str,
+    args: &[usize],
+    start_column: usize,
+) -> Result<String, PredReturn> {
+    let mut out = Formatter {
+        text: String::new(),
+        start_column,
+        last_stop: start_column,
+        fill_points: Vec::new(),
+    };
+    let mut args = args.iter().copied();
+    let mut chars = format.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '~' {
+            if c == '\n' {
+                out.newline();
+            } else {
+                out.text.push(c);
+            }
+            continue;
+        }
+
+        // Optional numeric argument: digits, `*` to take it from the
+        // arguments, or a backquoted character for its code
+        let mut numeric: Option<usize> = None;
+        match chars.peek() {
+            Some('*') => {
+                chars.next();
+                let Some(arg) = args.next() else {
+                    return Err(format_error(heap, "not enough arguments"));
+                };
+                match heap[heap.deref_addr(arg)] {
+                    (Tag::Int, n) if n as isize >= 0 => numeric = Some(n),
+                    _ => return Err(must_be_error(heap, "integer", arg)),
+                }
+            }
+            Some('`') => {
+                chars.next();
+                numeric = chars.next().map(|c| c as usize);
+            }
+            _ => {
+                while let Some(digit) = chars.peek().and_then(|c| c.to_digit(10)) {
+                    chars.next();
+                    numeric = Some(numeric.unwrap_or(0) * 10 + digit as usize);
+                }
+            }
+        }
+
+        let Some(directive) = chars.next() else {
+            return Err(format_error(heap, "truncated format directive"));
+        };
+        match directive {
+            '~' => out.text.push('~'),
+            'n' => (0..numeric.unwrap_or(1)).for_each(|_| out.newline()),
+            't' => {
+                let fill = numeric
+                    .and_then(|code| char::from_u32(code as u32))
+                    .unwrap_or(' ');
+                out.fill_points.push((out.text.len(), fill));
+            }
+            '|' => {
+                let target = numeric.unwrap_or_else(|| out.column());
+                out.column_stop(target);
+            }
+            '+' => {
+                let target = out.last_stop + numeric.unwrap_or(8);
+                out.column_stop(target);
+            }
+            'w' | 'p' | 'q' | 'a' | 'd' | 'f' | 'e' | 's' | 'c' | 'i' => {
+                let Some(arg) = args.next() else {
+                    return Err(format_error(heap, "not enough arguments"));
+                };
+                let arg = heap.deref_addr(arg);
+                match directive {
+                    'w' => out.text += &term_text(heap, arg, predicate_table, false),
+                    'p' | 'q' => out.text += &term_text(heap, arg, predicate_table, true),
+                    'a' => match heap[arg].0 {
+                        Tag::Con | Tag::Stri | Tag::Int | Tag::BigInt | Tag::Rat | Tag::Flt | Tag::ELis => {
+                            out.text += &text_of(heap, arg).unwrap_or_default()
+                        }
+                        _ => return Err(must_be_error(heap, "atomic", arg)),
+                    },
+                    'd' => {
+                        let value = match Number::try_from(heap[arg]) {
+                            Ok(Number::Int(value)) => BigInt::from(value),
+                            Ok(Number::Big(value)) => value,
+                            _ => return Err(must_be_error(heap, "integer", arg)),
+                        };
+                        out.text += &integer_text(&value, numeric.unwrap_or(0));
+                    }
+                    'f' | 'e' => {
+                        let value = match evaluate(heap, predicate_table, arg) {
+                            Ok(n) => n.float(),
+                            Err(error) => return Err(error),
+                        };
+                        let digits = numeric.unwrap_or(6);
+                        out.text += &if directive == 'f' {
+                            format!("{value:.digits$}")
+                        } else {
+                            exponent_text(value, digits)
+                        };
+                    }
+                    's' => match text_of(heap, arg).filter(|_| heap[arg].0 != Tag::Con) {
+                        Some(text) => out.text += &text,
+                        None => return Err(must_be_error(heap, "text", arg)),
+                    },
+                    'c' => match heap[arg] {
+                        (Tag::Int, code) if char::from_u32(code as u32).is_some() => {
+                            let c = char::from_u32(code as u32).unwrap();
+                            out.text
+                                .extend(std::iter::repeat_n(c, numeric.unwrap_or(1)));
+                        }
+                        _ => return Err(must_be_error(heap, "character_code", arg)),
+                    },
+                    _ => {} // 'i' skips its argument
+                }
+            }
+            other => {
+                return Err(format_error(heap, &format!("unknown directive ~{other}")));
+            }
+        }
+    }
+
+    if args.next().is_some() {
+        return Err(format_error(heap, "too many arguments"));
+    }
+    Ok(out.text)
+}
+
+/// An integer for `~Nd`: with `decimals` above zero, a decimal point is
+/// inserted that many digits from the right.
+fn integer_text(value: &BigInt, decimals: usize) -> String {
+    if decimals == 0 {
+        return value.to_string();
+    }
+    let digits = format!("{:0>width$}", value.magnitude(), width = decimals + 1);
+    let (whole, fraction) = digits.split_at(digits.len() - decimals);
+    let sign = if value.is_negative() { "-" } else { "" };
+    format!("{sign}{whole}.{fraction}")
+}
+
+/// A float for `~Ne`, in C's `%e` style such as `1.500000e+02`.
+fn exponent_text(value: fsize, digits: usize) -> String {
+    let text = format!("{value:.digits$e}");
+    match text.split_once('e') {
+        Some((mantissa, exponent)) => {
+            let exponent: i32 = exponent.parse().unwrap_or(0);
+            let sign = if exponent < 0 { '-' } else { '+' };
+            format!("{mantissa}e{sign}{:02}", exponent.abs())
+        }
+        None => text,
+    }
+}
+
+/// The format text and argument addresses of a `format(Format, Args)`
+/// call whose arguments start at `goal` argument `first`. A non-list Args
+/// is a single argument.
+fn format_call(
+    heap: &mut QueryHeap,
+    goal: usize,
+    first: usize,
+    arity: usize,
+) -> Result<(String, Vec<usize>), PredReturn> {
+    let format = goal_arg(heap, goal, first);
+    let Some(text) = text_of(heap, format) else {
+        return Err(must_be_error(heap, "text", format));
+    };
+    if arity == first + 1 {
+        return Ok((text, Vec::new()));
+    }
+    let args = goal_arg(heap, goal, first + 1);
+    let args = match heap[args].0 {
+        Tag::Lis | Tag::ELis => match read_list_addrs(heap, args) {
+            Some(args) => args,
+            None => return Err(must_be_error(heap, "list", args)),
+        },
+        _ => vec![args],
+    };
+    Ok((text, args))
+}
+
+// ── Predicates ────────────────────────────────────────────────────────────────
+
+/// Write the term argument of `goal` to the program output.
+fn write_term(heap: &QueryHeap, goal: usize, predicate_table: &PredicateTable, quoted: bool) {
+    let text = term_text(heap, goal_arg(heap, goal, 0), predicate_table, quoted);
+    predicate_table.output.write(&text);
+}
+
+/// `write(@Term)` — write Term with operators and without quotes.
+pub fn write(
+    heap: &mut QueryHeap,
+    _: &mut Hypothesis,
+    goal: usize,
+    predicate_table: &PredicateTable,
+    _: Config,
+) -> PredReturn {
+    write_term(heap, goal, predicate_table, false);
+    PredReturn::True
+}
+
+/// `writeq(@Term)` — write Term, quoting atoms and strings so that it can
+/// be read back. `print/1` writes the same way.
+pub fn writeq(
+    heap: &mut QueryHeap,
+    _: &mut Hypothesis,
+    goal: usize,
+    predicate_table: &PredicateTable,
+    _: Config,
+) -> PredReturn {
+    write_term(heap, goal, predicate_table, true);
+    PredReturn::True
+}
+
+/// `nl` — write a newline.
+pub fn nl(
+    _: &mut QueryHeap,
+    _: &mut Hypothesis,
+    _: usize,
+    predicate_table: &PredicateTable,
+    _: Config,
+) -> PredReturn {
+    predicate_table.output.write("\n");
+    PredReturn::True
+}
+
+/// `tab(+N)` — write N spaces, N being an arithmetic expression.
+pub fn tab(
+    heap: &mut QueryHeap,
+    _: &mut Hypothesis,
+    goal: usize,
+    predicate_table: &PredicateTable,
+    _: Config,
+) -> PredReturn {
+    let n = goal_arg(heap, goal, 0);
+    match evaluate(heap, predicate_table, n) {
+        Ok(Number::Int(n)) => {
+            predicate_table.output.write(&" ".repeat(n.max(0) as usize));
+            PredReturn::True
+        }
+        Ok(Number::Big(_)) => evaluation_error(heap, "int_overflow"),
+        Ok(_) => type_error(heap, "integer", n),
+        Err(error) => error,
+    }
+}
+
+/// `format(+Format)` and `format(+Format, :Args)` — write Args as directed
+/// by Format. Args is a list, or a single argument.
+///
+/// Format is an atom, string or code list in which `~` starts a directive,
+/// optionally preceded by a numeric argument `N`:
+///
+/// - `~w`, `~p`, `~q` — write the next argument as `write/1`, `print/1` or
+///   `writeq/1`.
+/// - `~a` — the next argument, which must be atomic.
+/// - `~d` — the next argument, which must be an integer. `~Nd` inserts a
+///   decimal point N digits from the right.
+/// - `~Nf`, `~Ne` — the next argument as a float with N digits (default 6)
+///   after the point, in fixed or exponential notation.
+/// - `~s` — the next argument, a string or code or character list.
+/// - `~c` — the character with the next argument as its code, N times.
+/// - `~i` — skip the next argument.
+/// - `~n` — N newlines. `~~` — a tilde.
+/// - `~t` — a fill point; ``~`ct`` fills with `c` instead of spaces.
+/// - `~N|` — a column stop at column N, `~N+` one N columns (default 8)
+///   past the last. The text since the last stop is padded out to the
+///   column at its fill points, or after it if it has none.
+///
+/// `~*c` takes the numeric argument from the arguments.
+pub fn format(
+    heap: &mut QueryHeap,
+    _: &mut Hypothesis,
+    goal: usize,
+    predicate_table: &PredicateTable,
+    _: Config,
+) -> PredReturn {
+    let arity = heap[resolve(heap, goal)].1 - 1;
+    let (format, args) = match format_call(heap, goal, 0, arity) {
+        Ok(call) => call,
+        Err(error) => return error,
+    };
+    let start_column = predicate_table.output.column();
+    match format_text(heap, predicate_table, &format, &args, start_column) {
+        Ok(text) => {
+            predicate_table.output.write(&text);
+            PredReturn::True
+        }
+        Err(error) => error,
+    }
+}
+
+/// `format(+Output, +Format, :Args)` — as `format/2`, writing to `Output`:
+/// `atom(A)`, `string(S)`, `codes(Cs)` or `chars(Cs)` to unify the text
+/// with, or the stream `user_output` or `user_error`.
+pub fn format_to(
+    heap: &mut QueryHeap,
+    _: &mut Hypothesis,
+    goal: usize,
+    predicate_table: &PredicateTable,
+    _: Config,
+) -> PredReturn {
+    let target = match output_target(heap, goal_arg(heap, goal, 0), true) {
+        Ok(target) => target,
+        Err(error) => return error,
+    };
+    let (format, args) = match format_call(heap, goal, 1, 3) {
+        Ok(call) => call,
+        Err(error) => return error,
+    };
+    let start_column = match target {
+        Target::Output => predicate_table.output.column(),
+        _ => 0,
+    };
+    let text = match format_text(heap, predicate_table, &format, &args, start_column) {
+        Ok(text) => text,
+        Err(error) => return error,
+    };
+    match target {
+        Target::Capture(kind, addr) => {
+            let value = capture_value(heap, kind, &text);
+            unify_result(heap, value, addr)
+        }
+        Target::Output => {
+            predicate_table.output.write(&text);
+            PredReturn::True
+        }
+        Target::Error => {
+            eprint!("{text}");
+            PredReturn::True
+        }
+    }
+}
+
+/// `with_output_to(+Output, :Goal)` — run Goal once, as `once/1`, and unify
+/// everything it wrote with `Output`: `atom(A)`, `string(S)`, `codes(Cs)`
+/// or `chars(Cs)`. Nothing is captured if Goal fails or throws.
+pub fn with_output_to(
+    heap: &mut QueryHeap,
+    hypothesis: &mut Hypothesis,
+    goal: usize,
+    predicate_table: &PredicateTable,
+    config: Config,
+) -> PredReturn {
+    let (kind, target) = match output_target(heap, goal_arg(heap, goal, 0), false) {
+        Ok(Target::Capture(kind, target)) => (kind, target),
+        Ok(_) => unreachable!("streams are not output targets of with_output_to/2"),
+        Err(error) => return error,
+    };
+    let inner_goal = resolve(heap, goal_arg(heap, goal, 1));
+
+    predicate_table.output.begin_capture();
+    let solution = collect_solutions_up_to(
+        heap,
+        hypothesis,
+        inner_goal,
+        &[inner_goal],
+        predicate_table,
+        config,
+        1,
+    );
+    let text = predicate_table.output.end_capture();
+
+    let instance = match solution {
+        Ok(solutions) => match solutions.first() {
+            Some(solution) => solution[0],
+            None => return PredReturn::False,
+        },
+        Err(ball) => return PredReturn::Throw(ball),
+    };
+    // Keep the bindings Goal made by unifying it with its solved copy
+    let value = capture_value(heap, kind, &text);
+    let solved = build_tuple_from_addrs(heap, &[instance, value]);
+    let target = build_tuple_from_addrs(heap, &[inner_goal, target]);
+    unify_result(heap, solved, target)
+}
+
+/// Built-in output predicates: `write/1`, `print/1`, `writeq/1`, `nl/0`,
+/// `tab/1`, `format/1,2,3` and `with_output_to/2`.
+pub static OUTPUT: PredicateModule = (
+    &[
+        ("write", 1, write),
+        ("print", 1, writeq),
+        ("writeq", 1, writeq),
+        ("nl", 0, nl),
+        ("tab", 1, tab),
+        ("format", 1, format),
+        ("format", 2, format),
+        ("format", 3, format_to),
+        ("with_output_to", 2, with_output_to),
+    ],
+    &[],
+);
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::{helpers::TestWrapper, DEFAULTS, MATHS, META_PREDICATES, SYNTAX},
+        OUTPUT,
+    };
+    use crate::program::output::OutputBuffer;
+
+    fn tw() -> (TestWrapper, OutputBuffer) {
+        let buffer = OutputBuffer::new();
+        let mut tw = TestWrapper::new(&[DEFAULTS, MATHS, META_PREDICATES, SYNTAX, OUTPUT]);
+        tw.app = tw.app.output(buffer.clone());
+        (tw, buffer)
+    }
+
+    /// Everything `query` writes, asserting that it succeeds.
+    fn output(query: &str) -> String {
+        let (tw, buffer) = tw();
+        tw.assert_true(query);
+        buffer.take()
+    }
+
+    // ── write/1, writeq/1, print/1 ────────────────────────────────────────
+
+    #[test]
+    fn write_uses_operators() {
+        assert_eq!(output("write(1+2*3)."), "1+2*3");
+        assert_eq!(output("write((1+2)*3)."), "(1+2)*3");
+        assert_eq!(output("write(1-(2-3))."), "1-(2-3)");
+        assert_eq!(output("write((1-2)-3)."), "1-2-3");
+        assert_eq!(output("write(1-(-1))."), "1- -1");
+        assert_eq!(output("write(-(1))."), "- 1");
+        assert_eq!(output("write(-(a))."), "-a");
+        assert_eq!(output("write(\\+a)."), "\\+a");
+        assert_eq!(output("write(f(a:-b))."), "f((a:-b))");
+        assert_eq!(output("write(a = b)."), "a=b");
+        assert_eq!(output("write(a mod b)."), "a mod b");
+    }
+
+    #[test]
+    fn write_terms() {
+        assert_eq!(output("write([a, 'B c', \"str\"])."), "[a,B c,str]");
+        assert!(output("write([a|T]).").starts_with("[a|_G"));
+        assert_eq!(output("write(f(x, {y}, 1.0, 2.5))."), "f(x,{y},1.0,2.5)");
+        assert_eq!(output("write([])."), "[]");
+    }
+
+    #[test]
+    fn writeq_quotes() {
+        assert_eq!(
+            output("writeq(['A', 'b c', abc, [], '+', \"s\"])."),
+            "['A','b c',abc,[],+,\"s\"]"
+        );
+        assert_eq!(output("writeq('a\\nb')."), "'a\\nb'");
+        assert_eq!(output("print(f('X'))."), "f('X')");
+    }
+
+    #[test]
+    fn write_uses_user_operators() {
+        assert_eq!(output("op(700, xfx, ===>), write(===>(a, b))."), "a===>b");
+        assert_eq!(
+            output("op(200, xfy, likes), write(likes(a, b))."),
+            "a likes b"
+        );
+    }
+
+    #[test]
+    fn nl_and_tab() {
+        assert_eq!(output("write(a), tab(2), write(b), nl."), "a  b\n");
+        assert_eq!(output("tab(1 + 1)."), "  ");
+        let (tw, _) = tw();
+        tw.assert_error("tab(a).", "type_error(evaluable,/(a,0))");
+    }
+
+    // ── format/1,2,3 ──────────────────────────────────────────────────────
+
+    #[test]
+    fn format_directives() {
+        assert_eq!(
+            output("format(\"~w ~q ~a~n\", [f('A'), f('A'), abc])."),
+            "f(A) f('A') abc\n"
+        );
+        assert_eq!(output("format('~d ~2d ~d', [42, 314, -7])."), "42 3.14 -7");
+        assert_eq!(
+            output("format(\"~s and ~s\", [[104, 105], \"there\"])."),
+            "hi and there"
+        );
+        assert_eq!(
+            output("format(\"~2f ~e\", [2, 150.0])."),
+            "2.00 1.500000e+02"
+        );
+        assert_eq!(
+            output("format(\"~c~3c~~~i~w\", [104, 105, skipped, x])."),
+            "hiii~x"
+        );
+        assert_eq!(output("format(\"~w\", hello)."), "hello");
+        assert_eq!(output("format(\"a~2nb\")."), "a\n\nb");
+    }
+
+    #[test]
+    fn format_columns() {
+        assert_eq!(output("format(\"~w~t~8|~w\", [ab, cd])."), "ab      cd");
+        assert_eq!(output("format(\"~t~w~8|\", [ab])."), "      ab");
+        assert_eq!(output("format(\"~t~w~t~8|\", [ab])."), "   ab   ");
+        assert_eq!(output("format(\"~t~d~4+~t~d~4+\", [1, 22])."), "   1  22");
+        assert_eq!(output("format(\"~`-t~6|\")."), "------");
+        assert_eq!(output("write(abc), format(\"~t~w~6|\", [x])."), "abc  x");
+    }
+
+    #[test]
+    fn format_errors() {
+        let (tw, _) = tw();
+        tw.assert_error(
+            "format(\"~w ~w\", [a]).",
+            "format(\"not enough arguments\")",
+        );
+        tw.assert_error("format(\"~w\", [a, b]).", "format(\"too many arguments\")");
+        tw.assert_error("format(\"~y\", [a]).", "format(\"unknown directive ~y\")");
+        tw.assert_error("format(\"~d\", [a]).", "type_error(integer,a)");
+        tw.assert_error("format(\"~d\", [X]).", "instantiation_error");
+        tw.assert_error("format(F, []).", "instantiation_error");
+    }
+
+    #[test]
+    fn format_to_output() {
+        let (tw, buffer) = tw();
+        tw.assert_binding("format(atom(A), \"~w-~w\", [a, b]).", ("A", "a-b"));
+        tw.assert_binding("format(string(S), \"~a\", [x]).", ("S", "\"x\""));
+        tw.assert_binding("format(codes(C), \"ab\", []).", ("C", "[97,98]"));
+        tw.assert_binding("format(chars(C), \"ab\", []).", ("C", "[a,b]"));
+        tw.assert_true("format(string(\"x\"), \"x\", []).");
+        tw.assert_false("format(string(\"y\"), \"x\", []).");
+        tw.assert_true("format(atom(ab), \"~w\", [ab]).");
+        tw.assert_true("format(codes([97]), \"a\", []).");
+        tw.assert_true("format(user_output, \"out\", []).");
+        tw.assert_error(
+            "format(file(x), \"\", []).",
+            "domain_error(output_sink,file(x))",
+        );
+        assert_eq!(buffer.take(), "out");
+    }
+
+    // ── with_output_to/2 ──────────────────────────────────────────────────
+
+    #[test]
+    fn with_output_to() {
+        let (tw, buffer) = tw();
+        tw.assert_binding(
+            "with_output_to(atom(A), (write(a), write(1))).",
+            ("A", "a1"),
+        );
+        tw.assert_binding(
+            "with_output_to(string(S), (X = 2, write(X))), Y is X + 1.",
+            ("Y", "3"),
+        );
+        tw.assert_binding(
+            "with_output_to(atom(A), (write(a), with_output_to(atom(B), write(b)), write(B))).",
+            ("A", "ab"),
+        );
+        tw.assert_false("with_output_to(atom(A), (write(a), fail)).");
+        tw.assert_true("with_output_to(string(\"hi\"), write(hi)).");
+        tw.assert_true("with_output_to(chars([h, i]), write(hi)).");
+        tw.assert_false("with_output_to(string(\"ho\"), write(hi)).");
+        tw.assert_error("with_output_to(atom(A), throw(error(x, y))).", "x");
+        tw.assert_error(
+            "with_output_to(user_output, true).",
+            "domain_error(output_sink,user_output)",
+        );
+        // One solution only, as once/1
+        assert_eq!(
+            tw.all_bindings("with_output_to(atom(A), (X = 1 ; X = 2)).", "X"),
+            ["1"]
+        );
+        assert_eq!(buffer.take(), "");
+    }
+}
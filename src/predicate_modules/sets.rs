@@ -2,12 +2,17 @@
 //!
 //! Sets in Prolog² are unordered, duplicate-free collections written with
 //! curly braces: `{a, b, c}`.  On the heap they are stored as
-//! `(Tag::Set, length)` followed by `length` element cells.
+//! `(Tag::Set, length)` followed by `length` element cells. Sets built by
+//! these predicates hold their elements in the standard order of terms, the
+//! same order `sort/2` and `compare/3` use.
 //!
 //! Set unification is equality-only (no variable binding) because the lack of
 //! element ordering makes correct binding impossible when two or more variables
 //! are present.  Predicates like `member/2` that need to enumerate choices use
 //! `PredReturn::Choices` to create backtrackable alternatives.
+//!
+//! An input set argument that is unbound or not a set raises an
+//! instantiation or `type_error(set, Culprit)` error.
 
 use super::{helpers::*, PredReturn, PredicateModule};
 use crate::{
@@ -106,7 +111,7 @@ pub fn set_member_pred(
     let elem_addr = goal_arg(heap, goal, 0);
     let set_arg = goal_arg(heap, goal, 1);
     let Some((base, len)) = read_set(heap, set_arg) else {
-        return false.into();
+        return must_be_error(heap, "set", set_arg);
     };
 
     if is_var(heap, elem_addr) {
@@ -137,7 +142,8 @@ pub fn set_member_pred(
 /// that subset. On backtracking the engine tries each alternative in turn.
 ///
 /// Fails immediately if `Size` is negative, if `Size > |Set|`, or if the first argument
-/// is neither a variable nor a set.
+/// is neither a variable nor a set. Throws if `Set` is not a set or `Size` is not an
+/// integer.
 pub fn subset_sized(
     heap: &mut QueryHeap,
     _hypothesis: &mut Hypothesis,
@@ -145,13 +151,14 @@ pub fn subset_sized(
     _predicate_table: &PredicateTable,
     _config: Config,
 ) -> PredReturn {
-    let k = match Number::try_from(heap[goal_arg(heap, goal, 2)]) {
+    let size_arg = goal_arg(heap, goal, 2);
+    let k = match Number::try_from(heap[size_arg]) {
         Ok(Number::Int(value)) => value as usize,
-        _ => return false.into(),
+        _ => return must_be_error(heap, "integer", size_arg),
     };
 
     let Some(set) = read_set(heap, goal_arg(heap, goal, 1)) else {
-        return false.into();
+        return must_be_error(heap, "set", goal_arg(heap, goal, 1));
     };
     if k > set.1 {
         return false.into();
@@ -202,10 +209,10 @@ pub fn set_union_pred(
     _config: Config,
 ) -> PredReturn {
     let Some((base1, len1)) = read_set(heap, goal_arg(heap, goal, 0)) else {
-        return false.into();
+        return must_be_error(heap, "set", goal_arg(heap, goal, 0));
     };
     let Some((base2, len2)) = read_set(heap, goal_arg(heap, goal, 1)) else {
-        return false.into();
+        return must_be_error(heap, "set", goal_arg(heap, goal, 1));
     };
     let result_addr = goal_arg(heap, goal, 2);
 
@@ -239,10 +246,10 @@ pub fn set_intersection_pred(
     _config: Config,
 ) -> PredReturn {
     let Some((base1, len1)) = read_set(heap, goal_arg(heap, goal, 0)) else {
-        return false.into();
+        return must_be_error(heap, "set", goal_arg(heap, goal, 0));
     };
     let Some((base2, len2)) = read_set(heap, goal_arg(heap, goal, 1)) else {
-        return false.into();
+        return must_be_error(heap, "set", goal_arg(heap, goal, 1));
     };
     let result_addr = goal_arg(heap, goal, 2);
 
@@ -269,10 +276,10 @@ pub fn set_difference_pred(
     _config: Config,
 ) -> PredReturn {
     let Some((base1, len1)) = read_set(heap, goal_arg(heap, goal, 0)) else {
-        return false.into();
+        return must_be_error(heap, "set", goal_arg(heap, goal, 0));
     };
     let Some((base2, len2)) = read_set(heap, goal_arg(heap, goal, 1)) else {
-        return false.into();
+        return must_be_error(heap, "set", goal_arg(heap, goal, 1));
     };
     let result_addr = goal_arg(heap, goal, 2);
 
@@ -299,10 +306,10 @@ pub fn set_symdiff_pred(
     _config: Config,
 ) -> PredReturn {
     let Some((base1, len1)) = read_set(heap, goal_arg(heap, goal, 0)) else {
-        return false.into();
+        return must_be_error(heap, "set", goal_arg(heap, goal, 0));
     };
     let Some((base2, len2)) = read_set(heap, goal_arg(heap, goal, 1)) else {
-        return false.into();
+        return must_be_error(heap, "set", goal_arg(heap, goal, 1));
     };
     let result_addr = goal_arg(heap, goal, 2);
 
@@ -334,7 +341,7 @@ pub fn set_size_pred(
     _config: Config,
 ) -> PredReturn {
     let Some((_base, len)) = read_set(heap, goal_arg(heap, goal, 0)) else {
-        return false.into();
+        return must_be_error(heap, "set", goal_arg(heap, goal, 0));
     };
     let n_addr = goal_arg(heap, goal, 1);
 
@@ -344,7 +351,7 @@ pub fn set_size_pred(
     } else {
         match heap[n_addr] {
             (Tag::Int, v) => (v == len).into(),
-            _ => PredReturn::False,
+            _ => type_error(heap, "integer", n_addr),
         }
     }
 }
@@ -358,7 +365,7 @@ pub fn set_add_pred(
     _config: Config,
 ) -> PredReturn {
     let Some((base, len)) = read_set(heap, goal_arg(heap, goal, 0)) else {
-        return false.into();
+        return must_be_error(heap, "set", goal_arg(heap, goal, 0));
     };
     let elem_addr = goal_arg(heap, goal, 1);
     let result_addr = goal_arg(heap, goal, 2);
@@ -398,7 +405,7 @@ pub fn set_del_pred(
     _config: Config,
 ) -> PredReturn {
     let Some((base, len)) = read_set(heap, goal_arg(heap, goal, 0)) else {
-        return false.into();
+        return must_be_error(heap, "set", goal_arg(heap, goal, 0));
     };
     let elem_addr = goal_arg(heap, goal, 1);
     let result_addr = goal_arg(heap, goal, 2);
@@ -425,7 +432,7 @@ pub fn set_to_list_pred(
     _config: Config,
 ) -> PredReturn {
     let Some((base, len)) = read_set(heap, goal_arg(heap, goal, 0)) else {
-        return false.into();
+        return must_be_error(heap, "set", goal_arg(heap, goal, 0));
     };
     let list_addr_arg = goal_arg(heap, goal, 1);
 
@@ -450,8 +457,9 @@ pub fn list_to_set_pred(
     let list_arg = goal_arg(heap, goal, 0);
     let set_arg = goal_arg(heap, goal, 1);
 
-    let Some(addrs) = read_list_addrs(heap, list_arg) else {
-        return false.into();
+    let addrs = match must_be_list(heap, list_arg) {
+        Ok(addrs) => addrs,
+        Err(error) => return error,
     };
 
     let new_set = build_set_from_addrs(heap, &addrs);
@@ -522,6 +530,17 @@ mod tests {
         assert!(!succeeds("set([a, b])."));
     }
 
+    // ── errors ──
+
+    #[test]
+    fn set_errors() {
+        let tw = test_wrapper();
+        tw.assert_error("set_member(a, S).", "instantiation_error");
+        tw.assert_error("set_union({a}, [b], U).", "type_error(set,[b])");
+        tw.assert_error("subset(S, {a, b}, two).", "type_error(integer,two)");
+        tw.assert_error("list_to_set(hello, S).", "type_error(list,hello)");
+    }
+
     // ── set_member ──
 
     #[test]
@@ -683,6 +702,14 @@ mod tests {
         assert!(succeeds("list_to_set([a, a, a], X), set_size(X, 1)."));
     }
 
+    #[test]
+    fn built_sets_are_in_standard_order() {
+        let results = query_result("list_to_set([c, 2, b(x), \"s\", a, 2], X).");
+        assert_eq!(results, ["{2,a,c,\"s\",b(x)}"]);
+        let results = query_result("set_union({c, a}, {b, a}, X).");
+        assert_eq!(results, ["{a,b,c}"]);
+    }
+
     // ── temp debug ──
     #[test]
     fn diag_singleton_count() {
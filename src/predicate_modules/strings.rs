@@ -9,10 +9,20 @@
 //! input arguments, always producing the "natural" output type for the
 //! predicate (e.g. `atom_concat` always produces a constant, `string_concat`
 //! always produces a string).
+//!
+//! An input argument that is unbound raises an instantiation error, and one
+//! of the wrong type a `type_error`. Text inputs that accept either a
+//! constant or a string report the expected type as `atomic`.
 
 use std::sync::Arc;
 
-use super::{helpers::*, PredReturn, PredicateModule};
+use super::{
+    helpers::*,
+    maths::Number,
+    output::{capture_value, output_target, term_text, text_of, Target},
+    syntax::parse_term_text,
+    PredReturn, PredicateModule,
+};
 use crate::{
     Config,
     heap::{
@@ -20,6 +30,7 @@ use crate::{
         query_heap::QueryHeap,
         symbol_db::SymbolDB,
     },
+    parser::operators::SYMBOL_CHARS,
     program::{hypothesis::Hypothesis, predicate_table::PredicateTable},
 };
 
@@ -34,6 +45,36 @@ fn read_text(heap: &QueryHeap, addr: usize) -> Option<Arc<str>> {
     }
 }
 
+/// Read the text of a required `Con` or `Stri` input argument, or produce the
+/// error for one that is unbound or of another type.
+fn must_be_text(heap: &mut QueryHeap, addr: usize) -> Result<Arc<str>, PredReturn> {
+    read_text(heap, addr).ok_or_else(|| must_be_error(heap, "atomic", addr))
+}
+
+/// Read the text of an atom, string or number, or `None` for anything else.
+fn atomic_text(heap: &QueryHeap, addr: usize) -> Option<String> {
+    match heap[addr] {
+        (Tag::Con | Tag::Stri | Tag::Int | Tag::BigInt | Tag::Rat | Tag::Flt, _) => text_of(heap, addr),
+        _ => None,
+    }
+}
+
+/// Read the text of a required input argument that may be given as any
+/// text: an atom, string, number, or list of codes or characters.
+fn must_be_any_text(heap: &mut QueryHeap, addr: usize) -> Result<String, PredReturn> {
+    text_of(heap, addr).ok_or_else(|| must_be_error(heap, "text", addr))
+}
+
+/// The error for a text conversion whose arguments are both unbound, or
+/// failure otherwise.
+fn unbound_error(heap: &mut QueryHeap, a: usize, b: usize) -> PredReturn {
+    if is_unbound(heap, a) && is_unbound(heap, b) {
+        instantiation_error(heap)
+    } else {
+        PredReturn::False
+    }
+}
+
 /// Push a fresh constant (`Con`) cell and return its heap address.
 fn push_const(heap: &mut QueryHeap, text: &str) -> usize {
     let id = SymbolDB::set_const(text);
@@ -42,7 +83,7 @@ fn push_const(heap: &mut QueryHeap, text: &str) -> usize {
 
 /// Push a fresh string (`Stri`) cell and return its heap address.
 fn push_string(heap: &mut QueryHeap, text: Arc<str>) -> usize {
-    let idx = SymbolDB::set_string(text.to_string());
+    let idx = SymbolDB::set_string(text.to_string(), heap.get_id());
     heap.heap_push((Tag::Stri, idx))
 }
 
@@ -58,24 +99,25 @@ fn read_list_cells(heap: &QueryHeap, addr: usize) -> Option<Vec<Cell>> {
     read_list_addrs(heap, addr).map(|addrs| addrs.iter().map(|&a| heap[a]).collect())
 }
 
-/// Parse a string as `Int` (tried first) or `Flt`, push the result, and
-/// return its address, or `None` if the text is not a valid number.
+/// Parse a string as a number, push the result, and return its address, or
+/// `None` if the text is not a valid number.
 fn parse_and_push_number(heap: &mut QueryHeap, s: &str) -> Option<usize> {
-    if let Ok(n) = s.parse::<isize>() {
-        Some(push_int(heap, n))
-    } else if let Ok(f) = s.parse::<f64>() {
-        Some(heap.heap_push((Tag::Flt, f.to_bits() as usize)))
-    } else {
-        None
-    }
+    Number::parse(s).map(|n| heap.heap_push(n.to_cell(heap.get_id())))
 }
 
 /// Format a numeric heap cell as a `String`, or `None` for non-numeric cells.
 fn number_to_string(heap: &QueryHeap, addr: usize) -> Option<String> {
-    match heap[addr] {
-        (Tag::Int, v) => Some((v as isize).to_string()),
-        (Tag::Flt, v) => Some(f64::from_bits(v as u64).to_string()),
-        _ => None,
+    Number::try_from(heap[addr]).ok().map(|n| n.to_string())
+}
+
+/// Whether `text` reads as the same number as the numeric cell at `addr`:
+/// equal, and both floats or both exact.
+fn reads_as_number(heap: &QueryHeap, text: &str, addr: usize) -> bool {
+    match (Number::parse(text), Number::try_from(heap[addr])) {
+        (Some(Number::Flt(f1)), Ok(Number::Flt(f2))) => f1.to_bits() == f2.to_bits(),
+        (Some(Number::Flt(_)), _) | (_, Ok(Number::Flt(_))) => false,
+        (Some(n1), Ok(n2)) => n1 == n2,
+        _ => false,
     }
 }
 
@@ -95,15 +137,15 @@ pub fn atom_string_pred(
     match (heap[const_a], heap[str_a]) {
         ((Tag::Con, id), (Tag::Stri, idx)) =>
             (SymbolDB::get_const(id) == SymbolDB::get_string(idx)).into(),
-        ((Tag::Con, id), (Tag::Ref, r)) => {
+        ((Tag::Con, id), (Tag::Ref | Tag::AVar, _)) => {
             let result = push_string(heap, SymbolDB::get_const(id));
-            PredReturn::Success(vec![(r, result)], vec![])
+            PredReturn::Success(bind_output(heap, str_a, result), vec![])
         }
-        ((Tag::Ref, r), (Tag::Stri, idx)) => {
+        ((Tag::Ref | Tag::AVar, _), (Tag::Stri, idx)) => {
             let result = push_const(heap, &SymbolDB::get_string(idx));
-            PredReturn::Success(vec![(r, result)], vec![])
+            PredReturn::Success(bind_output(heap, const_a, result), vec![])
         }
-        _ => PredReturn::False,
+        _ => unbound_error(heap, const_a, str_a),
     }
 }
 
@@ -119,28 +161,25 @@ pub fn atom_number_pred(
     let const_a = goal_arg(heap, goal, 0);
     let num_a   = goal_arg(heap, goal, 1);
     match (heap[const_a], heap[num_a]) {
-        ((Tag::Con, id), (Tag::Ref, r)) => {
+        ((Tag::Con, id), (Tag::Ref | Tag::AVar, _)) => {
             let text = SymbolDB::get_const(id);
             match parse_and_push_number(heap, &text) {
-                Some(result) => PredReturn::Success(vec![(r, result)], vec![]),
+                Some(result) => PredReturn::Success(bind_output(heap, num_a, result), vec![]),
                 None => PredReturn::False,
             }
         }
-        ((Tag::Ref, r), (Tag::Int, _) | (Tag::Flt, _)) => {
+        ((Tag::Ref | Tag::AVar, _), (Tag::Int | Tag::BigInt | Tag::Rat | Tag::Flt, _)) => {
             let text = number_to_string(heap, num_a).unwrap();
             let result = push_const(heap, &text);
-            PredReturn::Success(vec![(r, result)], vec![])
-        }
-        ((Tag::Con, id), (Tag::Int, v)) => {
-            let text = SymbolDB::get_const(id);
-            text.parse::<isize>().map_or(PredReturn::False, |n| (n == v as isize).into())
+            PredReturn::Success(bind_output(heap, const_a, result), vec![])
         }
-        ((Tag::Con, id), (Tag::Flt, v)) => {
-            let text = SymbolDB::get_const(id);
-            text.parse::<f64>()
-                .map_or(PredReturn::False, |f| (f.to_bits() == v as u64).into())
+        ((Tag::Con, id), (Tag::Int | Tag::BigInt | Tag::Rat | Tag::Flt, _)) => {
+            reads_as_number(heap, &SymbolDB::get_const(id), num_a).into()
         }
-        _ => PredReturn::False,
+        ((Tag::Con | Tag::Ref | Tag::AVar, _), _) if !is_unbound(heap, num_a) => type_error(heap, "number", num_a),
+        ((Tag::Con, _), _) => PredReturn::False,
+        (_, _) if !is_unbound(heap, const_a) => type_error(heap, "atom", const_a),
+        _ => instantiation_error(heap),
     }
 }
 
@@ -156,43 +195,123 @@ pub fn number_string_pred(
     let num_a = goal_arg(heap, goal, 0);
     let str_a = goal_arg(heap, goal, 1);
     match (heap[num_a], heap[str_a]) {
-        ((Tag::Int, _) | (Tag::Flt, _), (Tag::Ref, r)) => {
+        ((Tag::Int | Tag::BigInt | Tag::Rat | Tag::Flt, _), (Tag::Ref | Tag::AVar, _)) => {
             let text = number_to_string(heap, num_a).unwrap();
             let result = push_string(heap, text.into());
-            PredReturn::Success(vec![(r, result)], vec![])
+            PredReturn::Success(bind_output(heap, str_a, result), vec![])
         }
-        ((Tag::Ref, r), (Tag::Stri, idx)) => {
+        ((Tag::Ref | Tag::AVar, _), (Tag::Stri, idx)) => {
             let text = SymbolDB::get_string(idx);
             match parse_and_push_number(heap, &text) {
-                Some(result) => PredReturn::Success(vec![(r, result)], vec![]),
+                Some(result) => PredReturn::Success(bind_output(heap, num_a, result), vec![]),
                 None => PredReturn::False,
             }
         }
-        ((Tag::Int, v), (Tag::Stri, idx)) => {
-            let text = SymbolDB::get_string(idx);
-            text.parse::<isize>().map_or(PredReturn::False, |n| (n == v as isize).into())
+        ((Tag::Int | Tag::BigInt | Tag::Rat | Tag::Flt, _), (Tag::Stri, idx)) => {
+            reads_as_number(heap, &SymbolDB::get_string(idx), num_a).into()
         }
-        ((Tag::Flt, v), (Tag::Stri, idx)) => {
-            let text = SymbolDB::get_string(idx);
-            text.parse::<f64>()
-                .map_or(PredReturn::False, |f| (f.to_bits() == v as u64).into())
-        }
-        _ => PredReturn::False,
+        _ => unbound_error(heap, num_a, str_a),
+    }
+}
+
+/// Shared logic for `number_codes` / `number_chars`: with a complete list,
+/// read it as a number; otherwise write the number as a list.
+fn number_list_impl(heap: &mut QueryHeap, goal: usize, kind: &str) -> PredReturn {
+    let num_a  = goal_arg(heap, goal, 0);
+    let list_a = goal_arg(heap, goal, 1);
+    let is_number = matches!(heap[num_a], (Tag::Int | Tag::BigInt | Tag::Rat | Tag::Flt, _));
+    if !is_number && !is_unbound(heap, num_a) {
+        return type_error(heap, "number", num_a);
+    }
+    let (elements, tail) = read_list_with_tail(heap, list_a);
+    let complete = !is_unbound(heap, tail) && !elements.iter().any(|&e| is_unbound(heap, e));
+    if complete {
+        // Leading layout is allowed, as in ISO
+        let text = text_of(heap, list_a).unwrap_or_default();
+        let text = text.trim_start();
+        let digits = text.strip_prefix('-').unwrap_or(text);
+        let number = match digits.starts_with(|c: char| c.is_ascii_digit()) {
+            true => parse_and_push_number(heap, text),
+            false => None,
+        };
+        return match number {
+            Some(number) => unify_result(heap, number, num_a),
+            None => syntax_error(heap, "illegal_number"),
+        };
+    }
+    if !is_number {
+        return instantiation_error(heap);
     }
+    let text = text_of(heap, num_a).unwrap();
+    let list = capture_value(heap, kind, &text);
+    unify_result(heap, list, list_a)
 }
 
-/// `term_string/2`: convert any ground term to its Prolog text representation.
+/// `number_codes/2`: convert between a number and a list of character codes.
 ///
-/// Only `term_string(+Term, -String)` is supported.
-pub fn term_string_pred(
+/// Modes:
+/// - `number_codes(+Number, ?Codes)` — write the number as codes.
+/// - `number_codes(?Number, +Codes)` — read the codes as a number. Throws
+///   `syntax_error(illegal_number)` if they are not one.
+pub fn number_codes_pred(
     heap: &mut QueryHeap, _: &mut Hypothesis, goal: usize, _: &PredicateTable, _: Config,
+) -> PredReturn {
+    number_list_impl(heap, goal, "codes")
+}
+
+/// `number_chars/2`: as `number_codes/2`, but with a list of
+/// single-character constants.
+pub fn number_chars_pred(
+    heap: &mut QueryHeap, _: &mut Hypothesis, goal: usize, _: &PredicateTable, _: Config,
+) -> PredReturn {
+    number_list_impl(heap, goal, "chars")
+}
+
+/// Shared logic for `term_to_atom` / `term_string`: with text bound, read
+/// it as a term and unify it with the term argument; otherwise write the
+/// term as `writeq/1` would and make the text with `push`.
+fn term_text_impl(
+    heap: &mut QueryHeap,
+    goal: usize,
+    predicate_table: &PredicateTable,
+    push: fn(&mut QueryHeap, &str) -> usize,
 ) -> PredReturn {
     let term_a = goal_arg(heap, goal, 0);
-    let str_a  = goal_arg(heap, goal, 1);
-    let (Tag::Ref, r) = heap[str_a] else { return false.into(); };
-    let text = heap.term_string(term_a);
-    let result = push_string(heap, text.into());
-    PredReturn::Success(vec![(r, result)], vec![])
+    let text_a = goal_arg(heap, goal, 1);
+    if is_unbound(heap, text_a) {
+        let text = term_text(heap, term_a, predicate_table, true);
+        let result = push(heap, &text);
+        return unify_result(heap, result, text_a);
+    }
+    let text = match must_be_text(heap, text_a) {
+        Ok(text) => text,
+        Err(error) => return error,
+    };
+    match parse_term_text(heap, predicate_table, &text) {
+        Ok(Some(read)) => unify_result(heap, read.addr, term_a),
+        Ok(None) => syntax_error(heap, "end of file"),
+        Err(error) => syntax_error(heap, &error.to_string()),
+    }
+}
+
+/// `term_to_atom/2`: convert between a term and an atom of its text.
+///
+/// Modes:
+/// - `term_to_atom(+Term, -Atom)` — write Term quoted, as `writeq/1` would.
+/// - `term_to_atom(?Term, +Atom)` — read Atom as a term and unify it with
+///   Term. Throws `syntax_error(Message)` if Atom is not a term.
+pub fn term_to_atom_pred(
+    heap: &mut QueryHeap, _: &mut Hypothesis, goal: usize, predicate_table: &PredicateTable, _: Config,
+) -> PredReturn {
+    term_text_impl(heap, goal, predicate_table, push_const)
+}
+
+/// `term_string/2`: as `term_to_atom/2`, but converting to and from a
+/// string.
+pub fn term_string_pred(
+    heap: &mut QueryHeap, _: &mut Hypothesis, goal: usize, predicate_table: &PredicateTable, _: Config,
+) -> PredReturn {
+    term_text_impl(heap, goal, predicate_table, |h, s| push_string(h, Arc::from(s)))
 }
 
 /// `char_code/2`: bidirectional conversion between a single-character constant
@@ -219,14 +338,17 @@ pub fn char_code_pred(
     }
 
     match (heap[char_a], heap[code_a]) {
-        ((Tag::Con, _), (Tag::Ref, r)) => match single_char(heap, char_a) {
-            Some(c) => PredReturn::Success(vec![(r, push_int(heap, c as isize))], vec![]),
+        ((Tag::Con, _), (Tag::Ref | Tag::AVar, _)) => match single_char(heap, char_a) {
+            Some(c) => {
+                let result = push_int(heap, c as isize);
+                PredReturn::Success(bind_output(heap, code_a, result), vec![])
+            }
             None => PredReturn::False,
         },
-        ((Tag::Ref, r), (Tag::Int, v)) => match char::from_u32(v as u32) {
+        ((Tag::Ref | Tag::AVar, _), (Tag::Int, v)) => match char::from_u32(v as u32) {
             Some(c) => {
                 let result = push_const(heap, &c.to_string());
-                PredReturn::Success(vec![(r, result)], vec![])
+                PredReturn::Success(bind_output(heap, char_a, result), vec![])
             }
             None => PredReturn::False,
         },
@@ -234,7 +356,11 @@ pub fn char_code_pred(
             Some(c) => (c as usize == v).into(),
             None => PredReturn::False,
         },
-        _ => PredReturn::False,
+        ((Tag::Con | Tag::Ref | Tag::AVar, _), _) if !is_unbound(heap, code_a) => {
+            type_error(heap, "integer", code_a)
+        }
+        (_, _) if !is_unbound(heap, char_a) => type_error(heap, "character", char_a),
+        _ => instantiation_error(heap),
     }
 }
 
@@ -246,12 +372,15 @@ pub fn atom_length_pred(
 ) -> PredReturn {
     let const_a = goal_arg(heap, goal, 0);
     let len_a   = goal_arg(heap, goal, 1);
-    let (Tag::Con, id) = heap[const_a] else { return false.into(); };
+    let (Tag::Con, id) = heap[const_a] else { return must_be_error(heap, "atom", const_a); };
     let len = SymbolDB::get_const(id).chars().count();
     match heap[len_a] {
-        (Tag::Ref, r) => PredReturn::Success(vec![(r, push_int(heap, len as isize))], vec![]),
+        _ if is_unbound(heap, len_a) => {
+            let result = push_int(heap, len as isize);
+            PredReturn::Success(bind_output(heap, len_a, result), vec![])
+        }
         (Tag::Int, v) => (len == v).into(),
-        _ => PredReturn::False,
+        _ => type_error(heap, "integer", len_a),
     }
 }
 
@@ -261,18 +390,46 @@ pub fn string_length_pred(
 ) -> PredReturn {
     let str_a = goal_arg(heap, goal, 0);
     let len_a = goal_arg(heap, goal, 1);
-    let (Tag::Stri, idx) = heap[str_a] else { return false.into(); };
+    let (Tag::Stri, idx) = heap[str_a] else { return must_be_error(heap, "string", str_a); };
     let len = SymbolDB::get_string(idx).chars().count();
     match heap[len_a] {
-        (Tag::Ref, r) => PredReturn::Success(vec![(r, push_int(heap, len as isize))], vec![]),
+        _ if is_unbound(heap, len_a) => {
+            let result = push_int(heap, len as isize);
+            PredReturn::Success(bind_output(heap, len_a, result), vec![])
+        }
         (Tag::Int, v) => (len == v).into(),
-        _ => PredReturn::False,
+        _ => type_error(heap, "integer", len_a),
+    }
+}
+
+/// `string_code(+Index, +String, -Code)`: Code is the code of the
+/// character at 1-based Index in String. Fails if Index is out of range.
+pub fn string_code_pred(
+    heap: &mut QueryHeap, _: &mut Hypothesis, goal: usize, _: &PredicateTable, _: Config,
+) -> PredReturn {
+    let index_a = goal_arg(heap, goal, 0);
+    let str_a   = goal_arg(heap, goal, 1);
+    let code_a  = goal_arg(heap, goal, 2);
+    let (Tag::Int, index) = heap[index_a] else { return must_be_error(heap, "integer", index_a); };
+    let text = match must_be_any_text(heap, str_a) {
+        Ok(text) => text,
+        Err(error) => return error,
+    };
+    let index = index as isize;
+    match text.chars().nth((index - 1) as usize).filter(|_| index >= 1) {
+        Some(c) => {
+            let code = push_int(heap, c as isize);
+            unify_result(heap, code, code_a)
+        }
+        None => PredReturn::False,
     }
 }
 
 // ── Concatenation predicates ──────────────────────────────────────────────────
 
-/// Shared logic for `atom_concat` / `string_concat`.
+/// Shared logic for `atom_concat` / `string_concat` / `text_concat`. The
+/// parts and the whole may be atoms, strings or numbers. With only the whole
+/// bound, every split of it is offered on backtracking.
 fn concat_impl(
     heap: &mut QueryHeap,
     goal: usize,
@@ -282,34 +439,52 @@ fn concat_impl(
     let b = goal_arg(heap, goal, 1);
     let c = goal_arg(heap, goal, 2);
 
-    let a_text = read_text(heap, a);
-    let b_text = read_text(heap, b);
-    let c_text = read_text(heap, c);
+    let a_text = atomic_text(heap, a);
+    let b_text = atomic_text(heap, b);
+    let c_text = atomic_text(heap, c);
 
     match (a_text, b_text, c_text) {
-        (Some(at), Some(bt), None) if is_var(heap, c) => {
-            let (Tag::Ref, r) = heap[c] else { return false.into(); };
+        (Some(at), Some(bt), None) if is_unbound(heap, c) => {
             let result = make_result(heap, &format!("{at}{bt}"));
-            PredReturn::Success(vec![(r, result)], vec![])
+            PredReturn::Success(bind_output(heap, c, result), vec![])
         }
         (Some(at), Some(bt), Some(ct)) => {
-            (format!("{at}{bt}").as_str() == ct.as_ref()).into()
+            (format!("{at}{bt}") == ct).into()
         }
-        (Some(at), None, Some(ct)) if is_var(heap, b) => {
-            let (Tag::Ref, r) = heap[b] else { return false.into(); };
-            match ct.strip_prefix(at.as_ref()) {
-                Some(suffix) => PredReturn::Success(vec![(r, make_result(heap, suffix))], vec![]),
+        (Some(at), None, Some(ct)) if is_unbound(heap, b) => {
+            match ct.strip_prefix(at.as_str()) {
+                Some(suffix) => {
+                    let result = make_result(heap, suffix);
+                    PredReturn::Success(bind_output(heap, b, result), vec![])
+                }
                 None => PredReturn::False,
             }
         }
-        (None, Some(bt), Some(ct)) if is_var(heap, a) => {
-            let (Tag::Ref, r) = heap[a] else { return false.into(); };
-            match ct.strip_suffix(bt.as_ref()) {
-                Some(prefix) => PredReturn::Success(vec![(r, make_result(heap, prefix))], vec![]),
+        (None, Some(bt), Some(ct)) if is_unbound(heap, a) => {
+            match ct.strip_suffix(bt.as_str()) {
+                Some(prefix) => {
+                    let result = make_result(heap, prefix);
+                    PredReturn::Success(bind_output(heap, a, result), vec![])
+                }
                 None => PredReturn::False,
             }
         }
-        _ => PredReturn::False,
+        (None, None, Some(ct)) if is_unbound(heap, a) && is_unbound(heap, b) => {
+            let splits = ct.char_indices().map(|(i, _)| i).chain([ct.len()]);
+            let alternatives = splits
+                .filter_map(|i| {
+                    let prefix = make_result(heap, &ct[..i]);
+                    let suffix = make_result(heap, &ct[i..]);
+                    alternative(heap, &[prefix, suffix], &[a, b])
+                })
+                .collect();
+            choices(alternatives)
+        }
+        _ => match [a, b, c].into_iter().find(|&x| !is_unbound(heap, x) && atomic_text(heap, x).is_none()) {
+            Some(culprit) => type_error(heap, "atomic", culprit),
+            None if is_unbound(heap, c) => instantiation_error(heap),
+            None => PredReturn::False,
+        },
     }
 }
 
@@ -317,7 +492,7 @@ fn concat_impl(
 pub fn atom_concat_pred(
     heap: &mut QueryHeap, _: &mut Hypothesis, goal: usize, _: &PredicateTable, _: Config,
 ) -> PredReturn {
-    concat_impl(heap, goal, |h, s| push_const(h, s))
+    concat_impl(heap, goal, push_const)
 }
 
 /// `string_concat/3`: concatenate or split strings.
@@ -327,17 +502,33 @@ pub fn string_concat_pred(
     concat_impl(heap, goal, |h, s| push_string(h, Arc::from(s)))
 }
 
+/// `text_concat/3`: as `atom_concat/3`, but producing strings if any of
+/// the arguments is a string.
+pub fn text_concat_pred(
+    heap: &mut QueryHeap, _: &mut Hypothesis, goal: usize, _: &PredicateTable, _: Config,
+) -> PredReturn {
+    let any_string = (0..3).any(|n| matches!(heap[goal_arg(heap, goal, n)], (Tag::Stri, _)));
+    if any_string {
+        concat_impl(heap, goal, |h, s| push_string(h, Arc::from(s)))
+    } else {
+        concat_impl(heap, goal, push_const)
+    }
+}
+
 // ── Case-conversion predicates ────────────────────────────────────────────────
 
 fn upcase_impl(heap: &mut QueryHeap, goal: usize, string_mode: bool) -> PredReturn {
     let in_a  = goal_arg(heap, goal, 0);
     let out_a = goal_arg(heap, goal, 1);
-    let Some(text) = read_text(heap, in_a) else { return false.into(); };
+    let text = match must_be_text(heap, in_a) {
+        Ok(text) => text,
+        Err(error) => return error,
+    };
     let upper = text.to_uppercase();
     match heap[out_a] {
-        (Tag::Ref, r) => {
+        _ if is_unbound(heap, out_a) => {
             let result = if string_mode { push_string(heap, upper.into()) } else { push_const(heap, &upper) };
-            PredReturn::Success(vec![(r, result)], vec![])
+            PredReturn::Success(bind_output(heap, out_a, result), vec![])
         }
         _ => read_text(heap, out_a)
             .map_or(PredReturn::False, |existing| (existing.as_ref() == upper.as_str()).into()),
@@ -347,12 +538,15 @@ fn upcase_impl(heap: &mut QueryHeap, goal: usize, string_mode: bool) -> PredRetu
 fn downcase_impl(heap: &mut QueryHeap, goal: usize, string_mode: bool) -> PredReturn {
     let in_a  = goal_arg(heap, goal, 0);
     let out_a = goal_arg(heap, goal, 1);
-    let Some(text) = read_text(heap, in_a) else { return false.into(); };
+    let text = match must_be_text(heap, in_a) {
+        Ok(text) => text,
+        Err(error) => return error,
+    };
     let lower = text.to_lowercase();
     match heap[out_a] {
-        (Tag::Ref, r) => {
+        _ if is_unbound(heap, out_a) => {
             let result = if string_mode { push_string(heap, lower.into()) } else { push_const(heap, &lower) };
-            PredReturn::Success(vec![(r, result)], vec![])
+            PredReturn::Success(bind_output(heap, out_a, result), vec![])
         }
         _ => read_text(heap, out_a)
             .map_or(PredReturn::False, |existing| (existing.as_ref() == lower.as_str()).into()),
@@ -379,6 +573,9 @@ pub fn downcase_string_pred(
     heap: &mut QueryHeap, _: &mut Hypothesis, goal: usize, _: &PredicateTable, _: Config,
 ) -> PredReturn { downcase_impl(heap, goal, true) }
 
+// `string_upper/2` and `string_lower/2` are the SWI-Prolog names for
+// `upcase_string/2` and `downcase_string/2`, and share their code.
+
 // ── Character-list predicates ─────────────────────────────────────────────────
 
 /// Shared logic for `atom_chars` / `string_chars`.
@@ -390,16 +587,18 @@ fn chars_impl(
     let text_a = goal_arg(heap, goal, 0);
     let list_a = goal_arg(heap, goal, 1);
 
-    match (read_text(heap, text_a), is_var(heap, list_a)) {
+    match (read_text(heap, text_a), is_unbound(heap, list_a)) {
         (Some(text), true) => {
-            let (Tag::Ref, r) = heap[list_a] else { return false.into(); };
             let cells: Vec<Cell> = text.chars().map(|c| {
                 (Tag::Con, SymbolDB::set_const(c.to_string()))
             }).collect();
-            PredReturn::Success(vec![(r, build_list(heap, &cells))], vec![])
+            let result = build_list(heap, &cells);
+            PredReturn::Success(bind_output(heap, list_a, result), vec![])
         }
-        (None, _) if is_var(heap, text_a) => {
-            let (Tag::Ref, r) = heap[text_a] else { return false.into(); };
+        (None, _) if is_unbound(heap, text_a) => {
+            if let Err(error) = must_be_list(heap, list_a) {
+                return error;
+            }
             let Some(cells) = read_list_cells(heap, list_a) else { return false.into(); };
             let mut buf = String::new();
             for (tag, v) in cells {
@@ -411,7 +610,8 @@ fn chars_impl(
                     _ => return false.into(),
                 }
             }
-            PredReturn::Success(vec![(r, make_result(heap, &buf))], vec![])
+            let result = make_result(heap, &buf);
+            PredReturn::Success(bind_output(heap, text_a, result), vec![])
         }
         (Some(text), false) => {
             let Some(cells) = read_list_cells(heap, list_a) else { return false.into(); };
@@ -428,7 +628,7 @@ fn chars_impl(
             }
             PredReturn::True
         }
-        _ => PredReturn::False,
+        (None, _) => must_be_error(heap, "atomic", text_a),
     }
 }
 
@@ -436,7 +636,7 @@ fn chars_impl(
 pub fn atom_chars_pred(
     heap: &mut QueryHeap, _: &mut Hypothesis, goal: usize, _: &PredicateTable, _: Config,
 ) -> PredReturn {
-    chars_impl(heap, goal, |h, s| push_const(h, s))
+    chars_impl(heap, goal, push_const)
 }
 
 /// `string_chars/2`: convert between a string and a list of single-character constants.
@@ -455,14 +655,16 @@ fn codes_impl(
     let text_a = goal_arg(heap, goal, 0);
     let list_a = goal_arg(heap, goal, 1);
 
-    match (read_text(heap, text_a), is_var(heap, list_a)) {
+    match (read_text(heap, text_a), is_unbound(heap, list_a)) {
         (Some(text), true) => {
-            let (Tag::Ref, r) = heap[list_a] else { return false.into(); };
             let cells: Vec<Cell> = text.chars().map(|c| (Tag::Int, c as usize)).collect();
-            PredReturn::Success(vec![(r, build_list(heap, &cells))], vec![])
+            let result = build_list(heap, &cells);
+            PredReturn::Success(bind_output(heap, list_a, result), vec![])
         }
-        (None, _) if is_var(heap, text_a) => {
-            let (Tag::Ref, r) = heap[text_a] else { return false.into(); };
+        (None, _) if is_unbound(heap, text_a) => {
+            if let Err(error) = must_be_list(heap, list_a) {
+                return error;
+            }
             let Some(cells) = read_list_cells(heap, list_a) else { return false.into(); };
             let mut buf = String::new();
             for (tag, v) in cells {
@@ -472,7 +674,8 @@ fn codes_impl(
                     None => return false.into(),
                 }
             }
-            PredReturn::Success(vec![(r, make_result(heap, &buf))], vec![])
+            let result = make_result(heap, &buf);
+            PredReturn::Success(bind_output(heap, text_a, result), vec![])
         }
         (Some(text), false) => {
             let Some(cells) = read_list_cells(heap, list_a) else { return false.into(); };
@@ -484,7 +687,7 @@ fn codes_impl(
             }
             PredReturn::True
         }
-        _ => PredReturn::False,
+        (None, _) => must_be_error(heap, "atomic", text_a),
     }
 }
 
@@ -492,7 +695,7 @@ fn codes_impl(
 pub fn atom_codes_pred(
     heap: &mut QueryHeap, _: &mut Hypothesis, goal: usize, _: &PredicateTable, _: Config,
 ) -> PredReturn {
-    codes_impl(heap, goal, |h, s| push_const(h, s))
+    codes_impl(heap, goal, push_const)
 }
 
 /// `string_codes/2`: convert between a string and a list of Unicode code points.
@@ -504,7 +707,11 @@ pub fn string_codes_pred(
 
 // ── Substring predicates ──────────────────────────────────────────────────────
 
-/// Shared logic for `sub_atom` / `sub_string`.
+/// Shared logic for `sub_atom` / `sub_string`: `Sub` is the part of `Whole`
+/// that starts after `Before` characters, is `Length` characters long and
+/// leaves `After` characters. Every solution consistent with the bound
+/// arguments is offered on backtracking, from left to right and shortest
+/// first.
 fn sub_impl(
     heap: &mut QueryHeap,
     goal: usize,
@@ -516,81 +723,59 @@ fn sub_impl(
     let after_a  = goal_arg(heap, goal, 3);
     let sub_a    = goal_arg(heap, goal, 4);
 
-    let Some(whole_text) = read_text(heap, whole_a) else { return false.into(); };
-    let total_chars: Vec<char> = whole_text.chars().collect();
-    let total = total_chars.len();
-
-    let before_val = match heap[before_a] { (Tag::Int, v) => Some(v), _ => None };
-    let length_val = match heap[length_a] { (Tag::Int, v) => Some(v), _ => None };
-    let sub_text   = read_text(heap, sub_a);
-
-    // Extract mode: Whole + Before + Length are all bound.
-    if let (Some(before), Some(length)) = (before_val, length_val) {
-        if before + length > total { return false.into(); }
-        let sub_str: String = total_chars[before..before + length].iter().collect();
-        let after = total - before - length;
-
-        if !is_var(heap, sub_a) && !is_var(heap, after_a) {
-            let after_ok = matches!(heap[after_a], (Tag::Int, v) if v == after);
-            let sub_ok   = sub_text.map_or(false, |t| t.as_ref() == sub_str.as_str());
-            return (after_ok && sub_ok).into();
-        }
-
-        let mut bindings = Vec::new();
-        if is_var(heap, sub_a) {
-            let (Tag::Ref, r) = heap[sub_a] else { return false.into(); };
-            bindings.push((r, make_result(heap, &sub_str)));
-        } else if sub_text.map_or(true, |t| t.as_ref() != sub_str.as_str()) {
-            return false.into();
-        }
-        if is_var(heap, after_a) {
-            let (Tag::Ref, r) = heap[after_a] else { return false.into(); };
-            bindings.push((r, push_int(heap, after as isize)));
-        } else if !matches!(heap[after_a], (Tag::Int, v) if v == after) {
-            return false.into();
-        }
-        return PredReturn::Success(bindings, vec![]);
-    }
-
-    // Find-first mode: Whole + Sub are bound, position arguments unbound.
-    if let Some(sub_t) = sub_text {
-        if let Some(byte_pos) = whole_text.find(sub_t.as_ref()) {
-            let before = whole_text[..byte_pos].chars().count();
-            let length = sub_t.chars().count();
-            let after  = total - before - length;
-
-            let mut bindings = Vec::new();
-            if is_var(heap, before_a) {
-                let (Tag::Ref, r) = heap[before_a] else { return false.into(); };
-                bindings.push((r, push_int(heap, before as isize)));
-            } else if !matches!(heap[before_a], (Tag::Int, v) if v == before) {
-                return false.into();
-            }
-            if is_var(heap, length_a) {
-                let (Tag::Ref, r) = heap[length_a] else { return false.into(); };
-                bindings.push((r, push_int(heap, length as isize)));
-            } else if !matches!(heap[length_a], (Tag::Int, v) if v == length) {
-                return false.into();
+    let whole_text = match must_be_text(heap, whole_a) {
+        Ok(text) => text,
+        Err(error) => return error,
+    };
+    let chars: Vec<char> = whole_text.chars().collect();
+    let total = chars.len() as isize;
+
+    let mut bounds = [None; 3];
+    for (bound, addr) in bounds.iter_mut().zip([before_a, length_a, after_a]) {
+        match heap[addr] {
+            (Tag::Int, v) => *bound = Some(v as isize),
+            _ if is_unbound(heap, addr) => {}
+            _ => return type_error(heap, "integer", addr),
+        }
+    }
+    let [before, length, after] = bounds;
+    let sub: Option<Vec<char>> = match read_text(heap, sub_a) {
+        Some(text) => Some(text.chars().collect()),
+        None if is_unbound(heap, sub_a) => None,
+        None => return type_error(heap, "atomic", sub_a),
+    };
+
+    let mut alternatives = Vec::new();
+    let starts = before.map_or(0..=total, |b| b..=b);
+    for b in starts {
+        let lengths = match (&sub, length, after) {
+            (Some(sub), _, _) => sub.len() as isize..=sub.len() as isize,
+            (None, Some(l), _) => l..=l,
+            (None, None, Some(a)) => total - b - a..=total - b - a,
+            (None, None, None) => 0..=total - b,
+        };
+        for l in lengths {
+            let a = total - b - l;
+            if b < 0 || l < 0 || a < 0 || length.is_some_and(|x| x != l) || after.is_some_and(|x| x != a) {
+                continue;
             }
-            if is_var(heap, after_a) {
-                let (Tag::Ref, r) = heap[after_a] else { return false.into(); };
-                bindings.push((r, push_int(heap, after as isize)));
-            } else if !matches!(heap[after_a], (Tag::Int, v) if v == after) {
-                return false.into();
+            let part = &chars[b as usize..(b + l) as usize];
+            if sub.as_ref().is_some_and(|sub| sub[..] != *part) {
+                continue;
             }
-            return PredReturn::Success(bindings, vec![]);
+            let text: String = part.iter().collect();
+            let values = [push_int(heap, b), push_int(heap, l), push_int(heap, a), make_result(heap, &text)];
+            alternatives.extend(alternative(heap, &values, &[before_a, length_a, after_a, sub_a]));
         }
-        return false.into();
     }
-
-    PredReturn::False
+    choices(alternatives)
 }
 
 /// `sub_atom/5`: extract or check a sub-constant.
 pub fn sub_atom_pred(
     heap: &mut QueryHeap, _: &mut Hypothesis, goal: usize, _: &PredicateTable, _: Config,
 ) -> PredReturn {
-    sub_impl(heap, goal, |h, s| push_const(h, s))
+    sub_impl(heap, goal, push_const)
 }
 
 /// `sub_string/5`: extract or check a sub-string.
@@ -600,6 +785,317 @@ pub fn sub_string_pred(
     sub_impl(heap, goal, |h, s| push_string(h, Arc::from(s)))
 }
 
+// ── Splitting and joining ─────────────────────────────────────────────────────
+
+/// `split_string(+String, +SepChars, +PadChars, -SubStrings)`: split String
+/// at each character in SepChars, then strip the characters in PadChars
+/// from both ends of each part. Adjacent separators give empty strings.
+/// With SepChars empty, String is only stripped, so
+/// `split_string(S, "", " ", [T])` trims S.
+pub fn split_string_pred(
+    heap: &mut QueryHeap, _: &mut Hypothesis, goal: usize, _: &PredicateTable, _: Config,
+) -> PredReturn {
+    let mut texts = Vec::with_capacity(3);
+    for n in 0..3 {
+        let addr = goal_arg(heap, goal, n);
+        match must_be_any_text(heap, addr) {
+            Ok(text) => texts.push(text),
+            Err(error) => return error,
+        }
+    }
+    let [string, separators, pad] = &texts[..] else { unreachable!() };
+    let parts: Vec<&str> = match separators.is_empty() {
+        true => vec![string],
+        false => string.split(|c| separators.contains(c)).collect(),
+    };
+    let cells: Vec<Cell> = parts
+        .into_iter()
+        .map(|part| {
+            let part = part.trim_matches(|c| pad.contains(c));
+            (Tag::Stri, SymbolDB::set_string(part.to_string(), heap.get_id()))
+        })
+        .collect();
+    let list = build_list(heap, &cells);
+    unify_result(heap, list, goal_arg(heap, goal, 3))
+}
+
+/// Shared logic for `atomic_list_concat/2,3`. `separator` is the address of
+/// the separator argument, if there is one.
+fn atomic_list_concat_impl(heap: &mut QueryHeap, goal: usize, separator: Option<usize>) -> PredReturn {
+    let list_a   = goal_arg(heap, goal, 0);
+    let result_a = goal_arg(heap, goal, if separator.is_some() { 2 } else { 1 });
+    let separator_text = match separator {
+        Some(sep_a) => match atomic_text(heap, sep_a) {
+            Some(text) => text,
+            None => return must_be_error(heap, "atomic", sep_a),
+        },
+        None => String::new(),
+    };
+
+    // Join mode: a proper list of atomic elements
+    let (elements, tail) = read_list_with_tail(heap, list_a);
+    let texts: Option<Vec<String>> = match heap[tail] {
+        (Tag::ELis, _) => elements.iter().map(|&e| atomic_text(heap, e)).collect(),
+        _ => None,
+    };
+    if let Some(texts) = texts {
+        let result = push_const(heap, &texts.join(&separator_text));
+        return unify_result(heap, result, result_a);
+    }
+
+    // Split mode: the result is bound and there is a separator to split at
+    let result_text = atomic_text(heap, result_a);
+    if let (Some(sep_a), Some(text)) = (separator, &result_text) {
+        if separator_text.is_empty() {
+            return domain_error(heap, "non_empty_atom", sep_a);
+        }
+        let cells: Vec<Cell> = text
+            .split(separator_text.as_str())
+            .map(|part| (Tag::Con, SymbolDB::set_const(part)))
+            .collect();
+        let list = build_list(heap, &cells);
+        return unify_result(heap, list, list_a);
+    }
+
+    if !is_unbound(heap, tail) && !matches!(heap[tail], (Tag::ELis, _)) {
+        return type_error(heap, "list", list_a);
+    }
+    let culprit = elements.into_iter().find(|&e| !is_unbound(heap, e) && atomic_text(heap, e).is_none());
+    match culprit {
+        Some(culprit) => type_error(heap, "atomic", culprit),
+        None if result_text.is_none() && !is_unbound(heap, result_a) => type_error(heap, "atomic", result_a),
+        None => instantiation_error(heap),
+    }
+}
+
+/// `atomic_list_concat(+List, ?Atom)`: Atom is the concatenation of the
+/// atoms, strings and numbers in List.
+pub fn atomic_list_concat_pred(
+    heap: &mut QueryHeap, _: &mut Hypothesis, goal: usize, _: &PredicateTable, _: Config,
+) -> PredReturn {
+    atomic_list_concat_impl(heap, goal, None)
+}
+
+/// `atomic_list_concat/3`: join with a separator, or split.
+///
+/// Modes:
+/// - `atomic_list_concat(+List, +Sep, ?Atom)` — join the elements of List
+///   with Sep between them.
+/// - `atomic_list_concat(-List, +Sep, +Atom)` — split Atom at each Sep into
+///   a list of atoms. Sep must not be empty.
+pub fn atomic_list_concat_sep_pred(
+    heap: &mut QueryHeap, _: &mut Hypothesis, goal: usize, _: &PredicateTable, _: Config,
+) -> PredReturn {
+    let sep_a = goal_arg(heap, goal, 1);
+    atomic_list_concat_impl(heap, goal, Some(sep_a))
+}
+
+/// `normalize_space(+Out, +Input)`: remove layout from both ends of Input
+/// and replace each run of layout inside it by one space. Out is
+/// `atom(A)`, `string(S)`, `codes(Cs)` or `chars(Cs)`, as for `format/3`.
+pub fn normalize_space_pred(
+    heap: &mut QueryHeap, _: &mut Hypothesis, goal: usize, _: &PredicateTable, _: Config,
+) -> PredReturn {
+    let out_a = goal_arg(heap, goal, 0);
+    let in_a  = goal_arg(heap, goal, 1);
+    let (kind, target) = match output_target(heap, out_a, false) {
+        Ok(Target::Capture(kind, target)) => (kind, target),
+        Ok(_) => unreachable!("streams are not output targets of normalize_space/2"),
+        Err(error) => return error,
+    };
+    let text = match must_be_any_text(heap, in_a) {
+        Ok(text) => text,
+        Err(error) => return error,
+    };
+    let normalized = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let value = capture_value(heap, kind, &normalized);
+    unify_result(heap, value, target)
+}
+
+// ── Character classification ──────────────────────────────────────────────────
+
+/// The types known to `char_type/2` and `code_type/2`, with their arities.
+const CHAR_TYPES: &[(&str, usize)] = &[
+    ("alnum", 0), ("alpha", 0), ("csym", 0), ("csymf", 0), ("ascii", 0),
+    ("white", 0), ("cntrl", 0), ("digit", 0), ("digit", 1), ("space", 0),
+    ("end_of_line", 0), ("lower", 0), ("lower", 1), ("upper", 0), ("upper", 1),
+    ("punct", 0), ("graph", 0), ("print", 0), ("period", 0), ("quote", 0),
+    ("paren", 0), ("code", 1), ("to_lower", 1), ("to_upper", 1),
+    ("prolog_var_start", 0), ("prolog_atom_start", 0),
+    ("prolog_identifier_continue", 0), ("prolog_symbol", 0),
+];
+
+/// The argument of a character type of arity 1.
+enum TypeArg {
+    Int(usize),
+    Char(char),
+}
+
+/// `c` in upper case, if that is a single character.
+fn to_upper(c: char) -> char {
+    let mut upper = c.to_uppercase();
+    match (upper.next(), upper.next()) {
+        (Some(u), None) => u,
+        _ => c,
+    }
+}
+
+/// `c` in lower case, if that is a single character.
+fn to_lower(c: char) -> char {
+    let mut lower = c.to_lowercase();
+    match (lower.next(), lower.next()) {
+        (Some(l), None) => l,
+        _ => c,
+    }
+}
+
+/// Whether `c` has the character type `name/arity`: `None` if not, and
+/// otherwise the type's argument, if it has one. As in SWI-Prolog, `alpha`
+/// includes digits and the underscore.
+fn char_type_of(c: char, name: &str, arity: usize) -> Option<Option<TypeArg>> {
+    let holds = |test: bool| test.then_some(None);
+    let graph = !c.is_whitespace() && !c.is_control();
+    match (name, arity) {
+        ("alnum", 0) => holds(c.is_alphanumeric()),
+        ("alpha" | "csym" | "prolog_identifier_continue", 0) => holds(c.is_alphanumeric() || c == '_'),
+        ("csymf", 0) => holds(c.is_alphabetic() || c == '_'),
+        ("ascii", 0) => holds(c.is_ascii()),
+        ("white", 0) => holds(c == ' ' || c == '\t'),
+        ("cntrl", 0) => holds(c.is_control()),
+        ("digit", 0) => holds(c.is_ascii_digit()),
+        ("digit", 1) => c.to_digit(10).map(|weight| Some(TypeArg::Int(weight as usize))),
+        ("space", 0) => holds(c.is_whitespace()),
+        ("end_of_line", 0) => holds(c == '\n' || c == '\r'),
+        ("lower", 0) => holds(c.is_lowercase()),
+        ("lower", 1) => c.is_lowercase().then(|| Some(TypeArg::Char(to_upper(c)))),
+        ("upper", 0) => holds(c.is_uppercase()),
+        ("upper", 1) => c.is_uppercase().then(|| Some(TypeArg::Char(to_lower(c)))),
+        ("punct", 0) => holds(graph && !c.is_alphanumeric()),
+        ("graph", 0) => holds(graph),
+        ("print", 0) => holds(graph || c == ' '),
+        ("period", 0) => holds(matches!(c, '.' | '!' | '?')),
+        ("quote", 0) => holds(matches!(c, '\'' | '"' | '`')),
+        ("paren", 0) => holds(matches!(c, '(' | ')')),
+        ("code", 1) => Some(Some(TypeArg::Int(c as usize))),
+        ("to_lower", 1) => Some(Some(TypeArg::Char(to_lower(c)))),
+        ("to_upper", 1) => Some(Some(TypeArg::Char(to_upper(c)))),
+        ("prolog_var_start", 0) => holds(c.is_uppercase() || c == '_'),
+        ("prolog_atom_start", 0) => holds(c.is_lowercase()),
+        ("prolog_symbol", 0) => holds(SYMBOL_CHARS.contains(&c)),
+        _ => None,
+    }
+}
+
+/// Read a character given as a single-character constant or a code.
+fn read_char(heap: &QueryHeap, addr: usize) -> Option<char> {
+    match heap[addr] {
+        (Tag::Con, id) => {
+            let name = SymbolDB::get_const(id);
+            let mut chars = name.chars();
+            chars.next().filter(|_| chars.next().is_none())
+        }
+        (Tag::Int, code) => char::from_u32(code as u32),
+        _ => None,
+    }
+}
+
+/// Push `c` as a code with `codes` set, otherwise as a constant.
+fn push_char(heap: &mut QueryHeap, c: char, codes: bool) -> usize {
+    match codes {
+        true => push_int(heap, c as isize),
+        false => push_const(heap, &c.to_string()),
+    }
+}
+
+/// Shared logic for `char_type` / `code_type`. Characters are read as
+/// constants or codes, and produced as codes with `codes` set.
+///
+/// With the character unbound, the candidates are Latin-1 (code points up to
+/// 255) and the characters related to a bound type argument, as in
+/// `upper(a)`.
+fn char_type_impl(heap: &mut QueryHeap, goal: usize, codes: bool) -> PredReturn {
+    let char_a = goal_arg(heap, goal, 0);
+    let type_a = goal_arg(heap, goal, 1);
+
+    let (types, type_arg) = if is_unbound(heap, type_a) {
+        (CHAR_TYPES.to_vec(), None)
+    } else {
+        let addr = resolve(heap, type_a);
+        let (name, arity, arg) = match heap[addr] {
+            (Tag::Con, id) => (Some(SymbolDB::get_const(id)), 0, None),
+            (Tag::Comp, 2) => match heap[heap.deref_addr(addr + 1)] {
+                (Tag::Con, id) => (Some(SymbolDB::get_const(id)), 1, Some(heap.deref_addr(addr + 2))),
+                _ => (None, 0, None),
+            },
+            _ => (None, 0, None),
+        };
+        let known = name.and_then(|name| CHAR_TYPES.iter().find(|&&(n, a)| *n == *name && a == arity));
+        match known {
+            Some(&known) => (vec![known], arg),
+            None => return domain_error(heap, "char_type", type_a),
+        }
+    };
+
+    let char_bound = !is_unbound(heap, char_a);
+    let chars: Vec<char> = if !char_bound {
+        if is_unbound(heap, type_a) {
+            return instantiation_error(heap);
+        }
+        let mut chars: Vec<char> = (0..=255u8).map(char::from).collect();
+        if let Some(related) = type_arg.and_then(|arg| read_char(heap, arg)) {
+            for c in [related, to_upper(related), to_lower(related)] {
+                if !chars.contains(&c) {
+                    chars.push(c);
+                }
+            }
+        }
+        chars
+    } else {
+        match read_char(heap, char_a) {
+            Some(c) => vec![c],
+            None => return type_error(heap, if codes { "integer" } else { "character" }, char_a),
+        }
+    };
+
+    let mut alternatives = Vec::new();
+    for c in chars {
+        for &(name, arity) in &types {
+            let Some(arg) = char_type_of(c, name, arity) else { continue };
+            // A bound character is kept in the form it was given
+            let char_cell = if char_bound { char_a } else { push_char(heap, c, codes) };
+            let name = push_const(heap, name);
+            let type_term = match arg {
+                None => name,
+                Some(arg) => {
+                    let arg = match arg {
+                        TypeArg::Int(n) => push_int(heap, n as isize),
+                        TypeArg::Char(c) => push_char(heap, c, codes),
+                    };
+                    build_compound_from_addrs(heap, &[name, arg])
+                }
+            };
+            alternatives.extend(alternative(heap, &[char_cell, type_term], &[char_a, type_a]));
+        }
+    }
+    choices(alternatives)
+}
+
+/// `char_type(?Char, ?Type)`: Char has character type Type, such as
+/// `alpha`, `digit(Weight)` or `upper(Lower)`. Either argument may be
+/// unbound, enumerating the other on backtracking.
+pub fn char_type_pred(
+    heap: &mut QueryHeap, _: &mut Hypothesis, goal: usize, _: &PredicateTable, _: Config,
+) -> PredReturn {
+    char_type_impl(heap, goal, false)
+}
+
+/// `code_type(?Code, ?Type)`: as `char_type/2`, but with character codes.
+pub fn code_type_pred(
+    heap: &mut QueryHeap, _: &mut Hypothesis, goal: usize, _: &PredicateTable, _: Config,
+) -> PredReturn {
+    char_type_impl(heap, goal, true)
+}
+
 // ── Module registration ───────────────────────────────────────────────────────
 
 pub static STRINGS: PredicateModule = (
@@ -608,19 +1104,26 @@ pub static STRINGS: PredicateModule = (
         ("atom_string",   2, atom_string_pred),
         ("atom_number",   2, atom_number_pred),
         ("number_string", 2, number_string_pred),
+        ("number_codes",  2, number_codes_pred),
+        ("number_chars",  2, number_chars_pred),
         ("term_string",   2, term_string_pred),
+        ("term_to_atom",  2, term_to_atom_pred),
         ("char_code",     2, char_code_pred),
         // Length
         ("atom_length",   2, atom_length_pred),
         ("string_length", 2, string_length_pred),
+        ("string_code",   3, string_code_pred),
         // Concatenation
         ("atom_concat",   3, atom_concat_pred),
         ("string_concat", 3, string_concat_pred),
+        ("text_concat",   3, text_concat_pred),
         // Case
         ("upcase_atom",     2, upcase_atom_pred),
         ("downcase_atom",   2, downcase_atom_pred),
         ("upcase_string",   2, upcase_string_pred),
         ("downcase_string", 2, downcase_string_pred),
+        ("string_upper",    2, upcase_string_pred),
+        ("string_lower",    2, downcase_string_pred),
         // Character lists
         ("atom_chars",   2, atom_chars_pred),
         ("atom_codes",   2, atom_codes_pred),
@@ -629,6 +1132,14 @@ pub static STRINGS: PredicateModule = (
         // Substring
         ("sub_atom",   5, sub_atom_pred),
         ("sub_string", 5, sub_string_pred),
+        // Splitting and joining
+        ("split_string",       4, split_string_pred),
+        ("atomic_list_concat", 2, atomic_list_concat_pred),
+        ("atomic_list_concat", 3, atomic_list_concat_sep_pred),
+        ("normalize_space",    2, normalize_space_pred),
+        // Character classification
+        ("char_type", 2, char_type_pred),
+        ("code_type", 2, code_type_pred),
     ],
     &[include_str!("../../builtins/strings.pl")],
 );
@@ -662,6 +1173,31 @@ mod tests {
         tw().assert_false("atom_string(hello, \"world\").");
     }
 
+    // ── errors ────────────────────────────────────────────────────────────
+
+    #[test]
+    fn instantiation_errors() {
+        let tw = tw();
+        tw.assert_error("atom_string(A, S).", "instantiation_error");
+        tw.assert_error("atom_length(A, N).", "instantiation_error");
+        tw.assert_error("atom_concat(A, b, C).", "instantiation_error");
+        tw.assert_error("atom_chars(A, [a|T]).", "instantiation_error");
+        tw.assert_error("upcase_atom(A, U).", "instantiation_error");
+    }
+
+    #[test]
+    fn type_errors() {
+        let tw = tw();
+        tw.assert_error("atom_length(f(x), N).", "type_error(atom,f(x))");
+        tw.assert_error("atom_length(abc, three).", "type_error(integer,three)");
+        tw.assert_error("string_length(abc, N).", "type_error(string,abc)");
+        tw.assert_error("atom_number(A, abc).", "type_error(number,abc)");
+        tw.assert_error("char_code(C, a).", "type_error(integer,a)");
+        tw.assert_error("atom_concat(f(x), b, C).", "type_error(atomic,f(x))");
+        tw.assert_error("atom_chars(A, foo).", "type_error(list,foo)");
+        tw.assert_error("sub_atom(f(x), B, L, A, S).", "type_error(atomic,f(x))");
+    }
+
     // ── atom_number/2 ─────────────────────────────────────────────────────
 
     #[test]
@@ -687,6 +1223,20 @@ mod tests {
         assert_eq!(tw().binding("number_string(N, \"42\").", "N").as_deref(), Some("42"));
     }
 
+    #[test]
+    fn big_number_text() {
+        let big = "123456789012345678901234567890";
+        let tw = tw();
+        tw.assert_binding(&format!("atom_number('{big}', N)."), ("N", big));
+        tw.assert_binding("atom_number(A, 1r3).", ("A", "1r3"));
+        tw.assert_true(&format!("atom_number('{big}', {big})."));
+        tw.assert_false("atom_number('1r3', 0.3).");
+        tw.assert_binding("number_string(N, \"-2r4\").", ("N", "-1r2"));
+        tw.assert_binding(&format!("number_codes({big}, Cs), atom_codes(A, Cs)."), ("A", big));
+        tw.assert_true(&format!("term_string(T, \"{big} + -1r3\"), T = A + B, int(A), rational(B)."));
+        tw.assert_binding(&format!("term_string({big} + -1r3, S)."), ("S", &format!("\"{big}+ -1r3\"")));
+    }
+
     // ── term_string/2 ─────────────────────────────────────────────────────
 
     #[test]
@@ -699,6 +1249,44 @@ mod tests {
         assert_eq!(tw().binding("term_string(42, S).", "S").as_deref(), Some("\"42\""));
     }
 
+    #[test]
+    fn term_string_quotes() {
+        assert_eq!(
+            tw().binding("term_string(f('A b', [x], 1+2*3), S).", "S").as_deref(),
+            Some("\"f('A b',[x],1+2*3)\"")
+        );
+    }
+
+    #[test]
+    fn term_string_parses() {
+        let tw = tw();
+        tw.assert_binding("term_string(T, \"foo(X, Y, X)\"), T = foo(1, 2, Z).", ("Z", "1"));
+        tw.assert_binding("term_string(T, \"[a, b|c]\"), T = [_|R].", ("R", "[b|c]"));
+        tw.assert_true("term_string(T, \"1 + 2 * 3\"), T = +(1, *(2, 3)).");
+        tw.assert_true("term_string(f(x), \"f(x).\").");
+        tw.assert_false("term_string(f(y), \"f(x)\").");
+        tw.assert_true("term_string(f('A', \"s\", [1.5]), S), term_string(T, S), T == f('A', \"s\", [1.5]).");
+    }
+
+    #[test]
+    fn term_to_atom() {
+        let tw = tw();
+        tw.assert_binding("term_to_atom(f(a, 'B'), A).", ("A", "f(a,'B')"));
+        tw.assert_binding("term_to_atom(T, 'g(X, 3)'), T = g(2, N).", ("N", "3"));
+        tw.assert_error("term_to_atom(T, 'foo(').", "syntax_error(unexpected end of file)");
+        tw.assert_error("term_to_atom(T, 'a b').", "syntax_error(unexpected token `b`)");
+        tw.assert_error("term_to_atom(T, f(x)).", "type_error(atomic,f(x))");
+    }
+
+    #[test]
+    fn term_to_atom_round_trip() {
+        let tw = tw();
+        tw.assert_true("term_to_atom(f(A, A, B), At), term_to_atom(T, At), T = f(x, X, y), X == x.");
+        tw.assert_false("term_to_atom(f(A, A, B), At), term_to_atom(f(x, y, _), At).");
+        tw.assert_true("term_to_atom(T, 'g(_X, _X, _)'), T = g(a, Y, b), Y == a.");
+        tw.assert_false("term_to_atom(g(a, b, c), 'g(_X, _X, _)').");
+    }
+
     // ── char_code/2 ───────────────────────────────────────────────────────
 
     #[test]
@@ -869,4 +1457,151 @@ mod tests {
             Some("hello world"),
         );
     }
+
+    // ── Splitting and joining ─────────────────────────────────────────────
+
+    #[test]
+    fn split_string_fields() {
+        let tw = tw();
+        tw.assert_binding("split_string(\"a,b,,c\", \",\", \"\", L).", ("L", "[\"a\",\"b\",\"\",\"c\"]"));
+        tw.assert_binding("split_string(\"a b, c \", \",\", \" \", L).", ("L", "[\"a b\",\"c\"]"));
+        tw.assert_binding("split_string(\"  hi  \", \"\", \" \", L).", ("L", "[\"hi\"]"));
+        tw.assert_binding("split_string(a_b, \"_\", \"\", L).", ("L", "[\"a\",\"b\"]"));
+        tw.assert_true("split_string(\"a,b\", \",\", \"\", [\"a\", \"b\"]).");
+        tw.assert_false("split_string(\"a,b\", \",\", \"\", [\"a\", \"c\"]).");
+        tw.assert_error("split_string(S, \",\", \"\", L).", "instantiation_error");
+    }
+
+    #[test]
+    fn atomic_list_concat_modes() {
+        let tw = tw();
+        tw.assert_binding("atomic_list_concat([a, 1, \"s\", 2.5], X).", ("X", "a1s2.5"));
+        tw.assert_binding("atomic_list_concat([a, b, c], '-', X).", ("X", "a-b-c"));
+        tw.assert_true("atomic_list_concat([a, b], '-', 'a-b').");
+        tw.assert_binding("atomic_list_concat(L, '-', 'a-b--c').", ("L", "[a,b,,c]"));
+        tw.assert_binding("atomic_list_concat([a, X], ', ', 'a, b').", ("X", "b"));
+        tw.assert_false("atomic_list_concat([a, b], '-', 'a+b').");
+        tw.assert_error("atomic_list_concat(L, '', abc).", "domain_error(non_empty_atom,)");
+        tw.assert_error("atomic_list_concat([a|T], X).", "instantiation_error");
+        tw.assert_error("atomic_list_concat([a, f(x)], X).", "type_error(atomic,f(x))");
+    }
+
+    #[test]
+    fn normalize_space() {
+        let tw = tw();
+        tw.assert_binding("normalize_space(atom(A), \"  a   b \\tc \").", ("A", "a b c"));
+        tw.assert_binding("normalize_space(string(S), '  x  ').", ("S", "\"x\""));
+        tw.assert_binding("normalize_space(codes(C), \" a \").", ("C", "[97]"));
+        tw.assert_true("normalize_space(string(\"a b\"), \"a  b\").");
+        tw.assert_false("normalize_space(string(\"ab\"), \"a  b\").");
+        tw.assert_error("normalize_space(foo, \"a\").", "domain_error(output_sink,foo)");
+    }
+
+    // ── Text, codes and numbers ───────────────────────────────────────────
+
+    #[test]
+    fn string_code() {
+        let tw = tw();
+        tw.assert_binding("string_code(2, \"abc\", C).", ("C", "98"));
+        tw.assert_false("string_code(0, \"abc\", C).");
+        tw.assert_false("string_code(4, \"abc\", C).");
+        tw.assert_error("string_code(I, \"abc\", C).", "instantiation_error");
+    }
+
+    #[test]
+    fn number_codes_and_chars() {
+        let tw = tw();
+        tw.assert_binding("number_codes(12, L).", ("L", "[49,50]"));
+        tw.assert_binding("number_codes(X, \" 42\").", ("X", "42"));
+        tw.assert_binding("number_chars(X, ['-', '3', '.', '5']).", ("X", "-3.5"));
+        tw.assert_binding("number_chars(12, [A, B]).", ("B", "2"));
+        tw.assert_error("number_codes(X, \"4a\").", "syntax_error(illegal_number)");
+        tw.assert_error("number_chars(X, [a|T]).", "instantiation_error");
+        tw.assert_error("number_codes(a, L).", "type_error(number,a)");
+    }
+
+    #[test]
+    fn string_case() {
+        let tw = tw();
+        tw.assert_binding("string_upper(\"abc\", U).", ("U", "\"ABC\""));
+        tw.assert_binding("string_lower(\"ABC\", L).", ("L", "\"abc\""));
+    }
+
+    #[test]
+    fn concat_splits() {
+        let tw = tw();
+        assert_eq!(
+            tw.all_bindings("atom_concat(X, Y, abc), atom_concat(X, '+', P), atom_concat(P, Y, S).", "S"),
+            ["+abc", "a+bc", "ab+c", "abc+"],
+        );
+        tw.assert_binding("atom_concat(abc, 12, X).", ("X", "abc12"));
+        tw.assert_binding("text_concat(abc, X, \"abcdef\").", ("X", "\"def\""));
+        tw.assert_binding("text_concat(ab, cd, X).", ("X", "abcd"));
+        tw.assert_binding("text_concat(ab, \"cd\", X).", ("X", "\"abcd\""));
+    }
+
+    #[test]
+    fn sub_atom_enumerates() {
+        let tw = tw();
+        assert_eq!(tw.all_bindings("sub_atom(abab, B, L, A, ab).", "B"), ["0", "2"]);
+        assert_eq!(tw.all_bindings("sub_atom(abc, B, 2, A, S).", "S"), ["ab", "bc"]);
+        assert_eq!(tw.all_bindings("sub_atom(abc, 1, L, A, S).", "S"), ["", "b", "bc"]);
+        assert_eq!(tw.all_bindings("sub_atom(ab, B, L, A, S).", "S"), ["", "a", "ab", "", "b", ""]);
+        assert_eq!(tw.all_bindings("sub_string(\"hello\", B, L, 0, S).", "L").len(), 6);
+        tw.assert_binding("sub_string(\"hello\", B, L, 0, \"lo\").", ("B", "3"));
+        tw.assert_true("sub_string(\"hello\", 3, 2, 0, \"lo\").");
+        tw.assert_false("sub_string(\"hello\", 3, 2, 0, \"he\").");
+        tw.assert_error("sub_atom(abc, x, L, A, S).", "type_error(integer,x)");
+    }
+
+    // ── Character classification ──────────────────────────────────────────
+
+    #[test]
+    fn char_type_checks() {
+        let tw = tw();
+        tw.assert_true("char_type(a, alpha), char_type('_', csym), char_type('7', digit(7)).");
+        tw.assert_true("char_type(' ', white), char_type('.', period), char_type('+', prolog_symbol).");
+        tw.assert_false("char_type(a, digit(W)).");
+        tw.assert_binding("char_type('A', upper(L)).", ("L", "a"));
+        tw.assert_binding("char_type(b, to_upper(U)).", ("U", "B"));
+        tw.assert_binding("code_type(97, to_upper(U)).", ("U", "65"));
+        tw.assert_true("code_type(a, alpha).");
+    }
+
+    #[test]
+    fn char_type_enumerates() {
+        let tw = tw();
+        assert_eq!(
+            tw.all_bindings("char_type(C, digit(W)).", "C"),
+            ["0", "1", "2", "3", "4", "5", "6", "7", "8", "9"],
+        );
+        assert_eq!(tw.all_bindings("char_type(X, upper(a)).", "X"), ["A"]);
+        assert!(tw.all_bindings("char_type(a, T).", "T").contains(&"lower(A)".to_string()));
+        tw.assert_error("char_type(C, T).", "instantiation_error");
+        tw.assert_error("char_type(a, colour).", "domain_error(char_type,colour)");
+        tw.assert_error("char_type(ab, alpha).", "type_error(character,ab)");
+    }
+
+    // ── Anonymous outputs ─────────────────────────────────────────────────
+
+    #[test]
+    fn anonymous_outputs() {
+        let tw = tw();
+        tw.assert_true("atom_length(abc, _).");
+        tw.assert_true("string_length(\"abc\", _).");
+        tw.assert_true("char_code(_, 97).");
+        tw.assert_true("char_code(a, _).");
+        tw.assert_true("atom_number('42', _).");
+        tw.assert_false("atom_number(abc, _).");
+        tw.assert_true("atom_number(_, 42).");
+        tw.assert_true("atom_string(_, \"abc\").");
+        tw.assert_true("number_string(42, _).");
+        tw.assert_true("atom_concat(a, b, _).");
+        tw.assert_true("atom_concat(a, _, ab).");
+        tw.assert_false("atom_concat(_, c, ab).");
+        tw.assert_true("upcase_atom(abc, _).");
+        tw.assert_true("atom_chars(abc, _).");
+        tw.assert_true("atom_codes(_, [97]).");
+        tw.assert_error("atom_concat(_, b, _).", "instantiation_error");
+    }
 }
@@ -0,0 +1,371 @@
+//! Built-in predicates for the program's syntax.
+//!
+//! `op/3` changes the operator table kept on the predicate table. Queries
+//! opened afterwards, and clauses loaded after an `op/3` directive, are read
+//! with the new operators, and so are the terms `read_term/2` takes from the
+//! program's [`Input`](crate::program::input::Input).
+
+use std::collections::HashMap;
+
+use super::{helpers::*, PredReturn, PredicateModule};
+use crate::{
+    heap::{
+        heap::{Heap, Tag},
+        query_heap::QueryHeap,
+        symbol_db::SymbolDB,
+    },
+    parser::{
+        build_tree::TokenStream,
+        operators::{OpType, MAX_PRIORITY},
+        term::Term,
+        tokeniser::tokenise,
+        ParserError,
+    },
+    program::{hypothesis::Hypothesis, predicate_table::PredicateTable},
+    Config,
+};
+
+/// `op(+Priority, +Type, +Names)` — define each atom in Names, an atom or
+/// list of atoms, as an operator of Type (`xfx`, `xfy`, `yfx`, `fy`, `fx`,
+/// `xf` or `yf`) with Priority between 0 and 1200. Priority 0 removes the
+/// definition.
+///
+/// Throws an instantiation error for an unbound argument, a type error for
+/// an argument of the wrong type, `domain_error(operator_priority, P)` or
+/// `domain_error(operator_specifier, T)` for an unknown priority or type,
+/// and `permission_error(modify, operator, ',')` for `,`.
+pub fn op(
+    heap: &mut QueryHeap,
+    _: &mut Hypothesis,
+    goal: usize,
+    predicate_table: &PredicateTable,
+    _: Config,
+) -> PredReturn {
+    let priority_a = goal_arg(heap, goal, 0);
+    let type_a = goal_arg(heap, goal, 1);
+    let names_a = goal_arg(heap, goal, 2);
+
+    let priority = match heap[priority_a] {
+        (Tag::Int, p) if (p as isize) >= 0 && p <= MAX_PRIORITY => p,
+        (Tag::Int, _) => return domain_error(heap, "operator_priority", priority_a),
+        _ => return must_be_error(heap, "integer", priority_a),
+    };
+    let op_type = match heap[type_a] {
+        (Tag::Con, symbol) => match OpType::from_name(&SymbolDB::get_const(symbol)) {
+            Some(op_type) => op_type,
+            None => return domain_error(heap, "operator_specifier", type_a),
+        },
+        _ => return must_be_error(heap, "atom", type_a),
+    };
+    let name_addrs = match heap[names_a] {
+        (Tag::Con, _) => vec![names_a],
+        _ => match must_be_list(heap, names_a) {
+            Ok(addrs) => addrs,
+            Err(error) => return error,
+        },
+    };
+
+    let mut names = Vec::with_capacity(name_addrs.len());
+    for addr in name_addrs {
+        let addr = heap.deref_addr(addr);
+        match heap[addr] {
+            (Tag::Con, symbol) if &*SymbolDB::get_const(symbol) == "," => {
+                return permission_error(heap, "modify", "operator", addr)
+            }
+            (Tag::Con, symbol) => names.push(SymbolDB::get_const(symbol)),
+            _ => return must_be_error(heap, "atom", addr),
+        }
+    }
+
+    let mut operators = predicate_table.operators.write().unwrap();
+    for name in names {
+        operators.add(priority, op_type, &name);
+    }
+    PredReturn::True
+}
+
+// ── Reading terms ─────────────────────────────────────────────────────────────
+
+/// A term read from text, with the names of its variables in order of first
+/// appearance.
+pub struct ReadTerm {
+    pub addr: usize,
+    pub variable_names: Vec<(String, usize)>,
+}
+
+/// Read `text` as a single term, optionally ending with `.`, using the
+/// operators of `predicate_table`, and build it on the heap. `None` if the
+/// text holds no term at all.
+pub fn parse_term_text(
+    heap: &mut QueryHeap,
+    predicate_table: &PredicateTable,
+    text: &str,
+) -> Result<Option<ReadTerm>, ParserError> {
+    let operators = predicate_table.operators.read().unwrap().clone();
+    let mut tokens = TokenStream::new(tokenise(text)?).with_operators(operators);
+    if tokens.peek().is_none() {
+        return Ok(None);
+    }
+    let term = tokens.parse_expression()?;
+    if tokens.peek() == Some(".") {
+        tokens.next();
+    }
+    if let Some(token) = tokens.next() {
+        return Err(ParserError::UnexpectedToken { token: token.to_string() });
+    }
+
+    let mut var_values = HashMap::new();
+    let mut addr = term.encode(heap, &mut var_values, true);
+    if let Term::List(..) = term {
+        // A list encodes as its first cell, which needs a pointer to it
+        addr = heap.heap_push((Tag::Lis, addr));
+    }
+    let names: HashMap<usize, String> =
+        var_values.into_iter().map(|(name, addr)| (addr, name)).collect();
+    let mut variable_names: Vec<(String, usize)> = Vec::new();
+    for var in heap.term_vars(addr, false) {
+        if let Some(name) = names.get(&var) {
+            if !variable_names.iter().any(|&(_, seen)| seen == var) {
+                variable_names.push((name.clone(), var));
+            }
+        }
+    }
+    Ok(Some(ReadTerm { addr, variable_names }))
+}
+
+/// Read the options of `read_term/2` into `(option, target)` pairs.
+fn read_options(
+    heap: &mut QueryHeap,
+    options: usize,
+) -> Result<Vec<(&'static str, usize)>, PredReturn> {
+    let mut read = Vec::new();
+    for option in must_be_list(heap, options)? {
+        let option = heap.deref_addr(option);
+        if is_unbound(heap, option) {
+            return Err(instantiation_error(heap));
+        }
+        let addr = resolve(heap, option);
+        let name = match heap[addr] {
+            (Tag::Comp, 2) => match heap[heap.deref_addr(addr + 1)] {
+                (Tag::Con, id) => ["variable_names", "variables"]
+                    .into_iter()
+                    .find(|&name| *SymbolDB::get_const(id) == *name),
+                _ => None,
+            },
+            _ => None,
+        };
+        match name {
+            Some(name) => read.push((name, addr + 2)),
+            None => return Err(domain_error(heap, "read_option", option)),
+        }
+    }
+    Ok(read)
+}
+
+/// `read_term(-Term, +Options)` — read the next term from the program's
+/// input and unify it with Term, or `end_of_file` if there are no more.
+/// The term ends with a `.` followed by layout, and is read with the
+/// program's operators. Options:
+///
+/// - `variable_names(Vs)` — Vs is a list of `Name = Var` pairs for the
+///   named variables of the term, in order of first appearance.
+/// - `variables(Vs)` — Vs is the list of those variables.
+///
+/// Throws `syntax_error(Message)` for text that is not a term. `read(-Term)`
+/// is `read_term(Term, [])`.
+pub fn read_term(
+    heap: &mut QueryHeap,
+    _: &mut Hypothesis,
+    goal: usize,
+    predicate_table: &PredicateTable,
+    _: Config,
+) -> PredReturn {
+    let options = if heap[resolve(heap, goal)].1 == 3 {
+        match read_options(heap, goal_arg(heap, goal, 1)) {
+            Ok(options) => options,
+            Err(error) => return error,
+        }
+    } else {
+        Vec::new()
+    };
+
+    let text = match predicate_table.input.next_term_text() {
+        Ok(text) => text.unwrap_or_default(),
+        Err(_) => {
+            let stream = heap.set_const(SymbolDB::set_const("user_input"));
+            return permission_error(heap, "input", "stream", stream);
+        }
+    };
+    let read = match parse_term_text(heap, predicate_table, &text) {
+        Ok(read) => read,
+        Err(error) => return syntax_error(heap, &error.to_string()),
+    };
+    let (term, variable_names) = match read {
+        Some(ReadTerm { addr, variable_names }) => (addr, variable_names),
+        None => (heap.set_const(SymbolDB::set_const("end_of_file")), Vec::new()),
+    };
+
+    let mut values = vec![term];
+    let mut targets = vec![goal_arg(heap, goal, 0)];
+    let vars: Vec<usize> = variable_names.iter().map(|&(_, var)| var).collect();
+    for (option, target) in options {
+        let value = if option == "variables" {
+            build_list_from_addrs(heap, &vars)
+        } else {
+            let eq = SymbolDB::set_const("=");
+            let pairs: Vec<usize> = variable_names
+                .iter()
+                .map(|(name, var)| {
+                    let eq = heap.set_const(eq);
+                    let name = heap.set_const(SymbolDB::set_const(name.as_str()));
+                    build_compound_from_addrs(heap, &[eq, name, *var])
+                })
+                .collect();
+            build_list_from_addrs(heap, &pairs)
+        };
+        values.push(value);
+        targets.push(target);
+    }
+    let values = build_tuple_from_addrs(heap, &values);
+    let targets = build_tuple_from_addrs(heap, &targets);
+    unify_result(heap, values, targets)
+}
+
+// ── Module registration ───────────────────────────────────────────────────────
+
+pub static SYNTAX: PredicateModule = (
+    &[("op", 3, op), ("read_term", 2, read_term), ("read", 1, read_term)],
+    &[],
+);
+
+// ── Tests ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::{helpers::TestWrapper, DATABASE, DEFAULTS, MATHS, META_PREDICATES},
+        SYNTAX,
+    };
+
+    fn tw() -> TestWrapper {
+        TestWrapper::new(&[DEFAULTS, MATHS, META_PREDICATES, DATABASE, SYNTAX])
+    }
+
+    #[test]
+    fn infix_directive() {
+        let mut tw = tw();
+        tw.app = tw
+            .app
+            .load_code(
+                ":- op(700, xfx, =>).
+                 :- op(200, xfy, ::).
+                 rule(a => b).
+                 rule(X :: Y :: Z => X).",
+            )
+            .unwrap();
+        tw.assert_binding("rule(X => b).", ("X", "a"));
+        tw.assert_true("rule(R), R = =>(a, b).");
+        tw.assert_true("rule(a :: B => a), B = ::(_, _).");
+        tw.assert_true("X = (1 :: 2 :: 3), X = ::(1, ::(2, 3)).");
+    }
+
+    #[test]
+    fn priority_and_associativity() {
+        let tw = tw();
+        tw.assert_true("X = (1 - 2 - 3), X = -(-(1, 2), 3).");
+        tw.assert_true("X = (a :- b, c ; d), X = :-(a, ;((b, c), d)).");
+        tw.assert_binding("X is 2 + 3 * 4.", ("X", "14"));
+        tw.assert_binding("X is 10-2-3.", ("X", "5"));
+    }
+
+    #[test]
+    fn op_predicate_applies_to_later_queries() {
+        let tw = tw();
+        tw.assert_true("op(100, yfx, &&).");
+        tw.assert_true("X = (a && b && c), X = &&(&&(a, b), c).");
+        tw.assert_true("op(0, yfx, &&).");
+        assert!(tw.app.query_session("X = (a && b).").is_err());
+    }
+
+    #[test]
+    fn prefix_and_postfix() {
+        let mut tw = tw();
+        tw.app = tw
+            .app
+            .load_code(
+                ":- op(200, fy, ~).
+                 :- op(100, xf, factorial).
+                 :- op(900, fx, [quick, slow]).
+                 neg(~ ~ a).
+                 fact(3 factorial).
+                 speed(quick go).",
+            )
+            .unwrap();
+        tw.assert_true("neg(X), X = ~(~(a)).");
+        tw.assert_true("fact(X), X = factorial(3).");
+        tw.assert_true("speed(X), X = quick(go).");
+        tw.assert_true("X = - 1 + 2, X = +(-(1), 2).");
+        tw.assert_true("X = [-], X = ['-'].");
+        tw.assert_true("\\+ a = b, X = (\\+ a, b), X = (\\+(a), b).");
+        tw.assert_false("\\+ a = a.");
+    }
+
+    #[test]
+    fn op_errors() {
+        let tw = tw();
+        tw.assert_error("op(P, xfx, foo).", "instantiation_error");
+        tw.assert_error("op(700, T, foo).", "instantiation_error");
+        tw.assert_error("op(a, xfx, foo).", "type_error(integer,a)");
+        tw.assert_error("op(1201, xfx, foo).", "domain_error(operator_priority,1201)");
+        tw.assert_error("op(700, abc, foo).", "domain_error(operator_specifier,abc)");
+        tw.assert_error("op(700, xfx, [foo, 1]).", "type_error(atom,1)");
+        tw.assert_true(
+            "catch(op(700, xfx, ','), error(permission_error(modify, operator, Op), _), true),
+             Op == ','.",
+        );
+    }
+
+    // ── read_term/2 ───────────────────────────────────────────────────────
+
+    fn reader(input: &'static str) -> TestWrapper {
+        let mut tw = tw();
+        tw.app = tw.app.input(input.as_bytes());
+        tw
+    }
+
+    #[test]
+    fn read_terms_in_turn() {
+        let tw = reader("foo(X, Y, X).\n'bar baz'. [1,\n 2].\n");
+        tw.assert_true("read(T), T = foo(A, B, C), var(A), var(B), A == C.");
+        tw.assert_binding("read(T).", ("T", "bar baz"));
+        tw.assert_binding("read(T).", ("T", "[1,2]"));
+        tw.assert_binding("read(T).", ("T", "end_of_file"));
+        tw.assert_binding("read(T).", ("T", "end_of_file"));
+    }
+
+    #[test]
+    fn read_term_options() {
+        let tw = reader("p(X, Y, X, _).\nq(Z).\n");
+        tw.assert_bindings(
+            "read_term(T, [variable_names([N1 = V1, N2 = V2])]), T = p(A, B, C, _), V1 == A, V2 == B.",
+            &[("N1", "X"), ("N2", "Y")],
+        );
+        tw.assert_true("read_term(T, [variables([V])]), T == q(V).");
+    }
+
+    #[test]
+    fn read_uses_operators() {
+        let tw = reader("a ===> b.\n");
+        tw.assert_true("op(700, xfx, ===>), read(T), T = ===>(a, b).");
+    }
+
+    #[test]
+    fn read_errors() {
+        let tw = reader("foo(.\nbar.\n");
+        tw.assert_error("read_term(T, [bad]).", "domain_error(read_option,bad)");
+        tw.assert_error("read_term(T, O).", "instantiation_error");
+        tw.assert_error("read(T).", "syntax_error(unexpected end of file)");
+        // Reading goes on after the bad term
+        tw.assert_binding("read(T).", ("T", "bar"));
+    }
+}
@@ -0,0 +1,186 @@
+//! Built-in predicates for tabling.
+//!
+//! `table/1` declares predicates tabled, usually as a `:- table p/N`
+//! directive. Calls to a tabled predicate are answered from answer tables
+//! kept on the predicate table (see [`crate::resolution::tabling`]), so
+//! left-recursive predicates terminate and each call variant is proved only
+//! once.
+
+use super::{helpers::*, PredReturn, PredicateModule};
+use crate::{
+    heap::query_heap::QueryHeap,
+    program::{dynamic::predicate_indicators, hypothesis::Hypothesis, predicate_table::PredicateTable},
+    Config,
+};
+
+/// `table(+Indicators)` — declare the predicates `Name/Arity` tabled.
+/// Indicators may be a single indicator, a comma sequence or a list.
+///
+/// Tables are evaluated without a hypothesis, so tabled calls never learn
+/// clauses. A call made while the hypothesis has clauses for a predicate the
+/// table's evaluation called is resolved against the clauses as usual
+/// instead.
+pub fn table(
+    heap: &mut QueryHeap,
+    _: &mut Hypothesis,
+    goal: usize,
+    predicate_table: &PredicateTable,
+    _: Config,
+) -> PredReturn {
+    let predicates = match predicate_indicators(&*heap, goal_arg(heap, goal, 0)) {
+        Ok(predicates) => predicates,
+        Err(culprit) => return must_be_error(heap, "predicate_indicator", culprit),
+    };
+    for predicate in predicates {
+        predicate_table.tables.declare(predicate);
+    }
+    PredReturn::True
+}
+
+/// `abolish_all_tables` — drop every answer table, so later calls are
+/// evaluated again.
+pub fn abolish_all_tables(
+    _: &mut QueryHeap,
+    _: &mut Hypothesis,
+    _: usize,
+    predicate_table: &PredicateTable,
+    _: Config,
+) -> PredReturn {
+    predicate_table.tables.clear();
+    PredReturn::True
+}
+
+// ── Module registration ───────────────────────────────────────────────────────
+
+pub static TABLING: PredicateModule = (
+    &[("table", 1, table), ("abolish_all_tables", 0, abolish_all_tables)],
+    &[],
+);
+
+// ── Tests ─────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::{helpers::TestWrapper, DATABASE, DEFAULTS, MATHS, META_PREDICATES},
+        TABLING,
+    };
+
+    fn tw(code: &str) -> TestWrapper {
+        let tw = TestWrapper::new(&[DEFAULTS, MATHS, META_PREDICATES, DATABASE, TABLING]);
+        TestWrapper {
+            app: tw.app.load_code(code).unwrap(),
+        }
+    }
+
+    fn sorted(mut values: Vec<String>) -> Vec<String> {
+        values.sort();
+        values
+    }
+
+    #[test]
+    fn left_recursion_terminates() {
+        let tw = tw("
+            :- table path/2.
+            path(X, Y) :- path(X, Z), edge(Z, Y).
+            path(X, Y) :- edge(X, Y).
+            edge(a, b).
+            edge(b, c).
+            edge(c, a).
+            edge(c, d).
+        ");
+        assert_eq!(sorted(tw.all_bindings("path(a, Y).", "Y")), ["a", "b", "c", "d"]);
+        assert_eq!(sorted(tw.all_bindings("path(X, d).", "X")), ["a", "b", "c"]);
+        assert_eq!(tw.all_bindings("path(X, Y).", "X").len(), 12);
+        tw.assert_true("path(d, d) ; true.");
+        tw.assert_false("path(d, _).");
+    }
+
+    #[test]
+    fn mutual_recursion() {
+        let tw = tw("
+            :- table even/1, odd/1.
+            even(z).
+            even(X) :- odd(Y), next(Y, X).
+            odd(X) :- even(Y), next(Y, X).
+            next(z, s1).
+            next(s1, s2).
+            next(s2, s3).
+            next(s3, z).
+        ");
+        assert_eq!(sorted(tw.all_bindings("even(X).", "X")), ["s2", "z"]);
+        assert_eq!(sorted(tw.all_bindings("odd(X).", "X")), ["s1", "s3"]);
+        tw.assert_true("even(s2).");
+        tw.assert_false("odd(s2).");
+    }
+
+    #[test]
+    fn calls_are_proved_once() {
+        let tw = tw("
+            :- dynamic count/1.
+            :- table fib/2.
+            count(0).
+            tick :- retract(count(C)), C1 is C + 1, assertz(count(C1)).
+            fib(0, 0).
+            fib(1, 1).
+            fib(N, F) :-
+                N > 1, tick,
+                N1 is N - 1, N2 is N - 2,
+                fib(N1, F1), fib(N2, F2),
+                F is F1 + F2.
+        ");
+        tw.assert_binding("fib(40, F).", ("F", "102334155"));
+        let count = tw.binding("count(C).", "C").unwrap();
+        tw.assert_binding("fib(40, F).", ("F", "102334155"));
+        tw.assert_binding("fib(30, F).", ("F", "832040"));
+        tw.assert_binding("count(C).", ("C", &count));
+
+        tw.assert_true("abolish_all_tables.");
+        tw.assert_binding("fib(30, F).", ("F", "832040"));
+        assert_ne!(tw.binding("count(C).", "C").unwrap(), count);
+    }
+
+    #[test]
+    fn tables_follow_dynamic_predicates() {
+        let tw = tw("
+            :- dynamic link/2.
+            :- table reach/2.
+            reach(X, Y) :- link(X, Y).
+            reach(X, Y) :- reach(X, Z), link(Z, Y).
+            link(a, b).
+        ");
+        assert_eq!(tw.all_bindings("reach(a, Y).", "Y"), ["b"]);
+        tw.assert_true("assertz(link(b, c)).");
+        assert_eq!(sorted(tw.all_bindings("reach(a, Y).", "Y")), ["b", "c"]);
+    }
+
+    #[test]
+    fn hypothesis_clauses_bypass_tables() {
+        let tw = tw("
+            :- table reach/2.
+            reach(X, Y) :- edge(X, Y).
+            reach(X, Y) :- extra(X, Y).
+            edge(a, b).
+            edge(b, c).
+            P(X, Y) :- edge(X, Z), edge(Z, Y), {P}.
+        ");
+        assert_eq!(tw.all_bindings("reach(a, Y).", "Y"), ["b"]);
+        // The learned clause for extra/2 adds an answer the table lacks
+        tw.assert_true("extra(a, c), reach(a, c).");
+        // Tabled calls are evaluated without learning
+        tw.assert_false("reach(a, c).");
+        assert_eq!(tw.all_bindings("reach(a, Y).", "Y"), ["b"]);
+    }
+
+    #[test]
+    fn exceptions_in_tabled_calls() {
+        let tw = tw("
+            :- table p/1.
+            p(X) :- q(X).
+            p(_) :- throw(oops).
+            q(1).
+        ");
+        tw.assert_true("catch(p(_), oops, true).");
+        tw.assert_error("table(foo).", "type_error(predicate_indicator,foo)");
+    }
+}
@@ -0,0 +1,272 @@
+//! Just-in-time argument indexing of clause heads.
+//!
+//! The first time a predicate is called with a bound argument, an index is
+//! built for that argument position mapping each principal functor to the
+//! clauses whose head could match it. Later calls look the goal's argument up
+//! instead of trying every clause. Other argument positions are indexed the
+//! same way when the first bound argument leaves more than one candidate.
+
+use std::{collections::HashMap, sync::OnceLock};
+
+use crate::heap::heap::{Heap, Tag};
+
+use super::clause::Clause;
+
+/// The principal functor of an argument, as far as indexing is concerned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum ArgKey {
+    Atom(usize),
+    Int(usize),
+    Flt(usize),
+    Functor(usize, usize),
+    Tuple(usize),
+    List,
+    Nil,
+}
+
+impl ArgKey {
+    /// The key of the term at `addr`, or `None` if it is a variable or a
+    /// term that can unify with terms of another key.
+    pub(crate) fn of(heap: &impl Heap, addr: usize) -> Option<ArgKey> {
+        let mut addr = heap.deref_addr(addr);
+        if let (Tag::Str, pointer) = heap[addr] {
+            addr = pointer;
+        }
+        match heap[addr] {
+            (Tag::Con, symbol) => Some(ArgKey::Atom(symbol)),
+            (Tag::Int, bits) => Some(ArgKey::Int(bits)),
+            (Tag::Flt, bits) => Some(ArgKey::Flt(bits)),
+            (Tag::Lis, _) => Some(ArgKey::List),
+            (Tag::ELis, _) => Some(ArgKey::Nil),
+            (Tag::Tup, len) => Some(ArgKey::Tuple(len)),
+            (Tag::Comp, arity) => match heap[heap.deref_addr(addr + 1)] {
+                (Tag::Con, symbol) => Some(ArgKey::Functor(symbol, arity)),
+                _ => None,
+            },
+            // Variables, and big numbers, strings and sets, which are
+            // compared by value or not by key
+            _ => None,
+        }
+    }
+}
+
+/// Index of one argument position. Each key maps to the clauses whose head
+/// argument has that key or no key, in clause order.
+#[derive(Debug, Default)]
+struct ArgIndex {
+    keyed: HashMap<ArgKey, Box<[usize]>>,
+    unkeyed: Box<[usize]>,
+}
+
+impl ArgIndex {
+    fn new(heap: &impl Heap, clauses: &[Clause], arg: usize) -> Self {
+        let keys: Vec<Option<ArgKey>> = clauses
+            .iter()
+            .map(|clause| {
+                head_arg(heap, clause.head(), arg).and_then(|addr| ArgKey::of(heap, addr))
+            })
+            .collect();
+        let mut keyed: HashMap<ArgKey, Vec<usize>> = HashMap::new();
+        for key in keys.iter().flatten() {
+            keyed.entry(*key).or_default();
+        }
+        let mut unkeyed = Vec::new();
+        for (i, key) in keys.iter().enumerate() {
+            match key {
+                Some(key) => keyed.get_mut(key).unwrap().push(i),
+                None => {
+                    unkeyed.push(i);
+                    for clauses in keyed.values_mut() {
+                        clauses.push(i);
+                    }
+                }
+            }
+        }
+        ArgIndex {
+            keyed: keyed
+                .into_iter()
+                .map(|(key, clauses)| (key, clauses.into_boxed_slice()))
+                .collect(),
+            unkeyed: unkeyed.into_boxed_slice(),
+        }
+    }
+
+    fn lookup(&self, key: ArgKey) -> &[usize] {
+        self.keyed.get(&key).unwrap_or(&self.unkeyed)
+    }
+}
+
+/// The clauses picked out by an argument index: those whose argument `arg`
+/// can match the key `key`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Selection {
+    arg: usize,
+    key: ArgKey,
+}
+
+/// The argument indexes of a clause predicate, built on first use.
+#[derive(Debug, Default)]
+pub(crate) struct ClauseIndex {
+    args: Box<[OnceLock<ArgIndex>]>,
+}
+
+impl ClauseIndex {
+    pub(crate) fn new(arity: usize) -> Self {
+        ClauseIndex {
+            args: (0..arity).map(|_| OnceLock::new()).collect(),
+        }
+    }
+
+    /// The selection of clauses whose heads may unify with `goal`, or `None`
+    /// if no bound argument narrows them down.
+    pub(crate) fn select(
+        &self,
+        heap: &impl Heap,
+        clauses: &[Clause],
+        goal: usize,
+    ) -> Option<Selection> {
+        if clauses.len() < 2 {
+            return None;
+        }
+        let mut best: Option<(Selection, usize)> = None;
+        for (arg, index) in self.args.iter().enumerate() {
+            let Some(key) = head_arg(heap, goal, arg).and_then(|addr| ArgKey::of(heap, addr))
+            else {
+                continue;
+            };
+            let count = index
+                .get_or_init(|| ArgIndex::new(heap, clauses, arg))
+                .lookup(key)
+                .len();
+            if best.is_none_or(|(_, best)| count < best) {
+                best = Some((Selection { arg, key }, count));
+            }
+            if count <= 1 {
+                break;
+            }
+        }
+        best.filter(|&(_, count)| count < clauses.len())
+            .map(|(selection, _)| selection)
+    }
+
+    /// The positions, in order, of the clauses picked out by `selection`.
+    pub(crate) fn clauses(&self, selection: Selection) -> &[usize] {
+        self.args[selection.arg]
+            .get()
+            .expect("selection from an index that was reset")
+            .lookup(selection.key)
+    }
+}
+
+/// Address of argument `arg` of the compound term at `addr`.
+fn head_arg(heap: &impl Heap, addr: usize, arg: usize) -> Option<usize> {
+    let mut addr = heap.deref_addr(addr);
+    if let (Tag::Str, pointer) = heap[addr] {
+        addr = pointer;
+    }
+    match heap[addr] {
+        (Tag::Comp, arity) if arg + 1 < arity => Some(addr + arg + 2),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ArgKey, ClauseIndex};
+    use crate::{
+        heap::{
+            heap::{Cell, Heap, Tag},
+            symbol_db::SymbolDB,
+        },
+        parser::{build_tree::TokenStream, execute_tree::execute_tree, tokeniser::tokenise},
+        program::predicate_table::{Predicate, PredicateTable},
+    };
+
+    fn load(text: &str) -> (Vec<Cell>, PredicateTable) {
+        let mut heap = Vec::<Cell>::new();
+        let mut pred_table = PredicateTable::new();
+        let clauses = TokenStream::new(tokenise(text).unwrap())
+            .parse_all()
+            .unwrap();
+        execute_tree(clauses, &mut heap, &mut pred_table);
+        (heap, pred_table)
+    }
+
+    fn select(
+        heap: &impl Heap,
+        pred_table: &PredicateTable,
+        name: &str,
+        arity: usize,
+        goal: usize,
+    ) -> Option<Vec<usize>> {
+        let symbol = SymbolDB::set_const(name);
+        let Some(Predicate::Clauses(clauses)) = pred_table.get_predicate((symbol, arity)) else {
+            panic!()
+        };
+        let index = ClauseIndex::new(arity);
+        index
+            .select(heap, clauses, goal)
+            .map(|selection| index.clauses(selection).to_vec())
+    }
+
+    #[test]
+    fn arg_keys() {
+        let mut heap = Vec::<Cell>::new();
+        let f = SymbolDB::set_const("f");
+        heap.extend([
+            (Tag::Comp, 3),
+            (Tag::Con, f),
+            (Tag::Int, 1),
+            (Tag::Ref, 3),
+            (Tag::Str, 0),
+        ]);
+        assert_eq!(ArgKey::of(&heap, 4), Some(ArgKey::Functor(f, 3)));
+        assert_eq!(ArgKey::of(&heap, 2), Some(ArgKey::Int(1)));
+        assert_eq!(ArgKey::of(&heap, 3), None);
+    }
+
+    #[test]
+    fn first_argument() {
+        let (mut heap, pred_table) = load("p(a,1). p(b,2). p(X,3). p(a,4). p([],5). p([H|T],6).");
+        let [p, a, b, c] = ["p", "a", "b", "c"].map(SymbolDB::set_const);
+
+        let goal = heap.len();
+        heap.extend([
+            (Tag::Comp, 3),
+            (Tag::Con, p),
+            (Tag::Con, a),
+            (Tag::Ref, goal + 3),
+        ]);
+        assert_eq!(
+            select(&heap, &pred_table, "p", 2, goal),
+            Some(vec![0, 2, 3])
+        );
+
+        heap[goal + 2] = (Tag::Con, b);
+        assert_eq!(select(&heap, &pred_table, "p", 2, goal), Some(vec![1, 2]));
+
+        // An unknown key only matches the clauses with a variable argument
+        heap[goal + 2] = (Tag::Con, c);
+        assert_eq!(select(&heap, &pred_table, "p", 2, goal), Some(vec![2]));
+
+        heap[goal + 2] = (Tag::ELis, 0);
+        assert_eq!(select(&heap, &pred_table, "p", 2, goal), Some(vec![2, 4]));
+
+        // An unbound argument can't narrow the clauses
+        heap[goal + 2] = (Tag::Ref, goal + 2);
+        assert_eq!(select(&heap, &pred_table, "p", 2, goal), None);
+    }
+
+    #[test]
+    fn other_arguments() {
+        let (mut heap, pred_table) = load("q(a,x). q(a,y). q(b,x). q(a,z).");
+        let [q, a, y] = ["q", "a", "y"].map(SymbolDB::set_const);
+
+        let goal = heap.len();
+        heap.extend([(Tag::Comp, 3), (Tag::Con, q), (Tag::Con, a), (Tag::Con, y)]);
+        assert_eq!(select(&heap, &pred_table, "q", 2, goal), Some(vec![1]));
+
+        heap[goal + 2] = (Tag::Ref, goal + 2);
+        assert_eq!(select(&heap, &pred_table, "q", 2, goal), Some(vec![1]));
+    }
+}
@@ -0,0 +1,209 @@
+//! Dynamic predicates.
+//!
+//! Clauses of a dynamic predicate can be added and removed while queries run
+//! (`assertz/1`, `retract/1`, ...), so unlike the load-time clauses of the
+//! [`PredicateTable`](super::predicate_table::PredicateTable) they can't live
+//! on the program heap. Each clause instead owns its cells, with the clause
+//! variables numbered as `Arg` cells, and is laid onto the query heap when a
+//! goal calls the predicate.
+//!
+//! Calls follow the logical update view: a goal resolves against the clauses
+//! present when it was first called, whatever is asserted or retracted while
+//! it is still running.
+
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, RwLock,
+    },
+};
+
+use super::{clause::Clause, predicate_table::SymbolArity};
+use crate::heap::{
+    heap::{Cell, Heap, Tag},
+    symbol_db::SymbolDB,
+};
+
+/// A clause of a dynamic predicate, stored independently of any heap.
+#[derive(Debug)]
+pub struct DynamicClause {
+    id: usize,
+    cells: Box<[Cell]>,
+    literals: Box<[usize]>,
+}
+
+impl DynamicClause {
+    /// Copy the clause whose head and body literals are at `literals` in
+    /// `heap`. Unbound variables become clause variables; a clause already
+    /// encoded with `Arg` cells keeps its numbering.
+    pub fn new(heap: &impl Heap, literals: &[usize]) -> Self {
+        let mut cells = Vec::<Cell>::new();
+        let mut ref_map = HashMap::new();
+        let literals = literals
+            .iter()
+            .map(|&literal| {
+                let literal = match heap[heap.deref_addr(literal)] {
+                    (Tag::Str, ptr) => ptr,
+                    _ => heap.deref_addr(literal),
+                };
+                cells.copy_term(heap, literal, &mut ref_map)
+            })
+            .collect();
+
+        // Every copied variable cell refers to the first occurrence of its
+        // variable, which identifies it.
+        let mut args = HashMap::new();
+        for cell in cells.iter_mut() {
+            if let (Tag::Ref, var) = *cell {
+                let next = args.len();
+                *cell = (Tag::Arg, *args.entry(var).or_insert(next));
+            }
+        }
+
+        DynamicClause {
+            id: 0,
+            cells: cells.into_boxed_slice(),
+            literals,
+        }
+    }
+
+    /// Push the clause cells onto `heap`, relocating internal pointers, and
+    /// return the heap address each literal was moved to.
+    fn push_cells<H: Heap>(&self, heap: &mut H, mut push: impl FnMut(&mut H, Cell)) -> Vec<usize> {
+        let base = heap.heap_len();
+        for &(tag, value) in self.cells.iter() {
+            match tag {
+                Tag::Str | Tag::Lis => push(heap, (tag, value + base)),
+                _ => push(heap, (tag, value)),
+            }
+        }
+        self.literals.iter().map(|literal| literal + base).collect()
+    }
+
+    /// Lay the clause onto `heap` as a [`Clause`] for resolution.
+    pub fn load(&self, heap: &mut impl Heap) -> Clause {
+        let literals = self.push_cells(heap, |heap, cell| {
+            heap.heap_push(cell);
+        });
+        Clause::new(literals, None, None)
+    }
+
+    /// Lay the clause onto `heap` as ordinary terms with fresh variables, and
+    /// return the addresses of its head and body literals.
+    pub fn instantiate(&self, heap: &mut impl Heap) -> Vec<usize> {
+        let mut vars = HashMap::new();
+        self.push_cells(heap, |heap, cell| match cell {
+            (Tag::Arg, arg) => {
+                let addr = heap.heap_len();
+                heap.heap_push((Tag::Ref, *vars.entry(arg).or_insert(addr)));
+            }
+            cell => {
+                heap.heap_push(cell);
+            }
+        })
+    }
+
+    /// Identifier used to erase this clause from its predicate.
+    pub fn id(&self) -> usize {
+        self.id
+    }
+}
+
+/// The clauses of all dynamic predicates, shared by every query on an
+/// [`App`](crate::app::App).
+///
+/// The clause lists sit behind a lock so native predicates can change them
+/// through the shared predicate table. Readers take a snapshot of a
+/// predicate's clause list, which later changes never affect.
+#[derive(Debug, Default)]
+pub struct DynamicDatabase {
+    predicates: RwLock<HashMap<SymbolArity, Vec<Arc<DynamicClause>>>>,
+    next_id: AtomicUsize,
+}
+
+impl DynamicDatabase {
+    /// Declare a dynamic predicate. Declaring one twice keeps its clauses.
+    pub fn declare(&self, symbol_arity: SymbolArity) {
+        self.predicates
+            .write()
+            .unwrap()
+            .entry(symbol_arity)
+            .or_default();
+    }
+
+    pub fn is_dynamic(&self, symbol_arity: SymbolArity) -> bool {
+        self.predicates.read().unwrap().contains_key(&symbol_arity)
+    }
+
+    /// Snapshot of the clauses of a dynamic predicate, or `None` if it isn't
+    /// one.
+    pub fn clauses(&self, symbol_arity: SymbolArity) -> Option<Vec<Arc<DynamicClause>>> {
+        self.predicates.read().unwrap().get(&symbol_arity).cloned()
+    }
+
+    /// Add a clause to the end (or with `first`, the start) of a predicate,
+    /// declaring it dynamic if it wasn't already.
+    pub fn add_clause(&self, symbol_arity: SymbolArity, mut clause: DynamicClause, first: bool) {
+        clause.id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let mut predicates = self.predicates.write().unwrap();
+        let clauses = predicates.entry(symbol_arity).or_default();
+        if first {
+            clauses.insert(0, Arc::new(clause));
+        } else {
+            clauses.push(Arc::new(clause));
+        }
+    }
+
+    /// Remove the clause with identifier `id`. Returns false if it had
+    /// already been removed.
+    pub fn erase(&self, symbol_arity: SymbolArity, id: usize) -> bool {
+        let mut predicates = self.predicates.write().unwrap();
+        let Some(clauses) = predicates.get_mut(&symbol_arity) else {
+            return false;
+        };
+        match clauses.iter().position(|clause| clause.id == id) {
+            Some(i) => {
+                clauses.remove(i);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Read the predicate indicators `Name/Arity` in the term at `addr`, which
+/// may also be a comma sequence or list of them, as in `dynamic p/1, q/2`.
+/// Returns the address of the first term that isn't one on failure.
+pub fn predicate_indicators(heap: &impl Heap, addr: usize) -> Result<Vec<SymbolArity>, usize> {
+    let addr = match heap[heap.deref_addr(addr)] {
+        (Tag::Str, ptr) => ptr,
+        _ => heap.deref_addr(addr),
+    };
+    match heap[addr] {
+        (Tag::Tup, _) => {
+            let mut indicators = Vec::new();
+            for element in heap.str_iterator(addr) {
+                indicators.append(&mut predicate_indicators(heap, element)?);
+            }
+            Ok(indicators)
+        }
+        (Tag::Lis, ptr) => {
+            let mut indicators = predicate_indicators(heap, ptr)?;
+            indicators.append(&mut predicate_indicators(heap, ptr + 1)?);
+            Ok(indicators)
+        }
+        (Tag::ELis, _) => Ok(Vec::new()),
+        (Tag::Comp, 3) if heap[addr + 1] == (Tag::Con, SymbolDB::set_const("/")) => {
+            let name = heap[heap.deref_addr(addr + 2)];
+            let arity = heap[heap.deref_addr(addr + 3)];
+            match (name, arity) {
+                ((Tag::Con, symbol), (Tag::Int, arity)) if (arity as isize) >= 0 => {
+                    Ok(vec![(symbol, arity)])
+                }
+                _ => Err(addr),
+            }
+        }
+        _ => Err(addr),
+    }
+}
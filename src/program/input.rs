@@ -0,0 +1,144 @@
+//! Where `read_term/2` reads from.
+//!
+//! The program's [`Input`] reads from standard input unless a source is set
+//! with [`App::input`](crate::app::App::input), so library users and tests
+//! can supply the text instead. Terms are taken from it one at a time, each
+//! ending with a `.` followed by layout or the end of the input.
+
+use std::{
+    fmt,
+    io::{self, BufRead, BufReader},
+    sync::Mutex,
+};
+
+use crate::parser::operators::SYMBOL_CHARS;
+
+/// Byte offset just past the end token `.` of the first term in `text`, if
+/// there is a complete one. A `.` only ends a term when it is not part of a
+/// longer symbolic atom like `=..`, is outside quotes and comments, and is
+/// followed by layout or a `%` comment.
+fn term_end(text: &str) -> Option<usize> {
+    let mut chars = text.char_indices().peekable();
+    let mut previous = ' ';
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\'' | '"' | '`' => {
+                // Skip to the closing quote, passing over escapes
+                while let Some((_, q)) = chars.next() {
+                    if q == '\\' {
+                        chars.next();
+                    } else if q == c {
+                        break;
+                    }
+                }
+            }
+            '%' => {
+                while chars.next_if(|&(_, c)| c != '\n').is_some() {}
+            }
+            '/' if chars.peek().is_some_and(|&(_, c)| c == '*') => {
+                chars.next();
+                let mut last = ' ';
+                for (_, c) in chars.by_ref() {
+                    if last == '*' && c == '/' {
+                        break;
+                    }
+                    last = c;
+                }
+            }
+            '.' if !SYMBOL_CHARS.contains(&previous)
+                && chars
+                    .peek()
+                    .is_some_and(|&(_, next)| next.is_whitespace() || next == '%') =>
+            {
+                return Some(i + 1);
+            }
+            _ => {}
+        }
+        previous = c;
+    }
+    None
+}
+
+struct InputState {
+    source: Box<dyn BufRead + Send>,
+    /// Text read from the source but not yet taken as a term.
+    pending: String,
+}
+
+/// The input of a program, shared by every query on an
+/// [`App`](crate::app::App).
+pub struct Input {
+    state: Mutex<InputState>,
+}
+
+impl Default for Input {
+    fn default() -> Self {
+        Input {
+            state: Mutex::new(InputState {
+                source: Box::new(BufReader::new(io::stdin())),
+                pending: String::new(),
+            }),
+        }
+    }
+}
+
+impl fmt::Debug for Input {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Input").finish_non_exhaustive()
+    }
+}
+
+impl Input {
+    /// Read later input from `source`.
+    pub fn set_source(&self, source: Box<dyn BufRead + Send>) {
+        let mut state = self.state.lock().unwrap();
+        state.source = source;
+        state.pending.clear();
+    }
+
+    /// The text of the next term, up to and including its end `.`, reading
+    /// more lines as needed. `None` at the end of the input, when only
+    /// layout is left. Text left at the end of the input without an end
+    /// `.` is returned as it is, for the parser to reject.
+    pub fn next_term_text(&self) -> io::Result<Option<String>> {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if let Some(end) = term_end(&state.pending) {
+                let rest = state.pending.split_off(end);
+                return Ok(Some(std::mem::replace(&mut state.pending, rest)));
+            }
+            let InputState { source, pending } = &mut *state;
+            if source.read_line(pending)? == 0 {
+                let text = std::mem::take(pending);
+                return Ok((!text.trim().is_empty()).then_some(text));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{term_end, Input};
+
+    #[test]
+    fn finds_term_end() {
+        assert_eq!(term_end("a. b."), Some(2));
+        assert_eq!(term_end("X =.. Y.\n"), Some(8));
+        assert_eq!(term_end("'a. b'. c"), Some(7));
+        assert_eq!(term_end("f(\"x. y\").%c"), Some(10));
+        assert_eq!(term_end("a % b. c\n.\n"), Some(10));
+        assert_eq!(term_end("a /* b. */ .\n"), Some(12));
+        assert_eq!(term_end("a."), None);
+        assert_eq!(term_end("1.5 "), None);
+    }
+
+    #[test]
+    fn reads_terms_in_turn() {
+        let input = Input::default();
+        input.set_source(Box::new("a. b(X,\n  Y).\nc".as_bytes()));
+        assert_eq!(input.next_term_text().unwrap().as_deref(), Some("a."));
+        assert_eq!(input.next_term_text().unwrap().as_deref(), Some(" b(X,\n  Y)."));
+        assert_eq!(input.next_term_text().unwrap().as_deref(), Some("\nc"));
+        assert_eq!(input.next_term_text().unwrap(), None);
+    }
+}
@@ -2,9 +2,22 @@
 //!
 //! A program consists of a [`PredicateTable`](crate::program::predicate_table::PredicateTable)
 //! mapping symbol/arity pairs to either sets of [`Clause`](crate::program::clause::Clause)s or
-//! built-in predicate functions. During proof search, learned clauses are
-//! collected in a [`Hypothesis`](crate::program::hypothesis::Hypothesis).
+//! built-in predicate functions, with the clauses of dynamic predicates held in its
+//! [`DynamicDatabase`](crate::program::dynamic::DynamicDatabase) and the answers of
+//! tabled predicates in [`Tables`](crate::program::tabling::Tables). Text written by
+//! the program goes through its [`Output`](crate::program::output::Output), and terms it
+//! reads come from its [`Input`](crate::program::input::Input). During proof
+//! search, learned clauses are collected in a
+//! [`Hypothesis`](crate::program::hypothesis::Hypothesis).
+//!
+//! Clause predicates are indexed on their arguments as they are called, so
+//! that choice gathering only returns the clauses whose heads can match.
 
 pub mod clause;
+pub(crate) mod clause_index;
+pub mod dynamic;
 pub mod hypothesis;
+pub mod input;
+pub mod output;
 pub mod predicate_table;
+pub mod tabling;
@@ -0,0 +1,146 @@
+//! Where the text written by Prolog goes.
+//!
+//! The output predicates (`write/1`, `format/2`, ...) write through the
+//! [`Output`] of the predicate table, which passes text on to an
+//! [`OutputSink`]. The sink is standard output unless one is set with
+//! [`App::output`](crate::app::App::output), so library users and tests can
+//! capture the output instead. `with_output_to/2` and `format/3` redirect
+//! output into a capture buffer for the length of a goal.
+
+use std::{
+    fmt,
+    io::{self, Write},
+    sync::{Arc, Mutex},
+};
+
+/// A destination for the text written by Prolog.
+pub trait OutputSink: Send {
+    fn write_text(&mut self, text: &str);
+}
+
+impl OutputSink for io::Stdout {
+    fn write_text(&mut self, text: &str) {
+        let mut stdout = self.lock();
+        let _ = stdout.write_all(text.as_bytes());
+        let _ = stdout.flush();
+    }
+}
+
+impl OutputSink for io::Stderr {
+    fn write_text(&mut self, text: &str) {
+        let _ = self.write_all(text.as_bytes());
+    }
+}
+
+/// An [`OutputSink`] collecting text in memory. Clones share the same
+/// buffer, so one clone can be given to an [`App`](crate::app::App) and the
+/// other used to read what was written.
+#[derive(Clone, Debug, Default)]
+pub struct OutputBuffer(Arc<Mutex<String>>);
+
+impl OutputBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Everything written so far.
+    pub fn contents(&self) -> String {
+        self.0.lock().unwrap().clone()
+    }
+
+    /// Everything written so far, leaving the buffer empty.
+    pub fn take(&self) -> String {
+        std::mem::take(&mut self.0.lock().unwrap())
+    }
+}
+
+impl OutputSink for OutputBuffer {
+    fn write_text(&mut self, text: &str) {
+        self.0.lock().unwrap().push_str(text);
+    }
+}
+
+/// Column reached after writing `text` from column `column`.
+fn advance_column(column: usize, text: &str) -> usize {
+    match text.rfind('\n') {
+        Some(newline) => text[newline + 1..].chars().count(),
+        None => column + text.chars().count(),
+    }
+}
+
+struct OutputState {
+    sink: Box<dyn OutputSink>,
+    /// Column of the sink's current line, for `format/2` column stops.
+    column: usize,
+    /// Capture buffers of the enclosing `with_output_to/2` calls, innermost
+    /// last. Output goes to the innermost one while there are any.
+    captures: Vec<String>,
+}
+
+/// The output of a program, shared by every query on an
+/// [`App`](crate::app::App).
+pub struct Output {
+    state: Mutex<OutputState>,
+}
+
+impl Default for Output {
+    fn default() -> Self {
+        Output {
+            state: Mutex::new(OutputState {
+                sink: Box::new(io::stdout()),
+                column: 0,
+                captures: Vec::new(),
+            }),
+        }
+    }
+}
+
+impl fmt::Debug for Output {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Output").finish_non_exhaustive()
+    }
+}
+
+impl Output {
+    /// Send later output to `sink`.
+    pub fn set_sink(&self, sink: Box<dyn OutputSink>) {
+        let mut state = self.state.lock().unwrap();
+        state.sink = sink;
+        state.column = 0;
+    }
+
+    pub fn write(&self, text: &str) {
+        let mut state = self.state.lock().unwrap();
+        match state.captures.last_mut() {
+            Some(capture) => capture.push_str(text),
+            None => {
+                state.sink.write_text(text);
+                state.column = advance_column(state.column, text);
+            }
+        }
+    }
+
+    /// Column of the current output line.
+    pub fn column(&self) -> usize {
+        let state = self.state.lock().unwrap();
+        match state.captures.last() {
+            Some(capture) => advance_column(0, capture),
+            None => state.column,
+        }
+    }
+
+    /// Capture output until the matching [`end_capture`](Output::end_capture).
+    pub fn begin_capture(&self) {
+        self.state.lock().unwrap().captures.push(String::new());
+    }
+
+    /// Stop the innermost capture, returning what it collected.
+    pub fn end_capture(&self) -> String {
+        self.state
+            .lock()
+            .unwrap()
+            .captures
+            .pop()
+            .unwrap_or_default()
+    }
+}
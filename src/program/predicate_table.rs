@@ -1,11 +1,20 @@
 use std::{
     cmp::Ordering,
     ops::{Deref, DerefMut},
+    sync::{Arc, RwLock},
 };
 
-use crate::predicate_modules::PredicateFunction;
+use crate::{
+    heap::{heap::Heap, symbol_db::SymbolDB},
+    parser::operators::Operators,
+    predicate_modules::{maths::ArithmeticFunctions, PredicateFunction},
+};
 
-use super::clause::Clause;
+use super::{
+    clause::Clause,
+    clause_index::{ClauseIndex, Selection},
+    dynamic::DynamicDatabase, input::Input, output::Output, tabling::Tables,
+};
 
 /// A `(symbol_id, arity)` pair identifying a predicate.
 pub(crate) type SymbolArity = (usize, usize);
@@ -21,20 +30,34 @@ pub enum Predicate {
 }
 
 /// Internal entry in the predicate table.
-#[derive(PartialEq, Eq, Debug)]
+#[derive(Debug)]
 pub struct PredicateEntry {
     symbol_arity: SymbolArity,
     predicate: Predicate,
+    index: ClauseIndex,
 }
 
 /// The program's predicate table.
 ///
 /// Maps `(symbol, arity)` pairs to predicates (clause sets or built-in functions).
 /// Also tracks which predicates are designated as body predicates for MIL learning.
-#[derive(Debug, PartialEq)]
+/// Dynamic predicates are kept apart in [`DynamicDatabase`], as queries may change them.
+/// The answer [`Tables`] of tabled predicates last for the life of the table.
+/// The program's [`Operators`] live here too, behind a lock so `op/3` can
+/// change them from a query, and so do the [`Output`] that Prolog writes to
+/// and the [`Input`] it reads from. Every symbol of the program is interned
+/// in its own [`SymbolDB`], which must be entered while the program is used.
+#[derive(Debug)]
 pub struct PredicateTable {
     predicates: Vec<PredicateEntry>,
     body_list: Vec<usize>,
+    pub(crate) dynamic: DynamicDatabase,
+    pub(crate) tables: Tables,
+    pub(crate) operators: RwLock<Operators>,
+    pub(crate) output: Output,
+    pub(crate) input: Input,
+    pub(crate) functions: ArithmeticFunctions,
+    pub(crate) symbols: Arc<SymbolDB>,
 }
 
 //Return type for binary search of predicate keys
@@ -46,9 +69,18 @@ enum FindReturn {
 
 impl PredicateTable {
     pub fn new() -> Self {
+        let symbols = Arc::new(SymbolDB::new());
+        let _scope = symbols.enter();
         PredicateTable {
             predicates: vec![],
             body_list: vec![],
+            dynamic: DynamicDatabase::default(),
+            tables: Tables::default(),
+            operators: RwLock::default(),
+            output: Output::default(),
+            input: Input::default(),
+            functions: ArithmeticFunctions::default(),
+            symbols,
         }
     }
 
@@ -89,6 +121,7 @@ impl PredicateTable {
                     PredicateEntry {
                         symbol_arity,
                         predicate: Predicate::Function(predicate_fn),
+                        index: ClauseIndex::default(),
                     },
                 );
                 Ok(())
@@ -102,19 +135,28 @@ impl PredicateTable {
         clause: Clause,
         symbol_arity: SymbolArity,
     ) -> Result<(), &str> {
+        // Answers computed before the new clause may be missing some
+        self.tables.clear();
         match self.find_predicate(symbol_arity) {
-            FindReturn::Index(idx) => match &mut self.get_mut(idx).unwrap().predicate {
-                Predicate::Function(_) => return Err("Cannot add clause to function predicate"),
-                Predicate::Clauses(clauses) => {
-                    *clauses = [&**clauses, &[clause]].concat().into_boxed_slice();
+            FindReturn::Index(idx) => {
+                let entry = self.get_mut(idx).unwrap();
+                match &mut entry.predicate {
+                    Predicate::Function(_) => {
+                        return Err("Cannot add clause to function predicate")
+                    }
+                    Predicate::Clauses(clauses) => {
+                        *clauses = [&**clauses, &[clause]].concat().into_boxed_slice();
+                        entry.index = ClauseIndex::new(symbol_arity.1);
+                    }
                 }
-            },
+            }
             FindReturn::InsertPos(insert_idx) => {
                 self.insert(
                     insert_idx,
                     PredicateEntry {
                         symbol_arity,
                         predicate: Predicate::Clauses(Box::new([clause])),
+                        index: ClauseIndex::new(symbol_arity.1),
                     },
                 );
             }
@@ -130,6 +172,51 @@ impl PredicateTable {
         }
     }
 
+    /// A cursor over the clauses of `symbol_arity` whose heads may unify
+    /// with `goal`, narrowed by the argument indexes.
+    pub(crate) fn clause_cursor(
+        &self,
+        symbol_arity: SymbolArity,
+        heap: &impl Heap,
+        goal: usize,
+    ) -> ClauseCursor {
+        let entry = match self.find_predicate(symbol_arity) {
+            FindReturn::Index(i) => match &self[i].predicate {
+                Predicate::Clauses(clauses) => Some((i, self[i].index.select(heap, clauses, goal))),
+                Predicate::Function(_) => None,
+            },
+            FindReturn::InsertPos(_) => None,
+        };
+        ClauseCursor {
+            entry,
+            ..Default::default()
+        }
+    }
+
+    /// A cursor over the variable predicate clauses of `arity`, followed by
+    /// the clauses of the body predicates if `body` is set.
+    pub(crate) fn variable_cursor(&self, arity: usize, body: bool) -> ClauseCursor {
+        let entry = match self.find_predicate((0, arity)) {
+            FindReturn::Index(i) if matches!(self[i].predicate, Predicate::Clauses(_)) => {
+                Some((i, None))
+            }
+            _ => None,
+        };
+        ClauseCursor {
+            entry,
+            body_arity: body.then_some(arity),
+            ..Default::default()
+        }
+    }
+
+    /// The clauses of the entry at `idx`.
+    fn entry_clauses(&self, idx: usize) -> &[Clause] {
+        match &self[idx].predicate {
+            Predicate::Clauses(clauses) => clauses,
+            Predicate::Function(_) => &[],
+        }
+    }
+
     pub fn get_variable_clauses(&self, arity: usize) -> Option<&Box<[Clause]>> {
         match self.find_predicate((0, arity)) {
             FindReturn::Index(i) => match &self[i].predicate {
@@ -193,6 +280,91 @@ impl PredicateTable {
     }
 }
 
+/// A position in a run of program clauses. Choice points hold a cursor
+/// rather than copies of the clauses, reading each clause from the table as
+/// it is tried.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct ClauseCursor {
+    /// The entry whose clauses come first, and the argument index selection
+    /// narrowing them.
+    entry: Option<(usize, Option<Selection>)>,
+    /// The arity of the body predicates whose clauses follow, if any.
+    body_arity: Option<usize>,
+    /// Position in the body list of the body predicate being read.
+    body: usize,
+    /// Position of the next clause of the entry or body predicate.
+    next: usize,
+}
+
+impl ClauseCursor {
+    /// The next clause, moving past it.
+    pub(crate) fn next<'a>(&mut self, table: &'a PredicateTable) -> Option<&'a Clause> {
+        if let Some((entry, selection)) = self.entry {
+            let clauses = table.entry_clauses(entry);
+            let clause = match selection {
+                Some(selection) => table[entry]
+                    .index
+                    .clauses(selection)
+                    .get(self.next)
+                    .map(|&i| &clauses[i]),
+                None => clauses.get(self.next),
+            };
+            if clause.is_some() {
+                self.next += 1;
+                return clause;
+            }
+            self.entry = None;
+            self.next = 0;
+        }
+        let arity = self.body_arity?;
+        while let Some(&entry) = table.body_list.get(self.body) {
+            if table[entry].symbol_arity.1 == arity {
+                if let Some(clause) = table.entry_clauses(entry).get(self.next) {
+                    self.next += 1;
+                    return Some(clause);
+                }
+            }
+            self.body += 1;
+            self.next = 0;
+        }
+        self.body_arity = None;
+        None
+    }
+
+    /// The number of clauses left to read.
+    pub(crate) fn remaining(&self, table: &PredicateTable) -> usize {
+        let mut count = 0;
+        let mut next = self.next;
+        if let Some((entry, selection)) = self.entry {
+            count += match selection {
+                Some(selection) => table[entry].index.clauses(selection).len(),
+                None => table.entry_clauses(entry).len(),
+            } - next;
+            next = 0;
+        }
+        if let Some(arity) = self.body_arity {
+            for &entry in &table.body_list[self.body.min(table.body_list.len())..] {
+                if table[entry].symbol_arity.1 == arity {
+                    count += table.entry_clauses(entry).len().saturating_sub(next);
+                }
+                next = 0;
+            }
+        }
+        count
+    }
+
+    /// Whether every clause has been read.
+    pub(crate) fn is_done(&self, table: &PredicateTable) -> bool {
+        self.clone().next(table).is_none()
+    }
+
+    /// Skip every remaining clause.
+    pub(crate) fn exhaust(&mut self) {
+        self.entry = None;
+        self.body_arity = None;
+    }
+}
+
 impl Deref for PredicateTable {
     type Target = Vec<PredicateEntry>;
 
@@ -209,7 +381,10 @@ impl DerefMut for PredicateTable {
 
 #[cfg(test)]
 mod tests {
-    use super::{super::clause::Clause, Predicate, PredicateEntry, PredicateTable};
+    use super::{
+        super::{clause::Clause, clause_index::ClauseIndex},
+        Predicate, PredicateEntry, PredicateTable,
+    };
     use crate::{
         heap::{query_heap::QueryHeap, symbol_db::SymbolDB},
         predicate_modules::PredReturn,
@@ -240,6 +415,7 @@ mod tests {
                 Clause::new(vec![15, 19], None, None),
                 Clause::new(vec![23, 27], None, None),
             ])),
+            index: ClauseIndex::new(2),
         };
         let q_entry = PredicateEntry {
             symbol_arity: (q, 2),
@@ -247,10 +423,12 @@ mod tests {
                 Clause::new(vec![31, 35], None, None),
                 Clause::new(vec![39, 43], None, None),
             ])),
+            index: ClauseIndex::new(2),
         };
         let func_entry = PredicateEntry {
             symbol_arity: (pred_func, 2),
             predicate: Predicate::Function(pred_fn_placeholder),
+            index: ClauseIndex::default(),
         };
         let zero_entry = PredicateEntry {
             symbol_arity: (0, 2),
@@ -258,6 +436,7 @@ mod tests {
                 Clause::new(vec![0, 3], Some(vec![0, 1]), None),
                 Clause::new(vec![7, 11], Some(vec![0]), None),
             ])),
+            index: ClauseIndex::new(2),
         };
 
         let mut predicates = vec![zero_entry, p_entry, q_entry, func_entry];
@@ -273,6 +452,13 @@ mod tests {
             PredicateTable {
                 predicates,
                 body_list: vec![p_idx],
+                dynamic: Default::default(),
+                tables: Default::default(),
+                operators: Default::default(),
+                output: Default::default(),
+                input: Default::default(),
+                functions: Default::default(),
+                symbols: SymbolDB::current(),
             },
             p,
             q,
@@ -309,10 +495,7 @@ mod tests {
             FindReturn::InsertPos(p_idx)
         );
 
-        let pred_table = PredicateTable {
-            predicates: vec![],
-            body_list: vec![],
-        };
+        let pred_table = PredicateTable::new();
 
         assert_eq!(pred_table.find_predicate((50, 2)), FindReturn::InsertPos(0));
     }
@@ -464,4 +647,33 @@ mod tests {
         assert!(body2_ext.contains(&&Clause::new(vec![31, 35], None, None)));
         assert!(body2_ext.contains(&&Clause::new(vec![39, 43], None, None)));
     }
+
+    #[test]
+    fn variable_cursor() {
+        let (pred_table, _p, _q, _pred_func) = setup();
+
+        // Variable predicate clauses come first, then the body clauses
+        let mut cursor = pred_table.variable_cursor(2, true);
+        assert_eq!(cursor.remaining(&pred_table), 4);
+        let clauses: Vec<&Clause> = std::iter::from_fn(|| cursor.next(&pred_table)).collect();
+        assert_eq!(
+            clauses,
+            vec![
+                &Clause::new(vec![0, 3], Some(vec![0, 1]), None),
+                &Clause::new(vec![7, 11], Some(vec![0]), None),
+                &Clause::new(vec![15, 19], None, None),
+                &Clause::new(vec![23, 27], None, None),
+            ]
+        );
+        assert_eq!(cursor.remaining(&pred_table), 0);
+
+        let mut cursor = pred_table.variable_cursor(2, false);
+        cursor.next(&pred_table);
+        assert_eq!(cursor.remaining(&pred_table), 1);
+        cursor.exhaust();
+        assert_eq!(cursor.next(&pred_table), None);
+
+        let mut cursor = pred_table.variable_cursor(1, true);
+        assert_eq!(cursor.next(&pred_table), None);
+    }
 }
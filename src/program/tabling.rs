@@ -0,0 +1,213 @@
+//! Answer tables for tabled predicates.
+//!
+//! A predicate declared with `:- table p/N` is resolved by SLG-style
+//! tabling: each call variant is evaluated once, its answers collected into
+//! an [`AnswerTable`], and later calls of the same variant read the table
+//! instead of resolving against the clauses again. The evaluation itself
+//! lives in [`crate::resolution::tabling`]; this module holds the tables.
+//!
+//! Tables are evaluated without a hypothesis, and each records the
+//! predicates its evaluation called as a [`Footprint`]. A call made while
+//! the hypothesis has clauses for any of those predicates can't use the
+//! table, since those clauses could add answers.
+
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap, HashSet},
+    hash::{Hash, Hasher},
+    sync::{Arc, RwLock},
+};
+
+use super::{hypothesis::Hypothesis, predicate_table::SymbolArity};
+use crate::{
+    heap::{
+        heap::{Cell, Heap, Tag},
+        symbol_db::SymbolDB,
+    },
+    predicate_modules::helpers::copy_term_cell,
+};
+
+/// A term stored independently of any heap, with its variables numbered as
+/// `Arg` cells in order of appearance. The root cell is the last one.
+pub type Variant = Box<[Cell]>;
+
+/// Copy the term at `addr` in `heap` as a [`Variant`].
+pub fn variant(heap: &impl Heap, addr: usize) -> Variant {
+    let mut cells: Vec<Cell> = Vec::new();
+    copy_term_cell(&mut cells, heap, addr, &mut HashMap::new());
+    // Every copied variable cell refers to the first occurrence of its
+    // variable, which identifies it.
+    let mut args = HashMap::new();
+    for cell in cells.iter_mut() {
+        if let (Tag::Ref, var) = *cell {
+            let next = args.len();
+            *cell = (Tag::Arg, *args.entry(var).or_insert(next));
+        }
+    }
+    cells.into_boxed_slice()
+}
+
+/// A key identifying a variant up to variable renaming. String and big
+/// number cells are keyed by their value, as equal values can have different
+/// ids.
+pub fn variant_key(variant: &[Cell]) -> Variant {
+    variant
+        .iter()
+        .map(|&cell| match cell {
+            (Tag::Stri, id) => {
+                let mut hasher = DefaultHasher::new();
+                SymbolDB::get_string(id).hash(&mut hasher);
+                (Tag::Stri, hasher.finish() as usize)
+            }
+            (Tag::BigInt, id) => {
+                let mut hasher = DefaultHasher::new();
+                SymbolDB::get_big_int(id).hash(&mut hasher);
+                (Tag::BigInt, hasher.finish() as usize)
+            }
+            (Tag::Rat, id) => {
+                let mut hasher = DefaultHasher::new();
+                SymbolDB::get_rational(id).hash(&mut hasher);
+                (Tag::Rat, hasher.finish() as usize)
+            }
+            cell => cell,
+        })
+        .collect()
+}
+
+/// Lay `variant` onto `heap` with fresh variables and return the address of
+/// its root cell.
+fn instantiate(variant: &[Cell], heap: &mut impl Heap) -> usize {
+    let base = heap.heap_len();
+    let mut vars = HashMap::new();
+    for &(tag, value) in variant {
+        match tag {
+            Tag::Str | Tag::Lis => heap.heap_push((tag, value + base)),
+            Tag::Arg => {
+                let addr = heap.heap_len();
+                heap.heap_push((Tag::Ref, *vars.entry(value).or_insert(addr)))
+            }
+            _ => heap.heap_push((tag, value)),
+        };
+    }
+    heap.heap_len() - 1
+}
+
+/// The predicates a table's evaluation called. `open` marks an evaluation
+/// that called a goal with a variable predicate, which any hypothesis
+/// clause could match.
+#[derive(Debug, Default, Clone)]
+pub struct Footprint {
+    predicates: HashSet<SymbolArity>,
+    open: bool,
+}
+
+impl Footprint {
+    pub fn add(&mut self, symbol_arity: SymbolArity) {
+        self.predicates.insert(symbol_arity);
+    }
+
+    pub fn set_open(&mut self) {
+        self.open = true;
+    }
+
+    pub fn merge(&mut self, other: &Footprint) {
+        self.predicates.extend(&other.predicates);
+        self.open |= other.open;
+    }
+
+    pub fn contains(&self, symbol_arity: SymbolArity) -> bool {
+        self.predicates.contains(&symbol_arity)
+    }
+
+    /// Whether clauses of `hypothesis` could resolve goals of the evaluation.
+    pub fn depends_on(&self, heap: &impl Heap, hypothesis: &Hypothesis) -> bool {
+        if hypothesis.is_empty() {
+            return false;
+        }
+        self.open
+            || hypothesis.iter().any(|clause| {
+                let symbol_arity = heap.str_symbol_arity(clause.head());
+                symbol_arity.0 == 0 || self.contains(symbol_arity)
+            })
+    }
+}
+
+/// The answers found for one call variant.
+#[derive(Debug, Default)]
+pub struct AnswerTable {
+    answers: Vec<Variant>,
+    keys: HashSet<Variant>,
+    pub footprint: Footprint,
+}
+
+impl AnswerTable {
+    /// Add the term at `addr` as an answer. Returns false if it is a variant
+    /// of an answer already in the table.
+    pub fn insert(&mut self, heap: &impl Heap, addr: usize) -> bool {
+        let answer = variant(heap, addr);
+        if self.keys.insert(variant_key(&answer)) {
+            self.answers.push(answer);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Lay every answer onto `heap`, returning their addresses in the order
+    /// they were found.
+    pub fn load(&self, heap: &mut impl Heap) -> Vec<usize> {
+        self.answers
+            .iter()
+            .map(|answer| instantiate(answer, heap))
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.answers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.answers.is_empty()
+    }
+}
+
+/// The tabled predicates of a program and the completed answer tables of
+/// their calls, shared by every query on an [`App`](crate::app::App).
+#[derive(Debug, Default)]
+pub struct Tables {
+    tabled: RwLock<HashSet<SymbolArity>>,
+    complete: RwLock<HashMap<Variant, Arc<AnswerTable>>>,
+}
+
+impl Tables {
+    pub fn declare(&self, symbol_arity: SymbolArity) {
+        self.tabled.write().unwrap().insert(symbol_arity);
+    }
+
+    pub fn is_tabled(&self, symbol_arity: SymbolArity) -> bool {
+        self.tabled.read().unwrap().contains(&symbol_arity)
+    }
+
+    /// The completed table for the call with [`variant_key`] `key`, if
+    /// there is one.
+    pub fn get(&self, key: &[Cell]) -> Option<Arc<AnswerTable>> {
+        self.complete.read().unwrap().get(key).cloned()
+    }
+
+    pub fn complete(&self, key: Variant, table: AnswerTable) {
+        self.complete.write().unwrap().insert(key, Arc::new(table));
+    }
+
+    /// Drop every table whose evaluation called the predicate
+    /// `symbol_arity`, whose clauses have changed.
+    pub fn invalidate(&self, symbol_arity: SymbolArity) {
+        self.complete
+            .write()
+            .unwrap()
+            .retain(|_, table| !table.footprint.contains(symbol_arity));
+    }
+
+    /// Drop every table.
+    pub fn clear(&self) {
+        self.complete.write().unwrap().clear();
+    }
+}
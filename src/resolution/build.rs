@@ -33,7 +33,7 @@ pub fn build(
     src_addr: usize,
 ) -> usize {
     match heap[heap.deref_addr(src_addr)] {
-        (tag @ (Tag::Con | Tag::Flt | Tag::Int | Tag::Stri | Tag::ELis | Tag::Ref| Tag::AVar), value) => {
+        (tag @ (Tag::Con | Tag::Flt | Tag::Int | Tag::BigInt | Tag::Rat | Tag::Stri | Tag::ELis | Tag::Ref| Tag::AVar), value) => {
             heap.heap_push((tag, value))
         }
         (Tag::Arg, _arg_id) => build_arg(heap, substitution, meta_vars, src_addr),
@@ -129,13 +129,10 @@ fn build_complex_term(
         }
         (Tag::Str, ptr) => Some((Tag::Str, build_str(heap, substitution, meta_vars, ptr))),
         (Tag::Lis, ptr) => Some((Tag::Lis, build_list(heap, substitution, meta_vars, ptr))),
-        (Tag::Arg, id) => match meta_vars {
-            Some(bitflags) if !bitflags.get(id) => None,
-            _ => match substitution.get_arg(id) {
-                Some(addr) => build_complex_term(heap, substitution, meta_vars, addr),
-                None => None,
-            },
-        },
+        // An arg is bound to a goal term, which is referenced rather than
+        // copied: copying would rebuild a list passed down a recursion at
+        // every call
+        (Tag::Arg, _) => None,
         (Tag::Ref, ref_addr) if ref_addr == src_addr => {
             if let Some(bound_addr) = substitution.bound(ref_addr) {
                 build_complex_term(heap, substitution, meta_vars, bound_addr)
@@ -212,16 +209,10 @@ mod tests {
         substitution = Substitution::default();
         substitution.set_arg(0, 0);
         let addr = build(&mut heap, &mut substitution, None, 3);
+        // The bound term is referenced, not copied
         assert_eq!(
-            heap[addr - 3..(addr + 3)],
-            [
-                (Tag::Comp, 2),
-                (Tag::Con, f),
-                (Tag::Ref, 2),
-                (Tag::Comp, 2),
-                (Tag::Con, p),
-                (Tag::Str, addr - 3),
-            ]
+            heap[addr..(addr + 3)],
+            [(Tag::Comp, 2), (Tag::Con, p), (Tag::Ref, 0)]
         );
     }
 
@@ -4,22 +4,29 @@
 //! enum separates clause-based resolution from native predicate evaluation,
 //! keeping the two execution paths explicit at the type level.
 
+use std::{borrow::Cow, ops::Range};
+
 use smallvec::SmallVec;
 
 use crate::{
     heap::{
-        heap::{Heap, Tag},
+        gc::{Marker, Relocation},
+        heap::{Cell, Heap, Tag},
         query_heap::QueryHeap,
-        symbol_db::SymbolDB,
+        symbol_db::{known_symbol_id, SymbolDB},
+    },
+    predicate_modules::{
+        helpers::{apply_closure, must_be_error},
+        PredReturn, PredicateFunction,
     },
-    predicate_modules::{PredReturn, PredicateFunction},
     program::{
         clause::Clause,
         hypothesis::Hypothesis,
-        predicate_table::{Predicate, PredicateTable},
+        predicate_table::{ClauseCursor, Predicate, PredicateTable},
     },
     resolution::{
         build::{build, re_build_bound_arg_terms},
+        tabling,
         unification::unify,
     },
     Config,
@@ -28,13 +35,98 @@ use crate::{
 /// A variable binding: `(source_addr, target_addr)` on the heap.
 pub type Binding = (usize, usize);
 
+// Symbol ids of the control constructs.
+const CUT: usize = known_symbol_id(2);
+const DISJUNCTION: usize = known_symbol_id(3);
+const IF_THEN: usize = known_symbol_id(4);
+const SOFT_IF_THEN: usize = known_symbol_id(5);
+/// Internal marker placed after the condition of `C *-> T ; E`.
+const SOFT_CUT: usize = known_symbol_id(6);
+const CATCH: usize = known_symbol_id(7);
+
+/// One alternative of a disjunction: child goals paired with whether a cut
+/// inside the goal is local to the disjunction env (`true`) or cuts through
+/// to the enclosing clause (`false`).
+type Branch = Vec<(usize, bool)>;
+
+/// The outcome of trying an env's next choice: the child envs to prove on
+/// success, `None` on failure, or `Err(ball)` if the goal raised the term at
+/// heap address `ball` as an exception.
+pub(super) type TryResult = Result<Option<Vec<Env>>, usize>;
+
+/// What a goal is resolved against: the query heap, the hypothesis being
+/// learnt and the program, with whether the proof may still learn clauses and
+/// invent predicates.
+pub(super) struct TryContext<'a, 'h> {
+    pub heap: &'a mut QueryHeap<'h>,
+    pub hypothesis: &'a mut Hypothesis,
+    pub allow_new_clause: bool,
+    pub allow_new_pred: bool,
+    pub predicate_table: &'a PredicateTable,
+    pub config: Config,
+}
+
+/// The clauses a goal has left to try: hypothesis clauses first, then any
+/// dynamic clauses laid onto the heap for it, then program clauses. Clauses
+/// are read from the hypothesis and the predicate table as they are tried,
+/// so opening a goal doesn't copy them.
+#[derive(Debug, Default)]
+pub(crate) struct ClauseChoices {
+    /// The hypothesis clauses still to try. Clauses added to the hypothesis
+    /// after the goal was opened are not tried.
+    hypothesis: Range<usize>,
+    /// Dynamic clauses copied onto the heap, in reverse order.
+    loaded: Vec<Clause>,
+    program: ClauseCursor,
+}
+
+impl ClauseChoices {
+    fn new(hypothesis: &Hypothesis, loaded: Vec<Clause>, program: ClauseCursor) -> Self {
+        let mut loaded = loaded;
+        loaded.reverse();
+        ClauseChoices {
+            hypothesis: 0..hypothesis.len(),
+            loaded,
+            program,
+        }
+    }
+
+    fn next<'a>(
+        &mut self,
+        hypothesis: &Hypothesis,
+        predicate_table: &'a PredicateTable,
+    ) -> Option<Cow<'a, Clause>> {
+        if let Some(i) = self.hypothesis.next() {
+            return Some(Cow::Owned(hypothesis[i].clone()));
+        }
+        if let Some(clause) = self.loaded.pop() {
+            return Some(Cow::Owned(clause));
+        }
+        self.program.next(predicate_table).map(Cow::Borrowed)
+    }
+
+    fn is_empty(&self, predicate_table: &PredicateTable) -> bool {
+        self.hypothesis.is_empty() && self.loaded.is_empty() && self.program.is_done(predicate_table)
+    }
+
+    fn remaining(&self, predicate_table: &PredicateTable) -> usize {
+        self.hypothesis.len() + self.loaded.len() + self.program.remaining(predicate_table)
+    }
+
+    fn clear(&mut self) {
+        self.hypothesis = 0..0;
+        self.loaded.clear();
+        self.program.exhaust();
+    }
+}
+
 /// How a goal is resolved: either by unifying with clauses or by calling a
 /// native predicate function.
 #[derive(Debug)]
 pub(crate) enum Strategy {
     /// Resolution via clause unification (standard SLD + meta-interpretive learning).
     Clause {
-        choices: Vec<Clause>,
+        choices: ClauseChoices,
         /// Whether a hypothesis clause was added on the last successful try.
         new_clause: bool,
         /// Whether a new predicate symbol was invented on the last successful try.
@@ -55,6 +147,40 @@ pub(crate) enum Strategy {
         goals: Vec<usize>,
         expanded: bool,
     },
+    /// Disjunction `A ; B` and the conditionals `C -> T`, `C *-> T` and
+    /// `C -> T ; E`. Branches are popped one at a time on backtracking.
+    /// A goal applying a bound closure is run as a single branch.
+    Disjunction {
+        branches: Vec<Branch>,
+    },
+    /// The cut `!`, or the soft cut committing a `*->` to its then branch.
+    /// Succeeds once; the pruning itself is done by the proof, which owns the
+    /// goal stack.
+    Cut {
+        taken: bool,
+        soft: bool,
+    },
+    /// `catch(Goal, Catcher, Recovery)`: runs Goal like `call/1`. While Goal
+    /// is running, the proof unwinds exceptions raised inside it to this env,
+    /// which then runs Recovery if Catcher unifies with the ball.
+    Catch {
+        called: bool,
+        recovering: bool,
+    },
+    /// A call to a tabled predicate, answered from its answer table. The
+    /// answers are laid onto the heap on the first try and popped one at a
+    /// time.
+    Table {
+        answers: Vec<usize>,
+        called: bool,
+    },
+    /// A goal whose proof up to its last call exited deterministically,
+    /// merged with the envs it produced by last-call optimisation. Holds only
+    /// the bindings backtracking must undo, all to cells below `threshold`,
+    /// and fails if retried.
+    Collapsed {
+        threshold: usize,
+    },
     Unset,
 }
 
@@ -65,25 +191,47 @@ pub(crate) enum Strategy {
 #[derive(Debug)]
 pub(super) struct Env {
     pub(super) goal: usize,
-    pub(super) bindings: Box<[Binding]>,
+    pub(super) bindings: Vec<Binding>,
+    /// Heap cells overwritten by [`PredReturn::Assign`], paired with the
+    /// cells they replaced.
+    pub(super) assignments: Vec<(usize, Cell)>,
     pub(super) children: usize,
     pub(super) depth: usize,
     pub(crate) got_choices: bool,
     pub(super) heap_point: usize,
     pub(super) strategy: Strategy,
+    /// Stack index of the clause env a `!` in this goal cuts back to.
+    /// `None` for query goals, where a cut prunes everything to its left.
+    pub(super) cut_parent: Option<usize>,
+    /// Whether a call to a tabled predicate reads its answer table. Only
+    /// false for the goal of a table's own evaluation.
+    pub(super) use_table: bool,
+    /// Stack index of the env whose try produced this goal.
+    pub(super) parent: usize,
+    /// Whether this goal is the last one its parent produced.
+    pub(super) last_call: bool,
+    /// Stack index of the latest env before this one that backtracking or an
+    /// exception may return to, set when the proof reaches this goal.
+    pub(super) choice_point: Option<usize>,
 }
 
 impl Env {
     pub fn new(goal: usize, depth: usize, heap_point: usize) -> Self {
         Env {
             goal,
-            bindings: Box::new([]),
+            bindings: Vec::new(),
+            assignments: Vec::new(),
             children: 0,
             depth,
             got_choices: false,
             heap_point,
             // Default to an empty clause strategy; overwritten by get_choices.
             strategy: Strategy::Unset,
+            cut_parent: None,
+            use_table: true,
+            parent: 0,
+            last_call: false,
+            choice_point: None,
         }
     }
 
@@ -111,6 +259,65 @@ impl Env {
         )
     }
 
+    /// Whether backtracking or an exception may return to this env: it has
+    /// choices left, is a `catch/3` frame, or added to the hypothesis.
+    pub fn is_choice_point(&self, predicate_table: &PredicateTable) -> bool {
+        match &self.strategy {
+            Strategy::Clause {
+                choices,
+                new_clause,
+                invent_pred,
+                ..
+            } => *new_clause || *invent_pred || !choices.is_empty(predicate_table),
+            Strategy::Native { alternatives, .. } => !alternatives.is_empty(),
+            Strategy::Disjunction { branches } => !branches.is_empty(),
+            Strategy::Table { answers, .. } => !answers.is_empty(),
+            Strategy::Catch { .. } | Strategy::Unset => true,
+            Strategy::Conjunction { .. } | Strategy::Cut { .. } | Strategy::Collapsed { .. } => {
+                false
+            }
+        }
+    }
+
+    /// Turn this env into a [`Strategy::Collapsed`] env standing for itself
+    /// and the `finished` envs after it, with its last call as only child.
+    /// Only the bindings and assignments to cells below `threshold` are kept.
+    pub(super) fn collapse(&mut self, threshold: usize, finished: &[Env]) {
+        // An env collapsed before already holds only cells below its old
+        // threshold, so it is only filtered again if the threshold dropped
+        if !matches!(self.strategy, Strategy::Collapsed { threshold: old } if old <= threshold) {
+            self.bindings.retain(|&(src, _)| src < threshold);
+            self.assignments.retain(|&(addr, _)| addr < threshold);
+        }
+        for env in finished {
+            self.bindings
+                .extend(env.bindings.iter().filter(|&&(src, _)| src < threshold));
+            self.assignments.extend(
+                env.assignments
+                    .iter()
+                    .filter(|&&(addr, _)| addr < threshold),
+            );
+        }
+        self.strategy = Strategy::Collapsed { threshold };
+        self.children = 1;
+    }
+
+    /// `Some(soft)` if this goal is a cut.
+    pub fn cut(&self) -> Option<bool> {
+        match self.strategy {
+            Strategy::Cut { soft, .. } => Some(soft),
+            _ => None,
+        }
+    }
+
+    /// A child env for `goal`, whose cuts prune back to `cut_parent`.
+    fn child(&self, goal: usize, cut_parent: Option<usize>, heap: &QueryHeap) -> Env {
+        Env {
+            cut_parent,
+            ..Env::new(goal, self.depth + 1, heap.heap_len())
+        }
+    }
+
     // ── choice gathering ────────────────────────────────────────────────
 
     pub fn get_choices(
@@ -122,12 +329,46 @@ impl Env {
         self.got_choices = true;
         self.heap_point = heap.heap_len();
 
+        let goal = goal_addr(heap, self.goal);
+        if heap.deref_addr(self.goal) != self.goal
+            && matches!(heap[goal].0, Tag::Comp | Tag::Con | Tag::Tup)
+        {
+            // A variable goal calls the term it is bound to now, which may
+            // change on backtracking, so the term is called as a child. As
+            // with call/1, a cut in the term is local to it.
+            self.strategy = Strategy::Disjunction {
+                branches: vec![vec![(goal, true)]],
+            };
+            return;
+        }
+        self.goal = goal;
+
         if heap[self.goal].0 == Tag::Tup {
             self.get_tup_goals(heap);
+        } else if !matches!(heap[self.goal].0, Tag::Comp | Tag::Con) {
+            // An unbound variable or a number can't be called
+            self.strategy = Strategy::Native {
+                function: not_callable,
+                alternatives: Vec::new(),
+                called: false,
+            };
+        } else if let Some(functor) = bound_closure(heap, self.goal) {
+            self.get_closure_branch(heap, functor);
         } else {
             match heap.str_symbol_arity(self.goal) {
+                (CUT, 0) => self.strategy = Strategy::Cut { taken: false, soft: false },
+                (CATCH, 3) => {
+                    self.strategy = Strategy::Catch {
+                        called: false,
+                        recovering: false,
+                    }
+                }
+                (SOFT_CUT, 0) => self.strategy = Strategy::Cut { taken: false, soft: true },
+                (symbol @ (DISJUNCTION | IF_THEN | SOFT_IF_THEN), 2) => {
+                    self.get_control_branches(heap, symbol)
+                }
                 (0, arity) => self.get_choices_var_pred(hypothesis, predicate_table, arity),
-                sym_arr => self.get_choices_con_pred(hypothesis, predicate_table, sym_arr),
+                sym_arr => self.get_choices_con_pred(heap, hypothesis, predicate_table, sym_arr),
             }
         }
     }
@@ -150,6 +391,39 @@ impl Env {
         }
     }
 
+    /// If goal is a disjunction or conditional select disjunction strategy.
+    /// `C -> T` runs as `C, !, T` with the cut local to this env, so
+    /// committing to the condition also discards any else branch. `C *-> T`
+    /// uses a soft cut that only discards the else branch.
+    fn get_control_branches(&mut self, heap: &mut QueryHeap, symbol: usize) {
+        let goal = goal_addr(heap, self.goal);
+        let mut branches = if symbol == DISJUNCTION {
+            let left = goal_addr(heap, goal + 2);
+            let right = goal_addr(heap, goal + 3);
+            let first = match control_symbol(heap, left) {
+                Some(symbol @ (IF_THEN | SOFT_IF_THEN)) => conditional_branch(heap, left, symbol),
+                _ => vec![(left, false)],
+            };
+            vec![first, vec![(right, false)]]
+        } else {
+            vec![conditional_branch(heap, goal, symbol)]
+        };
+        branches.reverse();
+        self.strategy = Strategy::Disjunction { branches };
+    }
+
+    /// If the predicate variable of the goal is bound to a closure such as
+    /// `'<'(1)`, run the goal as `call/N` does: a single branch applying the
+    /// closure to the goal's arguments, with its own cut scope.
+    fn get_closure_branch(&mut self, heap: &mut QueryHeap, functor: usize) {
+        let args: Vec<usize> = heap.str_iterator(self.goal).skip(1).collect();
+        let branches = match apply_closure(heap, functor, &args) {
+            Some(goal) => vec![vec![(goal, true)]],
+            None => Vec::new(),
+        };
+        self.strategy = Strategy::Disjunction { branches };
+    }
+
     /// Get choices for a variable predicate goal
     /// Choices is built from:
     /// body predicates, variable predicate clauses, hypothesis clauses
@@ -160,28 +434,19 @@ impl Env {
         arity: usize,
     ) {
         // Variable goal — gather meta-rules and body clauses.
-        let mut choices = Vec::new();
-        choices.extend_from_slice(hypothesis);
-
-        if let Some(clauses) = predicate_table.get_variable_clauses(arity) {
-            choices.extend_from_slice(clauses);
-        }
-        choices.extend(predicate_table.get_body_clauses(arity).cloned());
-        let total = choices.len();
-        self.strategy = Strategy::Clause {
-            choices,
-            new_clause: false,
-            invent_pred: false,
-            total_choice_count: total,
-        };
+        tabling::record_open_call();
+        let cursor = predicate_table.variable_cursor(arity, true);
+        self.strategy = clause_strategy(hypothesis, Vec::new(), cursor, predicate_table);
     }
 
     /// Get choices for constant predicate goal
     /// If symbol/arity is a predicate function select Native strategy
     /// If symbol/arity is a known predicate use hashmap to get clauses + hypothesis
+    /// If symbol/arity is a dynamic predicate copy its current clauses onto the heap
     /// If symbol/arity is unkown predicate get hypothesis and variable predicate clauses
     fn get_choices_con_pred(
         &mut self,
+        heap: &mut QueryHeap,
         hypothesis: &mut Hypothesis,
         predicate_table: &PredicateTable,
         (symbol, arity): (usize, usize),
@@ -194,31 +459,32 @@ impl Env {
                     called: false,
                 };
             }
-            Some(Predicate::Clauses(clauses)) => {
-                let mut choices = Vec::new();
-                choices.extend_from_slice(hypothesis);
-                choices.extend_from_slice(clauses);
-                let total = choices.len();
-                self.strategy = Strategy::Clause {
-                    choices,
-                    new_clause: false,
-                    invent_pred: false,
-                    total_choice_count: total,
+            Some(Predicate::Clauses(_))
+                if self.use_table && predicate_table.tables.is_tabled((symbol, arity)) =>
+            {
+                self.strategy = Strategy::Table {
+                    answers: Vec::new(),
+                    called: false,
                 };
             }
+            Some(Predicate::Clauses(_)) => {
+                tabling::record_call((symbol, arity));
+                let cursor = predicate_table.clause_cursor((symbol, arity), heap, self.goal);
+                self.strategy = clause_strategy(hypothesis, Vec::new(), cursor, predicate_table);
+            }
             None => {
-                let mut choices = Vec::new();
-                choices.extend_from_slice(hypothesis);
-                if let Some(clauses) = predicate_table.get_variable_clauses(arity) {
-                    choices.extend_from_slice(clauses);
-                }
-                let total = choices.len();
-                self.strategy = Strategy::Clause {
-                    choices,
-                    new_clause: false,
-                    invent_pred: false,
-                    total_choice_count: total,
-                };
+                tabling::record_call((symbol, arity));
+                let (loaded, cursor) =
+                    if let Some(clauses) = predicate_table.dynamic.clauses((symbol, arity)) {
+                        let loaded = clauses.iter().map(|clause| clause.load(heap)).collect();
+                        // The copied clauses must outlive every try, which
+                        // truncates the heap back to the heap point.
+                        self.heap_point = heap.heap_len();
+                        (loaded, ClauseCursor::default())
+                    } else {
+                        (Vec::new(), predicate_table.variable_cursor(arity, false))
+                    };
+                self.strategy = clause_strategy(hypothesis, loaded, cursor, predicate_table);
             }
         }
     }
@@ -263,19 +529,36 @@ impl Env {
                 }
             }
             heap.truncate(self.heap_point);
+        } else if let Strategy::Disjunction { .. }
+        | Strategy::Catch { .. }
+        | Strategy::Collapsed { .. } = self.strategy
+        {
+            // Reclaims the cut markers built by `get_control_branches`, the
+            // ball copied for a recovery and the cells of collapsed envs.
+            heap.truncate(self.heap_point);
         }
         heap.unbind(&self.bindings);
+        self.restore_assignments(heap);
         let children = self.children;
         // Clear binding/child bookkeeping now that they have been undone on the
         // heap. Leaving stale bindings here is unsafe: on the subsequent RETRY
         // (got_choices stays true) a failing try does not overwrite them, so a
         // later `reset` would otherwise still see the old (already-unbound)
         // forward bindings and could re-process them against a truncated heap.
-        self.bindings = Box::new([]);
+        self.bindings.clear();
         self.children = 0;
         children
     }
 
+    /// Put back the heap cells overwritten by this env's last try, latest
+    /// first.
+    fn restore_assignments(&mut self, heap: &mut QueryHeap) {
+        for &(addr, cell) in self.assignments.iter().rev() {
+            heap[addr] = cell;
+        }
+        self.assignments.clear();
+    }
+
     // ── reset on backtrack-from ─────────────────────────────────────────
 
     /// Reset this env when backtracking past it, so it gets fresh choices on
@@ -288,7 +571,8 @@ impl Env {
         // self-reference, the source cell becomes a dangling forward ref and a
         // later deref panics. Mirrors `undo_try`'s binding handling.
         heap.unbind(&self.bindings);
-        self.bindings = Box::new([]);
+        self.restore_assignments(heap);
+        self.bindings.clear();
         self.children = 0;
         heap.truncate(self.heap_point);
         self.got_choices = false;
@@ -303,61 +587,165 @@ impl Env {
                 alternatives.clear();
             }
             Strategy::Conjunction { expanded, .. } => *expanded = false,
-            Strategy::Unset => (),
+            Strategy::Disjunction { branches } => branches.clear(),
+            Strategy::Cut { taken, .. } => *taken = false,
+            Strategy::Catch { called, recovering } => {
+                *called = false;
+                *recovering = false;
+            }
+            Strategy::Table { answers, called } => {
+                answers.clear();
+                *called = false;
+            }
+            Strategy::Collapsed { .. } | Strategy::Unset => (),
+        }
+    }
+
+    /// Discard every remaining choice of this env without undoing its current
+    /// bindings, so the next retry fails. Called on the envs a cut commits to.
+    pub fn prune(&mut self) {
+        match &mut self.strategy {
+            Strategy::Clause { choices, .. } => choices.clear(),
+            Strategy::Native { alternatives, .. } => alternatives.clear(),
+            Strategy::Disjunction { branches } => branches.clear(),
+            Strategy::Table { answers, .. } => answers.clear(),
+            Strategy::Conjunction { .. }
+            | Strategy::Cut { .. }
+            | Strategy::Catch { .. }
+            | Strategy::Collapsed { .. }
+            | Strategy::Unset => (),
+        }
+    }
+
+    // ── garbage collection ──────────────────────────────────────────────
+
+    /// Mark the heap cells this env may still use, now or on backtracking.
+    /// Both sides of each binding are kept, as are the cells its
+    /// assignments overwrote.
+    pub(super) fn mark(&self, marker: &mut Marker) {
+        marker.addr(self.goal);
+        for &(src, target) in self.bindings.iter() {
+            marker.addr(src);
+            marker.addr(target);
+        }
+        for &(addr, cell) in self.assignments.iter() {
+            marker.addr(addr);
+            marker.cell(cell);
+        }
+        match &self.strategy {
+            Strategy::Clause { choices, .. } => {
+                for clause in &choices.loaded {
+                    clause.iter().for_each(|&literal| marker.addr(literal));
+                }
+            }
+            Strategy::Native { alternatives, .. } => {
+                for (bindings, goals) in alternatives {
+                    for &(src, target) in bindings {
+                        marker.addr(src);
+                        marker.addr(target);
+                    }
+                    goals.iter().for_each(|&goal| marker.addr(goal));
+                }
+            }
+            Strategy::Conjunction { goals, .. } => goals.iter().for_each(|&goal| marker.addr(goal)),
+            Strategy::Disjunction { branches } => {
+                for branch in branches {
+                    branch.iter().for_each(|&(goal, _)| marker.addr(goal));
+                }
+            }
+            Strategy::Table { answers, .. } => {
+                answers.iter().for_each(|&answer| marker.addr(answer))
+            }
+            Strategy::Cut { .. }
+            | Strategy::Catch { .. }
+            | Strategy::Collapsed { .. }
+            | Strategy::Unset => (),
+        }
+    }
+
+    /// Update the heap addresses this env holds after a collection.
+    pub(super) fn relocate(&mut self, relocation: &Relocation) {
+        self.goal = relocation.addr(self.goal);
+        self.heap_point = relocation.addr(self.heap_point);
+        for (src, target) in self.bindings.iter_mut() {
+            *src = relocation.addr(*src);
+            *target = relocation.addr(*target);
+        }
+        for (addr, cell) in self.assignments.iter_mut() {
+            *addr = relocation.addr(*addr);
+            *cell = relocation.cell(*cell);
+        }
+        match &mut self.strategy {
+            Strategy::Clause { choices, .. } => {
+                for clause in &mut choices.loaded {
+                    clause.iter_mut().for_each(|literal| *literal = relocation.addr(*literal));
+                }
+            }
+            Strategy::Native { alternatives, .. } => {
+                for (bindings, goals) in alternatives {
+                    for (src, target) in bindings {
+                        *src = relocation.addr(*src);
+                        *target = relocation.addr(*target);
+                    }
+                    goals.iter_mut().for_each(|goal| *goal = relocation.addr(*goal));
+                }
+            }
+            Strategy::Conjunction { goals, .. } => {
+                goals.iter_mut().for_each(|goal| *goal = relocation.addr(*goal))
+            }
+            Strategy::Disjunction { branches } => {
+                for branch in branches {
+                    branch.iter_mut().for_each(|(goal, _)| *goal = relocation.addr(*goal));
+                }
+            }
+            Strategy::Table { answers, .. } => {
+                answers.iter_mut().for_each(|answer| *answer = relocation.addr(*answer))
+            }
+            Strategy::Cut { .. }
+            | Strategy::Catch { .. }
+            | Strategy::Collapsed { .. }
+            | Strategy::Unset => (),
         }
     }
 
     // ── try choices (dispatch) ──────────────────────────────────────────
 
-    pub fn try_choices(
+    /// Try the next choice of this env, which sits at `index` on the proof
+    /// stack.
+    pub(super) fn try_choices(
         &mut self,
-        heap: &mut QueryHeap,
-        hypothesis: &mut Hypothesis,
-        allow_new_clause: bool,
-        allow_new_pred: bool,
-        predicate_table: &PredicateTable,
-        config: Config,
+        index: usize,
+        ctx: &mut TryContext,
         debug: bool,
-    ) -> Option<Vec<Env>> {
-        if self.depth > config.max_depth {
+    ) -> TryResult {
+        if self.depth > ctx.config.max_depth {
             if debug {
                 eprintln!(
                     "[FAIL_ON_DEPTH] depth={} goal={}",
                     self.depth,
-                    heap.term_string(self.goal),
+                    ctx.heap.term_string(self.goal),
                 );
             }
-            return None;
+            return Ok(None);
         }
 
         match &self.strategy {
-            Strategy::Native { .. } => {
-                self.try_native(heap, hypothesis, predicate_table, config, debug)
-            }
-            Strategy::Clause { .. } => self.try_clause(
-                heap,
-                hypothesis,
-                allow_new_clause,
-                allow_new_pred,
-                predicate_table,
-                config,
-                debug,
-            ),
-            Strategy::Conjunction { .. } => self.try_conj(heap),
+            Strategy::Native { .. } => self.try_native(index, ctx, debug),
+            Strategy::Clause { .. } => Ok(self.try_clause(index, ctx, debug)),
+            Strategy::Conjunction { .. } => Ok(self.try_conj(ctx.heap)),
+            Strategy::Disjunction { .. } => Ok(self.try_disjunction(index, ctx.heap)),
+            Strategy::Cut { .. } => Ok(self.try_cut()),
+            Strategy::Catch { .. } => Ok(self.try_catch(index, ctx.heap)),
+            Strategy::Table { .. } => self.try_table(index, ctx, debug),
+            Strategy::Collapsed { .. } => Ok(None),
             Strategy::Unset => unreachable!("Shouldn't be able to try choices before getting them"),
         }
     }
 
     // ── native predicate resolution ─────────────────────────────────────
 
-    fn try_native(
-        &mut self,
-        heap: &mut QueryHeap,
-        hypothesis: &mut Hypothesis,
-        predicate_table: &PredicateTable,
-        config: Config,
-        _debug: bool,
-    ) -> Option<Vec<Env>> {
+    fn try_native(&mut self, index: usize, ctx: &mut TryContext, _debug: bool) -> TryResult {
+        let heap = &mut *ctx.heap;
         let Strategy::Native {
             function,
             alternatives,
@@ -370,26 +758,40 @@ impl Env {
         // First call: invoke the predicate function.
         if !*called {
             *called = true;
-            match function(heap, hypothesis, self.goal, predicate_table, config) {
-                PredReturn::True => return Some(Vec::new()),
-                PredReturn::False => return None,
+            match function(
+                heap,
+                ctx.hypothesis,
+                self.goal,
+                ctx.predicate_table,
+                ctx.config,
+            ) {
+                PredReturn::True => return Ok(Some(Vec::new())),
+                PredReturn::False => return Ok(None),
+                PredReturn::Throw(ball) => return Err(ball),
                 PredReturn::Success(bindings, goals) => {
-                    self.bindings = bindings.into_boxed_slice();
+                    self.bindings = bindings;
                     heap.bind(&self.bindings);
                     if goals.is_empty() {
-                        return Some(Vec::new());
+                        return Ok(Some(Vec::new()));
                     }
                     self.children = goals.len();
-                    return Some(
+                    return Ok(Some(
                         goals
                             .into_iter()
-                            .map(|g| Env::new(g, self.depth + 1, heap.heap_len()))
+                            .map(|g| self.child(g, Some(index), heap))
                             .collect(),
-                    );
+                    ));
                 }
                 PredReturn::Choices(alts) => {
                     *alternatives = alts;
                 }
+                PredReturn::Assign(cells) => {
+                    self.assignments = cells
+                        .into_iter()
+                        .map(|(addr, cell)| (addr, std::mem::replace(&mut heap[addr], cell)))
+                        .collect();
+                    return Ok(Some(Vec::new()));
+                }
             }
         }
 
@@ -397,34 +799,74 @@ impl Env {
         let Strategy::Native { alternatives, .. } = &mut self.strategy else {
             unreachable!()
         };
-        let (bindings, goals) = alternatives.pop()?;
-        self.bindings = bindings.into_boxed_slice();
+        let Some((bindings, goals)) = alternatives.pop() else {
+            return Ok(None);
+        };
+        self.bindings = bindings;
         heap.bind(&self.bindings);
         if goals.is_empty() {
-            Some(Vec::new())
+            Ok(Some(Vec::new()))
         } else {
             self.children = goals.len();
-            Some(
+            Ok(Some(
                 goals
                     .into_iter()
-                    .map(|g| Env::new(g, self.depth + 1, heap.heap_len()))
+                    .map(|g| self.child(g, Some(index), heap))
                     .collect(),
-            )
+            ))
         }
     }
 
+    // ── tabled resolution ───────────────────────────────────────────────
+
+    /// Try the next answer of a tabled call. If the hypothesis could add
+    /// answers the table can't be used, and the goal falls back to ordinary
+    /// clause resolution.
+    fn try_table(&mut self, index: usize, ctx: &mut TryContext, debug: bool) -> TryResult {
+        let Strategy::Table { answers, called } = &mut self.strategy else {
+            unreachable!()
+        };
+        if !*called {
+            *called = true;
+            let heap = &mut *ctx.heap;
+            let predicate_table = ctx.predicate_table;
+            match tabling::answers(heap, ctx.hypothesis, self.goal, predicate_table, ctx.config)? {
+                Some(mut table) => {
+                    table.reverse();
+                    *answers = table;
+                    // The answers must outlive every try, like dynamic clauses
+                    self.heap_point = heap.heap_len();
+                }
+                None => {
+                    let symbol_arity = heap.str_symbol_arity(self.goal);
+                    let cursor = predicate_table.clause_cursor(symbol_arity, heap, self.goal);
+                    self.strategy =
+                        clause_strategy(ctx.hypothesis, Vec::new(), cursor, predicate_table);
+                    return Ok(self.try_clause(index, ctx, debug));
+                }
+            }
+        }
+
+        let Strategy::Table { answers, .. } = &mut self.strategy else {
+            unreachable!()
+        };
+        while let Some(answer) = answers.pop() {
+            if let Some(substitution) = unify(ctx.heap, answer, self.goal) {
+                self.bindings = substitution.get_bindings().into_vec();
+                ctx.heap.bind(&self.bindings);
+                return Ok(Some(Vec::new()));
+            }
+        }
+        Ok(None)
+    }
+
     // ── clause-based resolution ─────────────────────────────────────────
 
-    fn try_clause(
-        &mut self,
-        heap: &mut QueryHeap,
-        hypothesis: &mut Hypothesis,
-        allow_new_clause: bool,
-        allow_new_pred: bool,
-        _predicate_table: &PredicateTable,
-        _config: Config,
-        debug: bool,
-    ) -> Option<Vec<Env>> {
+    fn try_clause(&mut self, index: usize, ctx: &mut TryContext, debug: bool) -> Option<Vec<Env>> {
+        let heap = &mut *ctx.heap;
+        let hypothesis = &mut *ctx.hypothesis;
+        let predicate_table = ctx.predicate_table;
+        let (allow_new_clause, allow_new_pred) = (ctx.allow_new_clause, ctx.allow_new_pred);
         let mut choices_tried = 0;
 
         // We need mutable access to strategy fields while also reading self.goal
@@ -440,7 +882,7 @@ impl Env {
                 unreachable!()
             };
 
-            let Some(clause) = choices.pop() else {
+            let Some(clause) = choices.next(hypothesis, predicate_table) else {
                 break;
             };
 
@@ -478,7 +920,7 @@ impl Env {
                         self.depth,
                         heap.term_string(self.goal),
                         clause.to_string(heap),
-                        choices.len()
+                        choices.remaining(predicate_table)
                     );
                 }
 
@@ -548,7 +990,7 @@ impl Env {
                     }
                 }
 
-                self.bindings = substitution.get_bindings();
+                self.bindings = substitution.get_bindings().into_vec();
                 self.children = new_goals.len();
                 if debug {
                     eprintln!("Bindings: {:?}", self.bindings);
@@ -558,7 +1000,7 @@ impl Env {
                 return Some(
                     new_goals
                         .into_iter()
-                        .map(|goal| Env::new(goal, self.depth + 1, heap.heap_len()))
+                        .map(|goal| self.child(goal, Some(index), heap))
                         .collect(),
                 );
             }
@@ -582,7 +1024,20 @@ impl Env {
         None
     }
 
+    fn try_cut(&mut self) -> Option<Vec<Env>> {
+        let Strategy::Cut { taken, .. } = &mut self.strategy else {
+            unreachable!()
+        };
+        if *taken {
+            None
+        } else {
+            *taken = true;
+            Some(Vec::new())
+        }
+    }
+
     fn try_conj(&mut self, heap: &QueryHeap) -> Option<Vec<Env>> {
+        let (depth, cut_parent) = (self.depth, self.cut_parent);
         let Strategy::Conjunction { goals, expanded } = &mut self.strategy else {
             unreachable!()
         };
@@ -591,12 +1046,148 @@ impl Env {
         } else {
             *expanded = true;
             self.children = goals.len();
+            // Conjunctions are transparent to cut.
             Some(
                 goals
                     .iter()
-                    .map(|goal| Env::new(*goal, self.depth + 1, heap.heap_len()))
+                    .map(|goal| Env {
+                        cut_parent,
+                        ..Env::new(*goal, depth + 1, heap.heap_len())
+                    })
                     .collect(),
             )
         }
     }
+
+    fn try_catch(&mut self, index: usize, heap: &QueryHeap) -> Option<Vec<Env>> {
+        let Strategy::Catch { called, .. } = &mut self.strategy else {
+            unreachable!()
+        };
+        if *called {
+            return None;
+        }
+        *called = true;
+        self.children = 1;
+        let goal = goal_addr(heap, goal_addr(heap, self.goal) + 2);
+        Some(vec![self.child(goal, Some(index), heap)])
+    }
+
+    /// Whether this is a `catch/3` env still running its goal, and so able to
+    /// catch an exception raised inside it.
+    pub fn catching(&self) -> bool {
+        matches!(
+            self.strategy,
+            Strategy::Catch {
+                called: true,
+                recovering: false
+            }
+        )
+    }
+
+    /// Handle an exception unwound to this `catch/3` env, which sits at
+    /// `index` on the proof stack. If the catcher unifies with the ball at
+    /// `ball`, returns the recovery goal to prove in place of the caught goal.
+    pub fn recover(&mut self, index: usize, ball: usize, heap: &mut QueryHeap) -> Option<Vec<Env>> {
+        let goal = goal_addr(heap, self.goal);
+        let sub = unify(heap, goal + 3, ball)?;
+        self.bindings = sub.get_bindings().into_vec();
+        heap.bind(&self.bindings);
+        self.strategy = Strategy::Catch {
+            called: true,
+            recovering: true,
+        };
+        self.children = 1;
+        let recovery = goal_addr(heap, goal + 4);
+        Some(vec![self.child(recovery, Some(index), heap)])
+    }
+
+    fn try_disjunction(&mut self, index: usize, heap: &QueryHeap) -> Option<Vec<Env>> {
+        let Strategy::Disjunction { branches } = &mut self.strategy else {
+            unreachable!()
+        };
+        let branch = branches.pop()?;
+        self.children = branch.len();
+        Some(
+            branch
+                .into_iter()
+                .map(|(goal, local)| {
+                    let cut_parent = if local { Some(index) } else { self.cut_parent };
+                    self.child(goal, cut_parent, heap)
+                })
+                .collect(),
+        )
+    }
+}
+
+/// The clause strategy for a goal, trying the hypothesis clauses first,
+/// then the `loaded` dynamic clauses, then the program clauses at `cursor`.
+fn clause_strategy(
+    hypothesis: &Hypothesis,
+    loaded: Vec<Clause>,
+    cursor: ClauseCursor,
+    predicate_table: &PredicateTable,
+) -> Strategy {
+    let choices = ClauseChoices::new(hypothesis, loaded, cursor);
+    let total = choices.remaining(predicate_table);
+    Strategy::Clause {
+        choices,
+        new_clause: false,
+        invent_pred: false,
+        total_choice_count: total,
+    }
+}
+
+/// Stand-in predicate function for a goal that can't be called.
+fn not_callable(
+    heap: &mut QueryHeap,
+    _hypothesis: &mut Hypothesis,
+    goal: usize,
+    _predicate_table: &PredicateTable,
+    _config: Config,
+) -> PredReturn {
+    must_be_error(heap, "callable", goal)
+}
+
+/// Follow references and structure indirections to the goal term itself.
+fn goal_addr(heap: &QueryHeap, addr: usize) -> usize {
+    let addr = heap.deref_addr(addr);
+    if let (Tag::Str, ptr) = heap[addr] {
+        ptr
+    } else {
+        addr
+    }
+}
+
+/// The term a compound goal's predicate variable is bound to, if it is
+/// anything other than an atom.
+fn bound_closure(heap: &QueryHeap, goal: usize) -> Option<usize> {
+    if heap[goal].0 != Tag::Comp {
+        return None;
+    }
+    let functor = heap.deref_addr(goal + 1);
+    match heap[functor].0 {
+        Tag::Con | Tag::Ref | Tag::Arg => None,
+        _ => Some(functor),
+    }
+}
+
+/// The functor of a binary compound goal at `addr`, if it has a constant one.
+fn control_symbol(heap: &QueryHeap, addr: usize) -> Option<usize> {
+    match heap[addr] {
+        (Tag::Comp, 3) => match heap[heap.deref_addr(addr + 1)] {
+            (Tag::Con, symbol) => Some(symbol),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The branch for the conditional `C -> T` or `C *-> T` at `addr`: the
+/// condition with a local cut scope, the commit marker, then `T`.
+fn conditional_branch(heap: &mut QueryHeap, addr: usize, symbol: usize) -> Branch {
+    let condition = goal_addr(heap, addr + 2);
+    let then = goal_addr(heap, addr + 3);
+    let marker = if symbol == IF_THEN { CUT } else { SOFT_CUT };
+    let marker = heap.heap_push((Tag::Con, marker));
+    vec![(condition, true), (marker, true), (then, false)]
 }
@@ -4,8 +4,11 @@
 //! [`Proof`](crate::resolution::proof::Proof) drives the search,
 //! [`unification`](crate::resolution::unification) handles term matching, and
 //! [`build`](crate::resolution::build) constructs new terms from substitutions.
+//! Calls to tabled predicates are answered by
+//! [`tabling`](crate::resolution::tabling).
 
 pub mod build;
 pub mod env;
 pub mod proof;
+pub mod tabling;
 pub mod unification;
@@ -1,24 +1,55 @@
 //! Proof search via SLD resolution with backtracking and predicate invention.
 
+use std::{
+    collections::HashMap,
+    ops::{Index, IndexMut, Range},
+};
+
 use crate::{
-    heap::{heap::Heap, query_heap::QueryHeap},
+    heap::{
+        heap::{Cell, Heap},
+        query_heap::QueryHeap,
+    },
+    predicate_modules::helpers::copy_term_cell,
     program::{hypothesis::Hypothesis, predicate_table::PredicateTable},
     Config,
 };
 
-use super::env::Env;
+use super::env::{Env, TryContext};
 
 /// The proof search engine.
 ///
 /// Maintains a goal stack and iteratively resolves goals against the predicate
 /// table and the current hypothesis. Call [`Proof::prove`] repeatedly to
 /// enumerate solutions via backtracking.
+///
+/// An exception no `catch/3` handles ends the proof: [`Proof::prove`]
+/// returns false and the ball can be collected with
+/// [`Proof::take_exception`].
+///
+/// Finished envs that backtracking can no longer return to are discarded by
+/// last-call optimisation: when the last goal of a clause is reached with no
+/// choice point left since its parent, the parent and the envs it produced
+/// are collapsed into one env holding just the bindings that backtracking to
+/// the previous choice point must undo.
+///
+/// Once the heap has grown by [`Config::gc_threshold`] cells, the cells the
+/// proof allocated are garbage collected, with the goal stack and the
+/// hypothesis as roots.
 pub struct Proof {
-    stack: Vec<Env>,
+    stack: GoalStack,
     pointer: usize,
     pub hypothesis: Hypothesis,
     h_clauses: usize,
     invented_preds: usize,
+    /// An uncaught exception ball, kept as a `(store, addr)` copy since the
+    /// heap it was raised on has been unwound.
+    exception: Option<(Vec<Cell>, usize)>,
+    /// Heap length when the proof started; only cells above it are
+    /// collected.
+    heap_start: usize,
+    /// Heap length after the last garbage collection.
+    gc_point: usize,
 }
 
 impl Proof {
@@ -34,6 +65,9 @@ impl Proof {
             hypothesis,
             h_clauses: 0,
             invented_preds: 0,
+            exception: None,
+            heap_start: heap.heap_len(),
+            gc_point: heap.heap_len(),
         }
     }
 
@@ -50,10 +84,26 @@ impl Proof {
             hypothesis,
             h_clauses,
             invented_preds: 0,
+            exception: None,
+            heap_start: heap.heap_len(),
+            gc_point: heap.heap_len(),
         }
     }
 
+    /// Create a proof of the tabled `goal` that resolves it against its
+    /// clauses rather than its answer table, to evaluate the table.
+    pub(crate) fn tabled_evaluation(heap: &QueryHeap, goal: usize) -> Self {
+        let mut proof = Proof::new(heap, &[goal]);
+        proof.stack[0].use_table = false;
+        proof
+    }
+
     pub fn prove(&mut self, heap: &mut QueryHeap, predicate_table: &PredicateTable, config: Config) -> bool {
+        // The stack is cleared once an uncaught exception ends the proof
+        if self.stack.is_empty() {
+            return false;
+        }
+
         // Handle restart after previous success
         if self.pointer == self.stack.len() {
             if config.debug {
@@ -80,6 +130,14 @@ impl Proof {
         }
 
         while self.pointer < self.stack.len() {
+            if !self.stack[self.pointer].got_choices {
+                self.last_call(config.debug);
+                if config.gc_threshold != 0
+                    && heap.heap_len().saturating_sub(self.gc_point) >= config.gc_threshold
+                {
+                    self.collect_garbage(heap, config.debug);
+                }
+            }
             if self.stack[self.pointer].got_choices {
                 if config.debug {
                     eprintln!(
@@ -92,7 +150,7 @@ impl Proof {
                 self.stack[self.pointer].get_choices(
                     heap,
                     &mut self.hypothesis,
-                    &predicate_table,
+                    predicate_table,
                 );
                 if config.debug {
                     eprintln!(
@@ -102,72 +160,279 @@ impl Proof {
                     );
                 }
             }
-            match self.stack[self.pointer].try_choices(
+            let mut ctx = TryContext {
                 heap,
-                &mut self.hypothesis,
-                self.h_clauses < config.max_clause,
-                self.invented_preds < config.max_pred,
+                hypothesis: &mut self.hypothesis,
+                allow_new_clause: self.h_clauses < config.max_clause,
+                allow_new_pred: self.invented_preds < config.max_pred,
                 predicate_table,
                 config,
-                config.debug,
-            ) {
-                Some(new_goals) => {
+            };
+            match self.stack[self.pointer].try_choices(self.pointer, &mut ctx, config.debug) {
+                Ok(Some(new_goals)) => {
+                    if let Some(soft) = self.stack[self.pointer].cut() {
+                        self.cut(soft, predicate_table, config.debug);
+                    }
                     if self.stack[self.pointer].new_clause() {
                         self.h_clauses += 1;
                     }
                     if self.stack[self.pointer].invent_pred() {
                         self.invented_preds += 1;
                     }
-                    self.pointer += 1;
-                    self.stack.splice(self.pointer..self.pointer, new_goals);
+                    self.advance(new_goals, predicate_table);
                 }
-                None => {
+                Ok(None) => {
                     if self.pointer == 0 {
                         if config.debug {
                             eprintln!("[FAILED] First goal exhausted");
                         }
                         return false;
                     }
-                    // Reset this goal so it gets fresh choices on a future visit
-                    self.stack[self.pointer].reset(heap);
-                    self.pointer -= 1;
-                    let children = self.stack[self.pointer].undo_try(
-                        &mut self.hypothesis,
-                        heap,
-                        &mut self.h_clauses,
-                        &mut self.invented_preds,
-                        config.debug,
-                    );
-                    // The parent's `undo_try` truncated the heap to the parent's
-                    // `heap_point`, which reclaims the cells the drained children
-                    // allocated. That is sufficient for ordinary bindings (whose
-                    // source vars live above the truncation point), but a forward
-                    // binding produced by `re_build_bound_arg_terms`
-                    // (old_var -> freshly_built_high_addr) has a source var BELOW
-                    // the truncation point that survives the truncate. If we drop
-                    // the child env without restoring that source ref, the cell is
-                    // left pointing at a truncated-away target and a later deref
-                    // panics. Restore any such surviving source refs here.
-                    let hl = heap.heap_len();
-                    for env in
-                        &self.stack[(self.pointer + 1)..(self.pointer + 1 + children)]
-                    {
-                        for &(src, _) in env.bindings.iter() {
-                            if src < hl {
-                                if let (crate::heap::heap::Tag::Ref, p) = &mut heap[src] {
-                                    *p = src;
-                                }
-                            }
-                        }
+                    self.backtrack(heap, config.debug);
+                }
+                Err(ball) => {
+                    if !self.throw(heap, ball, predicate_table, config.debug) {
+                        return false;
                     }
-                    self.stack
-                        .drain((self.pointer + 1)..(self.pointer + 1 + children));
                 }
             }
         }
         true
     }
 
+    /// Move on from the env at `pointer`, which just succeeded producing
+    /// `new_goals`, to the next goal, recording the latest choice point before
+    /// it.
+    fn advance(&mut self, mut new_goals: Vec<Env>, predicate_table: &PredicateTable) {
+        let parent = self.pointer;
+        let env = &self.stack[parent];
+        let choice_point = if env.is_choice_point(predicate_table) {
+            Some(parent)
+        } else {
+            env.choice_point
+        };
+        for goal in &mut new_goals {
+            goal.parent = parent;
+        }
+        if let Some(goal) = new_goals.last_mut() {
+            goal.last_call = true;
+        }
+        self.pointer += 1;
+        self.stack.insert(self.pointer, new_goals);
+        if let Some(next) = self.stack.get_mut(self.pointer) {
+            next.choice_point = choice_point;
+        }
+    }
+
+    /// Last-call optimisation for the fresh goal at `pointer`.
+    ///
+    /// If the goal is the last call of its parent and no choice point lies
+    /// after the parent, the parent and every env after it have finished
+    /// deterministically. The same holds up through each ancestor the parent
+    /// is itself the last call of. The outermost such ancestor is collapsed
+    /// with the envs after it, keeping only the bindings and assignments to
+    /// cells older than the previous choice point: newer cells are truncated
+    /// away before that choice point is retried.
+    fn last_call(&mut self, debug: bool) {
+        let choice_point = self.stack[self.pointer].choice_point;
+        let mut start = None;
+        let mut top = self.pointer;
+        while self.stack[top].last_call
+            && choice_point.is_none_or(|choice_point| self.stack[top].parent > choice_point)
+        {
+            top = self.stack[top].parent;
+            start = Some(top);
+        }
+        let Some(start) = start else {
+            return;
+        };
+        if debug {
+            eprintln!("[LCO] collapsing envs {}..{}", start, self.pointer);
+        }
+
+        // The env after a choice point got its choices right after the
+        // choice point's last try, so its heap point marks the heap then
+        let threshold = self.stack[choice_point.map_or(0, |index| index + 1)].heap_point;
+        let (collapsed, finished) = self.stack.prefix_mut(self.pointer)[start..].split_at_mut(1);
+        collapsed[0].collapse(threshold, finished);
+        self.stack.remove((start + 1)..self.pointer);
+
+        let old_pointer = self.pointer;
+        self.pointer = start + 1;
+        let env = &mut self.stack[self.pointer];
+        env.parent = start;
+        if let Some(cut_parent) = env.cut_parent {
+            if (start..old_pointer).contains(&cut_parent) {
+                env.cut_parent = Some(start);
+            }
+        }
+    }
+
+    /// Collect the heap cells allocated by this proof that no env or
+    /// hypothesis clause can reach any more.
+    fn collect_garbage(&mut self, heap: &mut QueryHeap, debug: bool) {
+        let len = heap.heap_len();
+        let relocation = heap.collect_garbage(self.heap_start, |marker| {
+            for env in self.stack.iter() {
+                env.mark(marker);
+            }
+            for clause in self.hypothesis.iter() {
+                clause.iter().for_each(|&literal| marker.addr(literal));
+            }
+            for constraints in &self.hypothesis.constraints {
+                constraints.iter().for_each(|&addr| marker.addr(addr));
+            }
+        });
+        for env in self.stack.iter_mut() {
+            env.relocate(&relocation);
+        }
+        for clause in self.hypothesis.iter_mut() {
+            clause.iter_mut().for_each(|literal| *literal = relocation.addr(*literal));
+        }
+        for constraints in &mut self.hypothesis.constraints {
+            constraints.iter_mut().for_each(|addr| *addr = relocation.addr(*addr));
+        }
+        self.gc_point = heap.heap_len();
+        if debug {
+            eprintln!("[GC] heap {} -> {} cells", len, self.gc_point);
+        }
+    }
+
+    /// Step back from the env at `pointer` to the one before it, undoing that
+    /// env's last try and dropping the children it produced.
+    fn backtrack(&mut self, heap: &mut QueryHeap, debug: bool) {
+        // Reset this goal so it gets fresh choices on a future visit
+        self.stack[self.pointer].reset(heap);
+        self.pointer -= 1;
+        let children = self.stack[self.pointer].undo_try(
+            &mut self.hypothesis,
+            heap,
+            &mut self.h_clauses,
+            &mut self.invented_preds,
+            debug,
+        );
+        // The parent's `undo_try` truncated the heap to the parent's
+        // `heap_point`, which reclaims the cells the drained children
+        // allocated. That is sufficient for ordinary bindings (whose
+        // source vars live above the truncation point), but a forward
+        // binding produced by `re_build_bound_arg_terms`
+        // (old_var -> freshly_built_high_addr) has a source var BELOW
+        // the truncation point that survives the truncate. If we drop
+        // the child env without restoring that source ref, the cell is
+        // left pointing at a truncated-away target and a later deref
+        // panics. Restore any such surviving source refs here.
+        let hl = heap.heap_len();
+        let dropped = (self.pointer + 1)..(self.pointer + 1 + children);
+        for env in self.stack.range(dropped.clone()) {
+            for &(src, _) in env.bindings.iter() {
+                if src < hl {
+                    if let (crate::heap::heap::Tag::Ref, p) = &mut heap[src] {
+                        *p = src;
+                    }
+                }
+            }
+        }
+        self.stack.remove(dropped);
+    }
+
+    /// One past the last stack index of the subtree rooted at `index`.
+    fn subtree_end(&self, index: usize) -> usize {
+        // Envs are in pre-order: count the subtrees still to be passed over
+        let mut end = index + 1;
+        let mut open = self.stack[index].children;
+        while open > 0 {
+            open += self.stack[end].children;
+            open -= 1;
+            end += 1;
+        }
+        end
+    }
+
+    /// Raise the exception `ball` from the env at `pointer`.
+    ///
+    /// The proof unwinds to the innermost enclosing `catch/3` whose catcher
+    /// unifies with the ball, undoing every binding made since, and continues
+    /// with its recovery goal. Returns false if no catch handles the ball, in
+    /// which case the proof is unwound completely and ends.
+    fn throw(
+        &mut self,
+        heap: &mut QueryHeap,
+        ball: usize,
+        predicate_table: &PredicateTable,
+        debug: bool,
+    ) -> bool {
+        // Unwinding truncates the heap, so the ball is kept as a copy
+        let mut store: Vec<Cell> = Vec::new();
+        let ball = copy_term_cell(&mut store, &*heap, ball, &mut HashMap::new());
+        if debug {
+            eprintln!("[THROW] ball={}", store.term_string(ball));
+        }
+
+        let catches: Vec<usize> = (0..self.pointer)
+            .rev()
+            .filter(|&i| self.stack[i].catching() && self.subtree_end(i) > self.pointer)
+            .collect();
+        for index in catches {
+            while self.pointer > index {
+                self.backtrack(heap, debug);
+            }
+            let ball = copy_term_cell(heap, &store, ball, &mut HashMap::new());
+            if let Some(recovery) = self.stack[index].recover(index, ball, heap) {
+                if debug {
+                    eprintln!("[CATCH] goal={}", heap.term_string(self.stack[index].goal));
+                }
+                self.advance(recovery, predicate_table);
+                return true;
+            }
+        }
+
+        while self.pointer > 0 {
+            self.backtrack(heap, debug);
+        }
+        self.stack[0].reset(heap);
+        self.stack.clear();
+        self.pointer = 0;
+        self.exception = Some((store, ball));
+        false
+    }
+
+    /// Take the exception that ended this proof, if any, copying the ball
+    /// onto `heap` and returning its address.
+    pub fn take_exception(&mut self, heap: &mut QueryHeap) -> Option<usize> {
+        let (store, ball) = self.exception.take()?;
+        Some(copy_term_cell(heap, &store, ball, &mut HashMap::new()))
+    }
+
+    /// Commit to the choices made since the cut's parent clause env: the
+    /// parent and every env between it and the cut at `pointer` lose their
+    /// remaining choices, so backtracking past the cut fails the parent goal.
+    /// A soft cut only prunes the parent, leaving the condition of a `*->`
+    /// free to backtrack.
+    ///
+    /// Bindings and hypothesis clauses are left in place; they are undone as
+    /// usual when backtracking walks back over the pruned envs.
+    fn cut(&mut self, soft: bool, predicate_table: &PredicateTable, debug: bool) {
+        let from = self.stack[self.pointer].cut_parent.unwrap_or(0);
+        if debug {
+            eprintln!("[CUT] soft={} pruning envs {}..{}", soft, from, self.pointer);
+        }
+        if soft {
+            self.stack[from].prune();
+        } else {
+            for env in &mut self.stack.prefix_mut(self.pointer)[from..] {
+                env.prune();
+            }
+        }
+        // The pruned envs may no longer be choice points
+        let mut choice_point = self.stack[from].choice_point;
+        for index in from..self.pointer {
+            if self.stack[index].is_choice_point(predicate_table) {
+                choice_point = Some(index);
+            }
+        }
+        self.stack[self.pointer].choice_point = choice_point;
+    }
+
     /// Undo every binding still recorded across the proof's goal stack,
     /// restoring each bound source ref to a self-reference.
     ///
@@ -186,10 +451,627 @@ impl Proof {
                     }
                 }
             }
-            env.bindings = Box::new([]);
+            env.bindings.clear();
+        }
+    }
+}
+
+/// The envs of a proof in pre-order.
+///
+/// Goals are added and dropped around the proof's pointer, so the envs are
+/// held as a gap buffer: those before the gap in order and those after it in
+/// reverse. A change moves the gap to where it is made instead of shifting
+/// every env after it.
+#[derive(Default)]
+struct GoalStack {
+    front: Vec<Env>,
+    back: Vec<Env>,
+}
+
+impl GoalStack {
+    fn len(&self) -> usize {
+        self.front.len() + self.back.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn move_gap(&mut self, index: usize) {
+        while self.front.len() > index {
+            self.back.extend(self.front.pop());
+        }
+        while self.front.len() < index {
+            self.front.extend(self.back.pop());
+        }
+    }
+
+    /// Insert `envs` in order before the env at `index`.
+    fn insert(&mut self, index: usize, envs: Vec<Env>) {
+        self.move_gap(index);
+        self.back.extend(envs.into_iter().rev());
+    }
+
+    /// Drop the envs in `range`.
+    fn remove(&mut self, range: Range<usize>) {
+        self.move_gap(range.start);
+        self.back.truncate(self.back.len() - range.len());
+    }
+
+    /// The envs before `end` as one slice.
+    fn prefix_mut(&mut self, end: usize) -> &mut [Env] {
+        self.move_gap(end);
+        &mut self.front
+    }
+
+    fn range(&self, range: Range<usize>) -> impl Iterator<Item = &Env> {
+        range.map(|index| &self[index])
+    }
+
+    fn get_mut(&mut self, index: usize) -> Option<&mut Env> {
+        (index < self.len()).then(|| &mut self[index])
+    }
+
+    fn iter(&self) -> impl Iterator<Item = &Env> {
+        self.front.iter().chain(self.back.iter().rev())
+    }
+
+    fn iter_mut(&mut self) -> impl Iterator<Item = &mut Env> {
+        self.front.iter_mut().chain(self.back.iter_mut().rev())
+    }
+
+    fn clear(&mut self) {
+        self.front.clear();
+        self.back.clear();
+    }
+}
+
+impl FromIterator<Env> for GoalStack {
+    fn from_iter<I: IntoIterator<Item = Env>>(envs: I) -> Self {
+        GoalStack {
+            front: envs.into_iter().collect(),
+            back: Vec::new(),
+        }
+    }
+}
+
+impl Index<usize> for GoalStack {
+    type Output = Env;
+
+    fn index(&self, index: usize) -> &Env {
+        match index.checked_sub(self.front.len()) {
+            None => &self.front[index],
+            Some(offset) => &self.back[self.back.len() - 1 - offset],
+        }
+    }
+}
+
+impl IndexMut<usize> for GoalStack {
+    fn index_mut(&mut self, index: usize) -> &mut Env {
+        match index.checked_sub(self.front.len()) {
+            None => &mut self.front[index],
+            Some(offset) => {
+                let back = self.back.len() - 1 - offset;
+                &mut self.back[back]
+            }
         }
     }
 }
 
 #[cfg(test)]
-mod tests {}
+mod tests {
+    use super::Proof;
+    use crate::{
+        heap::{
+            heap::{Cell, Heap},
+            query_heap::QueryHeap,
+        },
+        parser::{
+            build_tree::TokenStream,
+            execute_tree::{build_clause, execute_tree},
+            tokeniser::tokenise,
+        },
+        predicate_modules::{
+            helpers::TestWrapper, DEFAULTS, LISTS, MATHS, META_PREDICATES, STRINGS,
+        },
+        program::predicate_table::PredicateTable,
+        Config,
+    };
+
+    fn tw(code: &str) -> TestWrapper {
+        let tw = TestWrapper::new(&[DEFAULTS, MATHS, META_PREDICATES]);
+        TestWrapper {
+            app: tw.app.load_code(code).unwrap(),
+        }
+    }
+
+    // ── cut ───────────────────────────────────────────────────────────────
+
+    #[test]
+    fn cut_commits_to_clause() {
+        let tw = tw("
+            p(X) :- q(X), !.
+            p(z).
+            q(a).
+            q(b).
+        ");
+        assert_eq!(tw.all_bindings("p(X).", "X"), ["a"]);
+    }
+
+    #[test]
+    fn cut_only_prunes_goals_to_its_left() {
+        let tw = tw("
+            p(X, Y) :- q(X), !, q(Y).
+            q(a).
+            q(b).
+        ");
+        assert_eq!(tw.all_bindings("p(X, Y).", "Y"), ["a", "b"]);
+        assert_eq!(tw.all_bindings("p(X, Y).", "X"), ["a", "a"]);
+    }
+
+    #[test]
+    fn cut_is_local_to_its_clause() {
+        let tw = tw("
+            r(X) :- p(X).
+            r(c).
+            p(X) :- q(X), !.
+            q(a).
+            q(b).
+        ");
+        assert_eq!(tw.all_bindings("r(X).", "X"), ["a", "c"]);
+    }
+
+    #[test]
+    fn cut_then_fail() {
+        let tw = tw("
+            p(X) :- X == a, !, fail.
+            p(_).
+        ");
+        tw.assert_false("p(a).");
+        tw.assert_true("p(b).");
+    }
+
+    #[test]
+    fn cut_in_query() {
+        let tw = tw("
+            q(a).
+            q(b).
+        ");
+        assert_eq!(tw.all_bindings("q(X), !.", "X"), ["a"]);
+        assert_eq!(tw.all_bindings("q(X), !, q(Y).", "Y"), ["a", "b"]);
+    }
+
+    #[test]
+    fn cut_inside_conjunction_is_transparent() {
+        let tw = tw("
+            p(X) :- (q(X), !).
+            p(z).
+            q(a).
+            q(b).
+        ");
+        assert_eq!(tw.all_bindings("p(X).", "X"), ["a"]);
+    }
+
+    #[test]
+    fn cut_inside_not_is_local() {
+        let tw = tw("
+            q(a).
+            q(b).
+        ");
+        assert_eq!(tw.all_bindings("q(X), not((q(Y), !, Y == b)).", "X"), ["a", "b"]);
+    }
+
+    #[test]
+    fn cut_through_variable_is_local() {
+        let tw = tw("
+            t(1).
+            t(2).
+            p(X) :- G = !, t(X), G.
+            p(3).
+            q(X) :- t(X), G = (t(Y), !), G, Y == 1.
+            q(4).
+        ");
+        assert_eq!(tw.all_bindings("p(X).", "X"), ["1", "2", "3"]);
+        assert_eq!(tw.all_bindings("q(X).", "X"), ["1", "2", "4"]);
+    }
+
+    #[test]
+    fn cut_in_meta_rule() {
+        // The cut is carried into the learned clause and commits to the first
+        // `q/1` solution.
+        let tw = tw("
+            q(a).
+            q(b).
+            P(X) :- q(X), !, {P}.
+        ");
+        let solutions: Vec<_> = tw.app.query_session("p(X).").unwrap().collect();
+        assert_eq!(solutions.len(), 1);
+        assert_eq!(solutions[0].bindings[0].1, "a");
+        assert!(solutions[0].hypothesis.contains("p(Arg_0):-q(Arg_0),!."));
+    }
+
+    // ── disjunction and conditionals ──────────────────────────────────────
+
+    #[test]
+    fn disjunction() {
+        let tw = tw("
+            p(X) :- (X = a ; X = b).
+        ");
+        assert_eq!(tw.all_bindings("p(X).", "X"), ["a", "b"]);
+        assert_eq!(tw.all_bindings("X = a ; X = b ; X = c.", "X"), ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn disjunction_of_conjunctions() {
+        let tw = tw("
+            q(a).
+            q(b).
+        ");
+        assert_eq!(
+            tw.all_bindings("(q(X), X == b ; X = c).", "X"),
+            ["b", "c"]
+        );
+    }
+
+    #[test]
+    fn if_then_else() {
+        let tw = tw("
+            sign(X, S) :- (X > 0 -> S = pos ; X < 0 -> S = neg ; S = zero).
+        ");
+        assert_eq!(tw.all_bindings("sign(3, S).", "S"), ["pos"]);
+        assert_eq!(tw.all_bindings("sign(-3, S).", "S"), ["neg"]);
+        assert_eq!(tw.all_bindings("sign(0, S).", "S"), ["zero"]);
+    }
+
+    #[test]
+    fn if_then_commits_to_first_condition_solution() {
+        let tw = tw("
+            q(a).
+            q(b).
+        ");
+        assert_eq!(tw.all_bindings("(q(X) -> true ; X = c).", "X"), ["a"]);
+        assert_eq!(tw.all_bindings("(q(X), X == b -> true ; X = c).", "X"), ["b"]);
+        tw.assert_false("(q(X) -> X == b).");
+    }
+
+    #[test]
+    fn if_then_else_condition_fails() {
+        let tw = tw("
+            q(a).
+        ");
+        assert_eq!(tw.all_bindings("(q(b) -> X = then ; X = else).", "X"), ["else"]);
+        tw.assert_false("q(b) -> true.");
+    }
+
+    #[test]
+    fn soft_cut() {
+        let tw = tw("
+            q(a).
+            q(b).
+        ");
+        assert_eq!(tw.all_bindings("(q(X) *-> true ; X = c).", "X"), ["a", "b"]);
+        assert_eq!(tw.all_bindings("(q(d) *-> X = t ; X = e).", "X"), ["e"]);
+    }
+
+    #[test]
+    fn cut_in_condition_is_local() {
+        let tw = tw("
+            p(X) :- (q(X), ! -> true ; X = c).
+            p(d).
+            q(a).
+            q(b).
+        ");
+        assert_eq!(tw.all_bindings("p(X).", "X"), ["a", "d"]);
+    }
+
+    #[test]
+    fn cut_in_branch_cuts_clause() {
+        let in_disjunction = tw("
+            p(X) :- (X = a, ! ; X = b).
+            p(c).
+        ");
+        assert_eq!(in_disjunction.all_bindings("p(X).", "X"), ["a"]);
+
+        let in_then = tw("
+            p(X) :- (true -> X = a, ! ; X = b).
+            p(c).
+        ");
+        assert_eq!(in_then.all_bindings("p(X).", "X"), ["a"]);
+    }
+
+    #[test]
+    fn if_then_else_inside_not() {
+        let tw = tw("
+            q(a).
+        ");
+        tw.assert_true("not((q(a) -> fail ; true)), X = 1.");
+        tw.assert_false("not((q(b) -> fail ; true)).");
+        tw.assert_true("forall(q(X), (X == a -> true ; fail)).");
+    }
+
+    // ── variable goals ────────────────────────────────────────────────────
+
+    #[test]
+    fn callable_terms_in_variables() {
+        let tw = tw("
+            t(1).
+            t(2).
+            p(G) :- G.
+        ");
+        assert_eq!(tw.all_bindings("G = (t(X), X > 1), G.", "X"), ["2"]);
+        assert_eq!(tw.all_bindings("p((t(X), t(X))).", "X"), ["1", "2"]);
+        // The variable is called with whatever it is bound to on each retry
+        assert_eq!(
+            tw.all_bindings("(G = t(X) ; G = (X = 3)), G.", "X"),
+            ["1", "2", "3"]
+        );
+        let tw = TestWrapper::new(&[DEFAULTS, MATHS, META_PREDICATES, STRINGS]);
+        tw.assert_binding("G = atom_length(abc, L), G.", ("L", "3"));
+    }
+
+    #[test]
+    fn meta_call_through_head_argument() {
+        let tw = TestWrapper::new(&[DEFAULTS, MATHS, META_PREDICATES, STRINGS]);
+        let tw = TestWrapper {
+            app: tw.app.load_code("p(G) :- G.").unwrap(),
+        };
+        tw.assert_true("forall(atom_length(a, L), true).");
+        tw.assert_true("forall(between(1, 3, X), X > 0).");
+        tw.assert_false("forall(between(1, 3, X), X > 1).");
+        assert_eq!(tw.all_bindings("p(between(1, 3, X)).", "X"), ["1", "2", "3"]);
+        tw.assert_binding("p(atom_length(abc, L)).", ("L", "3"));
+    }
+
+    // ── exceptions ────────────────────────────────────────────────────────
+
+    #[test]
+    fn catch_matching_ball() {
+        let tw = tw("
+            p(X) :- X = 1, throw(oops(X)).
+        ");
+        assert_eq!(tw.all_bindings("catch(p(X), oops(B), true).", "B"), ["1"]);
+        assert_eq!(tw.all_bindings("catch(p(X), E, R = E).", "R"), ["oops(1)"]);
+    }
+
+    #[test]
+    fn catch_undoes_bindings() {
+        let tw = tw("
+            p(X) :- X = 1, throw(oops).
+        ");
+        tw.assert_true("catch(p(X), oops, true), var(X).");
+    }
+
+    #[test]
+    fn catch_without_exception() {
+        let tw = tw("
+            q(a).
+            q(b).
+        ");
+        assert_eq!(tw.all_bindings("catch(q(X), _, true).", "X"), ["a", "b"]);
+        tw.assert_false("catch(fail, _, true).");
+    }
+
+    #[test]
+    fn catch_non_matching_ball() {
+        let tw = tw("
+            p :- catch(throw(b), a, true).
+        ");
+        assert_eq!(tw.all_bindings("catch(p, B, true).", "B"), ["b"]);
+        assert_eq!(
+            tw.all_bindings("catch(catch(throw(b), b, X = inner), _, X = outer).", "X"),
+            ["inner"]
+        );
+    }
+
+    #[test]
+    fn rethrow_from_recovery() {
+        let tw = tw("q(a).");
+        assert_eq!(
+            tw.all_bindings("catch(catch(throw(a), a, throw(b)), B, true).", "B"),
+            ["b"]
+        );
+    }
+
+    #[test]
+    fn exception_after_catch_is_not_caught() {
+        let tw = tw("
+            q(a).
+        ");
+        let mut session = tw.app.query_session("catch(q(X), _, true), throw(late).").unwrap();
+        assert!(session.next_solution().is_err());
+    }
+
+    #[test]
+    fn cut_inside_catch_is_local() {
+        let tw = tw("
+            p(X) :- catch((q(X), !), _, true).
+            p(c).
+            q(a).
+            q(b).
+        ");
+        assert_eq!(tw.all_bindings("p(X).", "X"), ["a", "c"]);
+    }
+
+    #[test]
+    fn exception_through_negation() {
+        let tw = tw("q(a).");
+        assert_eq!(
+            tw.all_bindings("catch(not(throw(a)), B, true).", "B"),
+            ["a"]
+        );
+        tw.assert_error("not(X).", "instantiation_error");
+    }
+
+    #[test]
+    fn uncaught_exception_is_an_error() {
+        let tw = tw("
+            q(a).
+            q(b).
+        ");
+        let mut session = tw.app.query_session("q(X), throw(stop(X)).").unwrap();
+        match session.next_solution() {
+            Err(crate::Error::Exception(ball)) => assert_eq!(ball, "stop(a)"),
+            result => panic!("expected an exception, got {result:?}"),
+        }
+        assert!(session.next_solution().unwrap().is_none());
+        tw.assert_error("throw(X).", "instantiation_error");
+        tw.assert_error("X.", "instantiation_error");
+    }
+
+    // ── last-call optimisation ────────────────────────────────────────────
+
+    /// Prove `query` against `code`, returning its first goal as solved, the
+    /// size of the goal stack at that point and the number of cells the proof
+    /// left on the heap.
+    fn prove_first(code: &str, query: &str, gc_threshold: usize) -> (String, usize, usize) {
+        let mut prog = Vec::<Cell>::new();
+        let mut pred_table = PredicateTable::new();
+        let clauses = TokenStream::new(tokenise(code).unwrap()).parse_all().unwrap();
+        execute_tree(clauses, &mut prog, &mut pred_table);
+
+        let mut heap = QueryHeap::new(&prog, None);
+        let literals = TokenStream::new(tokenise(query).unwrap()).parse_goals().unwrap();
+        let goals = build_clause(literals, None, None, &mut heap, true);
+        let config = Config {
+            max_depth: 10_000,
+            gc_threshold,
+            ..Config::default()
+        };
+        let start = heap.heap_len();
+        let mut proof = Proof::new(&heap, &goals);
+        assert!(proof.prove(&mut heap, &pred_table, config));
+        (
+            heap.term_string(goals[0]),
+            proof.stack.len(),
+            heap.heap_len() - start,
+        )
+    }
+
+    #[test]
+    fn deterministic_recursion_runs_in_constant_stack() {
+        let code = "
+            count(z).
+            count(s(N)) :- count(N).
+            len([], z).
+            len([_|T], s(N)) :- len(T, N).
+        ";
+        let (_, stack, _) = prove_first(code, "count(s(s(s(s(s(s(s(s(s(s(z))))))))))).", 0);
+        assert!(stack < 5, "stack grew to {stack}");
+
+        let (goal, _, _) = prove_first(code, "len([a,b,c], N).", 0);
+        assert_eq!(goal, "len([a,b,c],s(s(s(z))))");
+
+        let list = format!("[{}]", vec!["a"; 200].join(","));
+        let (_, stack, _) = prove_first(code, &format!("len({list}, N), count(N)."), 0);
+        assert!(stack < 10, "stack grew to {stack}");
+    }
+
+    #[test]
+    fn last_call_keeps_choice_points() {
+        let tw = tw("
+            p(X) :- q(X), r(X).
+            q(a).
+            q(b).
+            r(_).
+            s(X) :- p(X), t(X).
+            t(b).
+        ");
+        assert_eq!(tw.all_bindings("p(X).", "X"), ["a", "b"]);
+        assert_eq!(tw.all_bindings("s(X).", "X"), ["b"]);
+        assert_eq!(tw.all_bindings("catch(p(X), _, true), X == b.", "X"), ["b"]);
+    }
+
+    #[test]
+    fn deep_recursion_runs_in_linear_time() {
+        let tw = TestWrapper::new(&[DEFAULTS, MATHS, META_PREDICATES]);
+        let app = tw.app.config(Config {
+            max_depth: usize::MAX,
+            ..Config::default()
+        });
+        let tw = TestWrapper {
+            app: app
+                .load_code(
+                    "
+                    mk(0, []).
+                    mk(K, [K|T]) :- K > 0, K1 is K - 1, mk(K1, T).
+                    cnt([], N, N).
+                    cnt([_|T], A, N) :- A1 is A + 1, cnt(T, A1, N).
+                    len([], 0).
+                    len([_|T], N) :- len(T, M), N is M + 1.
+                    ",
+                )
+                .unwrap(),
+        };
+        // A list passed down a recursion was copied at every call, and goals
+        // were inserted by shifting every pending goal after them
+        let start = std::time::Instant::now();
+        assert_eq!(
+            tw.binding("mk(100000, L), cnt(L, 0, N).", "N").as_deref(),
+            Some("100000")
+        );
+        assert_eq!(
+            tw.binding("mk(100000, L), len(L, N).", "N").as_deref(),
+            Some("100000")
+        );
+        let elapsed = start.elapsed();
+        assert!(elapsed.as_secs() < 30, "took {elapsed:?}");
+    }
+
+    // ── garbage collection ────────────────────────────────────────────────
+
+    #[test]
+    fn garbage_collection_bounds_heap() {
+        let code = "
+            run(z, done).
+            run(s(N), R) :- wrap(N, _), run(N, R).
+            wrap(N, w(N, N, N)).
+            member(X, [X|_]).
+            member(X, [_|T]) :- member(X, T).
+            same(X, X).
+        ";
+        let n = (0..300).fold("z".to_string(), |n, _| format!("s({n})"));
+        let query = format!("run({n}, R).");
+        let (goal, _, kept) = prove_first(code, &query, 0);
+        assert!(kept > 2000, "only {kept} cells allocated");
+        let (collected_goal, _, collected) = prove_first(code, &query, 200);
+        assert_eq!(collected_goal, goal);
+        assert!(collected < 500, "heap kept {collected} cells");
+
+        // Collections between a choice point and the failure returning to it
+        let query = format!("member(X, [a,b,c]), run({n}, _), same(X, c).");
+        let (goal, _, _) = prove_first(code, &query, 50);
+        assert_eq!(goal, "member(c,[a,b,c])");
+    }
+
+    #[test]
+    fn garbage_collection_keeps_live_terms() {
+        let tw = TestWrapper::new(&[DEFAULTS, MATHS, META_PREDICATES, LISTS]);
+        let app = tw.app.config(Config {
+            gc_threshold: 16,
+            ..Config::default()
+        });
+        let tw = TestWrapper {
+            app: app
+                .load_code(
+                    "
+                    build(0, []).
+                    build(N, [f(N)|T]) :- N > 0, M is N - 1, build(M, T).
+                    elem(X, [X|_]).
+                    elem(X, [_|T]) :- elem(X, T).
+                    p(X) :- build(30, L), elem(f(X), L), X mod 10 =:= 0.
+                    ",
+                )
+                .unwrap(),
+        };
+        assert_eq!(tw.all_bindings("p(X).", "X"), ["30", "20", "10"]);
+        assert_eq!(
+            tw.all_bindings("findall(X, p(X), L), length(L, N).", "N"),
+            ["3"]
+        );
+        assert_eq!(
+            tw.all_bindings("catch((build(20, L), throw(L)), [_, f(X)|_], true).", "X"),
+            ["19"]
+        );
+        tw.assert_true("not((build(20, L), elem(f(21), L))).");
+    }
+}
@@ -0,0 +1,241 @@
+//! Evaluation of calls to tabled predicates.
+//!
+//! A call variant with no complete table is evaluated by proving it against
+//! the predicate's clauses over and over, adding each new answer to its
+//! table, until a round finds nothing new. A variant called again while it
+//! is being evaluated (left recursion, for instance) doesn't recurse: it
+//! consumes the answers found so far, and the repeated rounds pick up what
+//! it missed.
+//!
+//! Evaluations that consume each other's answers are completed together.
+//! Each evaluation records the lowest evaluation on the stack it depends on,
+//! its leader; only once the leader reaches a fixpoint are its tables
+//! complete and published to the shared [`Tables`]. Until then they are kept
+//! on the evaluating thread.
+//!
+//! [`Tables`]: crate::program::tabling::Tables
+
+use std::cell::RefCell;
+
+use crate::{
+    heap::{
+        heap::{Cell, Heap},
+        query_heap::QueryHeap,
+    },
+    program::{
+        hypothesis::Hypothesis,
+        predicate_table::{PredicateTable, SymbolArity},
+        tabling::{variant, variant_key, AnswerTable, Footprint, Variant},
+    },
+    Config,
+};
+
+use super::proof::Proof;
+
+/// A call being evaluated.
+struct Frame {
+    key: Variant,
+    /// Stack index of the lowest evaluation this one depends on.
+    leader: usize,
+    footprint: Footprint,
+}
+
+/// The table of a call evaluated but not yet complete.
+struct Incomplete {
+    key: Variant,
+    table: AnswerTable,
+    /// Stack index of the lowest evaluation its answers depend on.
+    depends: usize,
+}
+
+#[derive(Default)]
+struct Evaluation {
+    stack: Vec<Frame>,
+    incomplete: Vec<Incomplete>,
+    /// Answers added to any table on this thread, to detect a fixpoint.
+    added: usize,
+}
+
+impl Evaluation {
+    fn incomplete(&mut self, key: &[Cell]) -> Option<&mut Incomplete> {
+        self.incomplete.iter_mut().find(|table| *table.key == *key)
+    }
+}
+
+thread_local! {
+    static EVALUATION: RefCell<Evaluation> = RefCell::new(Evaluation::default());
+}
+
+/// Record that the running evaluation, if any, resolved a goal of
+/// `symbol_arity` against clauses.
+pub(crate) fn record_call(symbol_arity: SymbolArity) {
+    EVALUATION.with_borrow_mut(|evaluation| {
+        if let Some(frame) = evaluation.stack.last_mut() {
+            frame.footprint.add(symbol_arity);
+        }
+    })
+}
+
+/// Record that the running evaluation, if any, resolved a goal with a
+/// variable predicate.
+pub(crate) fn record_open_call() {
+    EVALUATION.with_borrow_mut(|evaluation| {
+        if let Some(frame) = evaluation.stack.last_mut() {
+            frame.footprint.set_open();
+        }
+    })
+}
+
+fn record_footprint(footprint: &Footprint) {
+    EVALUATION.with_borrow_mut(|evaluation| {
+        if let Some(frame) = evaluation.stack.last_mut() {
+            frame.footprint.merge(footprint);
+        }
+    })
+}
+
+/// The answers to the call `goal` of a tabled predicate, laid onto `heap`,
+/// evaluating its table first if it isn't complete.
+///
+/// Returns `Ok(None)` if the clauses of `hypothesis` could add answers, in
+/// which case the goal must be resolved as an ordinary call, and `Err(ball)`
+/// if the evaluation raised an exception.
+pub(crate) fn answers(
+    heap: &mut QueryHeap,
+    hypothesis: &Hypothesis,
+    goal: usize,
+    predicate_table: &PredicateTable,
+    config: Config,
+) -> Result<Option<Vec<usize>>, usize> {
+    let symbol_arity = heap.str_symbol_arity(goal);
+    record_call(symbol_arity);
+    if hypothesis.iter().any(|clause| {
+        let head = heap.str_symbol_arity(clause.head());
+        head.0 == 0 || head == symbol_arity
+    }) {
+        return Ok(None);
+    }
+
+    let key = variant_key(&variant(&*heap, goal));
+    if predicate_table.tables.get(&key).is_none() {
+        // A call already being evaluated consumes the answers found so far
+        let consumed = EVALUATION.with_borrow_mut(|evaluation| {
+            let index = evaluation.stack.iter().position(|frame| frame.key == key)?;
+            for frame in &mut evaluation.stack[index + 1..] {
+                frame.leader = frame.leader.min(index);
+            }
+            Some(evaluation.incomplete(&key).unwrap().table.load(heap))
+        });
+        if consumed.is_some() {
+            return Ok(consumed);
+        }
+        evaluate(heap, goal, &key, predicate_table, config)?;
+    }
+
+    match predicate_table.tables.get(&key) {
+        Some(table) => {
+            record_footprint(&table.footprint);
+            if table.footprint.depends_on(&*heap, hypothesis) {
+                Ok(None)
+            } else {
+                Ok(Some(table.load(heap)))
+            }
+        }
+        // Still incomplete, so this is a call inside another evaluation
+        None => Ok(Some(EVALUATION.with_borrow_mut(|evaluation| {
+            evaluation.incomplete(&key).unwrap().table.load(heap)
+        }))),
+    }
+}
+
+/// Evaluate the call `goal`, with variant key `key`, to a fixpoint.
+fn evaluate(
+    heap: &mut QueryHeap,
+    goal: usize,
+    key: &Variant,
+    predicate_table: &PredicateTable,
+    config: Config,
+) -> Result<(), usize> {
+    let index = EVALUATION.with_borrow_mut(|evaluation| {
+        let index = evaluation.stack.len();
+        let depends = match evaluation.incomplete(key) {
+            Some(incomplete) => incomplete.depends,
+            None => {
+                evaluation.incomplete.push(Incomplete {
+                    key: key.clone(),
+                    table: AnswerTable::default(),
+                    depends: index,
+                });
+                index
+            }
+        };
+        evaluation.stack.push(Frame {
+            key: key.clone(),
+            leader: depends,
+            footprint: Footprint::default(),
+        });
+        index
+    });
+    if config.debug {
+        eprintln!("[TABLE_EVAL] goal={} level={index}", heap.term_string(goal));
+    }
+
+    // Tables are evaluated without a hypothesis and can't learn one
+    let mut inner_config = config;
+    inner_config.max_clause = 0;
+
+    loop {
+        let added = EVALUATION.with_borrow(|evaluation| evaluation.added);
+        let snapshot_len = heap.heap_len();
+        let mut proof = Proof::tabled_evaluation(heap, goal);
+        while proof.prove(heap, predicate_table, inner_config) {
+            EVALUATION.with_borrow_mut(|evaluation| {
+                if evaluation.incomplete(key).unwrap().table.insert(&*heap, goal) {
+                    evaluation.added += 1;
+                }
+            });
+        }
+        proof.undo_all(heap);
+        heap.truncate(snapshot_len);
+
+        if let Some(ball) = proof.take_exception(heap) {
+            EVALUATION.with_borrow_mut(|evaluation| {
+                evaluation.stack.pop();
+                evaluation.incomplete.retain(|table| table.depends < index);
+            });
+            return Err(ball);
+        }
+        if EVALUATION.with_borrow(|evaluation| evaluation.added) == added {
+            break;
+        }
+    }
+
+    EVALUATION.with_borrow_mut(|evaluation| {
+        let frame = evaluation.stack.pop().unwrap();
+        if frame.leader >= index {
+            // Nothing this evaluation relied on is still being evaluated
+            let (complete, incomplete) = std::mem::take(&mut evaluation.incomplete)
+                .into_iter()
+                .partition(|table| table.depends >= index);
+            evaluation.incomplete = incomplete;
+            for Incomplete { key, mut table, .. } in complete {
+                if config.debug {
+                    eprintln!("[TABLE_COMPLETE] answers={}", table.len());
+                }
+                table.footprint = frame.footprint.clone();
+                predicate_table.tables.complete(key, table);
+            }
+        } else {
+            for table in &mut evaluation.incomplete {
+                if table.depends >= index {
+                    table.depends = frame.leader;
+                }
+            }
+        }
+        if let Some(parent) = evaluation.stack.last_mut() {
+            parent.leader = parent.leader.min(frame.leader);
+            parent.footprint.merge(&frame.footprint);
+        }
+    });
+    Ok(())
+}
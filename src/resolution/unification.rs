@@ -8,7 +8,10 @@ use std::{
 
 use smallvec::SmallVec;
 
-use crate::heap::heap::{Cell, Heap, Tag};
+use crate::heap::{
+    heap::{Cell, Heap, Tag},
+    symbol_db::SymbolDB,
+};
 
 /// Substitution mapping clause `Arg` cells to heap addresses.
 ///
@@ -218,6 +221,22 @@ fn unify_rec(
         {
             Some(binding)
         }
+        // Strings are stored by index, so equal text may have two indices
+        (Tag::Stri, Tag::Stri)
+            if SymbolDB::get_string(heap[addr_1].1) == SymbolDB::get_string(heap[addr_2].1) =>
+        {
+            Some(binding)
+        }
+        (Tag::BigInt, Tag::BigInt)
+            if SymbolDB::get_big_int(heap[addr_1].1) == SymbolDB::get_big_int(heap[addr_2].1) =>
+        {
+            Some(binding)
+        }
+        (Tag::Rat, Tag::Rat)
+            if SymbolDB::get_rational(heap[addr_1].1) == SymbolDB::get_rational(heap[addr_2].1) =>
+        {
+            Some(binding)
+        }
         (Tag::Comp, Tag::Comp) | (Tag::Tup, Tag::Tup) => {
             unify_func_or_tup(heap, binding, addr_1, addr_2)
         }
@@ -658,6 +677,15 @@ mod tests {
     /// clause `p(Z,Z)`  vs  goal `p(X,(X,Y))`
     ///
     /// `Z = X` and `Z = (X,Y)`  ⟹  `X = (X,Y)` — cyclic.
+    #[test]
+    fn strings_unify_by_text() {
+        let [ab, ab2, cd] = ["ab", "ab", "cd"].map(|text| SymbolDB::set_string(text.into(), 0));
+        let heap = vec![(Tag::Stri, ab), (Tag::Stri, ab2), (Tag::Stri, cd)];
+
+        assert!(unify(&heap, 0, 1).is_some());
+        assert_eq!(unify(&heap, 0, 2), None);
+    }
+
     /// The goal var `X` appears literally inside the tuple, so detection relies
     /// on the direct `Ref` path in `occurs`.
     #[test]
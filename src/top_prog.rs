@@ -41,6 +41,7 @@ struct HypothesisMsg {
 
 impl App {
     pub fn run_top_prog(&mut self) -> String{
+        let _symbols = self.predicate_table.symbols.enter();
         let Some(mut examples) = self.examples.clone() else {
             panic!("Can't start top prog without examples");
         };
@@ -121,9 +122,16 @@ impl App {
     }
 }
 
-/// Parse a single example string into a goal on the given query heap.
-fn parse_example(example: &str, query_heap: &mut QueryHeap) -> Result<usize, String> {
+/// Parse a single example string into a goal on the given query heap, with
+/// the program's operators.
+fn parse_example(
+    example: &str,
+    query_heap: &mut QueryHeap,
+    predicate_table: &PredicateTable,
+) -> Result<usize, String> {
+    let operators = predicate_table.operators.read().unwrap().clone();
     let literals = TokenStream::new(tokenise(example).map_err(|e| e.to_string())?)
+        .with_operators(operators)
         .parse_goals()
         .map_err(|e| format!("Example '{example}' incorrectly formatted: {e}"))?;
     let clause = build_clause(literals, None, None, query_heap, true);
@@ -162,9 +170,11 @@ fn generalise(
     let total = pos_examples.len();
     let completed = Arc::new(AtomicUsize::new(0));
     let heap_len = heap.len();
+    let symbols = predicate_table.symbols.clone();
 
     // Collector runs on its own OS thread, processing results as they arrive
     let collector = thread::spawn(move || {
+        let _symbols = symbols.enter();
         let mut hypothesis_cells = Vec::new();
         let mut hypotheses = Vec::new();
         let mut seen = HashSet::new();
@@ -210,6 +220,7 @@ fn generalise(
             let tx = tx.clone();
             let completed = completed.clone();
             s.spawn(move |_| {
+                let _symbols = predicate_table.symbols.enter();
                 generalise_thread(example, predicate_table, &heap, config, tx);
                 let done = completed.fetch_add(1, Ordering::Relaxed) + 1;
                 eprint!("\rGeneralise: {done}/{total} examples");
@@ -231,7 +242,7 @@ fn generalise_thread(
     tx: Sender<HypothesisMsg>,
 ) {
     let mut query_heap = QueryHeap::new(prog_heap, None);
-    let goal = match parse_example(&example, &mut query_heap) {
+    let goal = match parse_example(&example, &mut query_heap, predicate_table) {
         Ok(g) => g,
         Err(e) => {
             eprintln!("Failed to parse example '{}': {}", example, e);
@@ -282,6 +293,7 @@ fn specialise(
             let tx = tx.clone();
             let completed = completed.clone();
             s.spawn(move |_| {
+                let _symbols = predicate_table.symbols.enter();
                 let keep =
                     specialise_thread(neg_examples, hypothesis, heap, predicate_table, config);
                 let _ = tx.send((idx, keep));
@@ -312,6 +324,7 @@ fn specialise_thread(
         max_clause: 0,
         max_pred: 0,
         debug: false,
+        gc_threshold: config.gc_threshold,
     };
 
     // Build a Hypothesis from the clauses so we can use Proof::with_hypothesis
@@ -322,7 +335,7 @@ fn specialise_thread(
 
     for example in neg_examples {
         let mut query_heap = QueryHeap::new(heap, None);
-        let goal = match parse_example(example, &mut query_heap) {
+        let goal = match parse_example(example, &mut query_heap, predicate_table) {
             Ok(g) => g,
             Err(e) => {
                 eprintln!("Failed to parse negative example '{}': {}", example, e);
@@ -353,6 +366,7 @@ fn count_coverage(
         max_clause: 0,
         max_pred: 0,
         debug: false,
+        gc_threshold: config.gc_threshold,
     };
 
     let mut h = Hypothesis::new();
@@ -364,7 +378,7 @@ fn count_coverage(
         .iter()
         .filter(|example| {
             let mut query_heap = QueryHeap::new(heap, None);
-            let goal = match parse_example(example, &mut query_heap) {
+            let goal = match parse_example(example, &mut query_heap, predicate_table) {
                 Ok(g) => g,
                 Err(_) => return false,
             };
@@ -446,6 +460,7 @@ fn reduce<'a>(
         max_clause: 0,
         max_pred: 0,
         debug: false,
+        gc_threshold: config.gc_threshold,
     };
 
     let total = hypothesis.len();
@@ -471,7 +486,7 @@ fn reduce<'a>(
         // Check if all positive examples are still provable without clause i
         let redundant = pos_examples.iter().all(|example| {
             let mut query_heap = QueryHeap::new(heap, None);
-            let goal = match parse_example(example, &mut query_heap) {
+            let goal = match parse_example(example, &mut query_heap, predicate_table) {
                 Ok(g) => g,
                 Err(_) => return true, // skip unparseable examples
             };